@@ -0,0 +1,44 @@
+//! Creates a vault filled with generated records, e.g. for screenshots or manual testing.
+//!
+//! `cargo run --example fixture_vault --features fixtures -- <path> <password> [records] [seed]`
+use password_manager::fixtures::{create_fixture_vault, FixtureSpec};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (Some(path), Some(password)) = (args.first(), args.get(1)) else {
+        eprintln!("Usage: fixture_vault <path> <password> [records] [seed]");
+        return ExitCode::FAILURE;
+    };
+    let mut spec = FixtureSpec::default();
+    if let Some(records) = args.get(2) {
+        match records.parse() {
+            Ok(records) => spec.records = records,
+            Err(_) => {
+                eprintln!("Invalid number of records");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if let Some(seed) = args.get(3) {
+        match seed.parse() {
+            Ok(seed) => spec.seed = seed,
+            Err(_) => {
+                eprintln!("Invalid seed");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    match create_fixture_vault(&PathBuf::from(path), password, spec) {
+        Ok(records) => {
+            println!("Created {path} with {records} records");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
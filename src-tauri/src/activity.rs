@@ -0,0 +1,140 @@
+use crate::database::Database;
+use crate::error::Error;
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a computed summary is returned from [`ActivityCache`] before it is computed again.
+pub const CACHE_DURATION: Duration = Duration::from_secs(5 * 60);
+
+/// Number of records in [`ActivitySummary::recent`].
+const RECENT_RECORDS: u64 = 5;
+
+/// Record in [`ActivitySummary::recent`], only its id and title.
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct RecentRecord {
+    pub id: u64,
+    pub title: String,
+}
+
+/// Activity in the vault for the dashboard. Days are counted as 24 hours back from the time of the summary. Modified records include new records.
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct ActivitySummary {
+    pub added_7_days: u64,
+    pub added_30_days: u64,
+    pub modified_7_days: u64,
+    pub modified_30_days: u64,
+    /// Most recently modified records, newest first.
+    pub recent: Vec<RecentRecord>,
+}
+
+/// Computes the activity summary at the time.
+/// # Errors
+/// If the records cannot be counted or loaded.
+pub fn summary(database: &Database, now: DateTime<Utc>) -> Result<ActivitySummary, Error> {
+    let week = now - chrono::Duration::days(7);
+    let month = now - chrono::Duration::days(30);
+    Ok(ActivitySummary {
+        added_7_days: database.count_records_created_since(week)?,
+        added_30_days: database.count_records_created_since(month)?,
+        modified_7_days: database.count_records_modified_since(week)?,
+        modified_30_days: database.count_records_modified_since(month)?,
+        recent: database
+            .recently_modified_records(RECENT_RECORDS)?
+            .into_iter()
+            .map(|(id, title)| RecentRecord { id, title })
+            .collect(),
+    })
+}
+
+/// Last computed activity summary for tauri state, see [`CACHE_DURATION`].
+#[derive(Default)]
+pub struct ActivityCache(Mutex<Option<(Instant, ActivitySummary)>>);
+
+impl ActivityCache {
+    /// Returns the cached summary if it was computed less than [`CACHE_DURATION`] before `now`, otherwise computes and caches a new one.
+    /// # Errors
+    /// If the summary cannot be computed or the cache mutex is poisoned.
+    pub fn get_or_compute(
+        &self,
+        now: Instant,
+        compute: impl FnOnce() -> Result<ActivitySummary, Error>,
+    ) -> Result<ActivitySummary, Error> {
+        let mut guard = self
+            .0
+            .lock()
+            .map_err(|_| "Failed to access activity cache lock")?;
+        if let Some((computed, summary)) = guard.as_ref() {
+            if now.saturating_duration_since(*computed) < CACHE_DURATION {
+                return Ok(summary.clone());
+            }
+        }
+        let summary = compute()?;
+        *guard = Some((now, summary.clone()));
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::model::{Category, Record};
+    use tempfile::TempDir;
+
+    fn empty(added_7_days: u64) -> ActivitySummary {
+        ActivitySummary {
+            added_7_days,
+            added_30_days: 0,
+            modified_7_days: 0,
+            modified_30_days: 0,
+            recent: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        let now = Utc::now();
+        for (title, days) in [("Old", 40), ("Month", 20), ("Week", 3), ("Today", 0)] {
+            let mut record = Record::new(title.to_string(), String::new(), Category::Other);
+            let timestamp = now - chrono::Duration::days(days);
+            record.set_created(timestamp);
+            database
+                .save_record_with_content(&mut record, &mut [])
+                .unwrap();
+        }
+        let summary = summary(&database, now).unwrap();
+        assert_eq!(summary.added_7_days, 2);
+        assert_eq!(summary.added_30_days, 3);
+        // Saving sets the modification time of all records to now
+        assert_eq!(summary.modified_7_days, 4);
+        assert_eq!(summary.modified_30_days, 4);
+        let titles: Vec<&str> = summary
+            .recent
+            .iter()
+            .map(|record| record.title.as_str())
+            .collect();
+        assert_eq!(titles, ["Today", "Week", "Month", "Old"]);
+    }
+    #[test]
+    fn test_cache() {
+        let cache = ActivityCache::default();
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+        assert_eq!(cache.get_or_compute(start, || Ok(empty(1))), Ok(empty(1)));
+        let cached = start + CACHE_DURATION - second;
+        assert_eq!(cache.get_or_compute(cached, || Ok(empty(2))), Ok(empty(1)));
+        assert_eq!(
+            cache.get_or_compute(start + CACHE_DURATION, || Ok(empty(3))),
+            Ok(empty(3))
+        );
+        // Failed computation keeps the last summary
+        let later = start + CACHE_DURATION * 3;
+        assert!(cache
+            .get_or_compute(later, || Err(Error::Other("Failed")))
+            .is_err());
+        let cached = start + CACHE_DURATION + second;
+        assert_eq!(cache.get_or_compute(cached, || Ok(empty(4))), Ok(empty(3)));
+    }
+}
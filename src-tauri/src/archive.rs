@@ -0,0 +1,902 @@
+use crate::database::model::value::*;
+use crate::database::model::{Category, Content, Record, Value};
+use crate::database::{self, Database, MetadataEntry};
+use crate::error::Error;
+use crate::file_manager::atomic_write;
+use crate::import::ImportSummary;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Magic bytes at the start of every archive.
+const MAGIC: &[u8; 8] = b"PMARCHIV";
+
+/// Version of the archive format, stored after [`MAGIC`].
+const VERSION: u16 = 1;
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+const KEY_LENGTH: usize = 32;
+
+/// Length of the header: magic, version, Argon2id memory cost, iterations and parallelism, salt and nonce. The whole header is authenticated with the ciphertext.
+const HEADER_LENGTH: usize = MAGIC.len() + 2 + 3 * 4 + SALT_LENGTH + NONCE_LENGTH;
+
+/// Argon2id parameters of new archives: 64 MiB of memory, 3 iterations, 1 lane.
+const MEMORY_COST: u32 = 64 * 1024;
+const ITERATIONS: u32 = 3;
+const PARALLELISM: u32 = 1;
+
+/// Highest memory cost accepted when reading an archive, so a crafted header cannot make the key derivation use all memory.
+const MAX_MEMORY_COST: u32 = 1024 * 1024;
+
+/// Error returned for a wrong passphrase or a modified archive. The archive is authenticated, so the two cannot be told apart.
+pub const INVALID_ARCHIVE: &str = "Invalid passphrase or damaged archive";
+
+/// Extension of the files written by [`write_notes`], each is a Markdown note sealed like an archive.
+pub const NOTE_EXTENSION: &str = "md.archive";
+
+/// Longest file name of a note in characters, without the extension and the number added to duplicates.
+const MAX_NOTE_NAME_LENGTH: usize = 100;
+
+/// Names reserved by Windows, with or without an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Content of a record in an archive. The value is stored the same way as in the database, so passwords and structured values are included.
+#[derive(Serialize, Deserialize, Zeroize)]
+struct ArchiveContent {
+    label: String,
+    position: u32,
+    required: bool,
+    kind: String,
+    value: String,
+    /// Missing in archives created before the time was tracked.
+    #[zeroize(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    value_last_changed: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Record with its content in an archive. Values are zeroized on drop.
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct ArchiveRecord {
+    title: String,
+    subtitle: String,
+    category: String,
+    #[zeroize(skip)]
+    created: chrono::DateTime<chrono::Utc>,
+    #[zeroize(skip)]
+    last_modified: chrono::DateTime<chrono::Utc>,
+    locked: bool,
+    content: Vec<ArchiveContent>,
+    /// Metadata of integrations, only if it was included in the export.
+    #[zeroize(skip)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    metadata: Vec<MetadataEntry>,
+}
+
+/// Record with its content and metadata read from an archive.
+pub type ArchiveEntry = (Record, Vec<Content>, Vec<MetadataEntry>);
+
+impl ArchiveRecord {
+    fn new(record: &Record, content: &[Content], metadata: &[MetadataEntry]) -> ArchiveRecord {
+        ArchiveRecord {
+            title: record.title().to_string(),
+            subtitle: record.subtitle().to_string(),
+            category: record.category().as_str().to_string(),
+            created: record.created(),
+            last_modified: record.last_modified(),
+            locked: record.locked(),
+            content: content
+                .iter()
+                .map(|content| ArchiveContent {
+                    label: content.label().to_string(),
+                    position: content.position(),
+                    required: content.required(),
+                    kind: content.kind().to_string(),
+                    value: content.value().to_secret_string().expose_secret().clone(),
+                    value_last_changed: content.value_last_changed(),
+                })
+                .collect(),
+            metadata: metadata.to_vec(),
+        }
+    }
+
+    /// Converts the archived record back to a record with content and metadata, keeping its creation and modification time, lock and the times values were last changed.
+    /// # Errors
+    /// If a value is not valid for its kind.
+    fn to_entry(&self) -> Result<ArchiveEntry, &'static str> {
+        let mut record = Record::new(
+            self.title.clone(),
+            self.subtitle.clone(),
+            Category::from_string(self.category.clone()),
+        );
+        record.set_created(self.created);
+        record.set_last_modified(self.last_modified);
+        record.set_locked(self.locked);
+        let content = self
+            .content
+            .iter()
+            .map(|content| {
+                let mut restored = Content::new(
+                    content.label.clone(),
+                    content.position,
+                    content.required,
+                    to_value(&content.kind, content.value.clone())?,
+                );
+                restored.set_value_last_changed(content.value_last_changed);
+                Ok(restored)
+            })
+            .collect::<Result<Vec<Content>, &'static str>>()?;
+        Ok((record, content, self.metadata.clone()))
+    }
+}
+
+/// Converts a value stored as text back to a value of its kind, like values loaded from the database.
+/// # Errors
+/// If the kind is unknown or the value is not valid for it.
+fn to_value(kind: &str, value: String) -> Result<Value, &'static str> {
+    Ok(match kind {
+        "Number" => Value::Number(Number::new(value)?),
+        "Text" => Value::Text(Text::new(value)),
+        "LongText" => Value::LongText(LongText::new(value)),
+        "SensitiveText" => Value::SensitiveText(SensitiveText::new(value)),
+        "Date" => Value::Date(Date::new(value)?),
+        "Password" => Value::Password(Password::new(value)),
+        "TOTPSecret" => Value::TOTPSecret(TOTPSecret::new(value)?),
+        "Url" => Value::Url(Url::new(value)?),
+        "Email" => Value::Email(Email::new(value)?),
+        "PhoneNumber" => Value::PhoneNumber(PhoneNumber::new(value)?),
+        "BankCardNumber" => Value::BankCardNumber(BankCardNumber::new(value)?),
+        "QuestionAnswer" => {
+            Value::QuestionAnswer(QuestionAnswer::from_json(&Zeroizing::new(value))?)
+        }
+        "RecoveryCodes" => Value::RecoveryCodes(RecoveryCodes::from_json(&Zeroizing::new(value))?),
+        _ => return Err("Unknown kind"),
+    })
+}
+
+/// Collects all records with their content and metadata. Locked records are left out unless `include_locked` is set. Records missing in `metadata` are archived without it.
+/// # Return
+/// Returns the archived records and the number of records that were left out because they are locked.
+pub fn collect(
+    records: &[Record],
+    content: &HashMap<u64, Vec<Content>>,
+    metadata: &HashMap<u64, Vec<MetadataEntry>>,
+    include_locked: bool,
+) -> (Vec<ArchiveRecord>, usize) {
+    let mut archived = Vec::new();
+    let mut locked = 0;
+    for record in records {
+        if record.locked() && include_locked.not() {
+            locked += 1;
+            continue;
+        }
+        let content = content
+            .get(&record.id())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let metadata = metadata
+            .get(&record.id())
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        archived.push(ArchiveRecord::new(record, content, metadata));
+    }
+    (archived, locked)
+}
+
+/// Derives the key of the archive from the passphrase with Argon2id.
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    params: Params,
+) -> Result<Zeroizing<[u8; KEY_LENGTH]>, &'static str> {
+    let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(
+            database::normalize_password(passphrase)
+                .expose_secret()
+                .as_bytes(),
+            salt,
+            key.as_mut(),
+        )
+        .map_err(|_| "Failed to derive key")?;
+    Ok(key)
+}
+
+/// Encrypts the data with XChaCha20-Poly1305 and a key derived from the passphrase with the given Argon2id parameters, see [`HEADER_LENGTH`].
+fn seal(data: &[u8], passphrase: &str, params: Params) -> Result<Vec<u8>, &'static str> {
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+    let mut archive = Vec::with_capacity(HEADER_LENGTH + data.len() + 16);
+    archive.extend_from_slice(MAGIC);
+    archive.extend_from_slice(&VERSION.to_le_bytes());
+    archive.extend_from_slice(&params.m_cost().to_le_bytes());
+    archive.extend_from_slice(&params.t_cost().to_le_bytes());
+    archive.extend_from_slice(&params.p_cost().to_le_bytes());
+    archive.extend_from_slice(&salt);
+    archive.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt, params)?;
+    let ciphertext = XChaCha20Poly1305::new(key.as_ref().into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: data,
+                aad: &archive,
+            },
+        )
+        .map_err(|_| "Failed to encrypt archive")?;
+    archive.extend_from_slice(&ciphertext);
+    Ok(archive)
+}
+
+/// Decrypts an archive created by [`seal`].
+/// # Errors
+/// Returns [`INVALID_ARCHIVE`] if the passphrase is wrong or the archive was modified, or an error if the data is not an archive or has an unknown version.
+fn open(archive: &[u8], passphrase: &str) -> Result<Zeroizing<Vec<u8>>, &'static str> {
+    if archive.len() < HEADER_LENGTH || archive.starts_with(MAGIC).not() {
+        return Err("Not an encrypted archive");
+    }
+    let (header, ciphertext) = archive.split_at(HEADER_LENGTH);
+    let number = |offset: usize| -> u32 {
+        let offset = MAGIC.len() + 2 + offset * 4;
+        u32::from_le_bytes(header[offset..offset + 4].try_into().unwrap_or_default())
+    };
+    let version = u16::from_le_bytes([header[MAGIC.len()], header[MAGIC.len() + 1]]);
+    if version != VERSION {
+        return Err("Unsupported archive version");
+    }
+    let (memory, iterations, parallelism) = (number(0), number(1), number(2));
+    if memory > MAX_MEMORY_COST {
+        return Err(INVALID_ARCHIVE);
+    }
+    let params = Params::new(memory, iterations, parallelism, Some(KEY_LENGTH))
+        .map_err(|_| INVALID_ARCHIVE)?;
+    let salt = &header[HEADER_LENGTH - NONCE_LENGTH - SALT_LENGTH..HEADER_LENGTH - NONCE_LENGTH];
+    let nonce = &header[HEADER_LENGTH - NONCE_LENGTH..];
+
+    let key = derive_key(passphrase, salt, params)?;
+    XChaCha20Poly1305::new(key.as_ref().into())
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| INVALID_ARCHIVE)
+}
+
+/// Writes the records to a new archive encrypted with the passphrase. Records are serialized to JSON, which is encrypted with XChaCha20-Poly1305 using a key derived from the passphrase with Argon2id.
+/// # Return
+/// Returns the number of written records.
+/// # Errors
+/// If the passphrase is empty, the file already exists or the archive cannot be written.
+pub fn write(
+    path: &Path,
+    passphrase: &str,
+    records: &[ArchiveRecord],
+) -> Result<usize, &'static str> {
+    if passphrase.trim().is_empty() {
+        return Err("Passphrase can not be empty");
+    }
+    if path.exists() {
+        return Err("File already exists");
+    }
+    let params = Params::new(MEMORY_COST, ITERATIONS, PARALLELISM, Some(KEY_LENGTH))
+        .map_err(|_| "Failed to derive key")?;
+    let json = Zeroizing::new(serde_json::to_vec(records).map_err(|_| "Failed to write archive")?);
+    atomic_write(path, &seal(&json, passphrase, params)?).map_err(|_| "Failed to write archive")?;
+    Ok(records.len())
+}
+
+/// Reads records of an archive written by [`write`].
+/// # Errors
+/// Returns [`INVALID_ARCHIVE`] if the passphrase is wrong or the archive was modified, or an error if the file is not an archive, has an unknown version or contains an invalid value.
+pub fn read(path: &Path, passphrase: &str) -> Result<Vec<ArchiveEntry>, &'static str> {
+    let archive = fs::read(path).map_err(|_| "Failed to read file")?;
+    let json = open(&archive, passphrase)?;
+    let records: Vec<ArchiveRecord> =
+        serde_json::from_slice(&json).map_err(|_| "Failed to read archive")?;
+    records.iter().map(ArchiveRecord::to_entry).collect()
+}
+
+/// Merges records from an archive into the database with their metadata. Records that already exist, with the same title, category and creation time, are skipped and reported with their position in the archive, starting at 1. Every record is saved in its own transaction.
+/// # Errors
+/// If a record cannot be saved. Records imported before it are kept.
+pub fn import(database: &Database, entries: &[ArchiveEntry]) -> Result<ImportSummary, Error> {
+    let mut summary = ImportSummary {
+        imported: 0,
+        updated: 0,
+        skipped: Vec::new(),
+        failed: Vec::new(),
+        skipped_file: None,
+    };
+    for (index, (record, content, metadata)) in entries.iter().enumerate() {
+        if database.insert_record_copy(record, content, metadata)? {
+            summary.imported += 1;
+        } else {
+            summary
+                .skipped
+                .push((index + 1, "Record already exists".to_string()));
+        }
+    }
+    Ok(summary)
+}
+
+/// Note record with its text written to its own file by [`write_notes`]. The text is zeroized on drop.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct ArchiveNote {
+    title: String,
+    #[zeroize(skip)]
+    created: chrono::DateTime<chrono::Utc>,
+    #[zeroize(skip)]
+    last_modified: chrono::DateTime<chrono::Utc>,
+    text: String,
+}
+
+impl ArchiveNote {
+    /// Writes the note as Markdown with front matter of the title, creation and modification time. The title is quoted as a JSON string, which is also valid YAML.
+    fn to_markdown(&self) -> Zeroizing<String> {
+        Zeroizing::new(format!(
+            "---\ntitle: {}\ncreated: {}\nmodified: {}\n---\n\n{}",
+            serde_json::Value::from(self.title.as_str()),
+            self.created.to_rfc3339(),
+            self.last_modified.to_rfc3339(),
+            self.text
+        ))
+    }
+
+    /// Reads a note written by [`ArchiveNote::to_markdown`].
+    /// # Errors
+    /// If the front matter is missing or one of its fields is missing or invalid.
+    fn from_markdown(markdown: &str) -> Result<ArchiveNote, &'static str> {
+        const INVALID_NOTE: &str = "Invalid note";
+        let (front_matter, text) = markdown
+            .strip_prefix("---\n")
+            .and_then(|markdown| markdown.split_once("\n---\n"))
+            .ok_or(INVALID_NOTE)?;
+        let fields: HashMap<&str, &str> = front_matter
+            .lines()
+            .filter_map(|line| line.split_once(": "))
+            .collect();
+        let time = |name: &str| {
+            fields
+                .get(name)
+                .and_then(|time| chrono::DateTime::parse_from_rfc3339(time).ok())
+                .map(|time| time.with_timezone(&chrono::Utc))
+                .ok_or(INVALID_NOTE)
+        };
+        Ok(ArchiveNote {
+            title: fields
+                .get("title")
+                .and_then(|title| serde_json::from_str(title).ok())
+                .ok_or(INVALID_NOTE)?,
+            created: time("created")?,
+            last_modified: time("modified")?,
+            text: text.strip_prefix('\n').unwrap_or(text).to_string(),
+        })
+    }
+
+    /// Converts the note to a note record with its text as content, keeping its creation and modification time.
+    fn to_entry(&self) -> ArchiveEntry {
+        let mut record = Record::new(self.title.clone(), String::new(), Category::Note);
+        record.set_created(self.created);
+        record.set_last_modified(self.last_modified);
+        let content = match self.text.is_empty() {
+            true => Vec::new(),
+            false => vec![Content::new(
+                "Note".to_string(),
+                0,
+                true,
+                Value::LongText(LongText::new(self.text.clone())),
+            )],
+        };
+        (record, content, Vec::new())
+    }
+}
+
+/// Collects all note records with their long texts, separated by an empty line. Other categories are skipped and locked notes are left out unless `include_locked` is set.
+/// # Return
+/// Returns the notes and the number of notes that were left out because they are locked.
+pub fn collect_notes(
+    records: &[Record],
+    content: &HashMap<u64, Vec<Content>>,
+    include_locked: bool,
+) -> (Vec<ArchiveNote>, usize) {
+    let mut notes = Vec::new();
+    let mut locked = 0;
+    for record in records
+        .iter()
+        .filter(|record| record.category() == &Category::Note)
+    {
+        if record.locked() && include_locked.not() {
+            locked += 1;
+            continue;
+        }
+        let text: Vec<&str> = content
+            .get(&record.id())
+            .into_iter()
+            .flatten()
+            .filter_map(|content| match content.value() {
+                Value::LongText(text) => Some(text.value()),
+                _ => None,
+            })
+            .collect();
+        notes.push(ArchiveNote {
+            title: record.title().to_string(),
+            created: record.created(),
+            last_modified: record.last_modified(),
+            text: text.join("\n\n"),
+        });
+    }
+    (notes, locked)
+}
+
+/// File name of a note without the extension. Path separators, characters not allowed on Windows and control characters are replaced with `_`, leading dots and whitespace and trailing dots and whitespace are removed, so the name can not leave the directory. Empty names become `Note` and names reserved by Windows get an `_` suffix.
+fn note_file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|character| match character {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            character if character.is_control() => '_',
+            character => character,
+        })
+        .take(MAX_NOTE_NAME_LENGTH)
+        .collect();
+    let name = name
+        .trim_start_matches(|character: char| character == '.' || character.is_whitespace())
+        .trim_end_matches(|character: char| character == '.' || character.is_whitespace());
+    if name.is_empty() {
+        return "Note".to_string();
+    }
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        return format!("{name}_");
+    }
+    name.to_string()
+}
+
+/// Writes every note to its own file in the directory, encrypted with the passphrase like an archive ([`write`]). File names are made from the titles ([`note_file_name`]) and numbered when the name is already used by another note or an existing file, ignoring case, so no file is overwritten.
+/// # Return
+/// Returns the paths of the written files in the order of the notes.
+/// # Errors
+/// If the passphrase is empty, the directory does not exist or a file cannot be written. Files written before the error are removed.
+pub fn write_notes(
+    directory: &Path,
+    passphrase: &str,
+    notes: &[ArchiveNote],
+) -> Result<Vec<PathBuf>, &'static str> {
+    let params = Params::new(MEMORY_COST, ITERATIONS, PARALLELISM, Some(KEY_LENGTH))
+        .map_err(|_| "Failed to derive key")?;
+    seal_notes(directory, passphrase, notes, params)
+}
+
+/// Writes the notes with the given Argon2id parameters, see [`write_notes`].
+fn seal_notes(
+    directory: &Path,
+    passphrase: &str,
+    notes: &[ArchiveNote],
+    params: Params,
+) -> Result<Vec<PathBuf>, &'static str> {
+    if passphrase.trim().is_empty() {
+        return Err("Passphrase can not be empty");
+    }
+    if directory.is_dir().not() {
+        return Err("Directory does not exist");
+    }
+    let mut used = HashSet::new();
+    let mut written = Vec::new();
+    for note in notes {
+        let name = note_file_name(&note.title);
+        let path = (1..)
+            .map(|number| match number {
+                1 => format!("{name}.{NOTE_EXTENSION}"),
+                number => format!("{name} ({number}).{NOTE_EXTENSION}"),
+            })
+            .find(|file_name| {
+                used.contains(&file_name.to_lowercase()).not()
+                    && directory.join(file_name).exists().not()
+            })
+            .map(|file_name| {
+                used.insert(file_name.to_lowercase());
+                directory.join(file_name)
+            })
+            .ok_or("Failed to write note")?;
+        let result = seal(note.to_markdown().as_bytes(), passphrase, params.clone())
+            .and_then(|sealed| atomic_write(&path, &sealed).map_err(|_| "Failed to write note"));
+        if let Err(error) = result {
+            for path in written {
+                fs::remove_file(path).unwrap_or_default();
+            }
+            return Err(error);
+        }
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Reads all notes written by [`write_notes`] from the directory, in the order of their file names. Other files are ignored.
+/// # Errors
+/// Returns [`INVALID_ARCHIVE`] if the passphrase is wrong or a note was modified, or an error if the directory or a note cannot be read.
+pub fn read_notes(directory: &Path, passphrase: &str) -> Result<Vec<ArchiveEntry>, &'static str> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+        .map_err(|_| "Failed to read directory")?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with('.').not() && name.ends_with(&format!(".{NOTE_EXTENSION}"))
+                })
+        })
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| {
+            let sealed = fs::read(path).map_err(|_| "Failed to read file")?;
+            let markdown = open(&sealed, passphrase)?;
+            let markdown = std::str::from_utf8(&markdown).map_err(|_| "Invalid note")?;
+            Ok(ArchiveNote::from_markdown(markdown)?.to_entry())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Parameters with a fast key derivation for tests.
+    fn fast() -> Params {
+        Params::new(64, 1, 1, Some(KEY_LENGTH)).unwrap()
+    }
+
+    fn metadata() -> HashMap<u64, Vec<MetadataEntry>> {
+        HashMap::from([(
+            1,
+            vec![MetadataEntry {
+                namespace: "ansible".to_string(),
+                key: "role".to_string(),
+                value: "web".to_string(),
+            }],
+        )])
+    }
+
+    fn entries() -> (Vec<Record>, HashMap<u64, Vec<Content>>) {
+        let mut mail = Record::new("Mail".to_string(), "john".to_string(), Category::Login);
+        mail.set_id(1);
+        let mut bank = Record::new("Bank".to_string(), String::new(), Category::Other);
+        bank.set_id(2);
+        bank.set_locked(true);
+        let content = HashMap::from([(
+            1,
+            vec![
+                Content::new(
+                    "Password".to_string(),
+                    0,
+                    true,
+                    Value::Password(Password::new("secret".to_string())),
+                ),
+                Content::new(
+                    "TOTP".to_string(),
+                    1,
+                    false,
+                    Value::TOTPSecret(
+                        TOTPSecret::new("JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP".to_string()).unwrap(),
+                    ),
+                ),
+                Content::new(
+                    "Question".to_string(),
+                    2,
+                    false,
+                    Value::QuestionAnswer(
+                        QuestionAnswer::new("Pet?".to_string(), "Rex".to_string()).unwrap(),
+                    ),
+                ),
+            ],
+        )]);
+        (vec![mail, bank], content)
+    }
+
+    #[test]
+    fn test_collect() {
+        let (records, content) = entries();
+        let (archived, locked) = collect(&records, &content, &HashMap::new(), false);
+        assert_eq!((archived.len(), locked), (1, 1));
+        assert_eq!(archived[0].content.len(), 3);
+        assert_eq!(archived[0].content[0].value, "secret");
+        assert!(archived[0].metadata.is_empty());
+        let (archived, locked) = collect(&records, &content, &metadata(), true);
+        assert_eq!((archived.len(), locked), (2, 0));
+        assert!(archived[1].content.is_empty());
+        assert_eq!(archived[0].metadata, metadata()[&1]);
+    }
+    #[test]
+    fn test_seal_open() {
+        let archive = seal(b"[]", "passphrase", fast()).unwrap();
+        assert!(archive.starts_with(MAGIC));
+        assert_eq!(open(&archive, "passphrase").unwrap().as_slice(), b"[]");
+        assert_eq!(open(&archive, "wrong").err(), Some(INVALID_ARCHIVE));
+
+        // Ciphertext and header are both authenticated
+        for index in [HEADER_LENGTH - 1, archive.len() - 1] {
+            let mut modified = archive.clone();
+            modified[index] ^= 1;
+            assert_eq!(open(&modified, "passphrase").err(), Some(INVALID_ARCHIVE));
+        }
+        let mut version = archive.clone();
+        version[MAGIC.len()] = 2;
+        assert_eq!(
+            open(&version, "passphrase").err(),
+            Some("Unsupported archive version")
+        );
+        assert_eq!(
+            open(b"title,password", "passphrase").err(),
+            Some("Not an encrypted archive")
+        );
+    }
+    #[test]
+    fn test_read_import() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("vault.archive");
+        let (records, content) = entries();
+        let (archived, _) = collect(&records, &content, &metadata(), true);
+        let json = serde_json::to_vec(&archived).unwrap();
+        fs::write(&path, seal(&json, "passphrase", fast()).unwrap()).unwrap();
+        assert_eq!(
+            write(&path, "passphrase", &archived).err(),
+            Some("File already exists")
+        );
+        assert_eq!(
+            write(&directory.path().join("new"), " ", &archived).err(),
+            Some("Passphrase can not be empty")
+        );
+
+        let entries = read(&path, "passphrase").unwrap();
+        assert_eq!(entries.len(), 2);
+        let (record, archived_content, archived_metadata) = &entries[0];
+        assert_eq!(record.title(), "Mail");
+        assert_eq!(record.created(), records[0].created());
+        assert_eq!(archived_content, &content[&1]);
+        assert_eq!(archived_metadata, &metadata()[&1]);
+        assert!(entries[1].0.locked());
+        assert_eq!(read(&path, "wrong").err(), Some(INVALID_ARCHIVE));
+
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        let summary = import(&database, &entries).unwrap();
+        assert_eq!((summary.imported, summary.skipped.len()), (2, 0));
+        let summary = import(&database, &entries).unwrap();
+        assert_eq!(summary.imported, 0);
+        assert_eq!(
+            summary.skipped,
+            vec![
+                (1, "Record already exists".to_string()),
+                (2, "Record already exists".to_string())
+            ]
+        );
+        let imported = database.get_all_records().unwrap();
+        assert_eq!(imported.len(), 2);
+        let mail = imported.iter().find(|record| record.title() == "Mail");
+        assert_eq!(
+            database
+                .get_record_metadata(mail.unwrap().id(), "ansible")
+                .unwrap()["role"],
+            "web"
+        );
+    }
+    #[test]
+    fn test_note_file_name() {
+        assert_eq!(note_file_name("Runbooks/Deploy"), "Runbooks_Deploy");
+        assert_eq!(note_file_name("../../etc/passwd"), "_.._etc_passwd");
+        assert_eq!(note_file_name(r"C:\Windows"), "C__Windows");
+        assert_eq!(note_file_name("Tab\tand \"quotes\"?"), "Tab_and _quotes__");
+        assert_eq!(note_file_name(" .hidden. "), "hidden");
+        assert_eq!(note_file_name("Zápisky 🦀"), "Zápisky 🦀");
+        assert_eq!(note_file_name("con"), "con_");
+        assert_eq!(note_file_name("NUL.txt"), "NUL.txt_");
+        assert_eq!(note_file_name("Console"), "Console");
+        assert_eq!(note_file_name(""), "Note");
+        assert_eq!(note_file_name("..."), "Note");
+        assert_eq!(
+            note_file_name(&"ž".repeat(150)).chars().count(),
+            MAX_NOTE_NAME_LENGTH
+        );
+    }
+    #[test]
+    fn test_note_markdown() {
+        let note = ArchiveNote {
+            title: "Deploy \"prod\"\n---".to_string(),
+            created: chrono::Utc::now(),
+            last_modified: chrono::Utc::now(),
+            text: "# Steps\n\n---\ntitle: not front matter\n".to_string(),
+        };
+        let markdown = note.to_markdown();
+        assert!(markdown.starts_with("---\ntitle: \"Deploy \\\"prod\\\"\\n---\"\ncreated: "));
+        let read = ArchiveNote::from_markdown(&markdown).unwrap();
+        assert_eq!(read.title, note.title);
+        assert_eq!(read.created, note.created);
+        assert_eq!(read.last_modified, note.last_modified);
+        assert_eq!(read.text, note.text);
+        assert_eq!(
+            ArchiveNote::from_markdown("# Steps").err(),
+            Some("Invalid note")
+        );
+        assert_eq!(
+            ArchiveNote::from_markdown("---\ntitle: \"Deploy\"\n---\n").err(),
+            Some("Invalid note")
+        );
+    }
+    #[test]
+    fn test_notes_round_trip() {
+        let directory = TempDir::new().unwrap();
+        let notes_directory = directory.path().join("notes");
+        fs::create_dir(&notes_directory).unwrap();
+        let note = |id: u64, title: &str, locked: bool| {
+            let mut record = Record::new(title.to_string(), String::new(), Category::Note);
+            record.set_id(id);
+            record.set_locked(locked);
+            record
+        };
+        let text = |text: &str| {
+            Content::new(
+                "Note".to_string(),
+                0,
+                true,
+                Value::LongText(LongText::new(text.to_string())),
+            )
+        };
+        let (mut records, _) = entries();
+        records.extend([
+            note(3, "Runbooks/Deploy", false),
+            note(4, "Runbooks/Deploy", false),
+            note(5, r"RUNBOOKS\DEPLOY", false),
+            note(6, "Zápisky 🦀", false),
+            note(7, "Secret", true),
+            note(8, "", false),
+        ]);
+        let content = HashMap::from([
+            (3, vec![text("1. Build\n2. Ship")]),
+            (4, vec![text("Second")]),
+            (5, vec![text("Third"), text("Appendix")]),
+            (6, vec![text("Příliš žluťoučký kůň")]),
+            (7, vec![text("hidden")]),
+        ]);
+        let (notes, locked) = collect_notes(&records, &content, false);
+        assert_eq!((notes.len(), locked), (5, 1));
+        assert_eq!(notes[2].text, "Third\n\nAppendix");
+        assert_eq!(collect_notes(&records, &content, true).0.len(), 6);
+
+        // Existing files are never overwritten
+        fs::write(notes_directory.join("Zápisky 🦀.md.archive"), "mine").unwrap();
+        let files = seal_notes(&notes_directory, "passphrase", &notes, fast()).unwrap();
+        let names: Vec<_> = files
+            .iter()
+            .map(|file| {
+                file.strip_prefix(&notes_directory)
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "Runbooks_Deploy.md.archive",
+                "Runbooks_Deploy (2).md.archive",
+                "RUNBOOKS_DEPLOY (3).md.archive",
+                "Zápisky 🦀 (2).md.archive",
+                "Note.md.archive"
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(notes_directory.join("Zápisky 🦀.md.archive")).unwrap(),
+            "mine"
+        );
+        assert!(fs::read(&files[0]).unwrap().starts_with(MAGIC));
+        assert_eq!(
+            seal_notes(&notes_directory, " ", &notes, fast()).err(),
+            Some("Passphrase can not be empty")
+        );
+        assert_eq!(
+            seal_notes(
+                &directory.path().join("missing"),
+                "passphrase",
+                &notes,
+                fast()
+            )
+            .err(),
+            Some("Directory does not exist")
+        );
+
+        fs::remove_file(notes_directory.join("Zápisky 🦀.md.archive")).unwrap();
+        fs::write(notes_directory.join("README.md"), "not a note").unwrap();
+        assert_eq!(
+            read_notes(&notes_directory, "wrong").err(),
+            Some(INVALID_ARCHIVE)
+        );
+        let entries = read_notes(&notes_directory, "passphrase").unwrap();
+        let read: Vec<_> = entries
+            .iter()
+            .map(|(record, content, _)| {
+                (
+                    record.title(),
+                    record.category(),
+                    record.created(),
+                    content
+                        .first()
+                        .map(|content| content.value().to_secret_string().expose_secret().clone()),
+                )
+            })
+            .collect();
+        assert_eq!(
+            read,
+            [
+                ("", &Category::Note, records[7].created(), None),
+                (
+                    r"RUNBOOKS\DEPLOY",
+                    &Category::Note,
+                    records[4].created(),
+                    Some("Third\n\nAppendix".to_string())
+                ),
+                (
+                    "Runbooks/Deploy",
+                    &Category::Note,
+                    records[3].created(),
+                    Some("Second".to_string())
+                ),
+                (
+                    "Runbooks/Deploy",
+                    &Category::Note,
+                    records[2].created(),
+                    Some("1. Build\n2. Ship".to_string())
+                ),
+                (
+                    "Zápisky 🦀",
+                    &Category::Note,
+                    records[5].created(),
+                    Some("Příliš žluťoučký kůň".to_string())
+                ),
+            ]
+        );
+
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        let summary = import(&database, &entries).unwrap();
+        assert_eq!((summary.imported, summary.skipped.len()), (5, 0));
+        assert_eq!(import(&database, &entries).unwrap().imported, 0);
+        let mut titles: Vec<String> = database
+            .get_all_records()
+            .unwrap()
+            .iter()
+            .map(|record| record.title().to_string())
+            .collect();
+        titles.sort();
+        assert_eq!(
+            titles,
+            [
+                "",
+                r"RUNBOOKS\DEPLOY",
+                "Runbooks/Deploy",
+                "Runbooks/Deploy",
+                "Zápisky 🦀"
+            ]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use std::fs;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+/// Argument passed to the application when it is started hidden at login.
+pub const HIDDEN_ARGUMENT: &str = "--hidden";
+
+/// Autostart status for the frontend.
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct AutostartStatus {
+    enabled: bool,
+    start_hidden: bool,
+}
+
+/// Autostart entry written directly to the OS location, no plugin is used.
+/// - macOS: LaunchAgent plist in ~/Library/LaunchAgents
+/// - Linux: XDG autostart .desktop file in ~/.config/autostart
+pub struct Autostart {
+    path: PathBuf,
+    name: String,
+    identifier: String,
+}
+
+impl Autostart {
+    /// Creates autostart for the application, entry path is based on the OS and the bundle identifier.
+    pub fn new(app_handle: &AppHandle) -> Option<Autostart> {
+        let config = app_handle.config();
+        let identifier = config.tauri.bundle.identifier.clone();
+        let name = config
+            .package
+            .product_name
+            .clone()
+            .unwrap_or(identifier.clone());
+
+        #[cfg(target_os = "macos")]
+        let path = tauri::api::path::home_dir()?
+            .join("Library")
+            .join("LaunchAgents")
+            .join(format!("{identifier}.plist"));
+        #[cfg(not(target_os = "macos"))]
+        let path = tauri::api::path::config_dir()?
+            .join("autostart")
+            .join(format!("{identifier}.desktop"));
+
+        Some(Autostart {
+            path,
+            name,
+            identifier,
+        })
+    }
+
+    /// Returns content of the autostart entry for the executable.
+    fn entry(&self, executable: &Path, start_hidden: bool) -> String {
+        if cfg!(target_os = "macos") {
+            launch_agent(&self.identifier, executable, start_hidden)
+        } else {
+            desktop_entry(&self.name, executable, start_hidden)
+        }
+    }
+
+    /// Writes the autostart entry for the executable.
+    /// # Errors
+    /// If the entry cannot be written
+    pub fn enable(&self, executable: &Path, start_hidden: bool) -> Result<(), &'static str> {
+        fs::create_dir_all(
+            self.path
+                .parent()
+                .ok_or("Failed to get autostart directory")?,
+        )
+        .map_err(|_| "Failed to create autostart directory")?;
+        fs::write(&self.path, self.entry(executable, start_hidden))
+            .map_err(|_| "Failed to write autostart entry")
+    }
+
+    /// Removes the autostart entry.
+    /// # Errors
+    /// If the entry exists and cannot be removed
+    pub fn disable(&self) -> Result<(), &'static str> {
+        if self.path.exists() {
+            fs::remove_file(&self.path).map_err(|_| "Failed to remove autostart entry")?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether the autostart entry exists and whether it starts the application hidden.
+    pub fn status(&self) -> AutostartStatus {
+        match fs::read_to_string(&self.path) {
+            Ok(entry) => AutostartStatus {
+                enabled: true,
+                start_hidden: entry.contains(HIDDEN_ARGUMENT),
+            },
+            Err(_) => AutostartStatus {
+                enabled: false,
+                start_hidden: false,
+            },
+        }
+    }
+
+    /// Rewrites the autostart entry if it does not point at the executable, e.g. after the application was updated or moved.
+    /// # Return
+    /// Returns `true` if the entry was rewritten.
+    /// # Errors
+    /// If the entry cannot be written
+    pub fn refresh(&self, executable: &Path) -> Result<bool, &'static str> {
+        let Ok(entry) = fs::read_to_string(&self.path) else {
+            return Ok(false);
+        };
+        let start_hidden = entry.contains(HIDDEN_ARGUMENT);
+        if entry == self.entry(executable, start_hidden) {
+            return Ok(false);
+        }
+        self.enable(executable, start_hidden)?;
+        Ok(true)
+    }
+}
+
+/// Returns XDG autostart .desktop entry. The executable is quoted as the Desktop Entry Specification requires.
+fn desktop_entry(name: &str, executable: &Path, start_hidden: bool) -> String {
+    let mut exec = String::from("\"");
+    for character in executable.to_string_lossy().chars() {
+        if ['"', '`', '$', '\\'].contains(&character) {
+            exec.push('\\');
+        }
+        exec.push(character);
+    }
+    exec.push('"');
+    if start_hidden {
+        exec.push(' ');
+        exec.push_str(HIDDEN_ARGUMENT);
+    }
+    format!(
+        "[Desktop Entry]\nType=Application\nName={name}\nExec={exec}\nTerminal=false\nX-GNOME-Autostart-enabled=true\n"
+    )
+}
+
+/// Returns macOS LaunchAgent plist starting the executable at login.
+fn launch_agent(identifier: &str, executable: &Path, start_hidden: bool) -> String {
+    let escape = |value: &str| {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let mut arguments = format!(
+        "\t\t<string>{}</string>\n",
+        escape(&executable.to_string_lossy())
+    );
+    if start_hidden {
+        arguments.push_str(&format!("\t\t<string>{HIDDEN_ARGUMENT}</string>\n"));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+        <plist version=\"1.0\">\n\
+        <dict>\n\
+        \t<key>Label</key>\n\
+        \t<string>{}</string>\n\
+        \t<key>ProgramArguments</key>\n\
+        \t<array>\n\
+        {arguments}\
+        \t</array>\n\
+        \t<key>RunAtLoad</key>\n\
+        \t<true/>\n\
+        </dict>\n\
+        </plist>\n",
+        escape(identifier)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn autostart(directory: &TempDir) -> Autostart {
+        Autostart {
+            path: directory.path().join("autostart").join("entry"),
+            name: "Password Manager".to_string(),
+            identifier: "cz.vsb.PasswordManager".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_desktop_entry() {
+        let entry = desktop_entry("Password Manager", Path::new("/opt/my \"app\"/run"), false);
+        assert!(entry.starts_with("[Desktop Entry]\n"));
+        assert!(entry.contains("Name=Password Manager\n"));
+        assert!(entry.contains("Exec=\"/opt/my \\\"app\\\"/run\"\n"));
+
+        let entry = desktop_entry("Password Manager", Path::new("/usr/bin/app"), true);
+        assert!(entry.contains("Exec=\"/usr/bin/app\" --hidden\n"));
+    }
+    #[test]
+    fn test_launch_agent() {
+        let entry = launch_agent(
+            "cz.vsb.PasswordManager",
+            Path::new("/Apps/A&B.app/run"),
+            true,
+        );
+        assert!(entry.contains("<string>cz.vsb.PasswordManager</string>"));
+        assert!(
+            entry.contains("<string>/Apps/A&amp;B.app/run</string>\n\t\t<string>--hidden</string>")
+        );
+        assert!(entry.contains("<key>RunAtLoad</key>\n\t<true/>"));
+    }
+    #[test]
+    fn test_autostart_enable_disable() {
+        let directory = TempDir::new().unwrap();
+        let autostart = autostart(&directory);
+        assert_eq!(
+            autostart.status(),
+            AutostartStatus {
+                enabled: false,
+                start_hidden: false
+            }
+        );
+
+        autostart.enable(Path::new("/usr/bin/app"), true).unwrap();
+        assert_eq!(
+            autostart.status(),
+            AutostartStatus {
+                enabled: true,
+                start_hidden: true
+            }
+        );
+
+        autostart.disable().unwrap();
+        assert!(autostart.status().enabled.not());
+        assert!(autostart.disable().is_ok());
+    }
+    #[test]
+    fn test_autostart_refresh() {
+        let directory = TempDir::new().unwrap();
+        let autostart = autostart(&directory);
+        assert_eq!(autostart.refresh(Path::new("/usr/bin/app")), Ok(false));
+        assert!(autostart.path.exists().not());
+
+        autostart.enable(Path::new("/old/app"), true).unwrap();
+        assert_eq!(autostart.refresh(Path::new("/old/app")), Ok(false));
+        assert_eq!(autostart.refresh(Path::new("/new/app")), Ok(true));
+        assert_eq!(
+            fs::read_to_string(&autostart.path).unwrap(),
+            autostart.entry(Path::new("/new/app"), true)
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use crate::build_info::{self, BuildInfo};
+use crate::database::model::{Category, Record};
+use crate::database::Database;
+use crate::error::Error;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Maximum number of iterations of one benchmark run.
+pub const MAX_ITERATIONS: u32 = 100;
+
+/// Minimum, median and maximum duration of a measured step in microseconds.
+#[derive(Debug, PartialEq, Clone, Copy, serde::Serialize)]
+pub struct Timing {
+    pub min: u64,
+    pub median: u64,
+    pub max: u64,
+}
+
+impl Timing {
+    /// Computes timing from measured durations. Returns zero timing if there are no samples.
+    fn from_samples(samples: &mut [Duration]) -> Timing {
+        samples.sort();
+        let micros = |duration: Option<&Duration>| {
+            duration
+                .map(|duration| duration.as_micros() as u64)
+                .unwrap_or_default()
+        };
+        Timing {
+            min: micros(samples.first()),
+            median: micros(samples.get(samples.len() / 2)),
+            max: micros(samples.last()),
+        }
+    }
+}
+
+/// Result of [`run`]. Contains only timings, never any record data.
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct Benchmark {
+    /// Build of the application that ran the benchmark.
+    pub build: BuildInfo,
+    pub iterations: u32,
+    /// Key derivation and opening of the database.
+    pub open: Timing,
+    /// Reading all records.
+    pub read_records: Timing,
+    /// Reading content of all records, one query per record.
+    pub read_content: Timing,
+    /// Reading content of all records with one query, see [`Database::get_all_content_grouped`].
+    pub read_content_grouped: Timing,
+    /// Writing and deleting a probe record. `None` if the database is read-only.
+    pub write_delete: Option<Timing>,
+}
+
+/// Measures opening of the database file, reading all records and their content and writing and deleting a probe record, `iterations` times. The file is opened read-only, so the lock of the open database is not affected. The probe record is written through the open database.
+/// # Errors
+/// If the number of iterations is not between 1 and [`MAX_ITERATIONS`], if the password is wrong or if any step fails.
+pub fn run(
+    path: &Path,
+    password: &str,
+    database: &Database,
+    iterations: u32,
+) -> Result<Benchmark, Error> {
+    if iterations == 0 || iterations > MAX_ITERATIONS {
+        return Err(Error::Validation("Invalid number of iterations".into()));
+    }
+
+    let capacity = iterations as usize;
+    let mut open = Vec::with_capacity(capacity);
+    let mut read_records = Vec::with_capacity(capacity);
+    let mut read_content = Vec::with_capacity(capacity);
+    let mut read_content_grouped = Vec::with_capacity(capacity);
+    let mut write_delete = Vec::with_capacity(capacity);
+
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let vault = Database::open_path_read_only(password, path, "Benchmark")?;
+        open.push(start.elapsed());
+
+        let start = Instant::now();
+        let records = vault.get_all_records()?;
+        read_records.push(start.elapsed());
+
+        let start = Instant::now();
+        for record in &records {
+            vault.get_all_content_for_record(record.id())?;
+        }
+        read_content.push(start.elapsed());
+
+        let start = Instant::now();
+        vault.get_all_content_grouped()?;
+        read_content_grouped.push(start.elapsed());
+
+        if database.read_only_reason().is_none() {
+            let start = Instant::now();
+            let mut probe = Record::new(
+                "Benchmark probe".to_string(),
+                "".to_string(),
+                Category::Other,
+            );
+            database.save_record_with_content(&mut probe, &mut [])?;
+            database.delete_record(probe)?;
+            write_delete.push(start.elapsed());
+        }
+    }
+
+    let write_delete = if write_delete.is_empty() {
+        None
+    } else {
+        Some(Timing::from_samples(&mut write_delete))
+    };
+    Ok(Benchmark {
+        build: build_info::current(),
+        iterations,
+        open: Timing::from_samples(&mut open),
+        read_records: Timing::from_samples(&mut read_records),
+        read_content: Timing::from_samples(&mut read_content),
+        read_content_grouped: Timing::from_samples(&mut read_content_grouped),
+        write_delete,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::model::{value, Content, Value};
+    use tempfile::TempDir;
+
+    fn seeded(directory: &TempDir) -> (std::path::PathBuf, Database) {
+        let path = directory.path().join("database");
+        let database = Database::open_path("password", &path).unwrap();
+        for index in 0..20 {
+            let mut record = Record::new(format!("Record {index}"), "".to_string(), Category::Note);
+            let mut content = vec![Content::new(
+                "Note".to_string(),
+                0,
+                true,
+                Value::LongText(value::LongText::new("a".repeat(2000))),
+            )];
+            database
+                .save_record_with_content(&mut record, &mut content)
+                .unwrap();
+        }
+        (path, database)
+    }
+
+    fn assert_monotonic(timing: &Timing) {
+        assert!(timing.min <= timing.median);
+        assert!(timing.median <= timing.max);
+    }
+
+    #[test]
+    fn test_timing_from_samples() {
+        let mut samples = [3, 1, 2].map(Duration::from_micros);
+        assert_eq!(
+            Timing::from_samples(&mut samples),
+            Timing {
+                min: 1,
+                median: 2,
+                max: 3
+            }
+        );
+        assert_eq!(
+            Timing::from_samples(&mut []),
+            Timing {
+                min: 0,
+                median: 0,
+                max: 0
+            }
+        );
+    }
+    #[test]
+    fn test_run() {
+        let directory = TempDir::new().unwrap();
+        let (path, database) = seeded(&directory);
+
+        let benchmark = run(&path, "password", &database, 3).unwrap();
+        assert_eq!(benchmark.iterations, 3);
+        assert_monotonic(&benchmark.open);
+        assert_monotonic(&benchmark.read_records);
+        assert_monotonic(&benchmark.read_content);
+        assert_monotonic(&benchmark.read_content_grouped);
+        assert_monotonic(&benchmark.write_delete.unwrap());
+        assert!(benchmark.open.max > 0);
+        assert_eq!(database.get_all_records().unwrap().len(), 20);
+
+        let json = serde_json::to_value(&benchmark).unwrap();
+        let mut keys: Vec<&String> = json.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "build",
+                "iterations",
+                "open",
+                "read_content",
+                "read_content_grouped",
+                "read_records",
+                "write_delete"
+            ]
+        );
+        assert!(json["open"]["median"].is_u64());
+        assert_eq!(json["build"]["version"], env!("CARGO_PKG_VERSION"));
+    }
+    #[test]
+    fn test_run_invalid() {
+        let directory = TempDir::new().unwrap();
+        let (path, database) = seeded(&directory);
+        assert_eq!(
+            run(&path, "password", &database, 0),
+            Err(Error::Validation("Invalid number of iterations".into()))
+        );
+        assert_eq!(
+            run(&path, "password", &database, MAX_ITERATIONS + 1),
+            Err(Error::Validation("Invalid number of iterations".into()))
+        );
+        assert_eq!(
+            run(&path, "wrong", &database, 1),
+            Err(Error::InvalidPassword)
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+
+/// Version, git commit and build date of the application, set at compile time by `build.rs`.
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// Output of `git describe`, or "unknown" if the application was built without git.
+    pub git: &'static str,
+    pub built: Option<DateTime<Utc>>,
+}
+
+/// Returns build information of the running application.
+pub fn current() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git: env!("BUILD_GIT_DESCRIBE"),
+        built: env!("BUILD_TIMESTAMP")
+            .parse::<i64>()
+            .ok()
+            .and_then(|timestamp| DateTime::from_timestamp(timestamp, 0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current() {
+        let info = current();
+        assert!(semver::Version::parse(info.version).is_ok());
+        assert!(!info.git.is_empty());
+        assert!(info.built.is_some_and(|built| built <= Utc::now()));
+    }
+}
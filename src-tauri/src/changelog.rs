@@ -0,0 +1,141 @@
+use crate::database::Database;
+use serde::{Deserialize, Serialize};
+
+/// Setting with the last version whose changes were seen by the user.
+pub const LAST_SEEN_VERSION: &str = "last_seen_version";
+
+/// Changelog embedded at compile time.
+const CHANGELOG: &str = include_str!("../changelog.toml");
+
+/// Release with its changes.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Release {
+    version: semver::Version,
+    date: Option<chrono::NaiveDate>,
+    entries: Vec<String>,
+}
+
+/// Changelog file structure.
+#[derive(Deserialize)]
+struct Changelog {
+    release: Vec<Release>,
+}
+
+/// Parses changelog in TOML format. Releases are returned sorted by version, the newest first.
+/// # Errors
+/// If the changelog is not valid
+pub fn parse(data: &str) -> Result<Vec<Release>, &'static str> {
+    let mut releases = toml::from_str::<Changelog>(data)
+        .map_err(|_| "Invalid changelog")?
+        .release;
+    releases.sort_by(|a, b| b.version.cmp(&a.version));
+    Ok(releases)
+}
+
+/// Returns releases from the embedded changelog, the newest first.
+pub fn releases() -> Vec<Release> {
+    parse(CHANGELOG).unwrap_or_default()
+}
+
+/// Returns releases newer than the given version. If there is no version, all releases are returned.
+pub fn since(releases: Vec<Release>, version: Option<&semver::Version>) -> Vec<Release> {
+    releases
+        .into_iter()
+        .filter(|release| match version {
+            Some(version) => release.version > *version,
+            None => true,
+        })
+        .collect()
+}
+
+/// Checks if there are releases up to the current version that the user has not seen yet. If no version was seen yet (new database), the current version is saved as seen and nothing is shown.
+pub fn show_whats_new(database: &Database, current: &semver::Version) -> bool {
+    let last_seen = database
+        .get_setting(LAST_SEEN_VERSION)
+        .ok()
+        .and_then(|version| semver::Version::parse(version.expose_secret()).ok());
+    match last_seen {
+        Some(last_seen) => since(releases(), Some(&last_seen))
+            .iter()
+            .any(|release| release.version <= *current),
+        None => {
+            database
+                .save_setting(LAST_SEEN_VERSION, &current.to_string())
+                .unwrap_or_default();
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ops::Not;
+
+    const DATA: &str = r#"
+        [[release]]
+        version = "1.0.0"
+        date = "2024-04-01"
+        entries = ["First"]
+
+        [[release]]
+        version = "1.2.0"
+        entries = ["Third"]
+
+        [[release]]
+        version = "1.1.0"
+        date = "2024-06-01"
+        entries = ["Second", "Fix"]
+    "#;
+
+    fn versions(releases: &[Release]) -> Vec<String> {
+        releases
+            .iter()
+            .map(|release| release.version.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let releases = parse(DATA).unwrap();
+        assert_eq!(versions(&releases), vec!["1.2.0", "1.1.0", "1.0.0"]);
+        assert_eq!(releases[1].entries, vec!["Second", "Fix"]);
+        assert_eq!(releases[0].date, None);
+        assert_eq!(
+            releases[2].date,
+            chrono::NaiveDate::from_ymd_opt(2024, 4, 1)
+        );
+    }
+    #[test]
+    fn test_parse_invalid() {
+        assert!(parse(
+            r#"[[release]]
+            version = "1.0"
+            entries = []"#
+        )
+        .is_err());
+        assert!(parse("release = 1").is_err());
+    }
+    #[test]
+    fn test_since() {
+        let releases = parse(DATA).unwrap();
+        let version = semver::Version::new(1, 0, 0);
+        assert_eq!(
+            versions(&since(releases.clone(), Some(&version))),
+            vec!["1.2.0", "1.1.0"]
+        );
+        let version = semver::Version::parse("1.2.0-beta.1").unwrap();
+        assert_eq!(
+            versions(&since(releases.clone(), Some(&version))),
+            vec!["1.2.0"]
+        );
+        let version = semver::Version::new(1, 2, 0);
+        assert!(since(releases.clone(), Some(&version)).is_empty());
+        assert_eq!(since(releases, None).len(), 3);
+    }
+    #[test]
+    fn test_embedded_changelog() {
+        assert!(parse(CHANGELOG).is_ok());
+        assert!(releases().is_empty().not());
+    }
+}
@@ -0,0 +1,65 @@
+use sha1::{Digest, Sha1};
+use std::ops::Not;
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+/// Hash of the text the application last copied to the clipboard for tauri state, so the clipboard is cleared only while it still holds that text. Only the hash is kept, never the text.
+#[derive(Default)]
+pub struct ClipboardOwner(Mutex<Option<Vec<u8>>>);
+
+fn hash(text: &str) -> Vec<u8> {
+    Sha1::digest(text.as_bytes()).to_vec()
+}
+
+impl ClipboardOwner {
+    /// Records the text the application copied to the clipboard.
+    pub fn copied(&self, text: &str) {
+        if let Ok(mut guard) = self.0.lock() {
+            *guard = Some(hash(text));
+        }
+    }
+
+    /// Returns true if the text is the one the application copied last.
+    pub fn owns(&self, text: &str) -> bool {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|guard| guard.clone())
+            .is_some_and(|copied| copied == hash(text))
+    }
+
+    /// Clears the clipboard if it still holds the text the application copied last. Text copied by other applications is left alone.
+    /// # Return
+    /// Returns true if the clipboard was cleared.
+    /// # Errors
+    /// If the clipboard is not available or cannot be cleared.
+    pub fn clear(&self) -> Result<bool, &'static str> {
+        let mut clipboard = arboard::Clipboard::new().map_err(|_| "Clipboard is not available")?;
+        let current = Zeroizing::new(clipboard.get_text().unwrap_or_default());
+        if self.owns(&current).not() {
+            return Ok(false);
+        }
+        clipboard.clear().map_err(|_| "Failed to clear clipboard")?;
+        if let Ok(mut guard) = self.0.lock() {
+            *guard = None;
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_owns() {
+        let owner = ClipboardOwner::default();
+        assert!(!owner.owns(""));
+        owner.copied("secret");
+        assert!(owner.owns("secret"));
+        assert!(!owner.owns("copied by another application"));
+        owner.copied("123456");
+        assert!(!owner.owns("secret"));
+        assert!(owner.owns("123456"));
+    }
+}
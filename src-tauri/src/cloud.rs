@@ -1,81 +1,435 @@
-use crate::database::{Database, DATABASE_FILE_NAME};
+use crate::database::model::SecretValue;
+use crate::database::{Database, VaultSecret, DATABASE_FILE_NAME};
+use crate::error::Error;
+use secrecy::{ExposeSecret, SecretString};
 use ssh2::Session;
 use std::fs::File;
+use std::io::{Read, Write};
 use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::ops::Not;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager};
 use tokio::sync::Semaphore;
 
+/// Error returned when cloud is enabled, but its credentials are missing, e.g. after the application was terminated while enabling cloud.
+pub const MISCONFIGURED: &str = "Cloud is not configured";
+
+/// Error returned when the downloaded database cannot be opened with the master password of the local one, e.g. after the password was changed on another device.
+pub const DIFFERENT_PASSWORD: &str = "Cloud copy uses a different master password";
+
+/// Settings with cloud address and username.
+const CREDENTIAL_SETTINGS: [&str; 2] = ["cloud_address", "cloud_username"];
+
+/// Settings with cloud authentication, see [`SshAuth`].
+const AUTH_SETTINGS: [&str; 3] = ["cloud_password", "cloud_private_key", "cloud_passphrase"];
+
+/// Authentication to the SSH server, shared by cloud sync and one-off transfers, see [`connect`].
+#[derive(serde::Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum SshAuth {
+    Password {
+        password: SecretString,
+    },
+    /// Private key file, the public key is derived from it.
+    PrivateKey {
+        private_key: PathBuf,
+        passphrase: Option<SecretString>,
+    },
+}
+
+impl SshAuth {
+    /// Returns the settings the authentication is saved as, see [`SshAuth::from_database`].
+    /// # Errors
+    /// If the path of the private key is not valid UTF-8.
+    fn settings(&self) -> Result<Vec<(&'static str, &str)>, Error> {
+        Ok(match self {
+            SshAuth::Password { password } => vec![(AUTH_SETTINGS[0], password.expose_secret())],
+            SshAuth::PrivateKey {
+                private_key,
+                passphrase,
+            } => {
+                let mut settings = vec![(
+                    AUTH_SETTINGS[1],
+                    private_key
+                        .to_str()
+                        .ok_or(Error::Validation("Invalid private key path".into()))?,
+                )];
+                if let Some(passphrase) = passphrase {
+                    settings.push((AUTH_SETTINGS[2], passphrase.expose_secret()));
+                }
+                settings
+            }
+        })
+    }
+
+    /// Loads the authentication saved by [`CloudManager::enable`], a private key takes precedence over a password.
+    /// # Errors
+    /// Returns [`MISCONFIGURED`] if neither is saved.
+    fn from_database(database: &Database) -> Result<SshAuth, Error> {
+        let load = |name: &str| match database.get_setting(name) {
+            Ok(value) => Ok(Some(SecretString::new(value.expose_secret().to_string()))),
+            Err(Error::NotFound(_)) => Ok(None),
+            Err(error) => Err(error),
+        };
+        if let Some(private_key) = load(AUTH_SETTINGS[1])? {
+            return Ok(SshAuth::PrivateKey {
+                private_key: PathBuf::from(private_key.expose_secret()),
+                passphrase: load(AUTH_SETTINGS[2])?,
+            });
+        }
+        Ok(SshAuth::Password {
+            password: load(AUTH_SETTINGS[0])?.ok_or(Error::Other(MISCONFIGURED))?,
+        })
+    }
+}
+
 /// Semaphore for [`CloudManager`].
 static SEM: Semaphore = Semaphore::const_new(1);
 
+/// Prevents cloud transfers until the returned permit is dropped.
+/// # Errors
+/// If a transfer is in progress.
+pub fn pause_transfers() -> Result<tokio::sync::SemaphorePermit<'static>, Error> {
+    SEM.try_acquire()
+        .map_err(|_| Error::Other("Cloud transfer is in progress"))
+}
+
+/// Waits until no cloud transfer is in progress and prevents new ones until the returned permit is dropped.
+/// # Errors
+/// If the semaphore is closed.
+pub async fn transfer_permit() -> Result<tokio::sync::SemaphorePermit<'static>, Error> {
+    SEM.acquire()
+        .await
+        .map_err(|_| Error::Other("Failed to acquire permit"))
+}
+
+/// Returns address, username and authentication of the cloud saved in the database.
+/// # Errors
+/// Returns [`MISCONFIGURED`] if any of them is missing.
+pub fn credentials(database: &Database) -> Result<(SecretValue, SecretValue, SshAuth), Error> {
+    let load = |name: &str| {
+        database.get_setting(name).map_err(|error| match error {
+            Error::NotFound(_) => Error::Other(MISCONFIGURED),
+            error => error,
+        })
+    };
+    Ok((
+        load(CREDENTIAL_SETTINGS[0])?,
+        load(CREDENTIAL_SETTINGS[1])?,
+        SshAuth::from_database(database)?,
+    ))
+}
+
+/// Returns path of the backup of the database file, kept by [`CloudManager::download`].
+fn backup_path(database_path: &Path) -> PathBuf {
+    database_path.with_file_name(format!("{}.backup", DATABASE_FILE_NAME))
+}
+
+/// Checks that the downloaded database opens with the password or the derived key, see [`Database::open_path_read_only_with_secret`].
+/// # Errors
+/// Returns [`DIFFERENT_PASSWORD`] if the secret is not valid for the downloaded database.
+fn verify_download(path: &Path, secret: &VaultSecret) -> Result<(), Error> {
+    Database::open_path_read_only_with_secret(secret, path, "Verification")
+        .map(|_| ())
+        .map_err(|error| match error {
+            Error::InvalidPassword => Error::Other(DIFFERENT_PASSWORD),
+            error => error,
+        })
+}
+
+/// Replaces the database file with the content of `source`. The content is first written to a temporary file and the database is replaced only if exactly `size` bytes were copied and `verify` accepts the temporary file. The previous database is kept as a backup.
+/// # Errors
+/// If the copy fails, has a different size or is rejected by `verify`. The database file is left in place.
+fn replace_database(
+    source: &mut impl Read,
+    size: u64,
+    database_path: &Path,
+    verify: impl FnOnce(&Path) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let temporary_path = database_path.with_file_name(format!("{}.download", DATABASE_FILE_NAME));
+    let copy = || -> Result<(), Error> {
+        let mut temporary =
+            File::create(&temporary_path).map_err(Error::io("Failed to create local file"))?;
+        let copied =
+            std::io::copy(source, &mut temporary).map_err(Error::io("Failed to copy file"))?;
+        temporary
+            .flush()
+            .map_err(Error::io("Failed to copy file"))?;
+        temporary
+            .sync_all()
+            .map_err(Error::io("Failed to copy file"))?;
+        if copied != size {
+            return Err(Error::Network("Downloaded file is incomplete"));
+        }
+        drop(temporary);
+        verify(&temporary_path)
+    };
+    if let Err(error) = copy() {
+        std::fs::remove_file(&temporary_path).unwrap_or_default();
+        return Err(error);
+    }
+
+    let backup_path = backup_path(database_path);
+    if database_path.exists() {
+        std::fs::rename(database_path, &backup_path)
+            .map_err(Error::io("Failed to create backup"))?;
+    }
+    if std::fs::rename(&temporary_path, database_path).is_err() {
+        std::fs::rename(&backup_path, database_path).unwrap_or_default();
+        return Err(Error::Other("Failed to replace local file"));
+    }
+    Ok(())
+}
+
+/// Restores the database from the backup if the database file is missing, e.g. after an interrupted download.
+/// # Return
+/// True if the database was restored.
+/// # Errors
+/// If the backup cannot be renamed.
+pub fn restore_backup(database_path: &Path) -> Result<bool, Error> {
+    let backup_path = backup_path(database_path);
+    if database_path.exists() || backup_path.exists().not() {
+        return Ok(false);
+    }
+    std::fs::rename(backup_path, database_path).map_err(Error::io("Failed to restore backup"))?;
+    Ok(true)
+}
+
+/// Connects to the SSH server with the password or the private key. The address is an IP address with an optional port, 22 by default.
+pub fn connect(address: &str, username: &str, auth: &SshAuth) -> Result<Session, Error> {
+    let mut session = Session::new().map_err(|_| Error::Network("Failed to initialize session"))?;
+    session.set_tcp_stream(
+        TcpStream::connect_timeout(
+            &SocketAddr::from_str(address)
+                .or_else(|_| IpAddr::from_str(address).map(|ip| SocketAddr::new(ip, 22)))
+                .map_err(|_| Error::Validation("Invalid address".into()))?,
+            Duration::from_secs(5),
+        )
+        .map_err(|_| Error::Network("Failed to connect"))?,
+    );
+    session
+        .handshake()
+        .map_err(|_| Error::Network("Handshake failed"))?;
+    match auth {
+        SshAuth::Password { password } => {
+            session.userauth_password(username, password.expose_secret())
+        }
+        SshAuth::PrivateKey {
+            private_key,
+            passphrase,
+        } => session.userauth_pubkey_file(
+            username,
+            None,
+            private_key,
+            passphrase
+                .as_ref()
+                .map(|passphrase| passphrase.expose_secret().as_str()),
+        ),
+    }
+    .map_err(|_| Error::Network("Wrong credentials"))?;
+
+    Ok(session)
+}
+
+/// Returns path of the database in the cloud.
+fn cloud_database_path() -> PathBuf {
+    PathBuf::from("PasswordManager").join(DATABASE_FILE_NAME)
+}
+
+/// Returns the last modified time of the cloud database, None if it does not exist.
+pub fn cloud_m_time(session: &Session) -> Result<Option<i64>, Error> {
+    let sftp = session
+        .sftp()
+        .map_err(|_| Error::Network("Failed to initialize sftp"))?;
+    let path = cloud_database_path();
+    if sftp.open(path.as_path()).is_err() {
+        return Ok(None);
+    }
+    Ok(Some(
+        sftp.stat(path.as_path())
+            .map_err(|_| Error::Network("Failed to get cloud metadata"))?
+            .mtime
+            .ok_or(Error::Network("Failed to get cloud mtime"))? as i64,
+    ))
+}
+
+/// Downloads the cloud database and replaces the database file, see [`replace_database`]. The database is replaced only if the downloaded one opens with the password or the derived key. Progress is reported with the number of bytes received and the total. The caller holds the transfer permit, see [`transfer_permit`].
+/// # Errors
+/// Returns [`DIFFERENT_PASSWORD`] if the cloud copy uses a different master password. The database file is left in place.
+pub fn download_database(
+    session: &Session,
+    database_path: &Path,
+    secret: &VaultSecret,
+    progress: impl FnMut(u64, u64),
+) -> Result<(), Error> {
+    let sftp = session
+        .sftp()
+        .map_err(|_| Error::Network("Failed to initialize sftp"))?;
+    let path = cloud_database_path();
+    let total = sftp
+        .stat(path.as_path())
+        .map_err(|_| Error::Network("Failed to get cloud metadata"))?
+        .size
+        .ok_or(Error::Network("Failed to get cloud file size"))?;
+    let mut reader = ProgressReader {
+        inner: sftp
+            .open(path.as_path())
+            .map_err(|_| Error::Network("Failed to open cloud file"))?,
+        read: 0,
+        total,
+        progress,
+    };
+    replace_database(&mut reader, total, database_path, |path| {
+        verify_download(path, secret)
+    })
+}
+
+/// Remote file system for one-off transfers, see [`push`] and [`pull`]. Implemented by SFTP, so the transfers do not depend on a session.
+pub trait RemoteStorage {
+    /// Returns size of the remote file in bytes.
+    fn size(&self, path: &Path) -> Result<u64, Error>;
+    /// Creates the remote file for writing, an existing file is truncated.
+    fn create(&self, path: &Path) -> Result<Box<dyn Write + '_>, Error>;
+    /// Opens the remote file for reading.
+    fn open(&self, path: &Path) -> Result<Box<dyn Read + '_>, Error>;
+}
+
+impl RemoteStorage for ssh2::Sftp {
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        self.stat(path)
+            .map_err(|_| Error::Network("Failed to get remote metadata"))?
+            .size
+            .ok_or(Error::Network("Failed to get remote file size"))
+    }
+
+    fn create(&self, path: &Path) -> Result<Box<dyn Write + '_>, Error> {
+        Ok(Box::new(ssh2::Sftp::create(self, path).map_err(|_| {
+            Error::Network("Failed to create remote file")
+        })?))
+    }
+
+    fn open(&self, path: &Path) -> Result<Box<dyn Read + '_>, Error> {
+        Ok(Box::new(ssh2::Sftp::open(self, path).map_err(|_| {
+            Error::Network("Failed to open remote file")
+        })?))
+    }
+}
+
+/// Reader which reports the number of bytes read so far and the total.
+struct ProgressReader<R, F> {
+    inner: R,
+    read: u64,
+    total: u64,
+    progress: F,
+}
+
+impl<R: Read, F: FnMut(u64, u64)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        let length = self.inner.read(buffer)?;
+        self.read += length as u64;
+        (self.progress)(self.read, self.total);
+        Ok(length)
+    }
+}
+
+/// Copies the database file to `remote_path`, independent of cloud sync. Progress is reported with the number of bytes sent and the total.
+/// # Errors
+/// If the file cannot be copied or the remote file has a different size afterwards.
+pub fn push(
+    storage: &impl RemoteStorage,
+    database_path: &Path,
+    remote_path: &Path,
+    progress: impl FnMut(u64, u64),
+) -> Result<(), Error> {
+    let local = File::open(database_path).map_err(Error::io("Failed to open local file"))?;
+    let total = local
+        .metadata()
+        .map_err(Error::io("Failed to get local metadata"))?
+        .len();
+    let mut reader = ProgressReader {
+        inner: local,
+        read: 0,
+        total,
+        progress,
+    };
+    {
+        let mut remote = storage.create(remote_path)?;
+        std::io::copy(&mut reader, &mut remote).map_err(Error::io("Failed to copy file"))?;
+        remote.flush().map_err(Error::io("Failed to copy file"))?;
+    }
+    if storage.size(remote_path)? != total {
+        return Err(Error::Network("Uploaded file is incomplete"));
+    }
+    Ok(())
+}
+
+/// Replaces the database file with the file at `remote_path`, independent of cloud sync. The database is replaced only if the pulled one opens with the secret, usually the key of the open vault, see [`Database::vault_secret`]. The previous database is kept as a backup, see [`replace_database`]. Progress is reported with the number of bytes received and the total.
+/// # Errors
+/// If the file cannot be copied or is incomplete. Returns [`DIFFERENT_PASSWORD`] if the pulled file does not open with the secret. The database file is left in place.
+pub fn pull(
+    storage: &impl RemoteStorage,
+    remote_path: &Path,
+    database_path: &Path,
+    secret: &VaultSecret,
+    progress: impl FnMut(u64, u64),
+) -> Result<(), Error> {
+    let total = storage.size(remote_path)?;
+    let mut reader = ProgressReader {
+        inner: storage.open(remote_path)?,
+        read: 0,
+        total,
+        progress,
+    };
+    replace_database(&mut reader, total, database_path, |path| {
+        verify_download(path, secret)
+    })
+}
+
 pub struct CloudManager<'a> {
     session: Session,
     app_handle: &'a AppHandle,
 }
 
 impl<'a> CloudManager<'a> {
-    fn connect(address: &str, username: &str, password: &str) -> Result<Session, &'static str> {
-        let mut session = Session::new().map_err(|_| "Failed to initialize session")?;
-        session.set_tcp_stream(
-            TcpStream::connect_timeout(
-                &SocketAddr::from_str(address)
-                    .or_else(|_| IpAddr::from_str(address).map(|ip| SocketAddr::new(ip, 22)))
-                    .map_err(|_| "Invalid address")?,
-                Duration::from_secs(5),
-            )
-            .map_err(|_| "Failed to connect")?,
-        );
-        session.handshake().map_err(|_| "Handshake failed")?;
-        session
-            .userauth_password(username, password)
-            .map_err(|_| "Wrong credentials")?;
-
-        Ok(session)
-    }
-
     /// Connects to the cloud using the credentials from the database.
+    /// # Errors
+    /// Returns [`MISCONFIGURED`] if the credentials are missing.
     pub fn connect_from_database(
         database: &Database,
         app_handle: &'a AppHandle,
-    ) -> Result<CloudManager<'a>, &'static str> {
-        let address = database
-            .get_setting("cloud_address")
-            .map_err(|_| "Failed to load address")?;
-        let username = database
-            .get_setting("cloud_username")
-            .map_err(|_| "Failed to load username")?;
-        let password = database
-            .get_setting("cloud_password")
-            .map_err(|_| "Failed to load password")?;
+    ) -> Result<CloudManager<'a>, Error> {
+        let (address, username, auth) = credentials(database)?;
 
         Ok(CloudManager {
-            session: Self::connect(
-                address.expose_secret(),
-                username.expose_secret(),
-                password.expose_secret(),
-            )?,
+            session: connect(address.expose_secret(), username.expose_secret(), &auth)?,
             app_handle,
         })
     }
 
-    /// Enables cloud sync and saves the credentials.
+    /// Enables cloud sync and saves the credentials in a single transaction. Authentication saved before is deleted first, see [`SshAuth::from_database`].
     pub fn enable(
         address: &str,
         username: &str,
-        password: &str,
+        auth: &SshAuth,
         app_handle: &'a AppHandle,
         database: &Database,
-    ) -> Result<CloudManager<'a>, &'static str> {
-        let session = Self::connect(address, username, password)?;
-        let _ = session.sftp().map_err(|_| "Failed to initialize sftp")?;
-
-        database.save_setting("cloud", true.to_string().as_str())?;
-        database.save_setting("cloud_address", address)?;
-        database.save_setting("cloud_username", username)?;
-        database.save_setting("cloud_password", password)?;
+    ) -> Result<CloudManager<'a>, Error> {
+        let session = connect(address, username, auth)?;
+        let _ = session
+            .sftp()
+            .map_err(|_| Error::Network("Failed to initialize sftp"))?;
+
+        database.delete_settings(&AUTH_SETTINGS)?;
+        let cloud = true.to_string();
+        let mut settings = vec![
+            ("cloud", cloud.as_str()),
+            (CREDENTIAL_SETTINGS[0], address),
+            (CREDENTIAL_SETTINGS[1], username),
+        ];
+        settings.extend(auth.settings()?);
+        database.save_settings(&settings)?;
 
         Ok(CloudManager {
             session,
@@ -83,12 +437,16 @@ impl<'a> CloudManager<'a> {
         })
     }
 
-    pub fn disable(database: &Database) -> Result<(), &'static str> {
-        database.save_setting("cloud", false.to_string().as_str())?;
-        database.delete_setting("cloud_address")?;
-        database.delete_setting("cloud_username")?;
-        database.delete_setting("cloud_password")?;
-        Ok(())
+    /// Disables cloud sync and deletes the credentials in a single transaction. Missing `cloud` setting means disabled, see [`CloudManager::is_enabled`].
+    pub fn disable(database: &Database) -> Result<(), Error> {
+        database.delete_settings(&[
+            "cloud",
+            CREDENTIAL_SETTINGS[0],
+            CREDENTIAL_SETTINGS[1],
+            AUTH_SETTINGS[0],
+            AUTH_SETTINGS[1],
+            AUTH_SETTINGS[2],
+        ])
     }
 
     pub fn is_enabled(database: &Database) -> bool {
@@ -98,39 +456,23 @@ impl<'a> CloudManager<'a> {
     }
 
     /// Returns true if the cloud database exists.
-    pub fn exists(&self) -> Result<bool, &'static str> {
-        let sftp = self
-            .session
-            .sftp()
-            .map_err(|_| "Failed to initialize sftp")?;
-        let cloud_path = PathBuf::from("PasswordManager").join(DATABASE_FILE_NAME);
-        Ok(sftp.open(cloud_path.as_path()).is_ok())
+    pub fn exists(&self) -> Result<bool, Error> {
+        Ok(cloud_m_time(&self.session)?.is_some())
     }
 
     /// Returns the last modified time of the cloud database.
-    pub fn m_time(&self) -> Result<i64, &'static str> {
-        let sftp = self
-            .session
-            .sftp()
-            .map_err(|_| "Failed to initialize sftp")?;
-
-        let cloud_database_path = PathBuf::from("PasswordManager").join(DATABASE_FILE_NAME);
-
-        Ok(sftp
-            .stat(cloud_database_path.as_path())
-            .map_err(|_| "Failed to get cloud metadata")?
-            .mtime
-            .ok_or("Failed to get cloud mtime")? as i64)
+    pub fn m_time(&self) -> Result<i64, Error> {
+        cloud_m_time(&self.session)?.ok_or(Error::Network("Failed to get cloud metadata"))
     }
 
-    pub async fn upload(&self) -> Result<(), &'static str> {
+    pub async fn upload(&self) -> Result<(), Error> {
         let local_database_path =
             Database::path(self.app_handle).ok_or("Failed to get database path")?;
 
         let sftp = self
             .session
             .sftp()
-            .map_err(|_| "Failed to initialize sftp")?;
+            .map_err(|_| Error::Network("Failed to initialize sftp"))?;
 
         let cloud_folder = Path::new("PasswordManager");
 
@@ -139,9 +481,13 @@ impl<'a> CloudManager<'a> {
             .await
             .map_err(|_| "Failed to acquire permit")?;
 
+        if let Some(database) = self.app_handle.try_state::<Database>() {
+            database.checkpoint()?;
+        }
+
         if sftp.opendir(cloud_folder).is_err() {
             sftp.mkdir(cloud_folder, 0o755)
-                .map_err(|_| "Failed to create folder")?;
+                .map_err(|_| Error::Network("Failed to create folder"))?;
         }
 
         let cloud_database_path = PathBuf::from(cloud_folder).join(DATABASE_FILE_NAME);
@@ -154,58 +500,343 @@ impl<'a> CloudManager<'a> {
                 backup_path.as_path(),
                 Some(ssh2::RenameFlags::all()),
             )
-            .map_err(|_| "Failed to create backup")?;
+            .map_err(|_| Error::Network("Failed to create backup"))?;
         }
 
         let mut cloud_database = sftp
             .create(cloud_database_path.as_path())
-            .map_err(|_| "Failed to create cloud file")?;
+            .map_err(|_| Error::Network("Failed to create cloud file"))?;
 
         let mut local_database =
-            File::open(local_database_path).map_err(|_| "Failed to open local file")?;
+            File::open(local_database_path).map_err(Error::io("Failed to open local file"))?;
 
         std::io::copy(&mut local_database, &mut cloud_database)
-            .map_err(|_| "Failed to copy file")?;
+            .map_err(Error::io("Failed to copy file"))?;
 
         drop(semaphore);
 
         Ok(())
     }
+}
 
-    pub async fn download(&self) -> Result<(), &'static str> {
-        let sftp = self
-            .session
-            .sftp()
-            .map_err(|_| "Failed to initialize sftp")?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::SecretString;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    /// Reader which fails after the given number of bytes, like an interrupted transfer.
+    struct FailingReader {
+        remaining: usize,
+    }
 
-        let cloud_database_path = PathBuf::from("PasswordManager").join(DATABASE_FILE_NAME);
+    impl Read for FailingReader {
+        fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+            if self.remaining == 0 {
+                return Err(std::io::Error::other("Connection lost"));
+            }
+            let length = buffer.len().min(self.remaining);
+            buffer[..length].fill(1);
+            self.remaining -= length;
+            Ok(length)
+        }
+    }
 
-        let mut local_database_path =
-            Database::path(self.app_handle).ok_or("Failed to get database path")?;
+    /// In-memory remote storage. Files written through it keep at most `limit` bytes, like an interrupted upload that was not reported.
+    #[derive(Default)]
+    struct MemoryStorage {
+        files: RefCell<HashMap<PathBuf, Vec<u8>>>,
+        limit: Option<usize>,
+    }
 
-        let semaphore = SEM
-            .acquire()
-            .await
-            .map_err(|_| "Failed to acquire permit")?;
+    struct MemoryWriter<'a> {
+        storage: &'a MemoryStorage,
+        path: PathBuf,
+    }
 
-        let mut cloud_database = sftp
-            .open(cloud_database_path.as_path())
-            .map_err(|_| "Failed to open cloud file")?;
+    impl Write for MemoryWriter<'_> {
+        fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+            let mut files = self.storage.files.borrow_mut();
+            let file = files.entry(self.path.clone()).or_default();
+            let room = self.storage.limit.map_or(buffer.len(), |limit| {
+                limit.saturating_sub(file.len()).min(buffer.len())
+            });
+            file.extend_from_slice(&buffer[..room]);
+            Ok(buffer.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
 
-        let mut backup_path =
-            local_database_path.with_file_name(format!("{}.backup", DATABASE_FILE_NAME));
+    impl RemoteStorage for MemoryStorage {
+        fn size(&self, path: &Path) -> Result<u64, Error> {
+            self.files
+                .borrow()
+                .get(path)
+                .map(|file| file.len() as u64)
+                .ok_or(Error::Network("Failed to get remote metadata"))
+        }
+        fn create(&self, path: &Path) -> Result<Box<dyn Write + '_>, Error> {
+            self.files
+                .borrow_mut()
+                .insert(path.to_path_buf(), Vec::new());
+            Ok(Box::new(MemoryWriter {
+                storage: self,
+                path: path.to_path_buf(),
+            }))
+        }
+        fn open(&self, path: &Path) -> Result<Box<dyn Read + '_>, Error> {
+            let file = self
+                .files
+                .borrow()
+                .get(path)
+                .cloned()
+                .ok_or("Failed to open remote file")?;
+            Ok(Box::new(std::io::Cursor::new(file)))
+        }
+    }
 
-        std::fs::rename(&mut local_database_path, &mut backup_path)
-            .map_err(|_| "Failed to create backup")?;
+    fn setup() -> (TempDir, PathBuf) {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join(DATABASE_FILE_NAME);
+        std::fs::write(&path, b"original").unwrap();
+        (directory, path)
+    }
 
-        let mut local_database =
-            File::create(local_database_path).map_err(|_| "Failed to create local file")?;
+    #[test]
+    fn test_replace_database() {
+        let (_directory, path) = setup();
+        replace_database(&mut &b"downloaded"[..], 10, &path, |_| Ok(())).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"downloaded");
+        assert_eq!(std::fs::read(backup_path(&path)).unwrap(), b"original");
+    }
+    #[test]
+    fn test_replace_database_failed_copy() {
+        let (directory, path) = setup();
+        assert_eq!(
+            replace_database(&mut FailingReader { remaining: 4096 }, 8192, &path, |_| Ok(
+                ()
+            ))
+            .unwrap_err(),
+            "Failed to copy file"
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), b"original");
+        assert!(backup_path(&path).exists().not());
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 1);
+    }
+    #[test]
+    fn test_replace_database_incomplete() {
+        let (_directory, path) = setup();
+        assert_eq!(
+            replace_database(&mut &b"down"[..], 10, &path, |_| Ok(())),
+            Err(Error::Network("Downloaded file is incomplete"))
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), b"original");
+    }
+    fn password(password: &str) -> VaultSecret {
+        VaultSecret::Password(SecretString::new(password.to_string()))
+    }
+    #[test]
+    fn test_replace_database_different_password() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join(DATABASE_FILE_NAME);
+        let cloud_path = directory.path().join("cloud");
+        drop(Database::open_path("local password", &path).unwrap());
+        drop(Database::open_path("changed password", &cloud_path).unwrap());
+        let local = std::fs::read(&path).unwrap();
+        let cloud = std::fs::read(&cloud_path).unwrap();
+
+        assert_eq!(
+            replace_database(&mut &cloud[..], cloud.len() as u64, &path, |path| {
+                verify_download(path, &password("local password"))
+            }),
+            Err(Error::Other(DIFFERENT_PASSWORD))
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), local);
+        assert!(backup_path(&path).exists().not());
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 2);
+        Database::open_path("local password", &path).unwrap();
 
-        std::io::copy(&mut cloud_database, &mut local_database)
-            .map_err(|_| "Failed to copy file")?;
+        replace_database(&mut &cloud[..], cloud.len() as u64, &path, |path| {
+            verify_download(path, &password("changed password"))
+        })
+        .unwrap();
+        assert_eq!(std::fs::read(backup_path(&path)).unwrap(), local);
+        Database::open_path("changed password", &path).unwrap();
+
+        let key = crate::database::derived_key("changed password", &path).unwrap();
+        assert_eq!(verify_download(&path, &VaultSecret::Key(key)), Ok(()));
+        let key = crate::database::derived_key("local password", &path).unwrap();
+        assert_eq!(
+            verify_download(&path, &VaultSecret::Key(key)),
+            Err(Error::Other(DIFFERENT_PASSWORD))
+        );
+    }
+    #[test]
+    fn test_restore_backup() {
+        let (_directory, path) = setup();
+        assert!(!restore_backup(&path).unwrap());
+        std::fs::rename(&path, backup_path(&path)).unwrap();
+        assert!(restore_backup(&path).unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), b"original");
+        assert!(!restore_backup(&path).unwrap());
+    }
+    #[test]
+    fn test_push_pull() {
+        let (_directory, path) = setup();
+        let storage = MemoryStorage::default();
+        let remote = Path::new("backups/vault");
+        let mut reported = Vec::new();
+        push(&storage, &path, remote, |sent, total| {
+            reported.push((sent, total))
+        })
+        .unwrap();
+        assert_eq!(storage.files.borrow()[remote], b"original");
+        assert_eq!(reported.first(), Some(&(8, 8)));
+
+        std::fs::remove_file(&path).unwrap();
+        let secret = Database::open_path("password", &path)
+            .unwrap()
+            .vault_secret()
+            .unwrap();
+        let vault = std::fs::read(&path).unwrap();
+        push(&storage, &path, remote, |_, _| {}).unwrap();
+        std::fs::write(&path, b"original").unwrap();
+        let mut last = (0, 0);
+        pull(&storage, remote, &path, &secret, |received, total| {
+            last = (received, total)
+        })
+        .unwrap();
+        assert_eq!(last, (vault.len() as u64, vault.len() as u64));
+        assert_eq!(std::fs::read(&path).unwrap(), vault);
+        assert_eq!(std::fs::read(backup_path(&path)).unwrap(), b"original");
+    }
+    #[test]
+    fn test_pull_rejected() {
+        let (directory, path) = setup();
+        let storage = MemoryStorage::default();
+        let remote = Path::new("vault");
+        let local = directory.path().join("local");
+        let secret = Database::open_path("password", &local)
+            .unwrap()
+            .vault_secret()
+            .unwrap();
+        let other = directory.path().join("other");
+        drop(Database::open_path("password", &other).unwrap());
+        std::fs::remove_file(&local).unwrap();
+
+        // A vault with the same password, but its own salt, so the key differs
+        push(&storage, &other, remote, |_, _| {}).unwrap();
+        assert_eq!(
+            pull(&storage, remote, &path, &secret, |_, _| {}),
+            Err(Error::Other(DIFFERENT_PASSWORD))
+        );
+        assert_eq!(
+            pull(&storage, remote, &path, &password("wrong"), |_, _| {}),
+            Err(Error::Other(DIFFERENT_PASSWORD))
+        );
+        storage
+            .files
+            .borrow_mut()
+            .insert(remote.to_path_buf(), b"garbage".to_vec());
+        assert!(pull(&storage, remote, &path, &secret, |_, _| {}).is_err());
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"original");
+        assert!(backup_path(&path).exists().not());
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 2);
+    }
+    #[test]
+    fn test_push_incomplete() {
+        let (_directory, path) = setup();
+        let storage = MemoryStorage {
+            limit: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            push(&storage, &path, Path::new("vault"), |_, _| {}),
+            Err(Error::Network("Uploaded file is incomplete"))
+        );
+    }
+    #[test]
+    fn test_pull_missing() {
+        let (_directory, path) = setup();
+        assert_eq!(
+            pull(
+                &MemoryStorage::default(),
+                Path::new("vault"),
+                &path,
+                &password("password"),
+                |_, _| {}
+            ),
+            Err(Error::Network("Failed to get remote metadata"))
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), b"original");
+        assert!(backup_path(&path).exists().not());
+    }
+    #[test]
+    fn test_credentials_misconfigured() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        // Application terminated while enabling cloud, before the credentials were saved
+        database.save_setting("cloud", "true").unwrap();
+        database.save_setting("cloud_address", "localhost").unwrap();
+        assert!(CloudManager::is_enabled(&database));
+        assert_eq!(
+            credentials(&database).err(),
+            Some(Error::Other(MISCONFIGURED))
+        );
 
-        drop(semaphore);
+        CloudManager::disable(&database).unwrap();
+        assert!(CloudManager::is_enabled(&database).not());
+        assert!(database.get_setting("cloud_address").is_err());
 
-        Ok(())
+        database
+            .save_settings(&[
+                ("cloud", "true"),
+                ("cloud_address", "localhost"),
+                ("cloud_username", "user"),
+                ("cloud_password", "password"),
+            ])
+            .unwrap();
+        let (address, username, auth) = credentials(&database).unwrap();
+        assert_eq!(address.expose_secret(), "localhost");
+        assert_eq!(username.expose_secret(), "user");
+        assert!(
+            matches!(auth, SshAuth::Password { password } if password.expose_secret() == "password")
+        );
+    }
+    #[test]
+    fn test_credentials_private_key() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        let auth: SshAuth = serde_json::from_str(
+            r#"{"method": "private_key", "private_key": "/home/user/.ssh/id_ed25519", "passphrase": "passphrase"}"#,
+        )
+        .unwrap();
+        let mut settings = vec![
+            ("cloud", "true"),
+            ("cloud_address", "localhost"),
+            ("cloud_username", "user"),
+        ];
+        settings.extend(auth.settings().unwrap());
+        database.save_settings(&settings).unwrap();
+        let (_, _, auth) = credentials(&database).unwrap();
+        assert!(matches!(
+            auth,
+            SshAuth::PrivateKey { private_key, passphrase: Some(passphrase) }
+                if private_key == Path::new("/home/user/.ssh/id_ed25519") && passphrase.expose_secret() == "passphrase"
+        ));
+
+        CloudManager::disable(&database).unwrap();
+        assert_eq!(
+            SshAuth::from_database(&database).err(),
+            Some(Error::Other(MISCONFIGURED))
+        );
+        let auth: SshAuth =
+            serde_json::from_str(r#"{"method": "password", "password": "password"}"#).unwrap();
+        assert!(matches!(auth, SshAuth::Password { .. }));
     }
 }
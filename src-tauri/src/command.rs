@@ -1,43 +1,388 @@
 pub mod authentication;
+pub mod autostart;
+pub mod benchmark;
+pub mod build_info;
+pub mod changelog;
 pub mod cloud;
 pub mod database;
+pub mod events;
+pub mod hooks;
+pub mod import;
+pub mod metrics;
 pub mod password;
 pub mod totp;
 pub mod validation;
 pub mod window;
 
 use super::*;
+use crate::clipboard::ClipboardOwner;
 use crate::database::model::value::ToSecretString;
 use crate::database::model::{value, Category, Content, Record, Value};
 use crate::database::Database;
+use crate::reauth::{self, Reauthentication};
+use crate::reveal::{self, RevealTimer};
+use crate::typing;
 use secrecy::{ExposeSecret, SecretString};
 use std::ops::Not;
 use tauri::State;
+use zeroize::Zeroizing;
 
-/// Takes value from database and copies it to the clipboard.
+/// Deserializes a command argument sent as a JSON string. Commands with sensitive payloads take it this way instead of typed arguments, so the JSON text lives only in the [`SecretString`], which is zeroized when it is dropped right after the typed values are built.
+/// # Errors
+/// If the payload is not valid JSON of the type.
+pub fn parse_secret_json<T: serde::de::DeserializeOwned>(
+    payload: SecretString,
+) -> Result<T, Error> {
+    serde_json::from_str(payload.expose_secret())
+        .map_err(|_| Error::Validation("Invalid payload".into()))
+}
+
+/// Returns the part of the content that is pasted: TOTP secrets as the current code, recovery codes as the first unused code with its index, other values as revealed.
+fn pasted_value(
+    content: &Content,
+    totp_manager: &TOTPManager,
+) -> Result<(SecretString, Option<usize>), Error> {
+    match content.value() {
+        Value::TOTPSecret(_) => {
+            let (code, _, _) = totp_manager
+                .get_code(&content.id())
+                .ok_or("Failed to get TOTP code")?;
+            Ok((SecretString::new(code), None))
+        }
+        Value::RecoveryCodes(recovery_codes) => {
+            let index = recovery_codes
+                .next_unused()
+                .ok_or("All recovery codes are used")?;
+            Ok((
+                SecretString::new(recovery_codes.codes()[index].code().to_string()),
+                Some(index),
+            ))
+        }
+        _ => Ok((content.value().to_revealed_secret_string(), None)),
+    }
+}
+
+/// Takes value from database and copies it to the clipboard. TOTP secrets are copied as the current code, recovery codes as the first unused code, which is then marked as used unless disabled by [`value::RecoveryCodes::MARK_COPIED_SETTING`].
 /// # Error
-/// If value cannot be copied to the clipboard
+/// If value cannot be copied to the clipboard, or [`Error::NeedsReauth`] if its record is locked and the master password was not verified recently.
 #[tauri::command]
 pub async fn copy_value_to_clipboard<'a>(
     id: u64,
     database: State<'a, Database>,
     totp_manager: State<'a, TOTPManager>,
-) -> Result<(), &'static str> {
+    reauthentication: State<'a, Reauthentication>,
+    clipboard_owner: State<'a, ClipboardOwner>,
+) -> Result<(), Error> {
+    reauthentication.check(&database, database.is_content_locked(id)?)?;
+    let content = database
+        .get_content(id)
+        .map_err(|_| "Failed to load content")?;
+    let (value, recovery_code) = pasted_value(&content, &totp_manager)?;
+
+    arboard::Clipboard::new()
+        .map_err(|_| "Clipboard is not available")?
+        .set_text(value.expose_secret())
+        .map_err(|_| "Failed to copy value to clipboard")?;
+    clipboard_owner.copied(value.expose_secret());
+
+    match recovery_code {
+        Some(index) if mark_copied_recovery_code(&database) => {
+            database.mark_recovery_code_used(id, index)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Types value of the content into the focused window with simulated keystrokes, so it never reaches the clipboard. The value is chosen as by [`copy_value_to_clipboard`]. Keystrokes are delayed by [`typing::DELAY_SETTING`] and new lines are refused unless [`typing::ENTER_SETTING`] allows them.
+///
+/// Typing counts as a reveal of the value and stops when the reveal expires or is cancelled, e.g. by locking a record, see [`RevealTimer`].
+/// # Error
+/// If the value cannot be typed or typing was aborted, or [`Error::NeedsReauth`] if its record is locked and the master password was not verified recently.
+#[tauri::command]
+pub async fn type_value<'a>(
+    id: u64,
+    database: State<'a, Database>,
+    totp_manager: State<'a, TOTPManager>,
+    reauthentication: State<'a, Reauthentication>,
+    reveal_timer: State<'a, RevealTimer>,
+    app_handle: AppHandle,
+) -> Result<(), Error> {
+    reauthentication.check(&database, database.is_content_locked(id)?)?;
     let content = database
         .get_content(id)
         .map_err(|_| "Failed to load content")?;
+    let (value, recovery_code) = pasted_value(&content, &totp_manager)?;
+    let keystrokes = typing::keystrokes(value.expose_secret(), typing::allow_enter(&database))?;
+    let delay = typing::delay(&database);
+
+    let token = reveal_timer.start(reveal::duration(&database), |_| {})?;
+    let typed = tauri::async_runtime::spawn_blocking(move || {
+        let mut keyboard = typing::SystemKeyboard::new()?;
+        typing::type_keystrokes(&mut keyboard, &keystrokes, delay, || {
+            app_handle.state::<RevealTimer>().is_pending(token).not()
+        })
+    })
+    .await
+    .map_err(|_| "Failed to type value")?;
+    reveal_timer.cancel(token);
+    typed?;
+
+    match recovery_code {
+        Some(index) if mark_copied_recovery_code(&database) => {
+            database.mark_recovery_code_used(id, index)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns whether a copied recovery code is marked as used, see [`value::RecoveryCodes::MARK_COPIED_SETTING`].
+fn mark_copied_recovery_code(database: &Database) -> bool {
+    database
+        .get_setting(value::RecoveryCodes::MARK_COPIED_SETTING)
+        .is_ok_and(|setting| setting.expose_secret() == "false")
+        .not()
+}
 
-    let value = if let Value::TOTPSecret(_) = content.value() {
-        let (code, _) = totp_manager
-            .get_code(&id)
-            .ok_or("Failed to get TOTP code")?;
-        SecretString::new(code)
+/// Maximum number of fields that can be copied at once by [`copy_record_block`].
+const MAX_BLOCK_FIELDS: usize = 5;
+
+/// Block of values copied by [`copy_record_block`].
+struct RecordBlock {
+    text: Zeroizing<String>,
+    /// Labels of the included values, in the order they appear in the text.
+    labels: Vec<String>,
+    /// Recovery codes in the text as content id and index of the code.
+    recovery_codes: Vec<(u64, usize)>,
+}
+
+/// Takes values with the given labels from the content of the record, each chosen as by [`pasted_value`]. Labels without content are skipped, User and Password are taken if no labels are given.
+/// # Error
+/// If more than [`MAX_BLOCK_FIELDS`] labels are requested, if none of the labels exist or if a value cannot be pasted.
+fn record_block(
+    record_id: u64,
+    content: &[Content],
+    include: Vec<String>,
+    totp_manager: &TOTPManager,
+) -> Result<RecordBlock, Error> {
+    if include.len() > MAX_BLOCK_FIELDS {
+        return Err(Error::Validation("Too many fields".into()));
+    }
+    let include = if include.is_empty() {
+        vec!["User".to_string(), "Password".to_string()]
     } else {
-        content.value().to_secret_string()
+        include
     };
 
+    let mut labels = Vec::with_capacity(include.len());
+    let mut values = Vec::with_capacity(include.len());
+    let mut recovery_codes = Vec::new();
+    for label in include {
+        let Some(content) = content.iter().find(|content| content.label() == label) else {
+            continue;
+        };
+        if let Value::TOTPSecret(totp_secret) = content.value() {
+            if totp_manager.get_code(&content.id()).is_none() {
+                totp_manager.add_secret(
+                    content.id(),
+                    record_id,
+                    totp_secret.value().to_string(),
+                )?;
+            }
+        }
+        let (value, recovery_code) = pasted_value(content, totp_manager)?;
+        if let Some(index) = recovery_code {
+            recovery_codes.push((content.id(), index));
+        }
+        values.push(value);
+        labels.push(label);
+    }
+    if labels.is_empty() {
+        return Err(Error::Validation("Nothing to copy".into()));
+    }
+
+    let mut text = Zeroizing::new(String::with_capacity(
+        values
+            .iter()
+            .map(|value| value.expose_secret().len() + 1)
+            .sum(),
+    ));
+    for value in &values {
+        if text.is_empty().not() {
+            text.push('\n');
+        }
+        text.push_str(value.expose_secret());
+    }
+    Ok(RecordBlock {
+        text,
+        labels,
+        recovery_codes,
+    })
+}
+
+/// Takes values with the given labels from the record and copies them to the clipboard, each on a separate line, see [`record_block`]. If no labels are given, User and Password are copied. Values are copied as by [`copy_value_to_clipboard`], copied recovery codes are marked as used unless disabled by [`value::RecoveryCodes::MARK_COPIED_SETTING`].
+/// # Return
+/// Returns labels of the values that were actually copied, in the order they appear in the block.
+/// # Error
+/// If more than [`MAX_BLOCK_FIELDS`] labels are requested, if content cannot be loaded, if none of the labels exist or if the block cannot be copied to the clipboard.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn copy_record_block<'a>(
+    record_id: u64,
+    include: Vec<String>,
+    database: State<'a, Database>,
+    totp_manager: State<'a, TOTPManager>,
+    reauthentication: State<'a, Reauthentication>,
+    clipboard_owner: State<'a, ClipboardOwner>,
+) -> Result<Vec<String>, Error> {
+    if include.len() > MAX_BLOCK_FIELDS {
+        return Err(Error::Validation("Too many fields".into()));
+    }
+    reauthentication.check(&database, database.is_record_locked(record_id)?)?;
+
+    let content = database
+        .get_all_content_for_record(record_id)
+        .map_err(|_| "Failed to load content")?;
+    let block = record_block(record_id, &content, include, &totp_manager)?;
+
     arboard::Clipboard::new()
         .map_err(|_| "Clipboard is not available")?
-        .set_text(value.expose_secret())
-        .map_err(|_| "Failed to copy value to clipboard")
+        .set_text(block.text.as_str())
+        .map_err(|_| "Failed to copy values to clipboard")?;
+    clipboard_owner.copied(&block.text);
+
+    if mark_copied_recovery_code(&database) {
+        for (id, index) in block.recovery_codes {
+            database.mark_recovery_code_used(id, index)?;
+        }
+    }
+    Ok(block.labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_secret_json() {
+        let (record, content): (Record, Vec<Content>) = parse_secret_json(SecretString::new(
+            r#"[{"title":"Mail","subtitle":"john","category":"Login"},[{"label":"Password","position":0,"required":true,"kind":"Password","value":"se\"cret"}]]"#
+                .to_string(),
+        ))
+        .unwrap();
+        assert_eq!(record.title(), "Mail");
+        assert_eq!(
+            content[0].value(),
+            &Value::Password(value::Password::new("se\"cret".to_string()))
+        );
+        assert_eq!(
+            parse_secret_json::<Record>(SecretString::new("{\"title\":".to_string())).unwrap_err(),
+            "Invalid payload"
+        );
+        assert!(parse_secret_json::<Record>(SecretString::new("[]".to_string())).is_err());
+    }
+
+    fn block_content() -> Vec<Content> {
+        let mut recovery_codes = Content::new(
+            "Recovery".to_string(),
+            3,
+            false,
+            Value::RecoveryCodes(
+                value::RecoveryCodes::new(vec![
+                    ("first".to_string(), true),
+                    ("second".to_string(), false),
+                ])
+                .unwrap(),
+            ),
+        );
+        recovery_codes.set_id(7);
+        vec![
+            Content::new(
+                "Password".to_string(),
+                1,
+                true,
+                Value::Password(value::Password::new("secret".to_string())),
+            ),
+            Content::new(
+                "User".to_string(),
+                0,
+                true,
+                Value::Text(value::Text::new("john".to_string())),
+            ),
+            Content::new(
+                "Note".to_string(),
+                2,
+                false,
+                Value::Text(value::Text::new("note".to_string())),
+            ),
+            recovery_codes,
+        ]
+    }
+    fn labels(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+    #[test]
+    fn test_record_block() {
+        let totp_manager = TOTPManager::new(1);
+        let content = block_content();
+
+        let block = record_block(1, &content, Vec::new(), &totp_manager).unwrap();
+        assert_eq!(block.text.as_str(), "john\nsecret");
+        assert_eq!(block.labels, labels(&["User", "Password"]));
+        assert!(block.recovery_codes.is_empty());
+
+        let block = record_block(
+            1,
+            &content,
+            labels(&["Note", "Missing", "Password"]),
+            &totp_manager,
+        )
+        .unwrap();
+        assert_eq!(block.text.as_str(), "note\nsecret");
+        assert_eq!(block.labels, labels(&["Note", "Password"]));
+
+        assert_eq!(
+            record_block(1, &content, labels(&["Missing"]), &totp_manager).err(),
+            Some(Error::Validation("Nothing to copy".into()))
+        );
+        assert_eq!(
+            record_block(1, &content[..1], Vec::new(), &totp_manager)
+                .unwrap()
+                .labels,
+            labels(&["Password"])
+        );
+    }
+    #[test]
+    fn test_record_block_limit() {
+        let totp_manager = TOTPManager::new(1);
+        let content = block_content();
+        assert!(record_block(
+            1,
+            &content,
+            labels(&["User"; MAX_BLOCK_FIELDS]),
+            &totp_manager
+        )
+        .is_ok());
+        assert_eq!(
+            record_block(
+                1,
+                &content,
+                labels(&["User"; MAX_BLOCK_FIELDS + 1]),
+                &totp_manager
+            )
+            .err(),
+            Some(Error::Validation("Too many fields".into()))
+        );
+    }
+    #[test]
+    fn test_record_block_recovery_code() {
+        let totp_manager = TOTPManager::new(1);
+        let block = record_block(
+            1,
+            &block_content(),
+            labels(&["User", "Recovery"]),
+            &totp_manager,
+        )
+        .unwrap();
+        assert_eq!(block.text.as_str(), "john\nsecond");
+        assert_eq!(block.recovery_codes, vec![(7, 1)]);
+    }
 }
@@ -1,27 +1,45 @@
 use super::*;
 use crate::cloud;
+use crate::database::retention::{self, RetentionReport};
+use crate::database::VaultSecret;
+use crate::events::{Event, LoginProgress, SessionStatePayload};
+use crate::hint::{self, Hint};
+use crate::keychain::{self, KeychainStatus, SystemKeychain};
+use crate::login::{self, LoginStage};
+use crate::policy::PasswordPolicy;
+use crate::reauth::Reauthentication;
+use crate::session::{self, Session, SessionState};
+use crate::throttle::FailedLogins;
 use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::time::Instant;
 
-/// Register process. Database must not exist. Adds the database to the app state, initializes the main window and closes the current window.
+/// Error returned when the current master password given for a password change is wrong.
+pub const CURRENT_PASSWORD_INCORRECT: &str = "Current password is incorrect";
+
+/// Register process. Database must not exist. The password must satisfy the [`PasswordPolicy`]. Adds the database to the app state, initializes the main window and closes the current window.
 /// # Restart
 /// Restarts the application if the database already exists. Error is shown in a blocking dialog.
 #[tauri::command(rename_all = "snake_case")]
 pub async fn register<'a>(
     password: SecretString,
     confirm_password: SecretString,
+    policy: State<'a, PasswordPolicy>,
     app_handle: AppHandle,
     window: Window,
-) -> Result<(), &'static str> {
+) -> Result<(), Error> {
     if Database::exists(&app_handle) {
         critical_error("Database already exists", &app_handle, &window);
-        return Err("Database already exists");
+        return Err(Error::Other("Database already exists"));
     }
 
     if password.expose_secret() != confirm_password.expose_secret() {
-        return Err("Passwords do not match.");
+        return Err(Error::Validation("Passwords do not match.".into()));
     }
+    policy.enforce(password.expose_secret())?;
 
     app_handle.manage(Database::open(password.expose_secret(), &app_handle)?);
+    session::transition(&app_handle, SessionState::Unlocked);
 
     #[cfg(target_os = "macos")]
     app_handle
@@ -33,22 +51,52 @@ pub async fn register<'a>(
     Ok(())
 }
 
-/// Helper function for login process. Checks databases versions and downloads the cloud database if it is newer. Shows a dialog if the local version is newer.
+/// Reports progress of the login to the login window, see [`Event::LoginProgress`].
+fn report_login(window: &Window) -> impl Fn(LoginProgress) + '_ {
+    |progress| {
+        crate::events::emit_to_window(window, Event::LoginProgress(progress)).unwrap_or_default()
+    }
+}
+
+/// Helper function for login process. Checks databases versions and downloads the cloud database if it is newer. Shows a dialog if the local version is newer. The cloud database replaces the local one only if it opens with the password or the derived key, see [`cloud::download_database`].
+///
+/// Each stage is reported to the login window and runs on the blocking thread pool, see [`login::run_stage`]. Connecting and comparing versions are limited by [`login::STAGE_TIMEOUT`], the download fails if the server stops responding for that long.
 async fn login_download(
     app_handle: &AppHandle,
     window: &Window,
     database: &Database,
-) -> Result<(), &'static str> {
-    let manager = cloud::CloudManager::connect_from_database(database, app_handle)?;
-    if manager.exists()? {
-        let cloud_mtime = chrono::DateTime::from_timestamp(manager.m_time()?, 0)
-            .ok_or("Failed to get cloud mtime")?;
+    secret: &VaultSecret,
+) -> Result<(), Error> {
+    let report = report_login(window);
+    let (address, username, auth) = cloud::credentials(database)?;
+    let session = login::run_stage(
+        LoginStage::ConnectingToCloud,
+        Some(login::STAGE_TIMEOUT),
+        &report,
+        move || {
+            let session = cloud::connect(address.expose_secret(), username.expose_secret(), &auth)?;
+            session.set_timeout(login::STAGE_TIMEOUT.as_millis() as u32);
+            Ok(session)
+        },
+    )
+    .await?;
+    let (session, cloud_mtime) = login::run_stage(
+        LoginStage::ComparingVersions,
+        Some(login::STAGE_TIMEOUT),
+        &report,
+        move || cloud::cloud_m_time(&session).map(|cloud_mtime| (session, cloud_mtime)),
+    )
+    .await?;
+
+    if let Some(cloud_mtime) = cloud_mtime {
+        let cloud_mtime =
+            chrono::DateTime::from_timestamp(cloud_mtime, 0).ok_or("Failed to get cloud mtime")?;
 
         let local_database_path =
             Database::path(app_handle).ok_or("Failed to get database path")?;
         let local_mtime = chrono::DateTime::from_timestamp(
-            std::fs::metadata(local_database_path)
-                .map_err(|_| "Failed to get local metadata")?
+            std::fs::metadata(&local_database_path)
+                .map_err(Error::io("Failed to get local metadata"))?
                 .mtime(),
             0,
         )
@@ -57,34 +105,103 @@ async fn login_download(
         if local_mtime <= cloud_mtime || tauri::api::dialog::blocking::MessageDialogBuilder::new("Local version is newer", format!("The local version is newer ({}) than the cloud one ({}). Which version do you want to use?", local_mtime.format("%Y-%m-%d %H:%M:%S"), cloud_mtime.format("%Y-%m-%d %H:%M:%S")))
             .buttons(tauri::api::dialog::MessageDialogButtons::OkCancelWithLabels("Cloud".to_string(), "Local".to_string())).kind(tauri::api::dialog::MessageDialogKind::Warning).parent(window).show()
         {
-            manager.download().await?;
+            let permit = cloud::transfer_permit().await?;
+            let secret = secret.clone();
+            let window = window.clone();
+            login::run_stage(LoginStage::Downloading, None, &report, move || {
+                let _permit = permit;
+                let report = report_login(&window);
+                let mut last = None;
+                cloud::download_database(
+                    &session,
+                    &local_database_path,
+                    &secret,
+                    |transferred, total| {
+                        let percent = Some(login::percent(transferred, total));
+                        if percent != last {
+                            last = percent;
+                            report(LoginProgress {
+                                stage: LoginStage::Downloading,
+                                percent,
+                            });
+                        }
+                    },
+                )
+            })
+            .await?;
         }
     }
 
     Ok(())
 }
 
-/// Login process. Database must exist. If cloud storage is enabled, it tries to download the database from the cloud. Adds the database to the app state, initializes the main window and closes the current window.
+/// Login process. Database must exist. Stages of the login are reported to the login window by [`Event::LoginProgress`]. If cloud storage is enabled and the database is writable, it tries to download the database from the cloud, see [`login_download`]. If the cloud credentials are missing, cloud storage is disabled instead of blocking the login. Adds the database to the app state, initializes the main window and closes the current window.
+///
+/// Failed logins are counted next to the database, see [`FailedLogins`]. After [`crate::throttle::FREE_ATTEMPTS`] failures, every next attempt has to wait for a doubling delay. The counter also shows the master password hint, see [`get_master_password_hint`].
+/// # Error
+/// Returns the remaining wait in seconds if the login is attempted too early.
 /// # Restart
 /// Restarts the application if the database does not exist. Error is shown in a blocking dialog.
 #[tauri::command]
-pub async fn login<'a>(
+pub async fn login(
     password: SecretString,
     app_handle: AppHandle,
     window: Window,
-) -> Result<(), &'static str> {
+) -> Result<(), Error> {
     if Database::exists(&app_handle).not() {
         critical_error("Database does not exist", &app_handle, &window);
-        return Err("Database does not exist");
+        return Err(Error::NotFound("Database does not exist"));
     }
 
-    let mut database = Database::open(password.expose_secret(), &app_handle)?;
+    let database_path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    let remaining = FailedLogins::load(&database_path).remaining(chrono::Utc::now());
+    if remaining.is_zero().not() {
+        return Err(Error::TooManyAttempts(
+            remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0),
+        ));
+    }
+
+    let report = report_login(&window);
+    report(LoginProgress {
+        stage: LoginStage::OpeningVault,
+        percent: None,
+    });
+    let database = Database::open(password.expose_secret(), &app_handle).inspect_err(|error| {
+        if let Error::InvalidPassword = error {
+            FailedLogins::record_failure(&database_path, chrono::Utc::now()).unwrap_or_default();
+        }
+    })?;
+    FailedLogins::reset(&database_path).unwrap_or_default();
+
+    open_session(
+        database,
+        &VaultSecret::Password(password),
+        app_handle,
+        &window,
+    )
+    .await
+}
 
-    if cloud::CloudManager::is_enabled(&database) {
-        if let Err(error) = login_download(&app_handle, &window, &database).await {
-            if tauri::api::dialog::blocking::ask(
-                Some(&window),
-                error,
+/// Finishes the login with the opened database. If cloud storage is enabled and the database is writable, it tries to download the database from the cloud and opens it again with the secret, see [`login_download`]. Adds the database to the app state, initializes the main window and closes the current window.
+async fn open_session(
+    mut database: Database,
+    secret: &VaultSecret,
+    app_handle: AppHandle,
+    window: &Window,
+) -> Result<(), Error> {
+    let report = report_login(window);
+    if database.read_only_reason().is_none() && cloud::CloudManager::is_enabled(&database) {
+        if let Err(error) = login_download(&app_handle, window, &database, secret).await {
+            if let Error::Other(cloud::MISCONFIGURED) = error {
+                cloud::CloudManager::disable(&database)?;
+                tauri::api::dialog::blocking::message(
+                    Some(window),
+                    "Cloud storage",
+                    "Cloud sync is disabled until it is configured again in the settings",
+                );
+            } else if tauri::api::dialog::blocking::ask(
+                Some(window),
+                error.to_string(),
                 "Do you wish to continue without cloud storage?",
             )
             .not()
@@ -92,12 +209,23 @@ pub async fn login<'a>(
                 return Err(error);
             }
         }
-        database = Database::open(password.expose_secret(), &app_handle)?;
+        report(LoginProgress {
+            stage: LoginStage::OpeningVault,
+            percent: None,
+        });
+        database = Database::open_with_secret(secret, &app_handle)?;
     }
 
     database.delete_data_breach_cache_older_24h()?;
+    app_handle
+        .state::<RetentionReport>()
+        .set(retention::run_retention(&database)?);
+    database.delete_trash_older_30_days()?;
+    database.prune_history(crate::database::HISTORY_KEEP)?;
 
     app_handle.manage(database);
+    crate::panic::register_saved(&app_handle, &app_handle.state::<Database>()).unwrap_or_default();
+    session::transition(&app_handle, SessionState::Unlocked);
 
     #[cfg(target_os = "macos")]
     app_handle
@@ -109,18 +237,205 @@ pub async fn login<'a>(
     Ok(())
 }
 
-/// Changes the master password.
+/// Login with the database key saved in the keychain of the system, see [`keychain::load`]. The user confirms their presence instead of typing the master password. The login continues like [`login`], also with the cloud download.
+/// # Error
+/// Returns [`keychain::NOT_SUPPORTED`] on systems without a supported keychain. Returns [`keychain::OUTDATED`] and disables keychain unlock if the saved key no longer opens the database.
+/// # Restart
+/// Restarts the application if the database does not exist. Error is shown in a blocking dialog.
+#[tauri::command]
+pub async fn login_with_keychain(app_handle: AppHandle, window: Window) -> Result<(), Error> {
+    if Database::exists(&app_handle).not() {
+        critical_error("Database does not exist", &app_handle, &window);
+        return Err(Error::NotFound("Database does not exist"));
+    }
+    if keychain::is_supported().not() {
+        return Err(Error::Other(keychain::NOT_SUPPORTED));
+    }
+
+    let database_path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    let secret = VaultSecret::Key(keychain::load(&SystemKeychain, &database_path)?);
+    report_login(&window)(LoginProgress {
+        stage: LoginStage::OpeningVault,
+        percent: None,
+    });
+    let database = match Database::open_with_secret(&secret, &app_handle) {
+        Err(Error::InvalidPassword) => {
+            keychain::disable(&SystemKeychain, &database_path).unwrap_or_default();
+            return Err(Error::Other(keychain::OUTDATED));
+        }
+        result => result?,
+    };
+    FailedLogins::reset(&database_path).unwrap_or_default();
+
+    open_session(database, &secret, app_handle, &window).await
+}
+
+/// Returns whether keychain unlock is supported on this system and enabled for the database, so the login window can offer it.
+#[tauri::command]
+pub async fn get_keychain_status(app_handle: AppHandle) -> Result<KeychainStatus, Error> {
+    let path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    Ok(KeychainStatus {
+        supported: keychain::is_supported(),
+        enabled: keychain::is_enabled(&path),
+    })
+}
+
+/// Saves the key derived from the master password to the keychain of the system, so the next login can use [`login_with_keychain`]. The password is verified first.
+/// # Error
+/// Returns [`keychain::NOT_SUPPORTED`] on systems without a supported keychain, [`CURRENT_PASSWORD_INCORRECT`] if the password is wrong, or an error if the key cannot be saved.
+#[tauri::command]
+pub async fn enable_keychain_unlock<'a>(
+    password: SecretString,
+    _database: State<'a, Database>,
+    app_handle: AppHandle,
+) -> Result<(), Error> {
+    if keychain::is_supported().not() {
+        return Err(Error::Other(keychain::NOT_SUPPORTED));
+    }
+    let path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    verify_current_password(&path, password.expose_secret())?;
+    let key = crate::database::derived_key(password.expose_secret(), &path)?;
+    Ok(keychain::enable(&SystemKeychain, &path, &key)?)
+}
+
+/// Deletes the key from the keychain of the system, see [`keychain::disable`].
+/// # Error
+/// Returns [`keychain::NOT_SUPPORTED`] on systems without a supported keychain, or an error if the key cannot be deleted.
+#[tauri::command]
+pub async fn disable_keychain_unlock(app_handle: AppHandle) -> Result<(), Error> {
+    let path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    Ok(keychain::disable(&SystemKeychain, &path)?)
+}
+
+/// Verifies the master password, so locked records can be viewed for a while, see [`Reauthentication`].
+/// # Error
+/// Returns an error if the password is wrong.
+#[tauri::command]
+pub async fn verify_master_password<'a>(
+    password: SecretString,
+    reauthentication: State<'a, Reauthentication>,
+    app_handle: AppHandle,
+) -> Result<(), Error> {
+    let path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    Database::open_path_read_only(password.expose_secret(), &path, "Verification")?;
+    reauthentication.verified_at(Instant::now());
+    Ok(())
+}
+
+/// Returns the current state of the session, for windows created after the last `session-state` event, see [`session::transition`].
+#[tauri::command]
+pub async fn get_session_state<'a>(
+    session: State<'a, Session>,
+) -> Result<SessionStatePayload, Error> {
+    Ok(session.current()?)
+}
+
+/// Verifies the current master password by opening the database file with it in a second, read-only connection.
+/// # Error
+/// Returns [`CURRENT_PASSWORD_INCORRECT`] if the password is wrong.
+fn verify_current_password(path: &Path, current_password: &str) -> Result<(), Error> {
+    match Database::open_path_read_only(current_password, path, "Verification") {
+        Ok(_) => Ok(()),
+        Err(Error::InvalidPassword) => Err(Error::Validation(CURRENT_PASSWORD_INCORRECT.into())),
+        Err(error) => Err(error),
+    }
+}
+
+/// Changes the master password after the current one is verified, see [`verify_current_password`]. The new password must satisfy the [`PasswordPolicy`]. The master password hint is removed, because it was set for the old password, and so is the key in the keychain, see [`disable_keychain_unlock`].
+/// # Error
+/// Returns "Passwords do not match." if the confirmation differs and [`CURRENT_PASSWORD_INCORRECT`] if the current password is wrong, so the frontend can highlight the field.
 #[tauri::command(rename_all = "snake_case")]
 pub async fn change_password<'a>(
+    current_password: SecretString,
     password: SecretString,
     confirm_password: SecretString,
+    policy: State<'a, PasswordPolicy>,
     database: State<'a, Database>,
-) -> Result<(), &'static str> {
+    app_handle: AppHandle,
+) -> Result<(), Error> {
     if password.expose_secret() != confirm_password.expose_secret() {
-        return Err("Passwords do not match.");
+        return Err(Error::Validation("Passwords do not match.".into()));
     }
+    policy.enforce(password.expose_secret())?;
 
+    let path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    verify_current_password(&path, current_password.expose_secret())?;
     database.change_key(password.expose_secret())?;
+    hint::clear(&path)?;
+    if keychain::is_enabled(&path) {
+        keychain::disable(&SystemKeychain, &path)?;
+    }
 
     Ok(())
 }
+
+/// Sets the master password hint shown on the login screen after `after_attempts` failed logins (default [`hint::DEFAULT_ATTEMPTS`]). The password is verified and the hint must not reveal it, see [`hint::check`]. Returns warnings for the user.
+/// # Error
+/// Returns an error if the password is wrong or the hint is rejected.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_master_password_hint<'a>(
+    password: SecretString,
+    hint: String,
+    after_attempts: Option<u32>,
+    _database: State<'a, Database>,
+    app_handle: AppHandle,
+) -> Result<Vec<&'static str>, Error> {
+    let path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    Database::open_path_read_only(password.expose_secret(), &path, "Verification")?;
+    hint::check(&hint, password.expose_secret())?;
+    let after_attempts = after_attempts.unwrap_or(hint::DEFAULT_ATTEMPTS);
+    hint::save(
+        &path,
+        &Hint {
+            hint: hint.trim().to_string(),
+            after_attempts,
+        },
+    )?;
+    let mut warnings = vec![hint::UNENCRYPTED_WARNING];
+    if after_attempts == 0 {
+        warnings.push("The hint is shown before any failed login");
+    }
+    Ok(warnings)
+}
+
+/// Removes the master password hint.
+#[tauri::command]
+pub async fn clear_master_password_hint<'a>(
+    _database: State<'a, Database>,
+    app_handle: AppHandle,
+) -> Result<(), Error> {
+    hint::clear(&Database::path(&app_handle).ok_or("Failed to get database path")?)
+}
+
+/// Returns the master password hint for the login screen, if it is set and there were enough failed logins since the last successful login, see [`FailedLogins`].
+#[tauri::command]
+pub async fn get_master_password_hint(app_handle: AppHandle) -> Result<Option<String>, Error> {
+    let path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    let failures = FailedLogins::load(&path).failures;
+    Ok(hint::load(&path).and_then(|hint| hint::shown(&hint, failures).map(str::to_string)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_verify_current_password() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database");
+        let database = Database::open_path("password", &path).unwrap();
+        assert_eq!(verify_current_password(&path, "password"), Ok(()));
+        assert_eq!(
+            verify_current_password(&path, "wrong"),
+            Err(Error::Validation(CURRENT_PASSWORD_INCORRECT.into()))
+        );
+
+        database.change_key("new password").unwrap();
+        assert_eq!(
+            verify_current_password(&path, "password"),
+            Err(Error::Validation(CURRENT_PASSWORD_INCORRECT.into()))
+        );
+        assert_eq!(verify_current_password(&path, "new password"), Ok(()));
+    }
+}
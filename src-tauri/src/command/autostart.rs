@@ -0,0 +1,29 @@
+use super::*;
+use crate::autostart::{Autostart, AutostartStatus};
+
+/// Enables or disables starting the application at login. With `start_hidden`, the application is started with [`crate::autostart::HIDDEN_ARGUMENT`].
+/// # Error
+/// Returns an error if the autostart entry cannot be written or removed.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_autostart(
+    enabled: bool,
+    start_hidden: bool,
+    app_handle: AppHandle,
+) -> Result<AutostartStatus, Error> {
+    let autostart = Autostart::new(&app_handle).ok_or("Autostart is not available")?;
+    if enabled {
+        let executable = std::env::current_exe().map_err(|_| "Failed to get executable path")?;
+        autostart.enable(&executable, start_hidden)?;
+    } else {
+        autostart.disable()?;
+    }
+    Ok(autostart.status())
+}
+
+/// Returns whether the application starts at login.
+#[tauri::command]
+pub async fn get_autostart_status(app_handle: AppHandle) -> Result<AutostartStatus, Error> {
+    Autostart::new(&app_handle)
+        .map(|autostart| autostart.status())
+        .ok_or(Error::Other("Autostart is not available"))
+}
@@ -0,0 +1,18 @@
+use super::*;
+use crate::benchmark::{self, Benchmark};
+use crate::cloud;
+
+/// Measures how long opening and reading the database takes on this machine, for support. Not shown in the user interface. Refused while a cloud transfer is in progress, transfers wait until the benchmark finishes.
+/// # Error
+/// Returns an error if a cloud transfer is in progress, if the password is wrong or if the benchmark fails, see [`benchmark::run`].
+#[tauri::command]
+pub async fn benchmark_vault<'a>(
+    iterations: u32,
+    password: SecretString,
+    database: State<'a, Database>,
+    app_handle: AppHandle,
+) -> Result<Benchmark, Error> {
+    let _permit = cloud::pause_transfers()?;
+    let path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    benchmark::run(&path, password.expose_secret(), &database, iterations)
+}
@@ -0,0 +1,7 @@
+use crate::build_info::{self, BuildInfo};
+
+/// Returns version, git commit and build date of the application, for bug reports.
+#[tauri::command]
+pub async fn build_info() -> BuildInfo {
+    build_info::current()
+}
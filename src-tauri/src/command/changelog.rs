@@ -0,0 +1,28 @@
+use super::*;
+use crate::changelog::{self, Release};
+
+/// Returns releases from the changelog newer than the given version, the newest first. If no version is given, all releases are returned.
+/// # Error
+/// Returns an error if the version is not valid semver version.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_changelog(since_version: Option<String>) -> Result<Vec<Release>, Error> {
+    let since_version = since_version
+        .map(|version| semver::Version::parse(&version).map_err(|_| "Invalid version"))
+        .transpose()?;
+    Ok(changelog::since(
+        changelog::releases(),
+        since_version.as_ref(),
+    ))
+}
+
+/// Marks changes up to the current version as seen, so what's new is not shown again.
+#[tauri::command]
+pub async fn mark_changelog_seen<'a>(
+    database: State<'a, Database>,
+    app_handle: AppHandle,
+) -> Result<(), Error> {
+    database.save_setting(
+        changelog::LAST_SEEN_VERSION,
+        &app_handle.package_info().version.to_string(),
+    )
+}
@@ -1,7 +1,11 @@
 use super::*;
 use crate::cloud;
 use crate::database::model::SecretValue;
+use crate::events::TransferProgress;
+use crate::hooks::{self, HookEvent, HookPayload};
+use crate::session::{self, SessionState};
 use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
 
 /// For sending cloud data to the frontend
 #[derive(Clone, serde::Serialize)]
@@ -12,7 +16,7 @@ pub struct CloudData {
 
 /// Returns cloud data if cloud is enabled.
 #[tauri::command]
-pub async fn cloud_data<'a>(database: State<'a, Database>) -> Result<CloudData, &'static str> {
+pub async fn cloud_data<'a>(database: State<'a, Database>) -> Result<CloudData, Error> {
     if cloud::CloudManager::is_enabled(&database) {
         let address = database
             .get_setting("cloud_address")
@@ -22,7 +26,7 @@ pub async fn cloud_data<'a>(database: State<'a, Database>) -> Result<CloudData,
             .map_err(|_| "Failed to load username")?;
         Ok(CloudData { address, username })
     } else {
-        Err("Cloud is not enabled")
+        Err(Error::Other("Cloud is not enabled"))
     }
 }
 
@@ -31,22 +35,22 @@ pub async fn cloud_data<'a>(database: State<'a, Database>) -> Result<CloudData,
 pub async fn enable_cloud<'a>(
     address: SecretString,
     username: SecretString,
-    password: SecretString,
+    auth: cloud::SshAuth,
     app_handle: AppHandle,
     window: Window,
     database: State<'a, Database>,
-) -> Result<(), &'static str> {
+) -> Result<(), Error> {
     let manager = cloud::CloudManager::enable(
         address.expose_secret(),
         username.expose_secret(),
-        password.expose_secret(),
+        &auth,
         &app_handle,
         &database,
     )?;
 
     if manager.exists()? && tauri::api::dialog::blocking::MessageDialogBuilder::new("Database detected", "Database detected on cloud, which version do you want to use? (the other one will be overwritten)")
         .buttons(tauri::api::dialog::MessageDialogButtons::OkCancelWithLabels("Cloud (restart app)".to_string(), "Local".to_string())).kind(tauri::api::dialog::MessageDialogKind::Warning).parent(&window).show() {
-        app_handle.restart();
+        session::lock(&app_handle, SessionState::Locked);
     }
 
     tauri::api::dialog::message(
@@ -55,8 +59,7 @@ pub async fn enable_cloud<'a>(
         "From now on your database will be uploaded to the cloud",
     );
 
-    window
-        .emit("upload", ())
+    crate::events::emit_to_main(&app_handle, Event::Upload)
         .map_err(|_| "Failed to start upload")?;
 
     Ok(())
@@ -64,7 +67,7 @@ pub async fn enable_cloud<'a>(
 
 /// Disables cloud storage and deletes the credentials.
 #[tauri::command]
-pub async fn disable_cloud<'a>(database: State<'a, Database>) -> Result<(), &'static str> {
+pub async fn disable_cloud<'a>(database: State<'a, Database>) -> Result<(), Error> {
     cloud::CloudManager::disable(&database)
 }
 
@@ -74,7 +77,7 @@ pub async fn cloud_upload<'a>(
     window: Window,
     app_handle: AppHandle,
     database: State<'a, Database>,
-) -> Result<String, &'static str> {
+) -> Result<String, Error> {
     if cloud::CloudManager::is_enabled(&database) {
         let manager = cloud::CloudManager::connect_from_database(&database, &app_handle)?;
         if manager.exists()? {
@@ -95,16 +98,97 @@ pub async fn cloud_upload<'a>(
             if local_mtime < cloud_mtime && !tauri::api::dialog::blocking::MessageDialogBuilder::new("Cloud version is newer", format!("The cloud version is newer ({}) than the local one ({}). Which version do you want to use?", cloud_mtime.format("%Y-%m-%d %H:%M:%S"), local_mtime.format("%Y-%m-%d %H:%M:%S")))
                 .buttons(tauri::api::dialog::MessageDialogButtons::OkCancelWithLabels("Local".to_string(), "Cloud".to_string())).kind(tauri::api::dialog::MessageDialogKind::Warning).parent(&window).show()
             {
-                return Err("Canceled by user");
+                return Err(Error::Other("Canceled by user"));
             }
         }
 
         manager.upload().await?;
+        hooks::trigger(&database, HookPayload::new(HookEvent::SyncCompleted));
         Ok(format!(
             "Last sync: {}",
             chrono::Local::now().time().format("%H:%M:%S")
         ))
     } else {
-        Err("Cloud is not enabled")
+        Err(Error::Other("Cloud is not enabled"))
     }
 }
+
+/// Sends progress of a one-off transfer to the main window.
+fn transfer_progress(app_handle: &AppHandle) -> impl FnMut(u64, u64) + '_ {
+    |transferred, total| {
+        crate::events::emit_to_main(
+            app_handle,
+            Event::TransferProgress(TransferProgress { transferred, total }),
+        )
+        .unwrap_or_default()
+    }
+}
+
+/// Copies the database to a path on an SSH server once, see [`cloud::push`]. Cloud settings are not used or changed. Progress is reported by [`Event::TransferProgress`].
+/// # Error
+/// Returns an error if a cloud transfer is in progress, if the connection fails or if the file cannot be copied.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn push_database_sftp(
+    address: SecretString,
+    username: SecretString,
+    auth: cloud::SshAuth,
+    remote_path: PathBuf,
+    app_handle: AppHandle,
+) -> Result<(), Error> {
+    let _permit = cloud::pause_transfers()?;
+    let session = cloud::connect(address.expose_secret(), username.expose_secret(), &auth)?;
+    let sftp = session.sftp().map_err(|_| "Failed to initialize sftp")?;
+    let database_path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    cloud::push(
+        &sftp,
+        &database_path,
+        &remote_path,
+        transfer_progress(&app_handle),
+    )
+}
+
+/// Replaces the database with a file from an SSH server once, after the user confirms it, see [`cloud::pull`]. The file must open with the key of the current database. The previous database is kept as a backup and the application restarts, so the new database is opened with the login. Cloud settings are not used or changed. Progress is reported by [`Event::TransferProgress`].
+/// # Error
+/// Returns an error if the user cancels it, if a cloud transfer is in progress, if the connection fails, if the file cannot be copied or if it does not open with the key of the current database.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn pull_database_sftp<'a>(
+    address: SecretString,
+    username: SecretString,
+    auth: cloud::SshAuth,
+    remote_path: PathBuf,
+    app_handle: AppHandle,
+    window: Window,
+    database: State<'a, Database>,
+) -> Result<(), Error> {
+    if tauri::api::dialog::blocking::MessageDialogBuilder::new(
+        "Replace database",
+        "The current database will be replaced by the downloaded one and kept as a backup. The application will restart.",
+    )
+    .buttons(tauri::api::dialog::MessageDialogButtons::OkCancelWithLabels(
+        "Replace".to_string(),
+        "Cancel".to_string(),
+    ))
+    .kind(tauri::api::dialog::MessageDialogKind::Warning)
+    .parent(&window)
+    .show()
+    .not()
+    {
+        return Err(Error::Other("Canceled by user"));
+    }
+
+    let permit = cloud::pause_transfers()?;
+    let session = cloud::connect(address.expose_secret(), username.expose_secret(), &auth)?;
+    let sftp = session.sftp().map_err(|_| "Failed to initialize sftp")?;
+    let database_path = Database::path(&app_handle).ok_or("Failed to get database path")?;
+    cloud::pull(
+        &sftp,
+        &remote_path,
+        &database_path,
+        &database.vault_secret()?,
+        transfer_progress(&app_handle),
+    )?;
+    drop(permit);
+
+    session::lock(&app_handle, SessionState::Locked);
+    Ok(())
+}
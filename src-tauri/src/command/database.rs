@@ -1,6 +1,23 @@
-use super::password::{check_password, PasswordProblem};
+use super::password;
 use super::*;
+use crate::activity::{self, ActivityCache, ActivitySummary};
+use crate::archive;
+use crate::database::listing::{RecordFilter, RecordSort, SortBy};
 use crate::database::model::SecretValue;
+use crate::database::retention::{self, RetentionReport, TableRetention};
+use crate::database::{
+    Acknowledgement, ContentVersion, Finding, TaggedRecord, UrlReplacement, VaultStats,
+};
+use crate::events::{self, CompromisedProgress, Event, ReMask};
+use crate::export;
+use crate::file_manager::{atomic_write, LastExport};
+use crate::hooks::{self, HookEvent, HookPayload};
+use crate::reauth::{self, Reauthentication};
+use crate::reveal::{self, RevealTimer, RevealedValue};
+use crate::search::{self, SearchResult};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use tauri::Runtime;
 
 /// Returns all records from the database.
 /// # Restart
@@ -16,48 +33,252 @@ pub async fn get_all_records<'a>(
         .map_err(|_| critical_error("Failed to load records", &app_handle, &window))
 }
 
-/// Returns ids of records that have compromised passwords. A password is considered compromised if it is a common password or if it is exposed in a data breach.
+/// Returns the record with the id, or `None` if it does not exist or is in the trash, see [`Database::get_record`].
+/// # Error
+/// Returns an error if the record cannot be loaded.
+#[tauri::command]
+pub async fn get_record<'a>(
+    id: u64,
+    database: State<'a, Database>,
+) -> Result<Option<Record>, Error> {
+    database.get_record(id)
+}
+
+/// Returns all records from the database with names of their tags, see [`Database::get_all_records_with_tags`].
 /// # Restart
 /// Restarts the application if any error occurs. Errors are shown in blocking dialogs.
 #[tauri::command]
+pub async fn get_all_records_with_tags<'a>(
+    database: State<'a, Database>,
+    app_handle: AppHandle,
+    window: Window,
+) -> Result<Vec<TaggedRecord>, ()> {
+    database
+        .get_all_records_with_tags()
+        .map_err(|_| critical_error("Failed to load records", &app_handle, &window))
+}
+
+/// Maximum number of records returned by [`get_records_window`] at once.
+const MAX_WINDOW: u64 = 500;
+
+/// Returns `count` records starting at index `start` of the record list filtered and sorted as given, for the virtual list. At most [`MAX_WINDOW`] records are returned.
+#[tauri::command]
+pub async fn get_records_window<'a>(
+    filter: RecordFilter,
+    sort: RecordSort,
+    start: u64,
+    count: u64,
+    database: State<'a, Database>,
+) -> Result<Vec<Record>, Error> {
+    database.get_records_window(&filter, sort, start, count.min(MAX_WINDOW))
+}
+
+/// Returns `limit` records starting at index `offset` of all records in the preset order, see [`Database::get_records_page`]. At most [`MAX_WINDOW`] records are returned.
+#[tauri::command]
+pub async fn get_records_page<'a>(
+    offset: u64,
+    limit: u64,
+    sort: SortBy,
+    database: State<'a, Database>,
+) -> Result<Vec<Record>, Error> {
+    database.get_records_page(offset, limit.min(MAX_WINDOW), sort)
+}
+
+/// Returns the number of records that are not in the trash, see [`get_records_page`].
+#[tauri::command]
+pub async fn get_record_count<'a>(database: State<'a, Database>) -> Result<u64, Error> {
+    database.count_records(&RecordFilter::default())
+}
+
+/// Moves the record between two records of the manual order, see [`Database::set_record_rank_between`]. Missing `prev_id` moves the record to the top, missing `next_id` to the bottom.
+/// # Error
+/// Returns an error if the record or a neighbor does not exist or the neighbors are not in order.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_record_rank_between<'a>(
+    id: u64,
+    prev_id: Option<u64>,
+    next_id: Option<u64>,
+    database: State<'a, Database>,
+) -> Result<(), Error> {
+    database.set_record_rank_between(id, prev_id, next_id)
+}
+
+/// Returns the number of records matching the filter, see [`get_records_window`].
+#[tauri::command]
+pub async fn count_records<'a>(
+    filter: RecordFilter,
+    database: State<'a, Database>,
+) -> Result<u64, Error> {
+    database.count_records(&filter)
+}
+
+/// Searches records by the query and returns them ranked, the best match first. With `deep`, field labels and values are searched too, only text, URL, email and phone number values, see [`Database::search_records`].
+#[tauri::command]
+pub async fn search_records<'a>(
+    query: String,
+    deep: bool,
+    database: State<'a, Database>,
+) -> Result<Vec<SearchResult>, Error> {
+    let records = database.search_records(&query, deep)?;
+    Ok(search::rank(&query, records))
+}
+
+/// Number of checked passwords after which [`get_compromised_records`] reports progress even if no compromised record was found.
+const COMPROMISED_PROGRESS_INTERVAL: usize = 50;
+
+/// Returns ids of records that have compromised passwords, ordered by id. A password is considered compromised if it is a common password or if it is exposed in a data breach. Passwords are read row by row in batches of [`password::MAX_GROUPED_PASSWORDS`] grouped by their hash ([`password::group_passwords`]), so records and other content are not loaded and each batch is checked before the next one is read. A password repeated in a later batch is answered from the cache of [`password::is_exposed`]. Records found compromised are reported to the window while the check runs ([`events::EventName::CompromisedProgress`]), the total grows with every batch. Records with the acknowledged [`Finding::Compromised`] are left out, unless `include_acknowledged` is set.
+/// # Restart
+/// Restarts the application if any error occurs. Errors are shown in blocking dialogs.
+#[tauri::command(rename_all = "snake_case")]
 pub async fn get_compromised_records<'a>(
+    include_acknowledged: Option<bool>,
     database: State<'a, Database>,
     app_handle: AppHandle,
     window: Window,
 ) -> Result<Vec<u64>, ()> {
-    let records = get_all_records(database.clone(), app_handle.clone(), window.clone()).await?;
-    let mut result: Vec<u64> = Vec::with_capacity(records.len());
-
-    for record in records {
-        let passwords = database
-            .get_all_passwords_for_record(record.id())
-            .map_err(|_| critical_error("Failed to load passwords", &app_handle, &window))?;
-
-        for password in passwords {
-            match check_password(password, database.clone()).await {
-                Ok(PasswordProblem::Common) | Ok(PasswordProblem::Exposed) => {
-                    result.push(record.id());
-                    break;
-                }
-                _ => continue,
+    let include_acknowledged = include_acknowledged.unwrap_or(false);
+    let mut found: HashSet<u64> = HashSet::new();
+    let (mut checked, mut total) = (0, 0);
+    let progress = |checked: usize, total: usize, records: Vec<u64>| {
+        let _ = events::emit_to_window(
+            &window,
+            Event::CompromisedProgress(CompromisedProgress {
+                checked,
+                total,
+                records,
+            }),
+        );
+    };
+
+    // Checked count of the last event, so the final one is not sent twice
+    let mut reported = None;
+    let mut after = Some(0);
+    while let Some(start) = after {
+        let groups = password::group_passwords(
+            &database,
+            include_acknowledged,
+            start,
+            password::MAX_GROUPED_PASSWORDS,
+        )
+        .map_err(|_| critical_error("Failed to load passwords", &app_handle, &window))?;
+        after = groups.next;
+        total += groups.hashes.len();
+        let common: Vec<u64> = groups
+            .common
+            .into_iter()
+            .filter(|id| found.insert(*id))
+            .collect();
+        if common.is_empty().not() || start == 0 {
+            progress(checked, total, common);
+            reported = Some(checked);
+        }
+
+        for (hash, ids) in groups.hashes {
+            checked += 1;
+            let new: Vec<u64> = ids
+                .into_iter()
+                .filter(|id| found.contains(id).not())
+                .collect();
+            // Records already found do not need another request
+            let exposed = new.is_empty().not()
+                && password::is_exposed(&hash, &database)
+                    .await
+                    .unwrap_or_default();
+            if exposed {
+                found.extend(&new);
+                progress(checked, total, new);
+                reported = Some(checked);
+            } else if checked % COMPROMISED_PROGRESS_INTERVAL == 0 {
+                progress(checked, total, Vec::new());
+                reported = Some(checked);
             }
         }
     }
+    if reported != Some(checked) {
+        progress(checked, total, Vec::new());
+    }
 
+    let mut result: Vec<u64> = found.into_iter().collect();
+    result.sort_unstable();
     Ok(result)
 }
 
-/// Returns all content for a specific record. If Record is new, it returns default content for the category. If content is TOTP secret, it is added to the TOTP manager.
+/// Returns ids of records with a password that has not changed in the last `days` days, see [`Database::get_stale_passwords`]. Acknowledged findings are left out, unless `include_acknowledged` is set.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_stale_passwords<'a>(
+    days: u32,
+    include_acknowledged: Option<bool>,
+    database: State<'a, Database>,
+) -> Result<Vec<u64>, Error> {
+    database.get_stale_passwords(days, include_acknowledged.unwrap_or(false))
+}
+
+/// Acknowledges the finding of the record with a note, so it is not reported until a password of the record changes, see [`Database::acknowledge_finding`].
+#[tauri::command]
+pub async fn acknowledge_finding<'a>(
+    id: u64,
+    finding: Finding,
+    note: String,
+    database: State<'a, Database>,
+) -> Result<(), Error> {
+    database.acknowledge_finding(id, finding, &note)
+}
+
+/// Returns all acknowledged findings, see [`acknowledge_finding`].
+#[tauri::command]
+pub async fn get_acknowledgements<'a>(
+    database: State<'a, Database>,
+) -> Result<Vec<Acknowledgement>, Error> {
+    database.get_acknowledgements()
+}
+
+/// Revokes the acknowledgement, so the finding of the record is reported again.
+#[tauri::command]
+pub async fn revoke_acknowledgement<'a>(
+    id: u64,
+    finding: Finding,
+    database: State<'a, Database>,
+) -> Result<(), Error> {
+    database.revoke_acknowledgement(id, finding)
+}
+
+/// Replaces the prefix of website values in all records, see [`Database::replace_url_prefix`]. With `dry_run`, the replacements are only returned for a preview.
+/// # Error
+/// Returns [`Error::NeedsReauth`] if an affected record is locked and the master password was not verified recently, or an error if the replacement is not valid.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn replace_url_prefix<'a>(
+    old_prefix: String,
+    new_prefix: String,
+    dry_run: bool,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+) -> Result<Vec<UrlReplacement>, Error> {
+    let replacements = database.replace_url_prefix(&old_prefix, &new_prefix, true)?;
+    let mut locked = false;
+    for replacement in replacements.iter() {
+        locked = locked || database.is_record_locked(replacement.id_record)?;
+    }
+    reauthentication.check(&database, locked)?;
+    match dry_run {
+        true => Ok(replacements),
+        false => database.replace_url_prefix(&old_prefix, &new_prefix, false),
+    }
+}
+
+/// Returns all content for a specific record. If Record is new, it returns default content for the category. If content is TOTP secret, it is added to the TOTP manager. Long texts contain only a preview, see [`Database::get_all_content_previews_for_record`].
+/// # Error
+/// Returns [`Error::NeedsReauth`] if the record is locked and the master password was not verified recently.
 /// # Restart
-/// Restarts the application if any error occurs. Errors are shown in blocking dialogs.
+/// Restarts the application if the content cannot be loaded. Errors are shown in blocking dialogs.
 #[tauri::command]
 pub async fn get_all_content_for_record<'a>(
     record: Record,
     database: State<'a, Database>,
     totp_manager: State<'a, TOTPManager>,
+    reauthentication: State<'a, Reauthentication>,
     app_handle: AppHandle,
     window: Window,
-) -> Result<Vec<Content>, ()> {
+) -> Result<Vec<Content>, Error> {
     if record.id() == 0 {
         let mut content: Vec<Content> = Vec::with_capacity(5);
         match record.category() {
@@ -121,18 +342,22 @@ pub async fn get_all_content_for_record<'a>(
                     Value::LongText(value::LongText::default()),
                 ));
             }
-            Category::Other => {}
+            Category::Other | Category::Custom(_) => {}
         }
         Ok(content)
     } else {
+        reauthentication.check(&database, database.is_record_locked(record.id())?)?;
         let content = database
-            .get_all_content_for_record(record.id())
-            .map_err(|_| critical_error("Failed to load content", &app_handle, &window))?;
+            .get_all_content_previews_for_record(record.id())
+            .map_err(|_| {
+                critical_error("Failed to load content", &app_handle, &window);
+                "Failed to load content"
+            })?;
 
         content.iter().for_each(|content| {
             if let Value::TOTPSecret(totp_secret) = content.value() {
                 totp_manager
-                    .add_secret(content.id(), totp_secret.value().to_string())
+                    .add_secret(content.id(), record.id(), totp_secret.value().to_string())
                     .unwrap_or_default();
             }
         });
@@ -142,57 +367,566 @@ pub async fn get_all_content_for_record<'a>(
 }
 
 /// Returns a specific content from the database. Does **not** manage TOTP secrets as [`get_all_content_for_record`] does.
+///
+/// If `expected_record_id` is given, the content must belong to that record, so a stale id cannot return a value of another record, see [`Database::get_record_content`].
+///
+/// Masked values are returned with a reveal token, the `re-mask` event with the token is emitted when the reveal duration from the settings elapses, see [`reveal::RevealTimer`].
+/// # Error
+/// Returns an error if the content cannot be loaded, [`crate::database::CONTENT_NOT_IN_RECORD`] if it belongs to another record than expected, or [`Error::NeedsReauth`] if its record is locked and the master password was not verified recently.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn get_content_value<'a, R: Runtime>(
+    id: u64,
+    expected_record_id: Option<u64>,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+    reveal_timer: State<'a, RevealTimer>,
+    app_handle: AppHandle<R>,
+) -> Result<RevealedValue, Error> {
+    reauthentication.check(&database, database.is_content_locked(id)?)?;
+    let content = match expected_record_id {
+        Some(id_record) => database.get_record_content(id, id_record)?,
+        None => database
+            .get_content(id)
+            .map_err(|_| "Failed to get content value")?,
+    };
+    let value = SecretValue::new(content.value().to_revealed_secret_string());
+    if content.value().is_masked().not() {
+        return Ok(RevealedValue {
+            value,
+            reveal_token: None,
+            reveal_seconds: None,
+        });
+    }
+    let duration = reveal::duration(&database);
+    let token = reveal_timer.start(duration, move |token| {
+        let _ = events::emit_all(&app_handle, Event::ReMask(ReMask { token }));
+    })?;
+    Ok(RevealedValue {
+        value,
+        reveal_token: Some(token),
+        reveal_seconds: Some(duration.as_secs()),
+    })
+}
+
+/// Returns previous values of the content, see [`Database::get_content_history`].
 /// # Error
-/// Returns an error if the content cannot be loaded.
+/// Returns [`Error::NeedsReauth`] if the record is locked and the master password was not verified recently, or an error if the history cannot be loaded.
 #[tauri::command]
-pub async fn get_content_value<'a>(
+pub async fn get_content_history<'a>(
     id: u64,
     database: State<'a, Database>,
-) -> Result<SecretValue, &'static str> {
-    database
-        .get_content(id)
-        .map(|content| SecretValue::new(content.value().to_secret_string()))
-        .map_err(|_| "Failed to get content value")
+    reauthentication: State<'a, Reauthentication>,
+) -> Result<Vec<ContentVersion>, Error> {
+    reauthentication.check(&database, database.is_content_locked(id)?)?;
+    database.get_content_history(id)
+}
+
+/// Restores a previous value of the content, see [`Database::restore_content_version`].
+/// # Error
+/// Returns [`Error::NeedsReauth`] if the record is locked and the master password was not verified recently, or an error if the version cannot be restored.
+#[tauri::command]
+pub async fn restore_content_version<'a>(
+    id: u64,
+    version: u64,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+) -> Result<(), Error> {
+    reauthentication.check(&database, database.is_content_locked(id)?)?;
+    database.restore_content_version(id, version)
+}
+
+/// Cancels the timer of a reveal, e.g. when the value was hidden by the user before the reveal duration elapsed.
+#[tauri::command]
+pub async fn cancel_reveal<'a>(
+    token: u64,
+    reveal_timer: State<'a, RevealTimer>,
+) -> Result<(), Error> {
+    reveal_timer.cancel(token);
+    Ok(())
+}
+
+/// Exports all records with their content to a CSV file chosen by the user, see [`export::to_csv`]. The user is warned that the file is not encrypted first. Locked records are left out and counted, unless `include_locked` is confirmed, which requires a recent verification of the master password like viewing their content. The file is written atomically and recorded as [`LastExport`].
+/// # Error
+/// Returns [`Error::NeedsReauth`] when including locked records without a recent verification, or an error if the user cancels it, or if the records cannot be loaded or the file cannot be written.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_csv<'a>(
+    include_locked: bool,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+    last_export: State<'a, LastExport>,
+    window: Window,
+) -> Result<ExportSummary, Error> {
+    if include_locked {
+        reauthentication.check(&database, true)?;
+    }
+    if tauri::api::dialog::blocking::MessageDialogBuilder::new(
+        "Export CSV",
+        "Passwords and all other values will be written to the file in plain text. Anyone with access to the file can read them.",
+    )
+    .buttons(tauri::api::dialog::MessageDialogButtons::OkCancelWithLabels(
+        "Export".to_string(),
+        "Cancel".to_string(),
+    ))
+    .kind(tauri::api::dialog::MessageDialogKind::Warning)
+    .parent(&window)
+    .show()
+    .not()
+    {
+        return Err(Error::Other("Canceled by user"));
+    }
+    let path = tauri::api::dialog::blocking::FileDialogBuilder::new()
+        .set_parent(&window)
+        .set_title("Export CSV")
+        .set_file_name("passwords.csv")
+        .save_file()
+        .ok_or("Canceled by user")?;
+
+    let (entries, locked) = export::collect(
+        database.get_all_records()?,
+        database.get_all_content_grouped()?,
+        include_locked,
+    );
+    let csv = export::to_csv(&entries)?;
+    atomic_write(&path, csv.as_slice()).map_err(Error::io("Failed to write file"))?;
+    last_export.set(path);
+    hooks::trigger(
+        &database,
+        HookPayload {
+            records: Some(entries.len()),
+            format: Some("csv"),
+            ..HookPayload::new(HookEvent::ExportFinished)
+        },
+    );
+    Ok(ExportSummary {
+        exported: entries.len(),
+        locked,
+    })
 }
 
-/// Saves a record to the database. Resets the TOTP manager.
+/// Format of [`export_inventory`].
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+pub enum InventoryFormat {
+    Csv,
+    Pdf,
+}
+
+/// Exports a list of records without any secrets to a CSV or PDF file chosen by the user, see [`export::InventoryItem`], and records it as [`LastExport`]. Records in the trash are left out, locked records are marked and listed without their content.
+/// # Return
+/// Returns the number of exported records.
+/// # Error
+/// Returns an error if the user cancels it, or if the records cannot be loaded or the file cannot be written.
+#[tauri::command]
+pub async fn export_inventory<'a>(
+    format: InventoryFormat,
+    database: State<'a, Database>,
+    last_export: State<'a, LastExport>,
+    window: Window,
+) -> Result<usize, Error> {
+    let (title, file_name) = match format {
+        InventoryFormat::Csv => ("Export Inventory CSV", "inventory.csv"),
+        InventoryFormat::Pdf => ("Export Inventory PDF", "inventory.pdf"),
+    };
+    let path = tauri::api::dialog::blocking::FileDialogBuilder::new()
+        .set_parent(&window)
+        .set_title(title)
+        .set_file_name(file_name)
+        .save_file()
+        .ok_or("Canceled by user")?;
+
+    let mut items = Vec::new();
+    for record in database.get_all_records()? {
+        let content = if record.locked() {
+            Vec::new()
+        } else {
+            database.get_all_content_previews_for_record(record.id())?
+        };
+        items.push(export::InventoryItem::new(&record, &content));
+    }
+    let file = match format {
+        InventoryFormat::Csv => export::to_inventory_csv(&items)?,
+        InventoryFormat::Pdf => export::to_inventory_pdf(&items),
+    };
+    atomic_write(&path, &file).map_err(Error::io("Failed to write file"))?;
+    last_export.set(path);
+    hooks::trigger(
+        &database,
+        HookPayload {
+            records: Some(items.len()),
+            format: Some(match format {
+                InventoryFormat::Csv => "inventory-csv",
+                InventoryFormat::Pdf => "inventory-pdf",
+            }),
+            ..HookPayload::new(HookEvent::ExportFinished)
+        },
+    );
+    Ok(items.len())
+}
+
+/// Result of [`export_encrypted_archive`] and [`export_csv`].
+#[derive(Debug, serde::Serialize)]
+pub struct ExportSummary {
+    /// Number of exported records.
+    pub exported: usize,
+    /// Number of records left out because they are locked.
+    pub locked: usize,
+}
+
+/// Exports all records with their content to a new archive encrypted with the passphrase, see [`archive::write`]. Metadata of integrations is included with `include_metadata`. Locked records are left out and counted, unless `include_locked` is confirmed, which requires a recent verification of the master password like viewing their content.
+/// # Error
+/// Returns [`Error::NeedsReauth`] when including locked records without a recent verification, or an error if the records cannot be loaded or the archive cannot be written.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_encrypted_archive<'a>(
+    path: PathBuf,
+    passphrase: SecretString,
+    include_locked: bool,
+    include_metadata: bool,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+    last_export: State<'a, LastExport>,
+) -> Result<ExportSummary, Error> {
+    if include_locked {
+        reauthentication.check(&database, true)?;
+    }
+    let metadata = match include_metadata {
+        true => database.get_all_metadata_grouped()?,
+        false => HashMap::new(),
+    };
+    let (records, locked) = archive::collect(
+        &database.get_all_records()?,
+        &database.get_all_content_grouped()?,
+        &metadata,
+        include_locked,
+    );
+    let exported = archive::write(&path, passphrase.expose_secret(), &records)?;
+    last_export.set(path);
+    hooks::trigger(
+        &database,
+        HookPayload {
+            records: Some(exported),
+            format: Some("archive"),
+            ..HookPayload::new(HookEvent::ExportFinished)
+        },
+    );
+    Ok(ExportSummary { exported, locked })
+}
+
+/// Result of [`export_notes_encrypted`].
+#[derive(Debug, serde::Serialize)]
+pub struct NotesExport {
+    /// Written files in the order of the notes.
+    files: Vec<PathBuf>,
+    /// Number of notes left out because they are locked.
+    locked: usize,
+}
+
+/// Exports every note record to its own Markdown file in the directory, encrypted with the passphrase like an archive, see [`archive::write_notes`]. The first file is recorded as [`LastExport`]. Locked notes are left out and counted, unless `include_locked` is confirmed, which requires a recent verification of the master password like viewing their content.
+/// # Error
+/// Returns [`Error::NeedsReauth`] when including locked notes without a recent verification, or an error if the records cannot be loaded or a file cannot be written.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_notes_encrypted<'a>(
+    directory: PathBuf,
+    passphrase: SecretString,
+    include_locked: bool,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+    last_export: State<'a, LastExport>,
+) -> Result<NotesExport, Error> {
+    if include_locked {
+        reauthentication.check(&database, true)?;
+    }
+    let (notes, locked) = archive::collect_notes(
+        &database.get_all_records()?,
+        &database.get_all_content_grouped()?,
+        include_locked,
+    );
+    let files = archive::write_notes(&directory, passphrase.expose_secret(), &notes)?;
+    if let Some(file) = files.first() {
+        last_export.set(file.clone());
+    }
+    hooks::trigger(
+        &database,
+        HookPayload {
+            records: Some(files.len()),
+            format: Some("notes"),
+            ..HookPayload::new(HookEvent::ExportFinished)
+        },
+    );
+    Ok(NotesExport { files, locked })
+}
+
+/// Record with its content, payload of [`save_record`].
+#[derive(serde::Deserialize)]
+struct RecordPayload {
+    record: Record,
+    content: Vec<Content>,
+}
+
+/// Saves a record with its content to the database and discards its draft. Content of the record that is not in the payload is deleted, see [`Database::save_record_replacing_content`]. Resets the TOTP manager.
+/// The record and content are sent as a JSON string `{"record": ..., "content": [...]}`, see [`parse_secret_json`].
 /// # Return
 /// Returns record id.
 /// # Error
-/// Returns an error if the record cannot be saved.
+/// Returns an error if the payload is not valid or the record cannot be saved.
 #[tauri::command]
 pub async fn save_record<'a>(
-    mut record: Record,
-    content: Vec<Content>,
+    payload: SecretString,
     database: State<'a, Database>,
     totp_manager: State<'a, TOTPManager>,
-) -> Result<u64, &'static str> {
-    database
-        .save_record(&mut record)
-        .map_err(|_| "Failed to save record")?;
-
-    for mut content in content {
-        database
-            .save_content(record.id(), &mut content)
-            .map_err(|_| "Failed to save content")?;
-    }
+) -> Result<u64, Error> {
+    let RecordPayload {
+        mut record,
+        mut content,
+    } = parse_secret_json(payload)?;
+    database.save_record_replacing_content(&mut record, &mut content)?;
 
     totp_manager.reset();
 
     Ok(record.id())
 }
 
-/// Deletes a record from the database.
+/// Saves a draft of the edited record, `id` is 0 for a new record. The snapshot is stored as is, the frontend decides its format.
+/// # Return
+/// Returns `false` if the draft was not written, because the last draft of the record is not older than [`crate::database::DRAFT_INTERVAL_SECONDS`].
+#[tauri::command]
+pub async fn save_draft<'a>(
+    id: u64,
+    snapshot: SecretString,
+    database: State<'a, Database>,
+) -> Result<bool, Error> {
+    database.save_draft(id, snapshot.expose_secret())
+}
+
+/// Returns the draft of the record, `id` is 0 for a new record.
+#[tauri::command]
+pub async fn get_draft<'a>(
+    id: u64,
+    database: State<'a, Database>,
+) -> Result<Option<SecretValue>, Error> {
+    database.get_draft(id)
+}
+
+/// Discards the draft of the record, `id` is 0 for a new record.
+#[tauri::command]
+pub async fn discard_draft<'a>(id: u64, database: State<'a, Database>) -> Result<(), Error> {
+    database.discard_draft(id)
+}
+
+/// Sets metadata of the record for an integration, see [`Database::set_record_metadata`].
+#[tauri::command]
+pub async fn set_record_metadata<'a>(
+    id: u64,
+    namespace: String,
+    key: String,
+    value: String,
+    database: State<'a, Database>,
+) -> Result<(), Error> {
+    database.set_record_metadata(id, &namespace, &key, &value)
+}
+
+/// Returns metadata of the record in the namespace as an object of keys and values.
+#[tauri::command]
+pub async fn get_record_metadata<'a>(
+    id: u64,
+    namespace: String,
+    database: State<'a, Database>,
+) -> Result<BTreeMap<String, String>, Error> {
+    database.get_record_metadata(id, &namespace)
+}
+
+/// Deletes the metadata key of the record in the namespace.
+#[tauri::command]
+pub async fn delete_record_metadata<'a>(
+    id: u64,
+    namespace: String,
+    key: String,
+    database: State<'a, Database>,
+) -> Result<(), Error> {
+    database.delete_record_metadata(id, &namespace, &key)
+}
+
+/// Adds the tag to the record, see [`Database::add_tag`].
+#[tauri::command]
+pub async fn add_tag<'a>(
+    id: u64,
+    name: String,
+    database: State<'a, Database>,
+) -> Result<(), Error> {
+    database.add_tag(id, &name)
+}
+
+/// Removes the tag from the record, see [`Database::remove_tag`].
+#[tauri::command]
+pub async fn remove_tag<'a>(
+    id: u64,
+    name: String,
+    database: State<'a, Database>,
+) -> Result<(), Error> {
+    database.remove_tag(id, &name)
+}
+
+/// Returns names of the tags of the record.
+#[tauri::command]
+pub async fn get_tags_for_record<'a>(
+    id: u64,
+    database: State<'a, Database>,
+) -> Result<Vec<String>, Error> {
+    database.get_tags_for_record(id)
+}
+
+/// Returns records with the tag.
+#[tauri::command]
+pub async fn get_records_by_tag<'a>(
+    name: String,
+    database: State<'a, Database>,
+) -> Result<Vec<Record>, Error> {
+    database.get_records_by_tag(&name)
+}
+
+/// Returns names of all tags.
+#[tauri::command]
+pub async fn get_all_tags<'a>(database: State<'a, Database>) -> Result<Vec<String>, Error> {
+    database.get_all_tags()
+}
+
+/// Locks or unlocks the record, see [`Record::locked`]. Unlocking requires a recent verification of the master password, like viewing the content.
 /// # Error
-/// Returns an error if the record cannot be deleted.
+/// Returns [`Error::NeedsReauth`] when unlocking without a recent verification, or an error if the record cannot be updated.
+///
+/// Locking cancels all pending reveals and masks their values right away.
 #[tauri::command]
-pub async fn delete_record<'a>(
-    record: Record,
+pub async fn set_record_locked<'a, R: Runtime>(
+    id: u64,
+    locked: bool,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+    reveal_timer: State<'a, RevealTimer>,
+    app_handle: AppHandle<R>,
+) -> Result<(), Error> {
+    if locked.not() {
+        reauthentication.check(&database, database.is_record_locked(id)?)?;
+    }
+    database.set_record_locked(id, locked)?;
+    if locked {
+        for token in reveal_timer.cancel_all() {
+            let _ = events::emit_all(&app_handle, Event::ReMask(ReMask { token }));
+        }
+    }
+    Ok(())
+}
+
+/// Marks the record as a favorite or removes the mark, see [`Record::favorite`]. Favorites are listed first.
+#[tauri::command]
+pub async fn set_favorite<'a>(
+    id: u64,
+    favorite: bool,
+    database: State<'a, Database>,
+) -> Result<(), Error> {
+    database.set_record_favorite(id, favorite)
+}
+
+/// Marks the recovery code with the given index as used, see [`Database::mark_recovery_code_used`].
+/// # Error
+/// Returns [`Error::NeedsReauth`] if the record is locked and the master password was not verified recently, or an error if the code cannot be marked.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn mark_recovery_code_used<'a>(
+    content_id: u64,
+    index: usize,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+) -> Result<(), Error> {
+    reauthentication.check(&database, database.is_content_locked(content_id)?)?;
+    database.mark_recovery_code_used(content_id, index)
+}
+
+/// Returns size of the database, size of its free pages and sizes before and after the last compaction.
+#[tauri::command]
+pub async fn vault_stats<'a>(database: State<'a, Database>) -> Result<VaultStats, Error> {
+    database.stats()
+}
+
+/// Returns rows kept in the auxiliary tables and rows deleted by their retention policies at login, see [`retention::run_retention`].
+#[tauri::command]
+pub async fn get_retention_stats<'a>(
+    database: State<'a, Database>,
+    report: State<'a, RetentionReport>,
+) -> Result<Vec<TableRetention>, Error> {
+    report.stats(&database)
+}
+
+/// Returns records added and modified in the last 7 and 30 days and the most recently modified records. The summary is cached for [`activity::CACHE_DURATION`].
+/// # Error
+/// Returns an error if the summary cannot be computed.
+#[tauri::command]
+pub async fn get_activity_summary<'a>(
     database: State<'a, Database>,
-) -> Result<(), &'static str> {
+    cache: State<'a, ActivityCache>,
+) -> Result<ActivitySummary, Error> {
+    cache.get_or_compute(std::time::Instant::now(), || {
+        activity::summary(&database, chrono::Utc::now())
+    })
+}
+
+/// Moves a record to the trash, see [`Database::delete_record`].
+/// # Error
+/// Returns an error if the record cannot be deleted.
+#[tauri::command]
+pub async fn delete_record<'a>(record: Record, database: State<'a, Database>) -> Result<(), Error> {
     database
         .delete_record(record)
-        .map_err(|_| "Failed to delete record")
+        .map_err(|_| Error::Other("Failed to delete record"))
+}
+
+/// Moves the records to the trash at once, see [`Database::delete_records`]. An empty list does nothing.
+/// # Error
+/// Returns an error if any of the records cannot be deleted, no record is deleted in that case.
+#[tauri::command]
+pub async fn delete_records<'a>(
+    ids: Vec<u64>,
+    database: State<'a, Database>,
+) -> Result<usize, Error> {
+    database.delete_records(&ids)
+}
+
+/// Moves the records to the category at once, see [`Database::move_records_to_category`]. An empty list does nothing.
+/// # Error
+/// Returns an error if any of the records cannot be moved, no record is moved in that case.
+#[tauri::command]
+pub async fn move_records_to_category<'a>(
+    ids: Vec<u64>,
+    category: Category,
+    database: State<'a, Database>,
+) -> Result<usize, Error> {
+    database.move_records_to_category(&ids, &category)
+}
+
+/// Repairs derived columns of all records and content, see [`Database::recompute_derived_columns`].
+/// # Error
+/// Returns an error if the columns cannot be recomputed, nothing is repaired in that case.
+#[tauri::command]
+pub async fn recompute_derived_columns<'a>(database: State<'a, Database>) -> Result<usize, Error> {
+    database.recompute_derived_columns()
+}
+
+/// Returns records in the trash, the most recently deleted first. Records are purged automatically 30 days after they were deleted.
+#[tauri::command]
+pub async fn get_trashed_records<'a>(database: State<'a, Database>) -> Result<Vec<Record>, Error> {
+    database.get_trashed_records()
+}
+
+/// Restores a record from the trash.
+/// # Error
+/// Returns an error if the record is not in the trash or cannot be restored.
+#[tauri::command]
+pub async fn restore_record<'a>(id: u64, database: State<'a, Database>) -> Result<(), Error> {
+    database.restore_record(id)
+}
+
+/// Deletes a record in the trash permanently.
+/// # Error
+/// Returns an error if the record is not in the trash or cannot be deleted.
+#[tauri::command]
+pub async fn purge_record<'a>(id: u64, database: State<'a, Database>) -> Result<(), Error> {
+    database.purge_record(id)
 }
 
 /// Deletes a content from the database.
@@ -202,8 +936,63 @@ pub async fn delete_record<'a>(
 pub async fn delete_content<'a>(
     content: Content,
     database: State<'a, Database>,
-) -> Result<(), &'static str> {
+) -> Result<(), Error> {
     database
         .delete_content(content)
-        .map_err(|_| "Failed to delete content")
+        .map_err(|_| Error::Other("Failed to delete content"))
+}
+
+/// Result of [`send_record_to_vault`].
+#[derive(Clone, serde::Serialize)]
+pub enum RecordTransfer {
+    Copied,
+    Duplicate,
+}
+
+/// Copies a record with all its content to another database file. The other database is opened only for the duration of the copy and its key and schema are left as they are, see [`Database::open_path_unchanged`]. The current database is not modified.
+/// # Return
+/// Returns [`RecordTransfer::Duplicate`] if the record already exists in the target database. Nothing is copied in that case.
+/// # Error
+/// Returns an error if the record is locked and the master password was not verified recently, if the target database does not exist, if the target password is wrong, if the target database is locked by another process, if it has a different schema version or if the record cannot be copied.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn send_record_to_vault<'a>(
+    record_id: u64,
+    target_path: PathBuf,
+    target_password: SecretString,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+    app_handle: AppHandle,
+) -> Result<RecordTransfer, Error> {
+    reauthentication.check(&database, database.is_record_locked(record_id)?)?;
+    if target_path.exists().not() {
+        return Err(Error::NotFound("Target database does not exist"));
+    }
+    if Database::path(&app_handle).and_then(|path| path.canonicalize().ok())
+        == target_path.canonicalize().ok()
+    {
+        return Err(Error::Validation(
+            "Target database is the current database".into(),
+        ));
+    }
+
+    let record = database
+        .get_all_records()?
+        .into_iter()
+        .find(|record| record.id() == record_id)
+        .ok_or(Error::NotFound("Record does not exist"))?;
+    let content = database.get_all_content_for_record(record_id)?;
+
+    let target = Database::open_path_unchanged(target_password.expose_secret(), &target_path)
+        .map_err(|error| match error {
+            Error::InvalidPassword => {
+                Error::Validation("Wrong password for the target database".into())
+            }
+            error => error,
+        })?;
+
+    Ok(if target.insert_record_copy(&record, &content, &[])? {
+        RecordTransfer::Copied
+    } else {
+        RecordTransfer::Duplicate
+    })
 }
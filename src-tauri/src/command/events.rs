@@ -0,0 +1,12 @@
+use crate::error::Error;
+use crate::events::{self, EventSchemas};
+
+/// Returns names of all backend events with JSON schemas of their payloads, so matching TypeScript types can be generated. Available only in development builds.
+#[tauri::command]
+pub async fn list_events() -> Result<EventSchemas, Error> {
+    if cfg!(debug_assertions) {
+        Ok(events::describe())
+    } else {
+        Err(Error::Other("Events are listed only in development builds"))
+    }
+}
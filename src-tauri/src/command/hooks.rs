@@ -0,0 +1,50 @@
+use super::*;
+use crate::hooks::{self, HookEvent, HookOutput, HookPayload};
+use std::collections::BTreeMap;
+
+/// Returns the command lines of all hooks by event, see [`hooks::load`].
+#[tauri::command]
+pub async fn get_hooks<'a>(
+    database: State<'a, Database>,
+) -> Result<BTreeMap<HookEvent, String>, Error> {
+    Ok(hooks::load(&database))
+}
+
+/// Sets the command line run on the event, see [`hooks::validate`]. Empty command line removes the hook.
+/// # Error
+/// Returns an error if the command line is not valid or the hooks cannot be saved.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_hook<'a>(
+    event: HookEvent,
+    command_line: String,
+    database: State<'a, Database>,
+) -> Result<(), Error> {
+    let mut hooks = hooks::load(&database);
+    if command_line.trim().is_empty() {
+        hooks.remove(&event);
+    } else {
+        hooks::validate(&command_line)?;
+        hooks.insert(event, command_line.trim().to_string());
+    }
+    hooks::save(&database, &hooks)
+}
+
+/// Runs the hook of the event with a sample payload and waits for it, so the user can verify the program.
+/// # Error
+/// Returns an error if no hook is set for the event, the program cannot be started or it does not finish within [`hooks::HOOK_TIMEOUT`].
+#[tauri::command]
+pub async fn test_hook<'a>(
+    event: HookEvent,
+    database: State<'a, Database>,
+) -> Result<HookOutput, Error> {
+    let command_line = hooks::load(&database)
+        .remove(&event)
+        .ok_or("No hook is set for the event")?;
+    let arguments = hooks::split(&command_line)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let child = hooks::start(&arguments, &HookPayload::new(event))?;
+        hooks::wait(child, hooks::HOOK_TIMEOUT)
+    })
+    .await
+    .map_err(|_| Error::Other("Failed to run hook"))?
+}
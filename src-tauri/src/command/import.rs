@@ -0,0 +1,278 @@
+use super::*;
+use crate::archive;
+use crate::events::{self, Event, ImportProgress};
+use crate::hooks::{self, HookEvent, HookPayload};
+use crate::import::bitwarden;
+use crate::import::browser::{self, ConflictPolicy, Decision, ExistingLogin};
+use crate::import::{self, generic, keepass, Converted, ImportSummary};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Loads a CSV file and returns its header, first rows and guessed mapping of columns to record fields ([`generic::preview`]).
+/// # Error
+/// Returns an error if the file cannot be read or parsed.
+#[tauri::command]
+pub async fn inspect_csv(path: PathBuf) -> Result<generic::Preview, Error> {
+    Ok(generic::preview(&generic::read(&path)?))
+}
+
+/// Imports records from a CSV file based on the mapping of column headers to record fields confirmed by the user. Fields are title, url, username, password, notes, category and totp. If `keep_unmapped` is set, unmapped columns are added as extra text content.
+/// Rows that fail validation are skipped and reported, all other records are saved in a single transaction.
+/// # Error
+/// Returns an error if the file cannot be read or parsed, if the mapping is invalid or if the records cannot be saved.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn import_csv_mapped<'a>(
+    path: PathBuf,
+    mapping: HashMap<String, String>,
+    keep_unmapped: bool,
+    database: State<'a, Database>,
+) -> Result<ImportSummary, Error> {
+    let table = generic::read(&path)?;
+    let Converted { records, failed } = generic::to_records(&table, &mapping, keep_unmapped)?;
+    let imported = database.import_records(&records)?;
+    let mut summary = ImportSummary {
+        imported,
+        updated: 0,
+        skipped: Vec::new(),
+        failed,
+        skipped_file: None,
+    };
+    summary.skipped_file = skipped_file(&database, || {
+        import::write_skipped_rows(&path, &table, &summary)
+    });
+    finished(&database, "csv", &summary);
+    Ok(summary)
+}
+
+/// Imports records from a CSV file in the generic format with title, category, website, username, password and notes columns ([`generic::GENERIC_FORMAT`]). Other columns are added as extra text content.
+/// Rows that fail validation are skipped and reported, all other records are saved in a single transaction.
+/// # Error
+/// Returns an error if the file cannot be read or parsed, if it has no title column or if the records cannot be saved.
+#[tauri::command]
+pub async fn import_csv<'a>(
+    path: PathBuf,
+    database: State<'a, Database>,
+) -> Result<ImportSummary, Error> {
+    let table = generic::read(&path)?;
+    let mapping = generic::generic_mapping(table.headers())?;
+    let Converted { records, failed } = generic::to_records(&table, &mapping, true)?;
+    let imported = database.import_records(&records)?;
+    let mut summary = ImportSummary {
+        imported,
+        updated: 0,
+        skipped: Vec::new(),
+        failed,
+        skipped_file: None,
+    };
+    summary.skipped_file = skipped_file(&database, || {
+        import::write_skipped_rows(&path, &table, &summary)
+    });
+    finished(&database, "csv", &summary);
+    Ok(summary)
+}
+
+/// Imports login, card and secure note items from an unencrypted Bitwarden JSON export ([`bitwarden::to_records`]). Values that fail validation are imported as text.
+/// Items of other types are skipped and reported with their position in the export, all other records are saved in a single transaction.
+/// # Error
+/// Returns an error if the file cannot be read, is not an unencrypted Bitwarden export or if the records cannot be saved.
+#[tauri::command]
+pub async fn import_bitwarden<'a>(
+    path: PathBuf,
+    database: State<'a, Database>,
+) -> Result<ImportSummary, Error> {
+    let export = bitwarden::read(&path)?;
+    let Converted { records, failed } = bitwarden::to_records(&export);
+    let imported = database.import_records(&records)?;
+    let mut summary = ImportSummary {
+        imported,
+        updated: 0,
+        skipped: Vec::new(),
+        failed,
+        skipped_file: None,
+    };
+    summary.skipped_file = skipped_file(&database, || {
+        import::write_skipped_positions(&path, &summary)
+    });
+    finished(&database, "bitwarden", &summary);
+    Ok(summary)
+}
+
+/// Imports entries of a KeePass database opened with its password ([`keepass::to_records`]). Group paths are kept as extra content, values that fail validation are imported as text.
+/// All records are saved in a single transaction, nothing is imported if any record cannot be saved.
+/// # Error
+/// Returns an error if the file cannot be read, the password is wrong or if the records cannot be saved.
+#[tauri::command]
+pub async fn import_keepass<'a>(
+    path: PathBuf,
+    password: SecretString,
+    database: State<'a, Database>,
+) -> Result<ImportSummary, Error> {
+    let keepass = keepass::read(&path, password.expose_secret())?;
+    let Converted { records, failed } = keepass::to_records(&keepass);
+    let imported = database.import_records(&records)?;
+    let mut summary = ImportSummary {
+        imported,
+        updated: 0,
+        skipped: Vec::new(),
+        failed,
+        skipped_file: None,
+    };
+    summary.skipped_file = skipped_file(&database, || {
+        import::write_skipped_positions(&path, &summary)
+    });
+    finished(&database, "keepass", &summary);
+    Ok(summary)
+}
+
+/// Imports records from an archive created by [`super::database::export_encrypted_archive`] and merges them into the database ([`archive::import`]). Records that already exist are skipped and reported with their position in the archive.
+/// # Error
+/// Returns [`archive::INVALID_ARCHIVE`] if the passphrase is wrong or the archive was modified, or an error if the file is not an archive or the records cannot be saved.
+#[tauri::command]
+pub async fn import_encrypted_archive<'a>(
+    path: PathBuf,
+    passphrase: SecretString,
+    database: State<'a, Database>,
+) -> Result<ImportSummary, Error> {
+    let entries = archive::read(&path, passphrase.expose_secret())?;
+    let mut summary = archive::import(&database, &entries)?;
+    summary.skipped_file = skipped_file(&database, || {
+        import::write_skipped_positions(&path, &summary)
+    });
+    finished(&database, "archive", &summary);
+    Ok(summary)
+}
+
+/// Imports notes written by [`super::database::export_notes_encrypted`] from the directory as note records ([`archive::read_notes`]). Notes that already exist, with the same title and creation time, are skipped and reported with their position in the order of file names.
+/// # Error
+/// Returns [`archive::INVALID_ARCHIVE`] if the passphrase is wrong or a note was modified, or an error if a note cannot be read or the records cannot be saved.
+#[tauri::command]
+pub async fn import_notes_encrypted<'a>(
+    directory: PathBuf,
+    passphrase: SecretString,
+    database: State<'a, Database>,
+) -> Result<ImportSummary, Error> {
+    let entries = archive::read_notes(&directory, passphrase.expose_secret())?;
+    let summary = archive::import(&database, &entries)?;
+    finished(&database, "notes", &summary);
+    Ok(summary)
+}
+
+/// Imports logins from a Chrome or Firefox password export ([`browser::to_logins`]). With `dedupe`, logins already stored for the same website and username are skipped and reported, and logins with a different password follow the policy ([`browser::decide`]). Progress is reported by [`Event::ImportProgress`].
+/// New records are saved in a single transaction, updated passwords are saved one by one.
+/// # Error
+/// Returns an error if the file cannot be read, is not a browser export or if the records cannot be saved.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn import_browser_csv<'a>(
+    path: PathBuf,
+    dedupe: bool,
+    policy: ConflictPolicy,
+    database: State<'a, Database>,
+    app_handle: AppHandle,
+) -> Result<ImportSummary, Error> {
+    let table = generic::read(&path)?;
+    let browser::Logins { logins, mut failed } = browser::to_logins(&table)?;
+    let total = logins.len();
+    let mut records = Vec::with_capacity(total);
+    let mut updated = 0;
+    let mut skipped = Vec::new();
+
+    for (index, login) in logins.iter().enumerate() {
+        let mut existing = Vec::new();
+        if dedupe {
+            for record in database
+                .find_records_for_url(login.url())
+                .unwrap_or_default()
+            {
+                let content = database.get_all_content_for_record(record.id())?;
+                existing.push(ExistingLogin::new(&record, &content));
+            }
+        }
+        match browser::decide(login, &existing, dedupe, policy) {
+            Decision::Insert => match browser::to_record(login) {
+                Ok(record) => records.push(record),
+                Err(error) => failed.push((login.line(), error.to_string())),
+            },
+            Decision::Skip(reason) => skipped.push((login.line(), reason.to_string())),
+            Decision::Update(id_record) => {
+                update_password(&database, id_record, login.password())?;
+                updated += 1;
+            }
+        }
+        events::emit_to_main(
+            &app_handle,
+            Event::ImportProgress(ImportProgress {
+                processed: index + 1,
+                total,
+            }),
+        )
+        .unwrap_or_default();
+    }
+
+    let imported = database.import_records(&records)?;
+    let mut summary = ImportSummary {
+        imported,
+        updated,
+        skipped,
+        failed,
+        skipped_file: None,
+    };
+    summary.skipped_file = skipped_file(&database, || {
+        import::write_skipped_rows(&path, &table, &summary)
+    });
+    finished(&database, "browser", &summary);
+    Ok(summary)
+}
+
+/// Writes the file with rows that were not imported, unless it is disabled ([`import::SKIPPED_FILE_SETTING`]). Records are already saved at this point, so a file that cannot be written is not an error.
+pub fn skipped_file(
+    database: &Database,
+    write: impl FnOnce() -> Result<Option<PathBuf>, Error>,
+) -> Option<PathBuf> {
+    if import::skipped_file_enabled(database) {
+        write().ok().flatten()
+    } else {
+        None
+    }
+}
+
+/// Runs the hook of [`HookEvent::ImportFinished`] with the number of imported and updated records.
+pub(super) fn finished(database: &Database, format: &'static str, summary: &ImportSummary) {
+    hooks::trigger(
+        database,
+        HookPayload {
+            records: Some(summary.imported + summary.updated),
+            format: Some(format),
+            ..HookPayload::new(HookEvent::ImportFinished)
+        },
+    );
+}
+
+/// Replaces the first password of the record.
+fn update_password(
+    database: &Database,
+    id_record: u64,
+    password: &SecretString,
+) -> Result<(), Error> {
+    let mut record = database
+        .get_all_records()?
+        .into_iter()
+        .find(|record| record.id() == id_record)
+        .ok_or("Record does not exist")?;
+    let content = database.get_all_content_for_record(id_record)?;
+    let mut content: Vec<Content> = content
+        .iter()
+        .find(|content| matches!(content.value(), Value::Password(_)))
+        .map(|content| {
+            let mut password = Content::new(
+                content.label().to_string(),
+                content.position(),
+                content.required(),
+                Value::Password(value::Password::new(password.expose_secret().to_string())),
+            );
+            password.set_id(content.id());
+            password
+        })
+        .into_iter()
+        .collect();
+    database.save_record_with_content(&mut record, &mut content)
+}
@@ -0,0 +1,18 @@
+use crate::error::Error;
+use crate::metrics::{CommandMetric, CommandMetrics};
+use std::collections::BTreeMap;
+use tauri::State;
+
+/// Returns call counts and durations of commands invoked since the start or the last reset, for finding slow commands. No arguments or results are recorded.
+#[tauri::command]
+pub async fn get_command_metrics<'a>(
+    metrics: State<'a, CommandMetrics>,
+) -> Result<BTreeMap<String, CommandMetric>, Error> {
+    metrics.snapshot().map_err(Error::from)
+}
+
+/// Forgets all recorded command calls, see [`get_command_metrics`].
+#[tauri::command]
+pub async fn reset_command_metrics<'a>(metrics: State<'a, CommandMetrics>) -> Result<(), Error> {
+    metrics.reset().map_err(Error::from)
+}
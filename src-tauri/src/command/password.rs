@@ -1,7 +1,12 @@
 use super::*;
 use crate::database::model::SecretValue;
+use crate::generator::{GeneratedPassword, GeneratorHistory};
+use crate::policy::{PasswordPolicy, PolicyViolation};
 use sha1::digest::generic_array::functional::FunctionalSequence;
 use sha1::{Digest, Sha1};
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::time::Duration;
 use tokio::sync::Semaphore;
 
 /// Indicates the problem with the password.
@@ -19,13 +24,13 @@ pub enum PasswordProblem {
 pub async fn check_password_from_database<'a>(
     id: u64,
     database: State<'a, Database>,
-) -> Result<PasswordProblem, &'static str> {
+) -> Result<PasswordProblem, Error> {
     let content = database
         .get_content(id)
         .map_err(|_| "Failed to load content")?;
 
     let Value::Password(password) = content.value() else {
-        return Err("Content is not a password");
+        return Err(Error::Validation("Content is not a password".into()));
     };
 
     let password = SecretValue::new(password.to_secret_string());
@@ -36,69 +41,167 @@ pub async fn check_password_from_database<'a>(
 /// Semaphore for [`check_password`].
 static SEM: Semaphore = Semaphore::const_new(1);
 
-/// Checks if the password is common or exposed. Uses https://haveibeenpwned.com API. Result is cached in the database. Uses a semaphore to prevent multiple requests for the same hash.
+/// How long [`check_password`] waits for another check to finish.
+const PERMIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Address of https://haveibeenpwned.com range API.
+const PWNED_PASSWORDS_API: &str = "https://api.pwnedpasswords.com";
+
+/// Returns whether the password with the SHA-1 hash is exposed. The cached status is used if there is one, otherwise it is requested from `api` and cached. Only one request runs at a time, the permit is released when the request finishes or fails.
+/// # Error
+/// If another request does not finish within [`PERMIT_TIMEOUT`] or if the request fails.
+async fn check_hash(
+    hash: &str,
+    database: &Database,
+    client: &reqwest::Client,
+    api: &str,
+) -> Result<bool, Error> {
+    if let Some(status) = database.get_data_breach_status(hash)? {
+        return Ok(status);
+    }
+    let exposed = {
+        let _permit = tokio::time::timeout(PERMIT_TIMEOUT, SEM.acquire())
+            .await
+            .map_err(|_| "Password check is busy, try again later")?
+            .map_err(|_| "Failed to acquire permit")?;
+        // Another check could have cached the status while waiting
+        if let Some(status) = database.get_data_breach_status(hash)? {
+            return Ok(status);
+        }
+        let (prefix, suffix) = hash.split_at(5);
+        let url = SecretString::new(format!("{api}/range/{prefix}"));
+        let response = SecretString::new(
+            client
+                .get(url.expose_secret())
+                .send()
+                .await
+                .map_err(|_| "Failed to get response")?
+                .text()
+                .await
+                .map_err(|_| "Failed to get response text")?,
+        );
+        response
+            .expose_secret()
+            .lines()
+            .any(|line| line.starts_with(suffix))
+    };
+    database.add_data_breach_cache(hash, exposed)?;
+    Ok(exposed)
+}
+
+/// Returns uppercase hex SHA-1 hash of the normalized password, see [`crate::database::normalize_password`]. The same password typed as composed or decomposed characters has the same hash, so it hits the same cache entry.
+fn password_hash(password: &str) -> SecretString {
+    let mut hasher = Sha1::new();
+    hasher.update(
+        crate::database::normalize_password(password)
+            .expose_secret()
+            .as_bytes(),
+    );
+    SecretString::new(
+        hasher
+            .finalize()
+            .fold(String::with_capacity(40), |mut acc, byte| {
+                acc.push_str(&format!("{:02x}", byte).to_uppercase());
+                acc
+            }),
+    )
+}
+
+/// Checks if the password is common or exposed. Uses https://haveibeenpwned.com API, see [`check_hash`]. Result is cached in the database.
 /// # Error
-/// If semaphore cannot be acquired or if the request fails.
+/// If another check is still running after [`PERMIT_TIMEOUT`] or if the request fails.
 #[tauri::command]
 pub async fn check_password<'a>(
     password: SecretValue,
     database: State<'a, Database>,
-) -> Result<PasswordProblem, &'static str> {
+) -> Result<PasswordProblem, Error> {
     if passwords::analyzer::is_common_password(password.expose_secret()) {
         return Ok(PasswordProblem::Common);
     }
-    let mut hasher = Sha1::new();
-    hasher.update(password.expose_secret().as_bytes());
-    let hash: SecretString = SecretString::new(hasher.finalize().fold(
-        String::with_capacity(40),
-        |mut acc, byte| {
-            acc.push_str(&format!("{:02x}", byte).to_uppercase());
-            acc
-        },
-    ));
-    let semaphore = SEM
-        .acquire()
-        .await
-        .map_err(|_| "Failed to acquire permit")?;
-    if let Some(status) = database.get_data_breach_status(hash.expose_secret())? {
-        return if status {
-            Ok(PasswordProblem::Exposed)
-        } else {
-            Ok(PasswordProblem::None)
-        };
-    }
-    let (prefix, suffix) = hash.expose_secret().split_at(5);
-    let url = SecretString::new(format!("https://api.pwnedpasswords.com/range/{}", prefix));
-    let response = SecretString::new(
-        reqwest::get(url.expose_secret())
-            .await
-            .map_err(|_| "Failed to get response")?
-            .text()
-            .await
-            .map_err(|_| "Failed to get response text")?,
-    );
-    let result = response
-        .expose_secret()
-        .lines()
-        .any(|line| line.starts_with(suffix));
-
-    drop(semaphore);
-    database.add_data_breach_cache(hash.expose_secret(), result)?;
+    let hash = password_hash(password.expose_secret());
+    let exposed = is_exposed(hash.expose_secret(), &database).await?;
 
-    Ok(if result {
+    Ok(if exposed {
         PasswordProblem::Exposed
     } else {
         PasswordProblem::None
     })
 }
 
+/// Returns whether the password with the SHA-1 hash ([`password_hash`]) is exposed. Uses https://haveibeenpwned.com API, see [`check_hash`].
+/// # Error
+/// If another check is still running after [`PERMIT_TIMEOUT`] or if the request fails.
+pub async fn is_exposed(hash: &str, database: &Database) -> Result<bool, Error> {
+    check_hash(hash, database, crate::http::client(), PWNED_PASSWORDS_API).await
+}
+
+/// Most distinct passwords grouped by one call of [`group_passwords`], so memory stays bounded and the check of the first passwords starts before all of them are read.
+pub const MAX_GROUPED_PASSWORDS: usize = 500;
+
+/// Passwords of records prepared for the search of compromised passwords, see [`group_passwords`].
+#[derive(Debug, Default, PartialEq)]
+pub struct PasswordGroups {
+    /// Ids of records with a common password.
+    pub common: Vec<u64>,
+    /// Ids of records with other passwords by the hash of the password ([`password_hash`]). Each distinct password of the batch is there once, so it is checked once.
+    pub hashes: BTreeMap<String, Vec<u64>>,
+    /// Id of the last grouped record, the next batch continues after it. Missing if all passwords were read.
+    pub next: Option<u64>,
+}
+
+/// Reads passwords of records with an id greater than `after` row by row ([`Database::scan_passwords`]) and groups them by their hash, so only hashes and record ids are kept in memory. Reading stops before the next record once `limit` distinct passwords are grouped, at least one record is always grouped. Common passwords are recognized right away and are not hashed. Records with the acknowledged [`crate::database::Finding::Compromised`] are left out, unless `include_acknowledged` is set.
+/// # Error
+/// If the passwords cannot be read.
+pub fn group_passwords(
+    database: &Database,
+    include_acknowledged: bool,
+    after: u64,
+    limit: usize,
+) -> Result<PasswordGroups, Error> {
+    let mut groups = PasswordGroups::default();
+    let mut last = None;
+    database.scan_passwords(include_acknowledged, after, |id_record, password| {
+        match last {
+            Some(last) if last == id_record => {}
+            Some(last) if groups.hashes.len() >= limit => {
+                groups.next = Some(last);
+                return ControlFlow::Break(());
+            }
+            _ => last = Some(id_record),
+        }
+        if passwords::analyzer::is_common_password(password) {
+            groups.common.push(id_record);
+        } else {
+            let ids = groups
+                .hashes
+                .entry(password_hash(password).expose_secret().clone())
+                .or_default();
+            if ids.last() != Some(&id_record) {
+                ids.push(id_record);
+            }
+        }
+        ControlFlow::Continue(())
+    })?;
+    groups.common.dedup();
+    Ok(groups)
+}
+
 /// Returns the strength of the password ([`passwords::scorer::score`])
 #[tauri::command]
 pub async fn password_strength(password: SecretValue) -> f64 {
     passwords::scorer::score(&passwords::analyzer::analyze(password.expose_secret()))
 }
 
-/// Generates a password using [`passwords::PasswordGenerator`].
+/// Returns all rules of the [`PasswordPolicy`] the new master password breaks, so the register and settings forms can show hints before submitting.
+#[tauri::command]
+pub async fn check_master_password<'a>(
+    password: SecretValue,
+    policy: State<'a, PasswordPolicy>,
+) -> Result<Vec<PolicyViolation>, Error> {
+    Ok(policy.check(password.expose_secret()))
+}
+
+/// Generates a password using [`passwords::PasswordGenerator`]. The password is added to the [`GeneratorHistory`].
 /// # Error
 /// If the password cannot be generated.
 #[tauri::command(rename_all = "snake_case")]
@@ -108,8 +211,9 @@ pub async fn generate_password<'a>(
     uppercase_letters: bool,
     lowercase_letters: bool,
     symbols: bool,
-) -> Result<SecretValue, &'static str> {
-    Ok(SecretValue::new(SecretString::new(
+    generator_history: State<'a, GeneratorHistory>,
+) -> Result<SecretValue, Error> {
+    let password = SecretString::new(
         passwords::PasswordGenerator {
             length,
             numbers,
@@ -121,5 +225,172 @@ pub async fn generate_password<'a>(
             strict: true,
         }
         .generate_one()?,
-    )))
+    );
+    generator_history.add(password.expose_secret());
+    Ok(SecretValue::new(password))
+}
+
+/// Returns passwords generated in this session, the most recent first.
+#[tauri::command]
+pub async fn get_generated_history<'a>(
+    generator_history: State<'a, GeneratorHistory>,
+) -> Result<Vec<GeneratedPassword>, ()> {
+    Ok(generator_history.get_all())
+}
+
+/// Removes all passwords from the [`GeneratorHistory`].
+#[tauri::command]
+pub async fn clear_generated_history<'a>(
+    generator_history: State<'a, GeneratorHistory>,
+) -> Result<(), ()> {
+    generator_history.clear();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::model::{value, Category, Content, Record, Value};
+    use crate::database::Finding;
+    use tempfile::TempDir;
+
+    /// Saves a login with the passwords and returns its id.
+    fn save_passwords(database: &Database, passwords: &[&str]) -> u64 {
+        let mut record = Record::new("Login".to_string(), "".to_string(), Category::Login);
+        let mut content: Vec<Content> = passwords
+            .iter()
+            .enumerate()
+            .map(|(position, password)| {
+                Content::new(
+                    "Password".to_string(),
+                    position as u32,
+                    false,
+                    Value::Password(value::Password::new(password.to_string())),
+                )
+            })
+            .collect();
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        record.id()
+    }
+
+    #[test]
+    fn test_group_passwords() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        let common = save_passwords(&database, &["password", "qwerty"]);
+        let shared = save_passwords(&database, &["Shared pass 1!", "Shared pass 1!"]);
+        let also_shared = save_passwords(&database, &["Shared pass 1!"]);
+        let acknowledged = save_passwords(&database, &["Acknowledged pass 2!"]);
+        database
+            .acknowledge_finding(acknowledged, Finding::Compromised, "")
+            .unwrap();
+        let trashed = save_passwords(&database, &["Trashed pass 3!"]);
+        database.delete_records(&[trashed]).unwrap();
+
+        let shared_hash = password_hash("Shared pass 1!").expose_secret().clone();
+        let groups = group_passwords(&database, false, 0, MAX_GROUPED_PASSWORDS).unwrap();
+        assert_eq!(
+            groups,
+            PasswordGroups {
+                common: vec![common],
+                hashes: BTreeMap::from([(shared_hash.clone(), vec![shared, also_shared])]),
+                next: None,
+            }
+        );
+        // Batches end before the next record once the limit is reached
+        let groups = group_passwords(&database, false, 0, 1).unwrap();
+        assert_eq!(
+            groups,
+            PasswordGroups {
+                common: vec![common],
+                hashes: BTreeMap::from([(shared_hash.clone(), vec![shared])]),
+                next: Some(shared),
+            }
+        );
+        let groups = group_passwords(&database, false, shared, 1).unwrap();
+        assert_eq!(
+            groups,
+            PasswordGroups {
+                common: Vec::new(),
+                hashes: BTreeMap::from([(shared_hash, vec![also_shared])]),
+                next: None,
+            }
+        );
+        // At least one record is grouped
+        assert_eq!(
+            group_passwords(&database, false, 0, 0).unwrap().next,
+            Some(common)
+        );
+        let groups = group_passwords(&database, true, 0, MAX_GROUPED_PASSWORDS).unwrap();
+        assert_eq!(groups.hashes.len(), 2);
+        assert_eq!(
+            groups.hashes[password_hash("Acknowledged pass 2!").expose_secret()],
+            [acknowledged]
+        );
+    }
+    #[tokio::test]
+    async fn test_check_hash_hanging_request() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        let cached = "0000000000000000000000000000000000000000";
+        database.add_data_breach_cache(cached, true).unwrap();
+
+        // Accepts connections and never responds
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((connection, _)) = listener.accept().await {
+                connections.push(connection);
+            }
+        });
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(300))
+            .build()
+            .unwrap();
+
+        let uncached = "1111111111111111111111111111111111111111";
+        let (hanging, cached_check) = tokio::join!(
+            check_hash(uncached, &database, &client, &api),
+            check_hash(cached, &database, &client, &api)
+        );
+        assert_eq!(hanging, Err(Error::Other("Failed to get response")));
+        assert_eq!(cached_check, Ok(true));
+
+        // Permit was released, so the next check is not busy
+        assert_eq!(
+            check_hash(uncached, &database, &client, &api).await,
+            Err(Error::Other("Failed to get response"))
+        );
+        assert_eq!(database.get_data_breach_status(uncached).unwrap(), None);
+    }
+    #[tokio::test]
+    async fn test_password_hash_normalized() {
+        assert_eq!(
+            password_hash("password").expose_secret(),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+        let composed = password_hash("caf\u{e9}");
+        let decomposed = password_hash("cafe\u{301}");
+        assert_eq!(composed.expose_secret(), decomposed.expose_secret());
+
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        database
+            .add_data_breach_cache(composed.expose_secret(), true)
+            .unwrap();
+        // Cache hit, the unreachable API is not used
+        assert_eq!(
+            check_hash(
+                decomposed.expose_secret(),
+                &database,
+                crate::http::client(),
+                "http://127.0.0.1:9"
+            )
+            .await,
+            Ok(true)
+        );
+    }
 }
@@ -1,12 +1,125 @@
 use super::*;
+use crate::file_manager::LastExport;
+use crate::hooks::{self, HookEvent, HookPayload};
+use crate::import::ImportSummary;
+use crate::totp::bundle;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
 
-/// Returns a TOTP code based on content id.
+/// Returns a TOTP code based on content id, its time to live and whether the system clock is off by more than half of the period. If `expected_record_id` is given, the secret must belong to that record.
 /// # Error
-/// Returns error when TOTP is not loaded into the TOTP manager or TOTP code cannot be generated
-#[tauri::command]
+/// Returns error when TOTP is not loaded into the TOTP manager or TOTP code cannot be generated, or [`crate::database::CONTENT_NOT_IN_RECORD`] if the secret belongs to another record than expected.
+#[tauri::command(rename_all = "snake_case")]
 pub async fn get_totp_code<'a>(
     id: u64,
+    expected_record_id: Option<u64>,
+    totp_manager: State<'a, TOTPManager>,
+) -> Result<(String, u64, bool), Error> {
+    if let Some(expected_record_id) = expected_record_id {
+        if totp_manager
+            .record_id(&id)
+            .is_some_and(|id_record| id_record != expected_record_id)
+        {
+            return Err(Error::NotFound(crate::database::CONTENT_NOT_IN_RECORD));
+        }
+    }
+    totp_manager
+        .get_code(&id)
+        .ok_or(Error::Other("Failed to get TOTP code"))
+}
+
+/// Server whose `Date` header is used to measure the clock offset. The application already uses it to check passwords.
+const TIME_SERVER: &str = "https://api.pwnedpasswords.com";
+
+/// Measures offset of the system clock in seconds from the `Date` header of [`TIME_SERVER`], see [`crate::totp::measure_offset`]. The offset is stored in the TOTP manager and applied to codes if [`crate::totp::COMPENSATE_SETTING`] is enabled.
+/// # Error
+/// Returns error when the request fails or the response has no valid date.
+#[tauri::command]
+pub async fn check_time_sync<'a>(
+    database: State<'a, Database>,
+    totp_manager: State<'a, TOTPManager>,
+) -> Result<i64, Error> {
+    let sent = Utc::now();
+    let response = crate::http::client()
+        .head(TIME_SERVER)
+        .send()
+        .await
+        .map_err(|_| "Failed to get response")?;
+    let received = Utc::now();
+    let server = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|date| date.to_str().ok())
+        .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+        .ok_or("Server did not send a valid date")?
+        .with_timezone(&Utc);
+
+    let offset = crate::totp::measure_offset(sent, received, server);
+    let compensate = database
+        .get_setting(crate::totp::COMPENSATE_SETTING)
+        .is_ok_and(|value| value.expose_secret() == "true");
+    totp_manager.set_clock_offset(offset, compensate);
+    Ok(offset)
+}
+
+/// Result of [`export_totp_bundle`].
+#[derive(Debug, serde::Serialize)]
+pub struct BundleExport {
+    /// Number of exported TOTP secrets.
+    exported: usize,
+    /// Number of TOTP secrets left out because their record is locked.
+    locked: usize,
+}
+
+/// Exports TOTP secrets of all records with their titles to a new bundle encrypted with the passphrase, see [`bundle::write`]. Secrets of locked records are left out and counted, unless `include_locked` is confirmed, which requires a recent verification of the master password like viewing their content.
+/// # Error
+/// Returns [`Error::NeedsReauth`] when including locked records without a recent verification, or an error if the bundle cannot be written.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn export_totp_bundle<'a>(
+    path: PathBuf,
+    passphrase: SecretString,
+    include_locked: bool,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+    last_export: State<'a, LastExport>,
+) -> Result<BundleExport, Error> {
+    if include_locked {
+        reauthentication.check(&database, true)?;
+    }
+    let (entries, locked) = bundle::collect(
+        &database.get_all_records()?,
+        &database.get_all_content_grouped()?,
+        include_locked,
+    );
+    let exported = bundle::write(&path, passphrase.expose_secret(), &entries)?;
+    last_export.set(path);
+    hooks::trigger(
+        &database,
+        HookPayload {
+            records: Some(exported),
+            format: Some("totp-bundle"),
+            ..HookPayload::new(HookEvent::ExportFinished)
+        },
+    );
+    Ok(BundleExport { exported, locked })
+}
+
+/// Imports TOTP secrets from a bundle created by [`export_totp_bundle`]. Records are matched by title, see [`bundle::import`].
+/// # Error
+/// Returns [`bundle::INVALID_BUNDLE`] if the passphrase is wrong or the bundle was modified, or an error if the records cannot be saved.
+#[tauri::command]
+pub async fn import_totp_bundle<'a>(
+    path: PathBuf,
+    passphrase: SecretString,
+    database: State<'a, Database>,
     totp_manager: State<'a, TOTPManager>,
-) -> Result<(String, u64), &'static str> {
-    totp_manager.get_code(&id).ok_or("Failed to get TOTP code")
+) -> Result<ImportSummary, Error> {
+    let entries = bundle::read(&path, passphrase.expose_secret())?;
+    let mut summary = bundle::import(&database, entries)?;
+    totp_manager.reset();
+    summary.skipped_file = super::import::skipped_file(&database, || {
+        crate::import::write_skipped_positions(&path, &summary)
+    });
+    super::import::finished(&database, "totp-bundle", &summary);
+    Ok(summary)
 }
@@ -1,7 +1,7 @@
 use super::*;
 use zeroize::Zeroize;
 
-/// Validates value based on its kind.
+/// Validates value based on its kind. Values longer than [`Value::max_length`] are invalid.
 /// - Number: Must be a valid number
 /// - LongText: Always valid
 /// - Date: Must be a valid date (YYYY-MM-DD)
@@ -10,11 +10,17 @@ use zeroize::Zeroize;
 /// - Email: Must be a valid email address ([`validator::validate_email`])
 /// - PhoneNumber: Must be a valid phone number ([`validator::validate_phone`])
 /// - BankCardNumber: Must be a valid bank card number ([`validate::card::from`])
+/// - QuestionAnswer: Must be a JSON object with non-empty question and answer ([`value::QuestionAnswer::from_json`])
+/// - RecoveryCodes: Must be a JSON array of objects with non-empty unique codes ([`value::RecoveryCodes::from_json`])
 /// - Other: Must not be empty
 /// # Return
 /// If the value is valid, returns `None`. If the value is invalid, returns an error message.
 #[tauri::command]
 pub async fn validate(kind: SecretString, value: SecretString) -> Option<String> {
+    if value.expose_secret().len() > Value::max_length(kind.expose_secret()) {
+        return Some("Value is too long".to_string());
+    }
+
     match kind.expose_secret().as_str() {
         "Number" => {
             if value
@@ -85,6 +91,12 @@ pub async fn validate(kind: SecretString, value: SecretString) -> Option<String>
                 .to_string(),
             ),
         },
+        "QuestionAnswer" => value::QuestionAnswer::from_json(value.expose_secret())
+            .err()
+            .map(|error| error.to_string()),
+        "RecoveryCodes" => value::RecoveryCodes::from_json(value.expose_secret())
+            .err()
+            .map(|error| error.to_string()),
         _ => {
             if value.expose_secret().trim().is_empty() {
                 Some("Value cannot be empty".to_string())
@@ -99,14 +111,14 @@ pub async fn validate(kind: SecretString, value: SecretString) -> Option<String>
 /// # Error
 /// Returns an error if content cannot be loaded, if the content is not a bank card number or if the card type cannot be evaluated.
 #[tauri::command]
-pub async fn card_type<'a>(id: u64, database: State<'a, Database>) -> Result<String, &'static str> {
+pub async fn card_type<'a>(id: u64, database: State<'a, Database>) -> Result<String, Error> {
     let card_number = {
         let content = database
             .get_content(id)
             .map_err(|_| "Failed to load content")?;
 
         let Value::BankCardNumber(card_number) = content.value() else {
-            return Err("Content is not a password");
+            return Err(Error::Validation("Content is not a password".into()));
         };
 
         card_number.to_secret_string()
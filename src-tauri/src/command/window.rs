@@ -1,4 +1,8 @@
 use super::*;
+use crate::changelog;
+use crate::file_manager::{self, LastExport, PathKind};
+use crate::panic::{self, AppPanic};
+use crate::presentation;
 use crate::window::*;
 
 /// Window types that can be created.
@@ -9,17 +13,117 @@ pub enum WindowType {
     Main,
 }
 
+/// Window type with the reason why the database is opened in read-only mode, if it is, and whether the changes since the last seen version should be shown.
+#[derive(Clone, serde::Serialize)]
+pub struct WindowInitialization {
+    window: WindowType,
+    read_only_reason: Option<&'static str>,
+    show_whats_new: bool,
+}
+
 /// Creates specific window based on the database state and returns the window type.
 #[tauri::command]
-pub fn initialize_window<'a>(app_handle: AppHandle) -> tauri::Result<WindowType> {
-    if app_handle.try_state::<Database>().is_some() {
+pub fn initialize_window<'a>(app_handle: AppHandle) -> tauri::Result<WindowInitialization> {
+    let read_only_reason = app_handle
+        .try_state::<Database>()
+        .and_then(|database| database.read_only_reason());
+    let show_whats_new = app_handle.try_state::<Database>().is_some_and(|database| {
+        changelog::show_whats_new(&database, &app_handle.package_info().version)
+    });
+    let window = if app_handle.try_state::<Database>().is_some() {
         create_main_window(app_handle)?;
-        Ok(WindowType::Main)
+        WindowType::Main
     } else if Database::exists(&app_handle) {
         create_login_window(app_handle)?;
-        Ok(WindowType::Login)
+        WindowType::Login
     } else {
         create_register_window(app_handle)?;
-        Ok(WindowType::Register)
+        WindowType::Register
+    };
+    Ok(WindowInitialization {
+        window,
+        read_only_reason,
+        show_whats_new,
+    })
+}
+
+/// Opens the presentation window with the title and content of the record, sensitive values are masked by the backend, see [`presentation::redact`]. Only one presentation window can be open.
+/// # Error
+/// Returns [`Error::NeedsReauth`] if the record is locked and the master password was not verified recently, or an error if the record cannot be loaded or the presentation window is already open.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn open_presentation_window<'a>(
+    record_id: u64,
+    database: State<'a, Database>,
+    reauthentication: State<'a, Reauthentication>,
+    app_handle: AppHandle,
+) -> Result<(), Error> {
+    reauthentication.check(&database, database.is_record_locked(record_id)?)?;
+    let record = database
+        .get_all_records()?
+        .into_iter()
+        .find(|record| record.id() == record_id)
+        .ok_or(Error::NotFound("Record does not exist"))?;
+    let content = database.get_all_content_for_record(record_id)?;
+    presentation::open(&app_handle, &presentation::redact(&record, &content)).map_err(Error::from)
+}
+
+/// Opens the file manager with the file of the given kind selected. Only paths resolved by [`file_manager::resolve`] are opened.
+/// # Error
+/// Returns an error if the path is not available or if the file manager cannot be opened.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn reveal_in_file_manager<'a>(
+    path_kind: PathKind,
+    last_export: State<'a, LastExport>,
+    app_handle: AppHandle,
+) -> Result<(), Error> {
+    let path = file_manager::resolve(
+        path_kind,
+        Database::path(&app_handle),
+        app_handle.path_resolver().app_local_data_dir(),
+        last_export.get(),
+    )?;
+    file_manager::reveal(&path).map_err(Error::from)
+}
+
+/// Sets the keyboard shortcut of the main menu item, empty shortcut removes it. The menu is built when the main window is created, so the change applies the next time the main window opens.
+/// # Error
+/// Returns an error if the item does not exist, the shortcut is not valid or is already used.
+#[tauri::command(rename_all = "snake_case")]
+pub async fn set_menu_accelerator<'a>(
+    item_id: String,
+    accelerator: String,
+    database: State<'a, Database>,
+) -> Result<(), String> {
+    accelerator::save(&database, &item_id, &accelerator)
+}
+
+/// Sets the global shortcut of the panic lock, empty shortcut removes it, see [`panic::panic_lock`]. The shortcut works while the application is running, even when it is not focused.
+/// # Error
+/// Returns an error if the shortcut is not valid or is already used. The previous shortcut is kept in that case.
+#[tauri::command]
+pub async fn set_panic_shortcut<'a>(
+    shortcut: String,
+    database: State<'a, Database>,
+    app_handle: AppHandle,
+) -> Result<(), Error> {
+    let shortcut = accelerator::parse(&shortcut)?;
+    let previous = database
+        .get_setting(panic::SHORTCUT_SETTING)
+        .map(|previous| previous.expose_secret().to_string())
+        .unwrap_or_default();
+    if let Err(error) = panic::register(&app_handle, &previous, &shortcut) {
+        panic::register(&app_handle, "", &previous).unwrap_or_default();
+        return Err(error.into());
+    }
+    if shortcut.is_empty() {
+        database.delete_settings(&[panic::SHORTCUT_SETTING])
+    } else {
+        database.save_setting(panic::SHORTCUT_SETTING, &shortcut)
     }
 }
+
+/// Clears the clipboard, hides all windows and locks the vault, same as the panic shortcut ([`panic::panic_lock`]).
+#[tauri::command]
+pub async fn panic_lock(app_handle: AppHandle) {
+    panic::panic_lock(&AppPanic(app_handle));
+}
@@ -1,22 +1,775 @@
 mod convert;
+pub mod listing;
+mod lock;
+mod migration;
 pub mod model;
+pub mod retention;
 
 use super::*;
 use crate::database::model::value::ToSecretString;
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use lock::VaultLock;
 use model::*;
-use rusqlite::{params, Connection, OptionalExtension, Result};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Result, TransactionBehavior};
 use secrecy::{ExposeSecret, SecretString};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::ops::Not;
-use std::path::PathBuf;
+use std::io::Read;
+use std::ops::{ControlFlow, Not};
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
 
 /// Name of the database file.
 pub const DATABASE_FILE_NAME: &str = "database.password_manager";
 
+/// Reason of the read-only mode when the data directory is not writable, shown by the window, see [`Database::read_only_reason`].
+pub const READ_ONLY_DIRECTORY: &str = "Data directory is not writable, changes can not be saved";
+
+/// Returns LIKE pattern matching values that contain the query. Wildcards in the query are escaped with a backslash.
+fn like_pattern(query: &str) -> String {
+    let escaped = query
+        .trim()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// Returns the text folded for case- and accent-insensitive search: decomposed (NFKD) without combining marks and lowercased. Dotless ı is folded to i and ß to ss, so Turkish and German words also match their ASCII spelling.
+pub fn normalize_search(text: &str) -> String {
+    text.nfkd()
+        .filter(|character| unicode_normalization::char::is_combining_mark(*character).not())
+        .flat_map(char::to_lowercase)
+        .map(|character| match character {
+            'ı' => 'i',
+            _ => character,
+        })
+        .collect::<String>()
+        .replace('ß', "ss")
+}
+
+/// Maximum length of a metadata value in bytes, see [`Database::set_record_metadata`].
+pub const MAX_METADATA_LENGTH: usize = 4 * 1024;
+
+/// Metadata entry of a record, see [`Database::set_record_metadata`].
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MetadataEntry {
+    pub namespace: String,
+    pub key: String,
+    pub value: String,
+}
+
+/// Maximum length of a tag name in characters, see [`Database::add_tag`].
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// Record with names of its tags, see [`Database::get_all_records_with_tags`].
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct TaggedRecord {
+    #[serde(flatten)]
+    pub record: Record,
+    pub tags: Vec<String>,
+}
+
+/// Number of previous values kept for every content when the history is pruned at login, see [`Database::prune_history`].
+pub const HISTORY_KEEP: usize = 10;
+
+/// Previous value of a content, see [`Database::get_content_history`].
+#[derive(Debug, serde::Serialize)]
+pub struct ContentVersion {
+    pub id: u64,
+    pub kind: String,
+    pub value: SecretValue,
+    /// Time when the value was replaced.
+    pub replaced: chrono::DateTime<chrono::Utc>,
+}
+
+/// Website value changed by [`Database::replace_url_prefix`].
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct UrlReplacement {
+    pub id_record: u64,
+    pub id_content: u64,
+    pub before: String,
+    pub after: String,
+}
+
+/// Replaces the prefix of the URL. The prefix matches only at a boundary: it ends with `/`, `?` or `#`, or the rest of the URL is empty or starts with one of `/`, `?`, `#` or `:`, so `https://sso.example.com` does not match `https://sso.example.com.evil.org`.
+/// # Return
+/// Returns `None` if the prefix does not match.
+fn replace_prefix(url: &str, old_prefix: &str, new_prefix: &str) -> Option<String> {
+    let rest = url.strip_prefix(old_prefix)?;
+    let boundary = old_prefix.ends_with(['/', '?', '#'])
+        || rest.is_empty()
+        || rest.starts_with(['/', '?', '#', ':']);
+    boundary.then(|| format!("{new_prefix}{rest}"))
+}
+
+/// Maximum length of an acknowledgement note in characters, see [`Database::acknowledge_finding`].
+pub const MAX_ACKNOWLEDGEMENT_NOTE_LENGTH: usize = 256;
+
+/// Password audit finding of a record that can be acknowledged, see [`Database::acknowledge_finding`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, serde::Serialize, serde::Deserialize)]
+pub enum Finding {
+    /// Common or exposed password, see [`crate::command::database::get_compromised_records`].
+    Compromised,
+    /// Password not changed for a long time, see [`Database::get_stale_passwords`].
+    Stale,
+}
+
+impl Finding {
+    /// Converts a string to a finding, None for unknown findings.
+    pub fn from_string(finding: &str) -> Option<Finding> {
+        match finding {
+            "Compromised" => Some(Finding::Compromised),
+            "Stale" => Some(Finding::Stale),
+            _ => None,
+        }
+    }
+    /// Converts a finding to a string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Finding::Compromised => "Compromised",
+            Finding::Stale => "Stale",
+        }
+    }
+}
+
+/// Finding the user accepted for a record, so it is not reported, see [`Database::acknowledge_finding`].
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct Acknowledgement {
+    pub id_record: u64,
+    pub finding: Finding,
+    pub note: String,
+    pub acknowledged: chrono::DateTime<chrono::Utc>,
+}
+
+/// Error returned when a content requested with its record does not belong to that record, see [`Database::get_record_content`].
+pub const CONTENT_NOT_IN_RECORD: &str = "Content does not belong to the record";
+
+/// Minimal time between two writes of a draft of the same record.
+pub const DRAFT_INTERVAL_SECONDS: u32 = 5;
+
+/// Setting which enables compaction of the database when the application exits, see [`Database::compact`].
+pub const COMPACT_SETTING: &str = "compact_on_close";
+
+/// Minimum size of the database file in bytes for compaction on close. Smaller files are not worth rebuilding.
+pub const COMPACT_THRESHOLD: u64 = 1024 * 1024;
+
+/// Setting with sizes of the database before and after the last compaction.
+const LAST_COMPACTION_SETTING: &str = "last_compaction";
+
+/// Size of the database file and of its free pages in bytes, and sizes before and after the last compaction.
+#[derive(Debug, PartialEq, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct VaultStats {
+    pub size: u64,
+    pub free: u64,
+    pub last_compaction: Option<(u64, u64)>,
+}
+
+/// Returns size of the database and of its free pages in bytes.
+fn size(connection: &Connection) -> Result<(u64, u64), Error> {
+    let pragma = |name: &str| {
+        connection
+            .pragma_query_value(None, name, |row| row.get::<_, u64>(0))
+            .map_err(Error::database("Failed to get database size"))
+    };
+    let page_size = pragma("page_size")?;
+    Ok((
+        pragma("page_count")? * page_size,
+        pragma("freelist_count")? * page_size,
+    ))
+}
+
+/// Long text values longer than this are stored with a preview of this length in bytes.
+pub const PREVIEW_LENGTH: usize = 1024;
+
+/// Returns preview of the long text value, the first [`PREVIEW_LENGTH`] bytes cut at a character boundary. Other kinds and short values have no preview.
+fn preview(kind: &str, value: &str) -> Option<Zeroizing<String>> {
+    if kind != "LongText" || value.len() <= PREVIEW_LENGTH {
+        return None;
+    }
+    let end = (0..=PREVIEW_LENGTH)
+        .rev()
+        .find(|index| value.is_char_boundary(*index))
+        .unwrap_or_default();
+    Some(Zeroizing::new(value[..end].to_string()))
+}
+
+/// Setting which disables deriving the subtitle of saved records from their content, see [`derive_subtitle`]. Enabled unless set to `false`.
+pub const AUTO_SUBTITLE_SETTING: &str = "auto_subtitle";
+
+/// Maximum length of a subtitle derived from a note, in characters.
+const NOTE_SUBTITLE_LENGTH: usize = 40;
+
+/// Derives the subtitle of the record from its content: user or email of logins, masked number of bank cards (`•••• 1234`) and the first line of notes, cut to [`NOTE_SUBTITLE_LENGTH`] characters. The full card number never gets to the subtitle, which is shown in the record list.
+/// # Return
+/// Returns `None` if the category has no rule or the content is empty, then the subtitle is kept.
+pub fn derive_subtitle(category: &Category, content: &[Content]) -> Option<String> {
+    let first = |matches: &dyn Fn(&Content) -> bool| {
+        content
+            .iter()
+            .filter(|content| matches(content))
+            .map(|content| content.value().to_secret_string())
+            .find(|value| value.expose_secret().trim().is_empty().not())
+    };
+    match category {
+        Category::Login => {
+            first(&|content| content.label() == "User" && matches!(content.value(), Value::Text(_)))
+                .or_else(|| first(&|content| matches!(content.value(), Value::Email(_))))
+                .map(|value| value.expose_secret().trim().to_string())
+        }
+        Category::BankCard => first(&|content| matches!(content.value(), Value::BankCardNumber(_)))
+            .map(|number| {
+                let digits: Zeroizing<Vec<char>> = Zeroizing::new(
+                    number
+                        .expose_secret()
+                        .chars()
+                        .filter(char::is_ascii_digit)
+                        .collect(),
+                );
+                let last: String = digits[digits.len().saturating_sub(4)..].iter().collect();
+                format!("•••• {last}")
+            }),
+        Category::Note => {
+            first(&|content| matches!(content.value(), Value::LongText(_))).map(|note| {
+                let line = note
+                    .expose_secret()
+                    .trim()
+                    .lines()
+                    .next()
+                    .unwrap_or_default();
+                line.trim().chars().take(NOTE_SUBTITLE_LENGTH).collect()
+            })
+        }
+        Category::Other | Category::Custom(_) => None,
+    }
+}
+
+/// Checks that the content can be saved: the value is not longer than [`Value::max_length`] and it is not only a preview.
+fn check_content(content: &Content, value: &str) -> Result<(), Error> {
+    if content.truncated() {
+        return Err(Error::Validation("Full value was not loaded".into()));
+    }
+    if value.len() > Value::max_length(content.kind()) {
+        return Err(Error::Validation("Value is too long".into()));
+    }
+    Ok(())
+}
+
+/// Checks that the metadata can be saved: namespace and key are not blank and the value is not longer than [`MAX_METADATA_LENGTH`].
+fn check_metadata(namespace: &str, key: &str, value: &str) -> Result<(), Error> {
+    if namespace.trim().is_empty() || key.trim().is_empty() {
+        return Err(Error::Validation(
+            "Namespace and key can not be empty".into(),
+        ));
+    }
+    if value.len() > MAX_METADATA_LENGTH {
+        return Err(Error::Validation("Value is too long".into()));
+    }
+    Ok(())
+}
+
+/// Returns the trimmed tag name.
+/// # Errors
+/// If the name is blank or longer than [`MAX_TAG_LENGTH`].
+fn check_tag(name: &str) -> Result<&str, Error> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::Validation("Tag can not be empty".into()));
+    }
+    if name.chars().count() > MAX_TAG_LENGTH {
+        return Err(Error::Validation("Tag is too long".into()));
+    }
+    Ok(name)
+}
+
+/// Inserts the record with its content as new rows and returns the id of the record. Derived columns are computed the same way as when a record is saved: the subtitle is derived from the content if `derive_subtitle` is true ([`derive_subtitle`]), title and subtitle are normalized for search and long texts get their previews. Records inserted by imports and copies go through this function. Copies of locked records stay locked and copies of favorites stay favorites.
+fn insert_record_full(
+    connection: &Connection,
+    record: &Record,
+    content: &[Content],
+    derive_subtitle: bool,
+) -> Result<u64, Error> {
+    let derived = derive_subtitle
+        .then(|| self::derive_subtitle(record.category(), content))
+        .flatten();
+    let subtitle = derived.as_deref().unwrap_or(record.subtitle());
+    connection
+        .execute(
+            "INSERT INTO Record (title, subtitle, created, last_modified, category, locked, favorite, title_normalized, subtitle_normalized) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);",
+            params![
+                record.title(),
+                subtitle,
+                record.created(),
+                record.last_modified(),
+                record.category().as_str(),
+                record.locked(),
+                record.favorite(),
+                normalize_search(record.title()),
+                normalize_search(subtitle)
+            ],
+        )
+        .map_err(Error::database("Failed to save record"))?;
+    let id_record = connection.last_insert_rowid() as u64;
+    for content in content {
+        check_content(content, content.value().to_secret_string().expose_secret())?;
+        insert_content(connection, id_record, content)
+            .map_err(Error::database("Failed to save content"))?;
+    }
+    Ok(id_record)
+}
+
+/// Deletes records matching the condition on the Record table with all their content, content history, metadata, tag links and drafts. Tags left without records are deleted too.
+/// # Return
+/// Returns the number of deleted records.
+fn purge_records(
+    connection: &Connection,
+    condition: &str,
+    params: impl rusqlite::Params + Copy,
+) -> Result<usize> {
+    connection.execute(
+        &format!(
+            "DELETE FROM ContentHistory WHERE id_content IN (SELECT id_content FROM Content WHERE id_record IN (SELECT id_record FROM Record WHERE {condition}));"
+        ),
+        params,
+    )?;
+    for table in [
+        "Content",
+        "RecordMetadata",
+        "RecordTag",
+        "Acknowledgement",
+        "Drafts",
+    ] {
+        connection.execute(
+            &format!(
+                "DELETE FROM {table} WHERE id_record IN (SELECT id_record FROM Record WHERE {condition});"
+            ),
+            params,
+        )?;
+    }
+    let purged = connection.execute(&format!("DELETE FROM Record WHERE {condition};"), params)?;
+    delete_unused_tags(connection)?;
+    Ok(purged)
+}
+
+/// Deletes tags that are not linked to any record.
+fn delete_unused_tags(connection: &Connection) -> Result<usize> {
+    connection.execute(
+        "DELETE FROM Tag WHERE id_tag NOT IN (SELECT id_tag FROM RecordTag);",
+        [],
+    )
+}
+
+/// Inserts the content as a new row of the given record. The value keeps the time of its last change if the content has one ([`Content::value_last_changed`]), otherwise the last modification of the record is used.
+fn insert_content(connection: &Connection, id_record: u64, content: &Content) -> Result<()> {
+    let secret_value = content.value().to_secret_string();
+    let preview = preview(content.kind(), secret_value.expose_secret());
+    connection.execute(
+        "INSERT INTO Content (label, position, required, kind, value, preview, id_record, value_last_changed)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, COALESCE(?8, (SELECT last_modified FROM Record WHERE id_record = ?7)));",
+        params![
+            content.label(),
+            content.position(),
+            content.required(),
+            content.kind(),
+            secret_value.expose_secret(),
+            preview.as_deref(),
+            id_record,
+            content.value_last_changed()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Inserts or updates the record based on its id. If the record is new, it will get an id. Updating a record that does not exist or is in the trash, e.g. deleted in the meantime, fails.
+fn write_record(connection: &Connection, record: &mut Record) -> Result<(), Error> {
+    record.set_last_modified(chrono::Utc::now());
+    let title = record.title();
+    let subtitle = record.subtitle();
+    let created = record.created();
+    let last_modified = record.last_modified();
+    let category = record.category().as_str();
+    let id_record = record.id();
+
+    let title_normalized = normalize_search(title);
+    let subtitle_normalized = normalize_search(subtitle);
+
+    let mut params = params![
+        title,
+        subtitle,
+        created,
+        last_modified,
+        category,
+        title_normalized,
+        subtitle_normalized,
+        id_record
+    ]
+    .to_vec();
+    let sql = if id_record == 0 {
+        params.pop();
+        "INSERT INTO Record (title, subtitle, created, last_modified, category, title_normalized, subtitle_normalized) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7);"
+    } else {
+        "UPDATE Record SET title = ?1, subtitle = ?2, created = ?3, last_modified = ?4, category = ?5, title_normalized = ?6, subtitle_normalized = ?7 WHERE id_record = ?8 AND deleted IS NULL;"
+    };
+    let changed = connection
+        .execute(sql, &*params)
+        .map_err(Error::database("Failed to save record"))?;
+    if changed == 0 {
+        return Err(Error::NotFound("Record does not exist"));
+    }
+    if id_record == 0 {
+        record.set_id(connection.last_insert_rowid() as u64);
+    }
+    Ok(())
+}
+
+/// Saves the current value of the content to its history before it is replaced with `value`. Only passwords, TOTP secrets and sensitive texts are kept, and only if the value changes.
+fn save_history(connection: &Connection, id_content: u64, value: &str) -> Result<usize> {
+    connection.execute(
+        "INSERT INTO ContentHistory (id_content, kind, value, replaced)
+        SELECT id_content, kind, value, ?2 FROM Content
+        WHERE id_content = ?1 AND value != ?3 AND kind IN ('Password', 'TOTPSecret', 'SensitiveText');",
+        params![id_content, chrono::Utc::now(), value],
+    )
+}
+
+/// Deletes acknowledged findings of the record with the password content if its stored value differs from the new one, so a changed password is audited again.
+fn clear_acknowledgements(connection: &Connection, id_content: u64, value: &str) -> Result<usize> {
+    connection.execute(
+        "DELETE FROM Acknowledgement WHERE id_record IN (
+            SELECT id_record FROM Content WHERE id_content = ?1 AND kind = 'Password' AND value != ?2
+        );",
+        params![id_content, value],
+    )
+}
+
+/// Inserts or updates the content of the given record based on its id. If the content is new, it will get an id. Previous value of an updated content is kept in its history, see [`save_history`]. Time of the last value change is updated only if the value differs from the stored one, acknowledged findings of a changed password are deleted, see [`clear_acknowledgements`].
+fn write_content(
+    connection: &Connection,
+    id_record: u64,
+    content: &mut Content,
+) -> Result<(), Error> {
+    let label = content.label();
+    let position = content.position();
+    let required = content.required();
+    let kind = content.kind();
+    let secret_value = content.value().to_secret_string();
+    let value = secret_value.expose_secret();
+    check_content(content, value)?;
+    let preview = preview(kind, value);
+    let preview = preview.as_deref();
+    let id_content = content.id();
+    let now = chrono::Utc::now();
+    let mut params = params![label, position, required, kind, value, preview, now].to_vec();
+    let sql = if id_content == 0 {
+        params.append(&mut params![id_record].to_vec());
+        "INSERT INTO Content (label, position, required, kind, value, preview, value_last_changed, id_record) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);"
+    } else {
+        save_history(connection, id_content, value)
+            .map_err(Error::database("Failed to save content history"))?;
+        clear_acknowledgements(connection, id_content, value)
+            .map_err(Error::database("Failed to clear acknowledgements"))?;
+        params.append(&mut params![id_content].to_vec());
+        "UPDATE Content SET label = ?1, position = ?2, required = ?3, kind = ?4, value = ?5, preview = ?6,
+        value_last_changed = CASE WHEN value = ?5 THEN value_last_changed ELSE ?7 END
+        WHERE id_content = ?8;"
+    };
+    connection
+        .execute(sql, &*params)
+        .map_err(Error::database("Failed to save content"))?;
+    if id_content == 0 {
+        content.set_id(connection.last_insert_rowid() as u64);
+    }
+    Ok(())
+}
+
+/// Deletes the content with its history.
+fn remove_content(connection: &Connection, id_content: u64) -> Result<(), Error> {
+    connection
+        .execute(
+            "DELETE FROM ContentHistory WHERE id_content = ?1;",
+            params![id_content],
+        )
+        .map_err(Error::database("Failed to delete content history"))?;
+    connection
+        .execute(
+            "DELETE FROM Content WHERE id_content = ?1;",
+            params![id_content],
+        )
+        .map_err(Error::database("Failed to delete content"))?;
+    Ok(())
+}
+
+/// Ranks the records again in their current manual order, evenly spaced by [`listing::renormalized_ranks`]. Records with a rank are ranked again, records without one only up to the last of `required`, so they keep their place and the rest stay after the ranked records.
+fn rank_records(connection: &Connection, required: &[u64]) -> Result<(), Error> {
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT id_record, manual_rank IS NOT NULL FROM Record WHERE deleted IS NULL {};",
+            listing::order_clause(
+                listing::RecordSort {
+                    field: listing::SortField::ManualOrder,
+                    descending: false,
+                },
+                true
+            )
+        ))
+        .map_err(Error::database("Failed to get ranks"))?;
+    let records: Vec<(u64, bool)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(Error::database("Failed to get ranks"))?
+        .collect::<Result<_>>()
+        .map_err(Error::database("Failed to get ranks"))?;
+    let ranked = records
+        .iter()
+        .rposition(|(id, ranked)| *ranked || required.contains(id))
+        .map_or(0, |last| last + 1);
+    let mut update = connection
+        .prepare("UPDATE Record SET manual_rank = ?1 WHERE id_record = ?2;")
+        .map_err(Error::database("Failed to save rank"))?;
+    for ((id, _), rank) in records[..ranked]
+        .iter()
+        .zip(listing::renormalized_ranks(ranked))
+    {
+        update
+            .execute(params![rank, id])
+            .map_err(Error::database("Failed to save rank"))?;
+    }
+    Ok(())
+}
+
+/// Deletes content of the record that is not in `kept`, see [`remove_content`].
+fn remove_other_content(
+    connection: &Connection,
+    id_record: u64,
+    kept: &[Content],
+) -> Result<(), Error> {
+    let mut stmt = connection
+        .prepare("SELECT id_content FROM Content WHERE id_record = ?1;")
+        .map_err(Error::database("Failed to get content"))?;
+    let existing: Vec<u64> = stmt
+        .query_map(params![id_record], |row| row.get(0))
+        .map_err(Error::database("Failed to get content"))?
+        .collect::<Result<_>>()
+        .map_err(Error::database("Failed to get content"))?;
+    for id_content in existing {
+        if kept.iter().all(|content| content.id() != id_content) {
+            remove_content(connection, id_content)?;
+        }
+    }
+    Ok(())
+}
+
 /// Database for the application. It uses SQLite with SQLCipher.
 pub struct Database {
     connection: Mutex<Connection>,
+    read_only: Option<&'static str>,
+    /// False if the database was opened read-only before the locked column was added, then no record is locked.
+    locked_column: bool,
+    /// False if the database was opened read-only before the favorite column was added, then no record is a favorite.
+    favorite_column: bool,
+    /// False if the database was opened read-only before the deleted column was added, then no record is in the trash.
+    deleted_column: bool,
+    /// False if the database was opened read-only before tags were added, then no record has tags.
+    tag_tables: bool,
+    /// False if the database was opened read-only before the content history was added, then no content has history.
+    history_table: bool,
+    /// False if the database was opened read-only before normalized titles were added, then titles are searched case-insensitively only for ASCII.
+    normalized_columns: bool,
+    /// False if the database was opened read-only before the time of the last value change was added, then the last modification of the record is used instead.
+    value_changed_column: bool,
+    /// False if the database was opened read-only before acknowledgements were added, then no finding is acknowledged.
+    acknowledgement_table: bool,
+    /// False if the database was opened read-only before the manual order was added, then records are in the order of creation.
+    manual_rank_column: bool,
+    /// Key the vault is unlocked with, see [`Database::vault_secret`].
+    key: Mutex<SecretString>,
+    _lock: Option<VaultLock>,
+}
+
+/// Checks if files can be created in the directory by creating and removing a probe file. If the directory does not exist yet, the closest existing parent is checked instead.
+pub fn is_writable(directory: &Path) -> bool {
+    match directory.ancestors().find(|path| path.exists()) {
+        Some(directory) if directory.is_dir() => {
+            let probe = directory.join(".write_probe");
+            fs::File::create(&probe)
+                .and_then(|_| fs::remove_file(&probe))
+                .is_ok()
+        }
+        _ => false,
+    }
+}
+
+/// Returns the password in Unicode Normalization Form C, so the same password typed as composed or decomposed characters gives the same key and hash.
+pub fn normalize_password(password: &str) -> SecretString {
+    SecretString::new(password.nfc().collect())
+}
+
+/// Opens the database with the normalized password, see [`normalize_password`]. Databases created before normalization may use the password as typed, so it is tried if the normalized one is invalid and differs. Writable databases are then re-keyed with the normalized password.
+fn open_normalized(
+    password: &str,
+    open: impl Fn(&str) -> Result<Database, Error>,
+) -> Result<Database, Error> {
+    let normalized = normalize_password(password);
+    match open(normalized.expose_secret()) {
+        Err(Error::InvalidPassword) if normalized.expose_secret() != password => {
+            let database = open(password)?;
+            if database.read_only.is_none() {
+                database.change_key(normalized.expose_secret())?;
+            }
+            Ok(database)
+        }
+        result => result,
+    }
+}
+
+/// Returns the `PRAGMA key` or `PRAGMA rekey` statement with the password as a string literal. Single quotes are doubled, so any password is a single literal and the key of quote-free passwords stays the same. The statement is built in place with the final capacity, so no copy of the password is left unzeroized.
+fn key_pragma(pragma: &str, password: &str) -> Zeroizing<String> {
+    let quotes = password.matches('\'').count();
+    let mut sql = Zeroizing::new(String::with_capacity(
+        pragma.len() + password.len() + quotes + 16,
+    ));
+    sql.push_str("PRAGMA ");
+    sql.push_str(pragma);
+    sql.push_str(" = '");
+    for character in password.chars() {
+        if character == '\'' {
+            sql.push('\'');
+        }
+        sql.push(character);
+    }
+    sql.push_str("';");
+    sql
+}
+
+/// Length of the salt of the database key. SQLCipher stores the salt of a raw key unencrypted in the first bytes of the file, so the database stays a single self-contained file.
+const KEY_SALT_LENGTH: usize = 16;
+const KEY_LENGTH: usize = 32;
+
+/// Argon2id parameters of the database key: 64 MiB of memory, 3 iterations, 1 lane. Changing them makes existing databases unreadable.
+#[cfg(not(test))]
+const KEY_PARAMS: (u32, u32, u32) = (64 * 1024, 3, 1);
+/// Cheap parameters, so tests opening many databases stay fast.
+#[cfg(test)]
+const KEY_PARAMS: (u32, u32, u32) = (64, 1, 1);
+
+/// Derives the database key from the password with Argon2id and returns it with the salt as a SQLCipher raw key `x'<key><salt>'`, so the PBKDF2 of SQLCipher is skipped.
+fn derive_key(password: &str, salt: &[u8; KEY_SALT_LENGTH]) -> Result<Zeroizing<String>, Error> {
+    let (memory, iterations, parallelism) = KEY_PARAMS;
+    let params = Params::new(memory, iterations, parallelism, Some(KEY_LENGTH))
+        .map_err(|_| "Failed to derive key")?;
+    let mut key = Zeroizing::new([0u8; KEY_LENGTH]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, key.as_mut())
+        .map_err(|_| "Failed to derive key")?;
+    let mut raw_key = Zeroizing::new(String::with_capacity(
+        2 * (KEY_LENGTH + KEY_SALT_LENGTH) + 3,
+    ));
+    raw_key.push_str("x'");
+    for byte in key.iter().chain(salt) {
+        for digit in [byte >> 4, byte & 0xf] {
+            raw_key.push(char::from_digit(digit as u32, 16).unwrap_or('0'));
+        }
+    }
+    raw_key.push('\'');
+    Ok(raw_key)
+}
+
+/// Returns a new random salt for the database key.
+fn new_salt() -> [u8; KEY_SALT_LENGTH] {
+    let mut salt = [0u8; KEY_SALT_LENGTH];
+    OsRng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Returns the salt stored at the start of an existing database file, or None if the file does not exist yet.
+fn read_salt(path: &Path) -> Option<[u8; KEY_SALT_LENGTH]> {
+    let mut salt = [0u8; KEY_SALT_LENGTH];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut salt))
+        .ok()
+        .map(|_| salt)
+}
+
+/// Secret the vault is unlocked with, see [`Database::open_with_secret`].
+#[derive(Debug, Clone)]
+pub enum VaultSecret {
+    /// Master password, the key is derived from it.
+    Password(SecretString),
+    /// Key already derived from the master password, see [`derived_key`].
+    Key(SecretString),
+}
+
+/// Returns the key of the existing database derived from the normalized password, see [`derive_key`]. The database opens with it without the password, see [`VaultSecret::Key`]. The key changes with the master password.
+/// # Errors
+/// If the database does not exist or the key cannot be derived.
+pub fn derived_key(password: &str, path: &Path) -> Result<SecretString, Error> {
+    let salt = read_salt(path).ok_or(Error::NotFound("Database does not exist"))?;
+    let key = derive_key(normalize_password(password).expose_secret(), &salt)?;
+    Ok(SecretString::new(key.to_string()))
+}
+
+/// Opens a connection with `open` and unlocks it with the key derived from the password, see [`derive_key`]. Databases created before key derivation use the password itself as the key, they are unlocked with a new connection if the derived key is invalid. Returns the connection, whether the database is such a legacy one and the key it was unlocked with.
+///
+/// A [`VaultSecret::Key`] unlocks the connection directly, such a database is never a legacy one.
+fn unlock_vault(
+    path: &Path,
+    secret: &VaultSecret,
+    open: impl Fn() -> Result<Connection, Error>,
+) -> Result<(Connection, bool, SecretString), Error> {
+    let password = match secret {
+        VaultSecret::Password(password) => password.expose_secret(),
+        VaultSecret::Key(key) => {
+            let connection = open()?;
+            unlock(&connection, key.expose_secret())?;
+            return Ok((connection, false, key.clone()));
+        }
+    };
+    let salt = read_salt(path);
+    let connection = open()?;
+    let key = derive_key(password, &salt.unwrap_or_else(new_salt))?;
+    match unlock(&connection, &key) {
+        Ok(()) => Ok((connection, false, SecretString::new(key.to_string()))),
+        Err(Error::InvalidPassword) if salt.is_some() => {
+            drop(connection);
+            let connection = open()?;
+            unlock(&connection, password)?;
+            Ok((connection, true, SecretString::new(password.to_string())))
+        }
+        Err(error) => Err(error),
+    }
+}
+
+/// Re-encrypts the unlocked database with the key derived from the password and a new salt, see [`derive_key`].
+/// # Return
+/// Returns the new key.
+fn rekey_vault(connection: &Connection, password: &str) -> Result<SecretString, Error> {
+    let key = derive_key(password, &new_salt())?;
+    connection
+        .execute_batch(&key_pragma("rekey", &key))
+        .map_err(Error::database("Failed to set a new key"))?;
+    Ok(SecretString::new(key.to_string()))
+}
+
+/// Unlocks freshly opened connection with the password and checks that the password is correct.
+pub fn unlock(connection: &Connection, password: &str) -> Result<(), Error> {
+    connection
+        .execute_batch(&key_pragma("key", password))
+        .map_err(Error::database("Failed to unlock database"))?;
+
+    connection
+        .execute_batch("PRAGMA cache_size = 0;")
+        .unwrap_or_default();
+
+    connection
+        .execute_batch("SELECT count(*) FROM sqlite_master;")
+        .map_err(|_| Error::InvalidPassword)?;
+
+    connection
+        .execute_batch("PRAGMA cipher_memory_security = ON;")
+        .map_err(Error::database("Failed to enable memory security"))
 }
 
 impl Database {
@@ -39,40 +792,109 @@ impl Database {
     }
 
     /// Opens database file. If the file does not exist, it will be created. Location of the file is based on the app local data directory.
+    /// If the data directory is not writable (read-only filesystem, sandbox), an existing database is opened in read-only mode, see [`Database::read_only_reason`].
     /// # Errors
-    /// If database cannot be opened
-    pub fn open(password: &str, app_handle: &AppHandle) -> Result<Database, &'static str> {
+    /// If database cannot be opened or if it does not exist and the data directory is not writable
+    pub fn open(password: &str, app_handle: &AppHandle) -> Result<Database, Error> {
         if password.trim().is_empty() {
-            return Err("Password can not be empty");
+            return Err(Error::Validation("Password can not be empty".into()));
         }
 
+        Database::open_with_secret(
+            &VaultSecret::Password(SecretString::new(password.to_string())),
+            app_handle,
+        )
+    }
+
+    /// Opens database file with the password or the derived key like [`Database::open`].
+    /// # Errors
+    /// If database cannot be opened or if it does not exist and the data directory is not writable
+    pub fn open_with_secret(
+        secret: &VaultSecret,
+        app_handle: &AppHandle,
+    ) -> Result<Database, Error> {
         let path = Database::path(app_handle).ok_or("Failed to get database path")?;
+        Database::open_data_path(secret, &path)
+    }
+
+    /// Opens database file on the given path in the data directory, creating the directory if needed. If the directory is not writable, an existing database is opened in read-only mode with [`READ_ONLY_DIRECTORY`] as the reason.
+    /// # Errors
+    /// If database cannot be opened or if it does not exist and the directory is not writable
+    fn open_data_path(secret: &VaultSecret, path: &Path) -> Result<Database, Error> {
+        let directory = path.parent().ok_or("Failed to get data directory path")?;
+        if is_writable(directory).not() {
+            return if path.exists() {
+                Database::open_path_read_only_with_secret(secret, path, READ_ONLY_DIRECTORY)
+            } else {
+                Err(Error::Other("Data directory is not writable"))
+            };
+        }
+
         if path.exists().not() {
-            fs::create_dir_all(path.parent().ok_or("Failed to get data directory path")?)
-                .map_err(|_| "Failed to create data directory")?;
+            fs::create_dir_all(directory).map_err(Error::io("Failed to create data directory"))?;
         }
-        let path = path.to_str().ok_or("Path is not valid UTF-8")?;
 
-        let Ok(connection) = Connection::open(path) else {
-            return Err("Failed to open database");
-        };
+        Database::open_path_with_secret(secret, path)
+    }
 
-        let sql = SecretString::new(format!("PRAGMA key = '{password}';"));
-        connection
-            .execute_batch(sql.expose_secret())
-            .map_err(|_| "Failed to unlock database")?;
+    /// Opens database file on the given path. If the file does not exist, it will be created. The password is normalized, see [`open_normalized`].
+    /// The database is locked by [`VaultLock`] while it is open. If another process holds the lock, the database is opened in read-only mode instead.
+    /// # Errors
+    /// If database cannot be opened
+    pub fn open_path(password: &str, path: &Path) -> Result<Database, Error> {
+        if password.trim().is_empty() {
+            return Err(Error::Validation("Password can not be empty".into()));
+        }
 
-        connection
-            .execute_batch("PRAGMA cache_size = 0;")
-            .unwrap_or_default();
+        open_normalized(password, |key| Database::open_path_with_key(key, path))
+    }
 
-        connection
-            .execute_batch("SELECT count(*) FROM sqlite_master;")
-            .map_err(|_| "Invalid password")?;
+    /// Opens database file on the given path with the password like [`Database::open_path`], or with the derived key used as is.
+    /// # Errors
+    /// If database cannot be opened
+    pub fn open_path_with_secret(secret: &VaultSecret, path: &Path) -> Result<Database, Error> {
+        match secret {
+            VaultSecret::Password(password) => Database::open_path(password.expose_secret(), path),
+            VaultSecret::Key(_) => Database::open_vault(secret, path),
+        }
+    }
+
+    /// Opens database file on the given path with the password used as is, see [`Database::open_path`].
+    fn open_path_with_key(password: &str, path: &Path) -> Result<Database, Error> {
+        Database::open_vault(
+            &VaultSecret::Password(SecretString::new(password.to_string())),
+            path,
+        )
+    }
+
+    /// Opens database file on the given path with the secret used as is. Legacy databases keyed with the password itself are re-keyed with the derived key, see [`unlock_vault`].
+    fn open_vault(secret: &VaultSecret, path: &Path) -> Result<Database, Error> {
+        let lock = match VaultLock::acquire(path) {
+            Ok(lock) => lock,
+            Err(Error::Locked) if path.exists() => {
+                return Database::open_vault_read_only(
+                    secret,
+                    path,
+                    "Database is opened by another process, changes can not be saved",
+                );
+            }
+            Err(error) => return Err(error),
+        };
+
+        if path.to_str().is_none() {
+            return Err(Error::Other("Path is not valid UTF-8"));
+        }
+
+        let (mut connection, legacy, mut key) = unlock_vault(path, secret, || {
+            Connection::open(path).map_err(Error::database("Failed to open database"))
+        })?;
+        if let (true, VaultSecret::Password(password)) = (legacy, secret) {
+            key = rekey_vault(&connection, password.expose_secret())?;
+        }
 
         connection
-            .execute_batch("PRAGMA cipher_memory_security = ON;")
-            .map_err(|_| "Failed to enable memory security")?;
+            .execute_batch("PRAGMA secure_delete = ON;")
+            .map_err(Error::database("Failed to enable secure delete"))?;
 
         connection
             .execute_batch("
@@ -103,271 +925,4707 @@ impl Database {
                             exposed integer not null,
                             checked datetime not null
                         );"
-            ).map_err(|_| "Failed to create database")?;
+            ).map_err(Error::database("Failed to create database"))?;
+
+        migration::migrate(&mut connection)?;
+
+        Ok(Database::writable(connection, key, lock))
+    }
+
+    /// Returns writable database with the unlocked and migrated connection.
+    fn writable(connection: Connection, key: SecretString, lock: VaultLock) -> Database {
+        Database {
+            connection: Mutex::new(connection),
+            read_only: None,
+            locked_column: true,
+            favorite_column: true,
+            deleted_column: true,
+            tag_tables: true,
+            history_table: true,
+            normalized_columns: true,
+            value_changed_column: true,
+            acknowledgement_table: true,
+            manual_rank_column: true,
+            key: Mutex::new(key),
+            _lock: Some(lock),
+        }
+    }
+
+    /// Opens existing database file on the given path for writing without changing its key or schema, e.g. another vault a record is sent to. The password is tried normalized and as typed like by [`open_normalized`], but the database is not re-keyed and legacy databases keyed with the password itself are not re-keyed either, see [`unlock_vault`]. Migrations are not applied.
+    /// # Errors
+    /// Returns [`Error::Locked`] if the database is opened by another process, or [`Error::Validation`] if its schema version differs from [`migration::VERSION`], so it needs to be opened by its own application first.
+    pub fn open_path_unchanged(password: &str, path: &Path) -> Result<Database, Error> {
+        if password.trim().is_empty() {
+            return Err(Error::Validation("Password can not be empty".into()));
+        }
+        let lock = VaultLock::acquire(path)?;
+        let unlock = |password: &str| {
+            unlock_vault(
+                path,
+                &VaultSecret::Password(SecretString::new(password.to_string())),
+                || {
+                    Connection::open_with_flags(
+                        path,
+                        OpenFlags::SQLITE_OPEN_READ_WRITE
+                            | OpenFlags::SQLITE_OPEN_URI
+                            | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+                    )
+                    .map_err(Error::database("Failed to open database"))
+                },
+            )
+        };
+        let normalized = normalize_password(password);
+        let (connection, _, key) = match unlock(normalized.expose_secret()) {
+            Err(Error::InvalidPassword) if normalized.expose_secret() != password => {
+                unlock(password)?
+            }
+            result => result?,
+        };
+
+        let version: usize = connection
+            .query_row("PRAGMA user_version;", [], |row| row.get(0))
+            .map_err(Error::database("Failed to get database version"))?;
+        if version != migration::VERSION {
+            return Err(Error::Validation(
+                "Database has a different version, open it in the application first".into(),
+            ));
+        }
+        connection
+            .execute_batch("PRAGMA secure_delete = ON;")
+            .map_err(Error::database("Failed to enable secure delete"))?;
+
+        Ok(Database::writable(connection, key, lock))
+    }
+
+    /// Opens existing database file on the given path in read-only mode. Tables are not created and all changes fail. The password is normalized, see [`open_normalized`].
+    /// # Errors
+    /// If database cannot be opened
+    pub fn open_path_read_only(
+        password: &str,
+        path: &Path,
+        reason: &'static str,
+    ) -> Result<Database, Error> {
+        if password.trim().is_empty() {
+            return Err(Error::Validation("Password can not be empty".into()));
+        }
+
+        open_normalized(password, |key| {
+            Database::open_path_read_only_with_key(key, path, reason)
+        })
+    }
+
+    /// Opens existing database file on the given path in read-only mode with the password like [`Database::open_path_read_only`], or with the derived key used as is.
+    /// # Errors
+    /// If database cannot be opened
+    pub fn open_path_read_only_with_secret(
+        secret: &VaultSecret,
+        path: &Path,
+        reason: &'static str,
+    ) -> Result<Database, Error> {
+        match secret {
+            VaultSecret::Password(password) => {
+                Database::open_path_read_only(password.expose_secret(), path, reason)
+            }
+            VaultSecret::Key(_) => Database::open_vault_read_only(secret, path, reason),
+        }
+    }
+
+    /// Opens existing database file on the given path in read-only mode with the password used as is, see [`Database::open_path_read_only`].
+    fn open_path_read_only_with_key(
+        password: &str,
+        path: &Path,
+        reason: &'static str,
+    ) -> Result<Database, Error> {
+        Database::open_vault_read_only(
+            &VaultSecret::Password(SecretString::new(password.to_string())),
+            path,
+            reason,
+        )
+    }
+
+    /// Opens existing database file on the given path in read-only mode with the secret used as is.
+    fn open_vault_read_only(
+        secret: &VaultSecret,
+        path: &Path,
+        reason: &'static str,
+    ) -> Result<Database, Error> {
+        let (connection, _, key) = unlock_vault(path, secret, || {
+            Connection::open_with_flags(
+                path,
+                OpenFlags::SQLITE_OPEN_READ_ONLY
+                    | OpenFlags::SQLITE_OPEN_URI
+                    | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )
+            .map_err(Error::database("Failed to open database"))
+        })?;
+
+        let has_column = |name: &str| {
+            connection
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM pragma_table_info('Record') WHERE name = ?1);",
+                    params![name],
+                    |row| row.get(0),
+                )
+                .map_err(Error::database("Failed to open database"))
+        };
+        let locked_column = has_column("locked")?;
+        let favorite_column = has_column("favorite")?;
+        let deleted_column = has_column("deleted")?;
+        let normalized_columns = has_column("title_normalized")?;
+        let manual_rank_column = has_column("manual_rank")?;
+        let value_changed_column = connection
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM pragma_table_info('Content') WHERE name = 'value_last_changed');",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(Error::database("Failed to open database"))?;
+        let has_table = |name: &str| {
+            connection
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1);",
+                    params![name],
+                    |row| row.get(0),
+                )
+                .map_err(Error::database("Failed to open database"))
+        };
+        let tag_tables = has_table("RecordTag")?;
+        let history_table = has_table("ContentHistory")?;
+        let acknowledgement_table = has_table("Acknowledgement")?;
 
         Ok(Database {
             connection: Mutex::new(connection),
+            read_only: Some(reason),
+            locked_column,
+            favorite_column,
+            deleted_column,
+            tag_tables,
+            history_table,
+            normalized_columns,
+            value_changed_column,
+            acknowledgement_table,
+            manual_rank_column,
+            key: Mutex::new(key),
+            _lock: None,
         })
     }
 
-    /// Changes the password for the database. It will re-encrypt the database with the new password.
+    /// Returns columns of the Record table in the order expected by [`convert::row_to_record`].
+    fn record_columns(&self) -> String {
+        format!(
+            "id_record, title, subtitle, created, last_modified, category, {}, {}",
+            if self.locked_column { "locked" } else { "0" },
+            if self.favorite_column {
+                "favorite"
+            } else {
+                "0"
+            }
+        )
+    }
+
+    /// Returns SQL expression of the time the value of a content was last changed, on Content joined with Record.
+    fn value_changed_expression(&self) -> &'static str {
+        match self.value_changed_column {
+            true => "Content.value_last_changed",
+            false => "Record.last_modified",
+        }
+    }
+
+    /// Returns the `ORDER BY` terms of the record list: favorites first, then the most recently modified records.
+    fn record_order(&self) -> &'static str {
+        if self.favorite_column {
+            "favorite DESC, julianday(last_modified) DESC, id_record DESC"
+        } else {
+            "julianday(last_modified) DESC, id_record DESC"
+        }
+    }
+
+    /// Returns SQL condition of the Record table matching records in the trash, or records not in the trash if `trashed` is false.
+    fn trash_condition(&self, trashed: bool) -> &'static str {
+        match (self.deleted_column, trashed) {
+            (true, false) => "deleted IS NULL",
+            (true, true) => "deleted IS NOT NULL",
+            (false, false) => "1",
+            (false, true) => "0",
+        }
+    }
+
+    /// Returns the reason why the database was opened in read-only mode, or `None` if it is writable.
+    pub fn read_only_reason(&self) -> Option<&'static str> {
+        self.read_only
+    }
+
+    /// Changes the password for the database. It will re-encrypt the database with a key derived from the new normalized password and a new salt, see [`normalize_password`] and [`derive_key`].
     /// # Errors
     /// If the new password is empty or if the key cannot be changed.
-    pub fn change_key(&self, new_password: &str) -> Result<(), &'static str> {
+    pub fn change_key(&self, new_password: &str) -> Result<(), Error> {
         if new_password.trim().is_empty() {
-            return Err("Password can not be empty");
+            return Err(Error::Validation("Password can not be empty".into()));
         }
-        let sql = SecretString::new(format!("PRAGMA rekey = '{new_password}';"));
-        self.connection
-            .lock()
-            .map_err(|_| "Failed to access database lock")?
-            .execute_batch(sql.expose_secret())
-            .map_err(|_| "Failed to set a new key")
+        let new_password = normalize_password(new_password);
+        let key = rekey_vault(
+            &*self
+                .connection
+                .lock()
+                .map_err(|_| "Failed to access database lock")?,
+            new_password.expose_secret(),
+        )?;
+        *self.key.lock().map_err(|_| "Failed to access key lock")? = key;
+        Ok(())
     }
 
-    pub fn get_setting(&self, name: &str) -> Result<SecretValue, &'static str> {
+    /// Returns the key the vault is unlocked with as a [`VaultSecret::Key`], so a copy of the vault can be checked to open with it. The key changes with the master password, see [`Database::change_key`].
+    /// # Errors
+    /// If the key cannot be accessed.
+    pub fn vault_secret(&self) -> Result<VaultSecret, Error> {
+        Ok(VaultSecret::Key(
+            self.key
+                .lock()
+                .map_err(|_| "Failed to access key lock")?
+                .clone(),
+        ))
+    }
+
+    pub fn get_setting(&self, name: &str) -> Result<SecretValue, Error> {
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
         let mut stmt = connection
             .prepare("SELECT value FROM Settings WHERE name = ?1;")
-            .map_err(|_| "Failed to prepare statement")?;
+            .map_err(Error::database("Failed to prepare statement"))?;
         stmt.query_row(params![name], |row| row.get(0))
-            .map_err(|_| "Failed to get setting")
+            .map_err(Error::database("Failed to get setting"))
     }
 
-    pub fn get_content(&self, id_content: u64) -> Result<Content, &'static str> {
+    /// Returns the draft of the record, `id_record` is 0 for a new record.
+    pub fn get_draft(&self, id_record: u64) -> Result<Option<SecretValue>, Error> {
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
-        let mut stmt = connection
-            .prepare("SELECT id_content, label, position, required, kind, value FROM Content WHERE id_content = ?1;")
-            .map_err(|_| "Failed to prepare statement")?;
-        stmt.query_row(params![id_content], convert::row_to_content)
-            .map_err(|_| "Failed to get content")
+        connection
+            .query_row(
+                "SELECT snapshot FROM Drafts WHERE id_record = ?1;",
+                params![id_record],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(Error::database("Failed to get draft"))
     }
 
-    pub fn get_all_records(&self) -> Result<Vec<Record>, &'static str> {
+    pub fn get_content(&self, id_content: u64) -> Result<Content, Error> {
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
         let mut stmt = connection
-            .prepare(
-                "SELECT id_record, title, subtitle, created, last_modified, category FROM Record;",
-            )
-            .map_err(|_| "Failed to prepare statement")?;
-        let result: Result<Vec<Record>> = stmt
-            .query_map([], convert::row_to_record)
-            .map_err(|_| "Failed to map records")?
-            .collect();
-        result.map_err(|_| "Failed to get records")
+            .prepare("SELECT id_content, label, position, required, kind, value FROM Content WHERE id_content = ?1;")
+            .map_err(Error::database("Failed to prepare statement"))?;
+        stmt.query_row(params![id_content], convert::row_to_content)
+            .map_err(Error::database("Failed to get content"))
     }
 
-    pub fn get_all_content_for_record(&self, id_record: u64) -> Result<Vec<Content>, &'static str> {
+    /// Returns a content of the record, like [`Database::get_content`].
+    /// # Errors
+    /// Returns [`CONTENT_NOT_IN_RECORD`] if the content does not exist or belongs to another record.
+    pub fn get_record_content(&self, id_content: u64, id_record: u64) -> Result<Content, Error> {
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
         let mut stmt = connection
-            .prepare("SELECT id_content, label, position, required, kind, value FROM Content WHERE id_record = ?1;")
-            .map_err(|_| "Failed to prepare statement")?;
-        let result: Result<Vec<Content>> = stmt
-            .query_map([id_record], convert::row_to_content)
-            .map_err(|_| "Failed to map content")?
-            .collect();
-        result.map_err(|_| "Failed to get content")
+            .prepare("SELECT id_content, label, position, required, kind, value FROM Content WHERE id_content = ?1 AND id_record = ?2;")
+            .map_err(Error::database("Failed to prepare statement"))?;
+        stmt.query_row(params![id_content, id_record], convert::row_to_content)
+            .map_err(Error::database(CONTENT_NOT_IN_RECORD))
     }
 
-    pub fn get_all_passwords_for_record(
-        &self,
-        id_record: u64,
-    ) -> Result<Vec<SecretValue>, &'static str> {
+    /// Returns previous values of the content, the most recently replaced first, see [`save_history`].
+    pub fn get_content_history(&self, id_content: u64) -> Result<Vec<ContentVersion>, Error> {
+        if self.history_table.not() {
+            return Ok(Vec::new());
+        }
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
         let mut stmt = connection
-            .prepare("SELECT value FROM Content WHERE id_record = ?1 AND kind = 'Password';")
-            .map_err(|_| "Failed to prepare statement")?;
-        let result: Result<Vec<SecretValue>> = stmt
-            .query_map([id_record], |row| row.get(0))
-            .map_err(|_| "Failed to map password")?
+            .prepare("SELECT id_version, kind, value, replaced FROM ContentHistory WHERE id_content = ?1 ORDER BY id_version DESC;")
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<ContentVersion>> = stmt
+            .query_map([id_content], |row| {
+                Ok(ContentVersion {
+                    id: row.get(0)?,
+                    kind: row.get(1)?,
+                    value: row.get(2)?,
+                    replaced: row.get(3)?,
+                })
+            })
+            .map_err(Error::database("Failed to map content history"))?
             .collect();
-        result.map_err(|_| "Failed to get passwords")
+        result.map_err(Error::database("Failed to get content history"))
     }
 
-    /// Based on the hash, it returns the breach status from the cache.
-    pub fn get_data_breach_status(&self, hash: &str) -> Result<Option<bool>, &'static str> {
+    /// Returns all records that are not in the trash. Favorites come first, then the most recently modified records. Uses the paged query of [`Database::get_records_window`] with a single page.
+    pub fn get_all_records(&self) -> Result<Vec<Record>, Error> {
+        self.query_records(
+            &format!("WHERE {}", self.trash_condition(false)),
+            Vec::new(),
+            &format!("ORDER BY {}", self.record_order()),
+            0,
+            u64::MAX,
+        )
+    }
+
+    /// Returns the record with the id, or `None` if it does not exist or is in the trash.
+    pub fn get_record(&self, id: u64) -> Result<Option<Record>, Error> {
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
-        let mut stmt = connection
-            .prepare("SELECT exposed FROM DataBreachCache WHERE hash = ?1;")
-            .map_err(|_| "Failed to prepare statement")?;
-        stmt.query_row(params![hash], |row| row.get(0))
+        connection
+            .query_row(
+                &format!(
+                    "SELECT {} FROM Record WHERE id_record = ?1 AND {};",
+                    self.record_columns(),
+                    self.trash_condition(false)
+                ),
+                [id],
+                convert::row_to_record,
+            )
             .optional()
-            .map_err(|_| "Failed to get breach status")
+            .map_err(Error::database("Failed to get record"))
     }
 
-    pub fn save_setting(&self, name: &str, value: &str) -> Result<(), &'static str> {
+    /// Returns all records that are not in the trash with names of their tags, in the order of [`Database::get_all_records`]. Tags are loaded in the same query, so the list does not need a query per record.
+    pub fn get_all_records_with_tags(&self) -> Result<Vec<TaggedRecord>, Error> {
+        if self.tag_tables.not() {
+            return Ok(self
+                .get_all_records()?
+                .into_iter()
+                .map(|record| TaggedRecord {
+                    record,
+                    tags: Vec::new(),
+                })
+                .collect());
+        }
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
-        connection
-            .execute(
-                "REPLACE INTO Settings (name, value) VALUES (?1, ?2);",
-                params![name, value],
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {}, Tag.name FROM Record LEFT JOIN RecordTag USING (id_record) LEFT JOIN Tag USING (id_tag) WHERE {} ORDER BY {}, Tag.name;",
+                self.record_columns(),
+                self.trash_condition(false),
+                self.record_order()
+            ))
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let rows: Result<Vec<(Record, Option<String>)>> = stmt
+            .query_map([], |row| Ok((convert::row_to_record(row)?, row.get(8)?)))
+            .map_err(Error::database("Failed to map records"))?
+            .collect();
+        let mut records: Vec<TaggedRecord> = Vec::new();
+        // Rows of the same record follow each other, the order ends with its id
+        for (record, tag) in rows.map_err(Error::database("Failed to get records"))? {
+            match records.last_mut() {
+                Some(last) if last.record.id() == record.id() => last.tags.extend(tag),
+                _ => records.push(TaggedRecord {
+                    record,
+                    tags: tag.into_iter().collect(),
+                }),
+            }
+        }
+        Ok(records)
+    }
+
+    /// Returns records with the tag that are not in the trash, in the order of [`Database::get_all_records`]. Tag names are compared case-insensitively.
+    pub fn get_records_by_tag(&self, name: &str) -> Result<Vec<Record>, Error> {
+        if self.tag_tables.not() {
+            return Ok(Vec::new());
+        }
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM Record WHERE {} AND id_record IN (
+                    SELECT id_record FROM RecordTag JOIN Tag USING (id_tag) WHERE name = ?1
+                ) ORDER BY {};",
+                self.record_columns(),
+                self.trash_condition(false),
+                self.record_order()
+            ))
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<Record>> = stmt
+            .query_map([name.trim()], convert::row_to_record)
+            .map_err(Error::database("Failed to map records"))?
+            .collect();
+        result.map_err(Error::database("Failed to get records"))
+    }
+
+    /// Returns names of the tags of the record in alphabetical order.
+    pub fn get_tags_for_record(&self, id_record: u64) -> Result<Vec<String>, Error> {
+        if self.tag_tables.not() {
+            return Ok(Vec::new());
+        }
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare("SELECT name FROM Tag JOIN RecordTag USING (id_tag) WHERE id_record = ?1 ORDER BY name;")
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<String>> = stmt
+            .query_map([id_record], |row| row.get(0))
+            .map_err(Error::database("Failed to map tags"))?
+            .collect();
+        result.map_err(Error::database("Failed to get tags"))
+    }
+
+    /// Returns names of all tags in alphabetical order. Tags are deleted when no record has them, so every tag is used.
+    pub fn get_all_tags(&self) -> Result<Vec<String>, Error> {
+        if self.tag_tables.not() {
+            return Ok(Vec::new());
+        }
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare("SELECT name FROM Tag ORDER BY name;")
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<String>> = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(Error::database("Failed to map tags"))?
+            .collect();
+        result.map_err(Error::database("Failed to get tags"))
+    }
+
+    /// Returns records in the trash, the most recently deleted first.
+    pub fn get_trashed_records(&self) -> Result<Vec<Record>, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM Record WHERE {} ORDER BY {};",
+                self.record_columns(),
+                self.trash_condition(true),
+                match self.deleted_column {
+                    true => "julianday(deleted) DESC, id_record DESC",
+                    false => "id_record",
+                }
+            ))
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<Record>> = stmt
+            .query_map([], convert::row_to_record)
+            .map_err(Error::database("Failed to map records"))?
+            .collect();
+        result.map_err(Error::database("Failed to get records"))
+    }
+
+    /// Returns the `WHERE` clause of the filter for the columns and tables of the database, see [`listing::where_clause`].
+    fn listing_where_clause(
+        &self,
+        filter: &listing::RecordFilter,
+    ) -> (String, Vec<rusqlite::types::Value>) {
+        listing::where_clause(
+            filter,
+            listing::Schema {
+                normalized: self.normalized_columns,
+                favorite: self.favorite_column,
+                deleted: self.deleted_column,
+                tags: self.tag_tables,
+            },
+        )
+    }
+
+    /// Returns at most `count` records starting at index `start` of the list filtered and sorted as given, see [`listing`].
+    pub fn get_records_window(
+        &self,
+        filter: &listing::RecordFilter,
+        sort: listing::RecordSort,
+        start: u64,
+        count: u64,
+    ) -> Result<Vec<Record>, Error> {
+        let (where_clause, parameters) = self.listing_where_clause(filter);
+        self.query_records(
+            &where_clause,
+            parameters,
+            listing::order_clause(sort, self.manual_rank_column),
+            start,
+            count,
+        )
+    }
+
+    /// Returns at most `limit` records starting at index `offset` of the list of all records that are not in the trash, in the preset order, see [`Database::get_records_window`].
+    pub fn get_records_page(
+        &self,
+        offset: u64,
+        limit: u64,
+        sort: listing::SortBy,
+    ) -> Result<Vec<Record>, Error> {
+        self.get_records_window(
+            &listing::RecordFilter::default(),
+            sort.into(),
+            offset,
+            limit,
+        )
+    }
+
+    /// Returns at most `count` records of the Record table starting at index `start`, matching the `WHERE` clause with its parameters and ordered by the `ORDER BY` clause. Both clauses must be fixed SQL, values are bound as parameters.
+    fn query_records(
+        &self,
+        where_clause: &str,
+        mut parameters: Vec<rusqlite::types::Value>,
+        order_clause: &str,
+        start: u64,
+        count: u64,
+    ) -> Result<Vec<Record>, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        parameters.push(rusqlite::types::Value::Integer(
+            i64::try_from(count).unwrap_or(i64::MAX),
+        ));
+        parameters.push(rusqlite::types::Value::Integer(
+            i64::try_from(start).unwrap_or(i64::MAX),
+        ));
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM Record {where_clause} {order_clause} LIMIT ?{} OFFSET ?{};",
+                self.record_columns(),
+                parameters.len() - 1,
+                parameters.len()
+            ))
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<Record>> = stmt
+            .query_map(
+                rusqlite::params_from_iter(parameters),
+                convert::row_to_record,
             )
-            .map_err(|_| "Failed to save setting")?;
-        Ok(())
+            .map_err(Error::database("Failed to map records"))?
+            .collect();
+        result.map_err(Error::database("Failed to get records"))
     }
 
-    /// Saves a record to the database. Based on the id, it will insert or update the record. If the record is new, it will get an id.
-    pub fn save_record(&self, record: &mut Record) -> Result<(), &'static str> {
-        record.set_last_modified(chrono::Local::now());
-        let title = record.title();
-        let subtitle = record.subtitle();
-        let created = record.created();
-        let last_modified = record.last_modified();
-        let category = record.category().as_str();
-        let id_record = record.id();
-
-        let mut params =
-            params![title, subtitle, created, last_modified, category, id_record].to_vec();
-        let sql = if id_record == 0 {
-            params.pop();
-            "INSERT INTO Record (title, subtitle, created, last_modified, category) VALUES (?1, ?2, ?3, ?4, ?5);"
-        } else {
-            "UPDATE Record SET title = ?1, subtitle = ?2, created = ?3, last_modified = ?4, category = ?5 WHERE id_record = ?6;"
+    /// Moves the record between the `previous` and `next` records of the manual order ([`listing::SortField::ManualOrder`]), `None` meaning the record becomes the first or the last one. The record gets a rank halfway between the ranks of its neighbors, so other records keep their ranks, see [`listing::rank_between`]. If a neighbor has no rank yet or the neighbors are too close, the records are ranked again in their current order first, see [`rank_records`]. Last modification times are not changed.
+    /// # Errors
+    /// If the record or a neighbor does not exist or is in the trash, if the record is its own neighbor or if the previous record is not before the next one. Nothing is changed in that case.
+    pub fn set_record_rank_between(
+        &self,
+        id: u64,
+        previous: Option<u64>,
+        next: Option<u64>,
+    ) -> Result<(), Error> {
+        if previous == Some(id) || next == Some(id) {
+            return Err(Error::Validation(
+                "Record can not be its own neighbor".into(),
+            ));
+        }
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(Error::database("Failed to start transaction"))?;
+        let rank = |id: u64| -> Result<Option<f64>, Error> {
+            transaction
+                .query_row(
+                    "SELECT manual_rank FROM Record WHERE id_record = ?1 AND deleted IS NULL;",
+                    [id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(Error::database("Failed to get rank"))?
+                .ok_or(Error::NotFound("Record does not exist"))
         };
+        // Rank between the neighbors, or None if a neighbor has no rank or there is no room
+        let between = || -> Result<Option<f64>, Error> {
+            let previous = previous.map(rank).transpose()?;
+            let next = next.map(rank).transpose()?;
+            Ok(match (previous, next) {
+                (Some(None), _) | (_, Some(None)) => None,
+                (previous, next) => listing::rank_between(previous.flatten(), next.flatten()),
+            })
+        };
+        rank(id)?;
+        let new_rank = match between()? {
+            Some(rank) => rank,
+            None => {
+                let neighbors: Vec<u64> = previous.into_iter().chain(next).collect();
+                rank_records(&transaction, &neighbors)?;
+                between()?.ok_or(Error::Validation(
+                    "Previous record is not before the next one".into(),
+                ))?
+            }
+        };
+        transaction
+            .execute(
+                "UPDATE Record SET manual_rank = ?1 WHERE id_record = ?2;",
+                params![new_rank, id],
+            )
+            .map_err(Error::database("Failed to save rank"))?;
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))
+    }
+
+    /// Returns the number of records matching the filter, see [`Database::get_records_window`].
+    pub fn count_records(&self, filter: &listing::RecordFilter) -> Result<u64, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let (where_clause, parameters) = self.listing_where_clause(filter);
+        connection
+            .query_row(
+                &format!("SELECT count(*) FROM Record {where_clause};"),
+                rusqlite::params_from_iter(parameters),
+                |row| row.get(0),
+            )
+            .map_err(Error::database("Failed to count records"))
+    }
+
+    /// Returns the number of records created since the time. Timestamps are compared as instants, so records saved with a different UTC offset, e.g. before a daylight saving time change, are counted correctly.
+    pub fn count_records_created_since(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, Error> {
+        self.count_records_since("created", since)
+    }
+
+    /// Returns the number of records modified since the time, including new records, see [`Database::count_records_created_since`].
+    pub fn count_records_modified_since(
+        &self,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, Error> {
+        self.count_records_since("last_modified", since)
+    }
+
+    /// Counts records with the timestamp column at or after the time. Uses the indexes of the column, see [`migration`].
+    fn count_records_since(
+        &self,
+        column: &'static str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> Result<u64, Error> {
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
         connection
-            .execute(sql, &*params)
-            .map_err(|_| "Failed to save record")?;
-        if id_record == 0 {
-            record.set_id(connection.last_insert_rowid() as u64);
+            .query_row(
+                &format!(
+                    "SELECT count(*) FROM Record WHERE julianday({column}) >= julianday(?1) AND {};",
+                    self.trash_condition(false)
+                ),
+                params![since],
+                |row| row.get(0),
+            )
+            .map_err(Error::database("Failed to count records"))
+    }
+
+    /// Returns ids and titles of the most recently modified records.
+    pub fn recently_modified_records(&self, limit: u64) -> Result<Vec<(u64, String)>, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT id_record, title FROM Record WHERE {}
+                ORDER BY julianday(last_modified) DESC, id_record DESC LIMIT ?1;",
+                self.trash_condition(false)
+            ))
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<(u64, String)>> = stmt
+            .query_map(params![limit], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(Error::database("Failed to map records"))?
+            .collect();
+        result.map_err(Error::database("Failed to get records"))
+    }
+
+    /// Returns candidate records for the search, matching the query in title or subtitle, case- and accent-insensitive ([`normalize_search`]). With `deep`, records with a matching field label or value are returned too. Only values of text, long text, URL, email and phone number kinds are searched, so a password, card number or other secret typed into the search never produces a hit. Ranking is done by [`crate::search::rank`].
+    pub fn search_records(&self, query: &str, deep: bool) -> Result<Vec<Record>, Error> {
+        let (title, subtitle, title_pattern) = match self.normalized_columns {
+            true => (
+                "title_normalized",
+                "subtitle_normalized",
+                like_pattern(&normalize_search(query)),
+            ),
+            false => ("title", "subtitle", like_pattern(query)),
+        };
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT {} FROM Record
+                WHERE {} AND ({title} LIKE ?3 ESCAPE '\\' OR {subtitle} LIKE ?3 ESCAPE '\\' OR (?2 AND id_record IN (
+                    SELECT id_record FROM Content WHERE label LIKE ?1 ESCAPE '\\'
+                    OR (kind IN ('Text', 'LongText', 'Url', 'Email', 'PhoneNumber') AND value LIKE ?1 ESCAPE '\\')
+                )));",
+                self.record_columns(),
+                self.trash_condition(false)
+            ))
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<Record>> = stmt
+            .query_map(
+                params![like_pattern(query), deep, title_pattern],
+                convert::row_to_record,
+            )
+            .map_err(Error::database("Failed to map records"))?
+            .collect();
+        result.map_err(Error::database("Failed to search records"))
+    }
+
+    /// Returns records that have a website with the same host as the URL, see [`value::Url::host`].
+    /// # Errors
+    /// If the URL is not valid or the records cannot be loaded.
+    pub fn find_records_for_url(&self, url: &str) -> Result<Vec<Record>, Error> {
+        let host = value::Url::new(url.to_string())
+            .map_err(|error| Error::Validation(error.into()))?
+            .host()
+            .ok_or(Error::Validation("Invalid URL".into()))?;
+        let ids: Vec<u64> = {
+            let connection = self
+                .connection
+                .lock()
+                .map_err(|_| "Failed to access database lock")?;
+            let mut stmt = connection
+                .prepare("SELECT id_record, value FROM Content WHERE kind = 'Url';")
+                .map_err(Error::database("Failed to prepare statement"))?;
+            let rows: Result<Vec<(u64, String)>> = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .map_err(Error::database("Failed to map content"))?
+                .collect();
+            rows.map_err(Error::database("Failed to get content"))?
+                .into_iter()
+                .filter(|(_, value)| {
+                    value::Url::new(value.clone())
+                        .ok()
+                        .and_then(|url| url.host())
+                        .is_some_and(|other| other == host)
+                })
+                .map(|(id_record, _)| id_record)
+                .collect()
+        };
+        Ok(self
+            .get_all_records()?
+            .into_iter()
+            .filter(|record| ids.contains(&record.id()))
+            .collect())
+    }
+
+    /// Replaces `old_prefix` of all website values with `new_prefix`, e.g. after a domain migration, see [`replace_prefix`]. With `dry_run`, the replacements are only returned. Otherwise they are saved in a single transaction and affected records are marked as modified.
+    /// # Return
+    /// Returns the replacements ordered by record and content id.
+    /// # Errors
+    /// If the prefix is empty, a replaced value is not a valid URL or it would duplicate another website of the same record. Nothing is saved in that case.
+    pub fn replace_url_prefix(
+        &self,
+        old_prefix: &str,
+        new_prefix: &str,
+        dry_run: bool,
+    ) -> Result<Vec<UrlReplacement>, Error> {
+        if old_prefix.is_empty() {
+            return Err(Error::Validation("Prefix can not be empty".into()));
         }
-        Ok(())
+        let mut grouped: Vec<(u64, Vec<Content>)> =
+            self.get_all_content_grouped()?.into_iter().collect();
+        grouped.sort_by_key(|(id_record, _)| *id_record);
+        let mut replacements = Vec::new();
+        let mut changed = Vec::new();
+        for (id_record, content) in grouped {
+            let urls: Vec<(Content, String, Option<String>)> = content
+                .into_iter()
+                .filter_map(|content| {
+                    let Value::Url(url) = content.value() else {
+                        return None;
+                    };
+                    let before = url.value().to_string();
+                    let after = replace_prefix(&before, old_prefix, new_prefix)
+                        .filter(|after| *after != before);
+                    Some((content, before, after))
+                })
+                .collect();
+            for (index, (_, before, after)) in urls.iter().enumerate() {
+                let Some(after) = after else {
+                    continue;
+                };
+                let duplicate =
+                    urls.iter()
+                        .enumerate()
+                        .any(|(other, (_, other_before, other_after))| {
+                            other != index
+                                && other_before != before
+                                && other_after.as_ref().unwrap_or(other_before) == after
+                        });
+                if duplicate {
+                    return Err(Error::Validation(
+                        "Replacement would duplicate a website of the record".into(),
+                    ));
+                }
+            }
+            for (mut content, before, after) in urls {
+                let Some(after) = after else {
+                    continue;
+                };
+                *content.value_mut() = Value::Url(
+                    value::Url::new(after.clone())
+                        .map_err(|_| Error::Validation("Replacement is not a valid URL".into()))?,
+                );
+                replacements.push(UrlReplacement {
+                    id_record,
+                    id_content: content.id(),
+                    before,
+                    after,
+                });
+                changed.push((id_record, content));
+            }
+        }
+        if dry_run || changed.is_empty() {
+            return Ok(replacements);
+        }
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        let now = chrono::Utc::now();
+        for (id_record, content) in changed.iter_mut() {
+            write_content(&transaction, *id_record, content)?;
+            transaction
+                .execute(
+                    "UPDATE Record SET last_modified = ?1 WHERE id_record = ?2;",
+                    params![now, *id_record],
+                )
+                .map_err(Error::database("Failed to save record"))?;
+        }
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))?;
+        Ok(replacements)
     }
 
-    /// Saves content to the database. Based on the id, it will insert or update the content. If the content is new, it will get an id.
-    pub fn save_content(&self, id_record: u64, content: &mut Content) -> Result<(), &'static str> {
-        let label = content.label();
-        let position = content.position();
-        let required = content.required();
-        let kind = content.kind();
-        let secret_value = content.value().to_secret_string();
-        let value = secret_value.expose_secret();
-        let id_content = content.id();
-        let mut params = params![label, position, required, kind, value].to_vec();
-        let sql = if id_content == 0 {
-            params.append(&mut params![id_record].to_vec());
-            "INSERT INTO Content (label, position, required, kind, value, id_record) VALUES (?1, ?2, ?3, ?4, ?5, ?6);"
-        } else {
-            params.append(&mut params![id_content].to_vec());
-            "UPDATE Content SET label = ?1, position = ?2, required = ?3, kind = ?4, value = ?5 WHERE id_content = ?6;"
+    /// Returns all content for the record like [`Database::get_all_content_for_record`], but long texts with a preview contain only the preview and are marked as truncated. Full value can be loaded by [`Database::get_content`]. In read-only mode, the preview column may not exist yet, so full values are returned.
+    pub fn get_all_content_previews_for_record(
+        &self,
+        id_record: u64,
+    ) -> Result<Vec<Content>, Error> {
+        if self.read_only.is_some() {
+            return self.get_all_content_for_record(id_record);
+        }
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare("SELECT id_content, label, position, required, kind, COALESCE(preview, value), preview IS NOT NULL FROM Content WHERE id_record = ?1;")
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<Content>> = stmt
+            .query_map([id_record], convert::row_to_content_preview)
+            .map_err(Error::database("Failed to map content"))?
+            .collect();
+        result.map_err(Error::database("Failed to get content"))
+    }
+
+    /// Returns all content of the record with the times values were last changed, see [`Content::value_last_changed`].
+    pub fn get_all_content_for_record(&self, id_record: u64) -> Result<Vec<Content>, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT id_content, label, position, required, kind, value, {} FROM Content JOIN Record USING (id_record) WHERE id_record = ?1;",
+                self.value_changed_expression()
+            ))
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<Content>> = stmt
+            .query_map([id_record], |row| {
+                let mut content = convert::row_to_content(row)?;
+                content.set_value_last_changed(row.get(6)?);
+                Ok(content)
+            })
+            .map_err(Error::database("Failed to map content"))?
+            .collect();
+        result.map_err(Error::database("Failed to get content"))
+    }
+
+    /// Returns content of all records grouped by record id, with one query instead of one per record. Content of each record is in the same order as [`Database::get_all_content_for_record`] returns it, also with the times values were last changed. Records without content are missing.
+    pub fn get_all_content_grouped(&self) -> Result<HashMap<u64, Vec<Content>>, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT id_content, label, position, required, kind, value, id_record, {} FROM Content JOIN Record USING (id_record) ORDER BY id_record, id_content;",
+                self.value_changed_expression()
+            ))
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let rows: Result<Vec<(u64, Content)>> = stmt
+            .query_map([], |row| {
+                let mut content = convert::row_to_content(row)?;
+                content.set_value_last_changed(row.get(7)?);
+                Ok((row.get(6)?, content))
+            })
+            .map_err(Error::database("Failed to map content"))?
+            .collect();
+        let mut grouped: HashMap<u64, Vec<Content>> = HashMap::new();
+        for (id_record, content) in rows.map_err(Error::database("Failed to get content"))? {
+            grouped.entry(id_record).or_default().push(content);
+        }
+        Ok(grouped)
+    }
+
+    /// Returns ids of records not in the trash with a password that has not changed in the last `days` days, see [`Content::value_last_changed`]. Records with the acknowledged [`Finding::Stale`] are left out, unless `include_acknowledged` is set.
+    pub fn get_stale_passwords(
+        &self,
+        days: u32,
+        include_acknowledged: bool,
+    ) -> Result<Vec<u64>, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let acknowledged = match include_acknowledged.not() && self.acknowledgement_table {
+            true => "AND id_record NOT IN (SELECT id_record FROM Acknowledgement WHERE finding = 'Stale')",
+            false => "",
         };
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT DISTINCT id_record FROM Content JOIN Record USING (id_record)
+                WHERE kind = 'Password' AND julianday({}) < julianday('now', ?1) AND {} {acknowledged}
+                ORDER BY id_record;",
+                self.value_changed_expression(),
+                self.trash_condition(false)
+            ))
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<u64>> = stmt
+            .query_map([format!("-{days} days")], |row| row.get(0))
+            .map_err(Error::database("Failed to map records"))?
+            .collect();
+        result.map_err(Error::database("Failed to get records"))
+    }
+
+    /// Calls `visit` with the record id and the value of every password of records with an id greater than `after` that are not in the trash, ordered by record id. Rows are read one by one with a single query, other content and the records are not loaded. The scan stops early when `visit` breaks, so it can be continued later after the last whole record. Records with the acknowledged [`Finding::Compromised`] are left out, unless `include_acknowledged` is set. The database stays locked until the scan finishes, so `visit` must not use it.
+    /// # Return
+    /// Returns the number of scanned passwords, without the one `visit` broke on.
+    /// # Errors
+    /// If the passwords cannot be read.
+    pub fn scan_passwords(
+        &self,
+        include_acknowledged: bool,
+        after: u64,
+        mut visit: impl FnMut(u64, &str) -> ControlFlow<()>,
+    ) -> Result<usize, Error> {
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
-        connection
-            .execute(sql, &*params)
-            .map_err(|_| "Failed to save content")?;
-        if id_content == 0 {
-            content.set_id(connection.last_insert_rowid() as u64);
+        let acknowledged = match include_acknowledged.not() && self.acknowledgement_table {
+            true => "AND id_record NOT IN (SELECT id_record FROM Acknowledgement WHERE finding = 'Compromised')",
+            false => "",
+        };
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT id_record, value FROM Content JOIN Record USING (id_record)
+                WHERE kind = 'Password' AND id_record > ?1 AND {} {acknowledged}
+                ORDER BY id_record;",
+                self.trash_condition(false)
+            ))
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let mut rows = stmt
+            .query([after])
+            .map_err(Error::database("Failed to get passwords"))?;
+        let mut scanned = 0;
+        while let Some(row) = rows
+            .next()
+            .map_err(Error::database("Failed to get passwords"))?
+        {
+            let id_record: u64 = row
+                .get(0)
+                .map_err(Error::database("Failed to get passwords"))?;
+            let value = Zeroizing::new(
+                row.get::<_, String>(1)
+                    .map_err(Error::database("Failed to get passwords"))?,
+            );
+            if visit(id_record, &value).is_break() {
+                break;
+            }
+            scanned += 1;
         }
-        Ok(())
+        Ok(scanned)
     }
 
-    /// To add password hash breach status to the cache.
-    pub fn add_data_breach_cache(&self, hash: &str, exposed: bool) -> Result<(), &'static str> {
+    /// Acknowledges the finding of the record with a note, so it is not reported any more. Acknowledging it again replaces the note. The acknowledgement is deleted when a password of the record changes, see [`clear_acknowledgements`].
+    /// # Errors
+    /// If the note is too long ([`MAX_ACKNOWLEDGEMENT_NOTE_LENGTH`]), the record does not exist or the acknowledgement cannot be saved.
+    pub fn acknowledge_finding(
+        &self,
+        id_record: u64,
+        finding: Finding,
+        note: &str,
+    ) -> Result<(), Error> {
+        if note.chars().count() > MAX_ACKNOWLEDGEMENT_NOTE_LENGTH {
+            return Err(Error::Validation("Note is too long".into()));
+        }
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
-        connection
-            .execute("REPLACE INTO DataBreachCache (hash, exposed, checked) VALUES (?1, ?2, datetime('now'));", params![hash, exposed])
-            .map_err(|_| "Failed to save content")?;
+        let inserted = connection
+            .execute(
+                "INSERT OR REPLACE INTO Acknowledgement (id_record, finding, note, acknowledged)
+                SELECT id_record, ?2, ?3, ?4 FROM Record WHERE id_record = ?1;",
+                params![id_record, finding.as_str(), note.trim(), chrono::Utc::now()],
+            )
+            .map_err(Error::database("Failed to save acknowledgement"))?;
+        if inserted == 0 {
+            return Err(Error::NotFound("Record does not exist"));
+        }
         Ok(())
     }
 
-    pub fn delete_setting(&self, name: &str) -> Result<(), &'static str> {
+    /// Returns all acknowledged findings ordered by record and finding.
+    pub fn get_acknowledgements(&self) -> Result<Vec<Acknowledgement>, Error> {
+        if self.acknowledgement_table.not() {
+            return Ok(Vec::new());
+        }
         let connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
-        connection
-            .execute("DELETE FROM Settings WHERE name = ?1;", params![name])
-            .map_err(|_| "Failed to delete setting")?;
+        let mut stmt = connection
+            .prepare("SELECT id_record, finding, note, acknowledged FROM Acknowledgement ORDER BY id_record, finding;")
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<Vec<Option<Acknowledgement>>> = stmt
+            .query_map([], |row| {
+                let finding: String = row.get(1)?;
+                Ok(match Finding::from_string(&finding) {
+                    Some(finding) => Some(Acknowledgement {
+                        id_record: row.get(0)?,
+                        finding,
+                        note: row.get(2)?,
+                        acknowledged: row.get(3)?,
+                    }),
+                    None => None,
+                })
+            })
+            .map_err(Error::database("Failed to map acknowledgements"))?
+            .collect();
+        Ok(result
+            .map_err(Error::database("Failed to get acknowledgements"))?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// Revokes the acknowledgement, so the finding of the record is reported again.
+    /// # Errors
+    /// If the finding of the record is not acknowledged or it cannot be deleted.
+    pub fn revoke_acknowledgement(&self, id_record: u64, finding: Finding) -> Result<(), Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let deleted = connection
+            .execute(
+                "DELETE FROM Acknowledgement WHERE id_record = ?1 AND finding = ?2;",
+                params![id_record, finding.as_str()],
+            )
+            .map_err(Error::database("Failed to revoke acknowledgement"))?;
+        if deleted == 0 {
+            return Err(Error::NotFound("Finding is not acknowledged"));
+        }
         Ok(())
     }
 
-    /// Deletes a record from the database. It will also delete all content for the record.
-    pub fn delete_record(&self, record: Record) -> Result<(), &'static str> {
+    /// Returns metadata of the record in the namespace as a map of keys to values.
+    pub fn get_record_metadata(
+        &self,
+        id_record: u64,
+        namespace: &str,
+    ) -> Result<BTreeMap<String, String>, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare(
+                "SELECT key, value FROM RecordMetadata WHERE id_record = ?1 AND namespace = ?2;",
+            )
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let result: Result<BTreeMap<String, String>> = stmt
+            .query_map(params![id_record, namespace], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .map_err(Error::database("Failed to map metadata"))?
+            .collect();
+        result.map_err(Error::database("Failed to get metadata"))
+    }
+
+    /// Returns metadata of all records in all namespaces grouped by record id. Records without metadata are missing.
+    pub fn get_all_metadata_grouped(&self) -> Result<HashMap<u64, Vec<MetadataEntry>>, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare("SELECT id_record, namespace, key, value FROM RecordMetadata ORDER BY id_record, namespace, key;")
+            .map_err(Error::database("Failed to prepare statement"))?;
+        let rows: Result<Vec<(u64, MetadataEntry)>> = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    MetadataEntry {
+                        namespace: row.get(1)?,
+                        key: row.get(2)?,
+                        value: row.get(3)?,
+                    },
+                ))
+            })
+            .map_err(Error::database("Failed to map metadata"))?
+            .collect();
+        let mut grouped: HashMap<u64, Vec<MetadataEntry>> = HashMap::new();
+        for (id_record, entry) in rows.map_err(Error::database("Failed to get metadata"))? {
+            grouped.entry(id_record).or_default().push(entry);
+        }
+        Ok(grouped)
+    }
+
+    /// Adds the tag to the record. The tag is created if no record has it yet, names differing only in case are the same tag and keep the case they were created with. Adding a tag the record already has does nothing.
+    /// # Errors
+    /// If the name is blank or longer than [`MAX_TAG_LENGTH`], the record does not exist or is in the trash, or the tag cannot be saved.
+    pub fn add_tag(&self, id_record: u64, name: &str) -> Result<(), Error> {
+        let name = check_tag(name)?;
         let mut connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
         let transaction = connection
             .transaction()
-            .map_err(|_| "Failed to start transaction")?;
+            .map_err(Error::database("Failed to start transaction"))?;
+        let exists: bool = transaction
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM Record WHERE id_record = ?1 AND deleted IS NULL);",
+                params![id_record],
+                |row| row.get(0),
+            )
+            .map_err(Error::database("Failed to check record"))?;
+        if exists.not() {
+            return Err(Error::NotFound("Record does not exist"));
+        }
         transaction
             .execute(
-                "DELETE FROM Content WHERE id_record = ?1;",
-                params![record.id()],
+                "INSERT INTO Tag (name) VALUES (?1) ON CONFLICT (name) DO NOTHING;",
+                params![name],
             )
-            .map_err(|_| "Failed to delete records content")?;
+            .map_err(Error::database("Failed to save tag"))?;
         transaction
             .execute(
-                "DELETE FROM Record WHERE id_record = ?1;",
-                params![record.id()],
+                "INSERT OR IGNORE INTO RecordTag (id_record, id_tag) SELECT ?1, id_tag FROM Tag WHERE name = ?2;",
+                params![id_record, name],
             )
-            .map_err(|_| "Failed to delete record")?;
+            .map_err(Error::database("Failed to save tag"))?;
         transaction
             .commit()
-            .map_err(|_| "Failed to commit transaction")
+            .map_err(Error::database("Failed to commit transaction"))
     }
 
-    pub fn delete_content(&self, content: Content) -> Result<(), &'static str> {
+    /// Removes the tag from the record. The tag is deleted when no other record has it. Missing tag is not an error.
+    pub fn remove_tag(&self, id_record: u64, name: &str) -> Result<(), Error> {
         let mut connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
         let transaction = connection
             .transaction()
-            .map_err(|_| "Failed to start transaction")?;
+            .map_err(Error::database("Failed to start transaction"))?;
         transaction
             .execute(
-                "DELETE FROM Content WHERE id_content = ?1;",
-                params![content.id()],
+                "DELETE FROM RecordTag WHERE id_record = ?1 AND id_tag IN (SELECT id_tag FROM Tag WHERE name = ?2);",
+                params![id_record, name.trim()],
             )
-            .map_err(|_| "Failed to delete content")?;
+            .map_err(Error::database("Failed to remove tag"))?;
+        delete_unused_tags(&transaction).map_err(Error::database("Failed to remove tag"))?;
         transaction
             .commit()
-            .map_err(|_| "Failed to commit transaction")
+            .map_err(Error::database("Failed to commit transaction"))
     }
 
-    /// Deletes all password hash breach status older than 24 hours.
-    pub fn delete_data_breach_cache_older_24h(&self) -> Result<(), &'static str> {
-        let connection = self
+    /// Restores a previous value of the content. The current value is moved to the history and the restored version is removed from it, so the value is not kept twice.
+    /// # Errors
+    /// If the version does not belong to the content or the value cannot be saved.
+    pub fn restore_content_version(&self, id_content: u64, id_version: u64) -> Result<(), Error> {
+        let mut connection = self
             .connection
             .lock()
             .map_err(|_| "Failed to access database lock")?;
-        connection
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        let (kind, value): (String, Zeroizing<String>) = transaction
+            .query_row(
+                "SELECT kind, value FROM ContentHistory WHERE id_version = ?1 AND id_content = ?2;",
+                params![id_version, id_content],
+                |row| Ok((row.get(0)?, Zeroizing::new(row.get(1)?))),
+            )
+            .optional()
+            .map_err(Error::database("Failed to get content history"))?
+            .ok_or(Error::NotFound("Version does not exist"))?;
+        save_history(&transaction, id_content, &value)
+            .map_err(Error::database("Failed to save content history"))?;
+        clear_acknowledgements(&transaction, id_content, &value)
+            .map_err(Error::database("Failed to clear acknowledgements"))?;
+        let preview = preview(&kind, &value);
+        transaction
             .execute(
-                "DELETE FROM DataBreachCache WHERE checked < datetime('now', '-1 day');",
-                [],
+                "UPDATE Content SET kind = ?1, value = ?2, preview = ?3, value_last_changed = ?4 WHERE id_content = ?5;",
+                params![kind, value.as_str(), preview.as_deref(), chrono::Utc::now(), id_content],
             )
-            .map_err(|_| "Failed to delete old breach status")?;
-        Ok(())
+            .map_err(Error::database("Failed to save content"))?;
+        transaction
+            .execute(
+                "UPDATE Record SET last_modified = ?1 WHERE id_record = (SELECT id_record FROM Content WHERE id_content = ?2);",
+                params![chrono::Utc::now(), id_content],
+            )
+            .map_err(Error::database("Failed to save record"))?;
+        transaction
+            .execute(
+                "DELETE FROM ContentHistory WHERE id_version = ?1;",
+                params![id_version],
+            )
+            .map_err(Error::database("Failed to save content history"))?;
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))
+    }
+
+    /// Returns true if the record is locked, see [`Record::locked`]. Missing record is not locked.
+    pub fn is_record_locked(&self, id_record: u64) -> Result<bool, Error> {
+        if self.locked_column.not() {
+            return Ok(false);
+        }
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        connection
+            .query_row(
+                "SELECT locked FROM Record WHERE id_record = ?1;",
+                [id_record],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::unwrap_or_default)
+            .map_err(Error::database("Failed to get record"))
+    }
+
+    /// Returns true if the content belongs to a locked record, see [`Record::locked`]. Missing content is not locked.
+    pub fn is_content_locked(&self, id_content: u64) -> Result<bool, Error> {
+        if self.locked_column.not() {
+            return Ok(false);
+        }
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        connection
+            .query_row(
+                "SELECT Record.locked FROM Content JOIN Record USING (id_record) WHERE id_content = ?1;",
+                [id_content],
+                |row| row.get(0),
+            )
+            .optional()
+            .map(Option::unwrap_or_default)
+            .map_err(Error::database("Failed to get content"))
+    }
+
+    /// Based on the hash, it returns the breach status from the cache.
+    pub fn get_data_breach_status(&self, hash: &str) -> Result<Option<bool>, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let mut stmt = connection
+            .prepare("SELECT exposed FROM DataBreachCache WHERE hash = ?1;")
+            .map_err(Error::database("Failed to prepare statement"))?;
+        stmt.query_row(params![hash], |row| row.get(0))
+            .optional()
+            .map_err(Error::database("Failed to get breach status"))
+    }
+
+    pub fn save_setting(&self, name: &str, value: &str) -> Result<(), Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        connection
+            .execute(
+                "REPLACE INTO Settings (name, value) VALUES (?1, ?2);",
+                params![name, value],
+            )
+            .map_err(Error::database("Failed to save setting"))?;
+        Ok(())
+    }
+
+    /// Returns whether subtitles are derived from content, see [`AUTO_SUBTITLE_SETTING`].
+    fn auto_subtitle(&self) -> bool {
+        self.get_setting(AUTO_SUBTITLE_SETTING)
+            .is_ok_and(|value| value.expose_secret() == "false")
+            .not()
+    }
+
+    /// Saves all settings in a single transaction, so either all or none of them are saved.
+    pub fn save_settings(&self, settings: &[(&str, &str)]) -> Result<(), Error> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        for (name, value) in settings {
+            transaction
+                .execute(
+                    "REPLACE INTO Settings (name, value) VALUES (?1, ?2);",
+                    params![name, value],
+                )
+                .map_err(Error::database("Failed to save setting"))?;
+        }
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))
+    }
+
+    /// Saves a record with all its content in a single transaction and discards the draft of the record. If the record is new, the draft of a new record is discarded. The subtitle is derived from the content unless [`AUTO_SUBTITLE_SETTING`] is disabled, see [`derive_subtitle`]. Other content of the record is kept.
+    /// # Errors
+    /// If the record or any of the content cannot be saved. Nothing is saved, new record and content keep id 0 and the draft is kept in that case.
+    pub fn save_record_with_content(
+        &self,
+        record: &mut Record,
+        content: &mut [Content],
+    ) -> Result<(), Error> {
+        self.write_record_with_content(record, content, false)
+    }
+
+    /// Saves a record like [`Database::save_record_with_content`], but the given content replaces all content of the record. Content of the record that is not in the list is deleted with its history in the same transaction.
+    /// # Errors
+    /// If the record or any of the content cannot be saved or the removed content cannot be deleted. Nothing is saved or deleted in that case.
+    pub fn save_record_replacing_content(
+        &self,
+        record: &mut Record,
+        content: &mut [Content],
+    ) -> Result<(), Error> {
+        self.write_record_with_content(record, content, true)
+    }
+
+    /// Saves the record and content in a single transaction, see [`Database::save_record_with_content`]. Other content of the record is deleted if `replace` is true.
+    fn write_record_with_content(
+        &self,
+        record: &mut Record,
+        content: &mut [Content],
+        replace: bool,
+    ) -> Result<(), Error> {
+        if self.auto_subtitle() {
+            if let Some(subtitle) = derive_subtitle(record.category(), content) {
+                record.set_subtitle(subtitle);
+            }
+        }
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        let id_draft = record.id();
+        let new_content: Vec<bool> = content.iter().map(|content| content.id() == 0).collect();
+        let result = (|| {
+            write_record(&transaction, record)?;
+            for content in content.iter_mut() {
+                write_content(&transaction, record.id(), content)?;
+            }
+            if replace {
+                remove_other_content(&transaction, record.id(), content)?;
+            }
+            transaction
+                .execute(
+                    "DELETE FROM Drafts WHERE id_record = ?1;",
+                    params![id_draft],
+                )
+                .map_err(Error::database("Failed to discard draft"))?;
+            transaction
+                .commit()
+                .map_err(Error::database("Failed to commit transaction"))
+        })();
+        if result.is_err() {
+            record.set_id(id_draft);
+            for (content, new) in content.iter_mut().zip(new_content) {
+                if new {
+                    content.set_id(0);
+                }
+            }
+        }
+        result
+    }
+
+    /// Inserts a copy of the record with its content and metadata into the database in a single transaction. The copy gets new ids, given record and content are not modified. The subtitle of the copy is derived from the content unless [`AUTO_SUBTITLE_SETTING`] is disabled, like when a record is saved.
+    /// # Return
+    /// Returns `false` and inserts nothing if a record with the same title, category and creation time already exists.
+    /// # Errors
+    /// If the copy cannot be inserted. A specific error is returned if the database is locked by another process.
+    pub fn insert_record_copy(
+        &self,
+        record: &Record,
+        content: &[Content],
+        metadata: &[MetadataEntry],
+    ) -> Result<bool, Error> {
+        let auto_subtitle = self.auto_subtitle();
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(Error::database("Failed to start transaction"))?;
+        let exists: bool = transaction
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM Record WHERE title = ?1 AND category = ?2 AND created = ?3);",
+                params![record.title(), record.category().as_str(), record.created()],
+                |row| row.get(0),
+            )
+            .map_err(Error::database("Failed to check for duplicate record"))?;
+        if exists {
+            return Ok(false);
+        }
+        let id_record = insert_record_full(&transaction, record, content, auto_subtitle)?;
+        for entry in metadata {
+            check_metadata(&entry.namespace, &entry.key, &entry.value)?;
+            transaction
+                .execute(
+                    "INSERT INTO RecordMetadata (id_record, namespace, key, value) VALUES (?1, ?2, ?3, ?4);",
+                    params![id_record, entry.namespace, entry.key, entry.value],
+                )
+                .map_err(Error::database("Failed to save metadata"))?;
+        }
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))?;
+        Ok(true)
+    }
+
+    /// Inserts new records with their content in a single transaction. Ids of the records and content are ignored, new ones are assigned. Subtitles are derived from the content unless [`AUTO_SUBTITLE_SETTING`] is disabled, like when a record is saved.
+    /// # Return
+    /// Returns the number of inserted records.
+    /// # Errors
+    /// If any of the records cannot be inserted. Nothing is inserted in that case.
+    pub fn import_records(&self, records: &[(Record, Vec<Content>)]) -> Result<usize, Error> {
+        let auto_subtitle = self.auto_subtitle();
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        for (record, content) in records {
+            insert_record_full(&transaction, record, content, auto_subtitle)?;
+        }
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))?;
+        Ok(records.len())
+    }
+
+    /// Computes the derived columns of all records again and repairs the ones that differ: subtitles derived from content unless [`AUTO_SUBTITLE_SETTING`] is disabled, title and subtitle normalized for search and previews of long texts. Last modification times are not changed.
+    /// # Return
+    /// Returns the number of repaired records and content.
+    /// # Errors
+    /// If the records or content cannot be read or updated. Nothing is repaired in that case.
+    pub fn recompute_derived_columns(&self) -> Result<usize, Error> {
+        let auto_subtitle = self.auto_subtitle();
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(Error::database("Failed to start transaction"))?;
+        let mut repaired = 0;
+        let mut content: HashMap<u64, Vec<Content>> = HashMap::new();
+        {
+            let mut select = transaction
+                .prepare("SELECT id_content, label, position, required, kind, value, id_record FROM Content ORDER BY id_record, id_content;")
+                .map_err(Error::database("Failed to prepare statement"))?;
+            let mut update = transaction
+                .prepare(
+                    "UPDATE Content SET preview = ?1 WHERE id_content = ?2 AND preview IS NOT ?1;",
+                )
+                .map_err(Error::database("Failed to prepare statement"))?;
+            let mut rows = select
+                .query([])
+                .map_err(Error::database("Failed to get content"))?;
+            while let Some(row) = rows
+                .next()
+                .map_err(Error::database("Failed to get content"))?
+            {
+                let item = convert::row_to_content(row)
+                    .map_err(Error::database("Failed to get content"))?;
+                let id_record: u64 = row
+                    .get(6)
+                    .map_err(Error::database("Failed to get content"))?;
+                let preview = preview(item.kind(), item.value().to_secret_string().expose_secret());
+                repaired += update
+                    .execute(params![preview.as_deref(), item.id()])
+                    .map_err(Error::database("Failed to update content"))?;
+                content.entry(id_record).or_default().push(item);
+            }
+        }
+        let records: Vec<(u64, String, String, Category)> = transaction
+            .prepare("SELECT id_record, title, subtitle, category FROM Record;")
+            .and_then(|mut select| {
+                select
+                    .query_map([], |row| {
+                        Ok((
+                            row.get(0)?,
+                            row.get(1)?,
+                            row.get(2)?,
+                            Category::from_string(row.get(3)?),
+                        ))
+                    })?
+                    .collect()
+            })
+            .map_err(Error::database("Failed to get records"))?;
+        for (id_record, title, subtitle, category) in records {
+            let subtitle = auto_subtitle
+                .then(|| {
+                    derive_subtitle(
+                        &category,
+                        content
+                            .get(&id_record)
+                            .map(Vec::as_slice)
+                            .unwrap_or_default(),
+                    )
+                })
+                .flatten()
+                .unwrap_or(subtitle);
+            repaired += transaction
+                .execute(
+                    "UPDATE Record SET subtitle = ?1, title_normalized = ?2, subtitle_normalized = ?3
+                    WHERE id_record = ?4 AND (subtitle IS NOT ?1 OR title_normalized IS NOT ?2 OR subtitle_normalized IS NOT ?3);",
+                    params![
+                        subtitle,
+                        normalize_search(&title),
+                        normalize_search(&subtitle),
+                        id_record
+                    ],
+                )
+                .map_err(Error::database("Failed to update record"))?;
+        }
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))?;
+        Ok(repaired)
+    }
+
+    /// Locks or unlocks the record, see [`Record::locked`].
+    /// # Errors
+    /// If the record does not exist or cannot be updated.
+    pub fn set_record_locked(&self, id_record: u64, locked: bool) -> Result<(), Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let updated = connection
+            .execute(
+                "UPDATE Record SET locked = ?1 WHERE id_record = ?2;",
+                params![locked, id_record],
+            )
+            .map_err(Error::database("Failed to lock record"))?;
+        if updated == 0 {
+            return Err(Error::NotFound("Record does not exist"));
+        }
+        Ok(())
+    }
+
+    /// Marks the record as a favorite or removes the mark, see [`Record::favorite`].
+    /// # Errors
+    /// If the record does not exist, is in the trash or cannot be updated.
+    pub fn set_record_favorite(&self, id_record: u64, favorite: bool) -> Result<(), Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let updated = connection
+            .execute(
+                "UPDATE Record SET favorite = ?1 WHERE id_record = ?2 AND deleted IS NULL;",
+                params![favorite, id_record],
+            )
+            .map_err(Error::database("Failed to update record"))?;
+        if updated == 0 {
+            return Err(Error::NotFound("Record does not exist"));
+        }
+        Ok(())
+    }
+
+    /// Sets metadata of the record. Metadata is namespaced, so different integrations do not overwrite each other's keys, and it is not part of the record content shown in the application.
+    /// # Errors
+    /// If the namespace or key is blank, the value is longer than [`MAX_METADATA_LENGTH`], the record does not exist or the metadata cannot be saved.
+    pub fn set_record_metadata(
+        &self,
+        id_record: u64,
+        namespace: &str,
+        key: &str,
+        value: &str,
+    ) -> Result<(), Error> {
+        check_metadata(namespace, key, value)?;
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let inserted = connection
+            .execute(
+                "INSERT INTO RecordMetadata (id_record, namespace, key, value)
+                SELECT id_record, ?2, ?3, ?4 FROM Record WHERE id_record = ?1
+                ON CONFLICT (id_record, namespace, key) DO UPDATE SET value = excluded.value;",
+                params![id_record, namespace, key, value],
+            )
+            .map_err(Error::database("Failed to save metadata"))?;
+        if inserted == 0 {
+            return Err(Error::NotFound("Record does not exist"));
+        }
+        Ok(())
+    }
+
+    /// Loads recovery codes of the content, applies `update` to them and saves them in a single transaction.
+    /// # Errors
+    /// If the content does not exist, is not [`value::RecoveryCodes`], if `update` fails or if the codes cannot be saved.
+    fn update_recovery_codes<T>(
+        &self,
+        id_content: u64,
+        update: impl FnOnce(&mut value::RecoveryCodes) -> Result<T, &'static str>,
+    ) -> Result<T, Error> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(Error::database("Failed to start transaction"))?;
+        let mut content = transaction
+            .query_row(
+                "SELECT id_content, label, position, required, kind, value FROM Content WHERE id_content = ?1;",
+                params![id_content],
+                convert::row_to_content,
+            )
+            .map_err(Error::database("Failed to get content"))?;
+        let Value::RecoveryCodes(recovery_codes) = content.value_mut() else {
+            return Err(Error::Validation("Content is not recovery codes".into()));
+        };
+        let result = update(recovery_codes)?;
+        transaction
+            .execute(
+                "UPDATE Content SET value = ?1 WHERE id_content = ?2;",
+                params![
+                    content.value().to_secret_string().expose_secret(),
+                    id_content
+                ],
+            )
+            .map_err(Error::database("Failed to save content"))?;
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))?;
+        Ok(result)
+    }
+
+    /// Marks the recovery code with the given index as used, see [`value::RecoveryCodes::mark_used`].
+    /// # Errors
+    /// If the content is not recovery codes, the code does not exist or the codes cannot be saved.
+    pub fn mark_recovery_code_used(&self, id_content: u64, index: usize) -> Result<(), Error> {
+        self.update_recovery_codes(id_content, |recovery_codes| recovery_codes.mark_used(index))
+    }
+
+    /// Saves a draft of the record, `id_record` is 0 for a new record. Drafts are throttled, an existing draft is overwritten only if it is older than [`DRAFT_INTERVAL_SECONDS`]. Nothing is saved in read-only mode.
+    /// # Return
+    /// Returns `true` if the draft was written.
+    pub fn save_draft(&self, id_record: u64, snapshot: &str) -> Result<bool, Error> {
+        if self.read_only.is_some() {
+            return Ok(false);
+        }
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let changed = connection
+            .execute(
+                "INSERT INTO Drafts (id_record, snapshot, saved) VALUES (?1, ?2, strftime('%Y-%m-%d %H:%M:%f', 'now'))
+                ON CONFLICT (id_record) DO UPDATE SET snapshot = excluded.snapshot, saved = excluded.saved
+                WHERE Drafts.saved <= strftime('%Y-%m-%d %H:%M:%f', 'now', ?3);",
+                params![id_record, snapshot, format!("-{DRAFT_INTERVAL_SECONDS} seconds")],
+            )
+            .map_err(Error::database("Failed to save draft"))?;
+        Ok(changed > 0)
+    }
+
+    /// To add password hash breach status to the cache. Nothing is cached in read-only mode.
+    pub fn add_data_breach_cache(&self, hash: &str, exposed: bool) -> Result<(), Error> {
+        if self.read_only.is_some() {
+            return Ok(());
+        }
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        connection
+            .execute("REPLACE INTO DataBreachCache (hash, exposed, checked) VALUES (?1, ?2, datetime('now'));", params![hash, exposed])
+            .map_err(Error::database("Failed to save content"))?;
+        Ok(())
+    }
+
+    /// Deletes all settings in a single transaction, so either all or none of them are deleted.
+    pub fn delete_settings(&self, names: &[&str]) -> Result<(), Error> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        for name in names {
+            transaction
+                .execute("DELETE FROM Settings WHERE name = ?1;", params![name])
+                .map_err(Error::database("Failed to delete setting"))?;
+        }
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))
+    }
+
+    /// Moves the record to the trash and discards its draft. Records in the trash are left out of all lists and searches until they are restored ([`Database::restore_record`]) or purged ([`Database::purge_record`]).
+    pub fn delete_record(&self, record: Record) -> Result<(), Error> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        transaction
+            .execute(
+                "DELETE FROM Drafts WHERE id_record = ?1;",
+                params![record.id()],
+            )
+            .map_err(Error::database("Failed to discard draft"))?;
+        transaction
+            .execute(
+                "UPDATE Record SET deleted = ?1 WHERE id_record = ?2 AND deleted IS NULL;",
+                params![chrono::Utc::now(), record.id()],
+            )
+            .map_err(Error::database("Failed to delete record"))?;
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))
+    }
+
+    /// Moves the records to the trash and discards their drafts in a single transaction, like [`Database::delete_record`].
+    /// # Return
+    /// Returns the number of deleted records.
+    /// # Errors
+    /// If any of the records does not exist, is already in the trash or cannot be deleted. Nothing is deleted in that case.
+    pub fn delete_records(&self, ids: &[u64]) -> Result<usize, Error> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        let deleted = chrono::Utc::now();
+        let ids: HashSet<&u64> = ids.iter().collect();
+        for id in &ids {
+            transaction
+                .execute("DELETE FROM Drafts WHERE id_record = ?1;", params![id])
+                .map_err(Error::database("Failed to discard draft"))?;
+            let updated = transaction
+                .execute(
+                    "UPDATE Record SET deleted = ?1 WHERE id_record = ?2 AND deleted IS NULL;",
+                    params![deleted, id],
+                )
+                .map_err(Error::database("Failed to delete record"))?;
+            if updated == 0 {
+                return Err(Error::NotFound("Record does not exist"));
+            }
+        }
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))?;
+        Ok(ids.len())
+    }
+
+    /// Moves the records to the category in a single transaction. The last modification time of the records is updated.
+    /// # Return
+    /// Returns the number of moved records.
+    /// # Errors
+    /// If any of the records does not exist, is in the trash or cannot be updated. Nothing is moved in that case.
+    pub fn move_records_to_category(
+        &self,
+        ids: &[u64],
+        category: &Category,
+    ) -> Result<usize, Error> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        let modified = chrono::Utc::now();
+        let ids: HashSet<&u64> = ids.iter().collect();
+        for id in &ids {
+            let updated = transaction
+                .execute(
+                    "UPDATE Record SET category = ?1, last_modified = ?2 WHERE id_record = ?3 AND deleted IS NULL;",
+                    params![category.as_str(), modified, id],
+                )
+                .map_err(Error::database("Failed to move record"))?;
+            if updated == 0 {
+                return Err(Error::NotFound("Record does not exist"));
+            }
+        }
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))?;
+        Ok(ids.len())
+    }
+
+    /// Restores the record from the trash.
+    /// # Errors
+    /// If the record is not in the trash or cannot be updated.
+    pub fn restore_record(&self, id_record: u64) -> Result<(), Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let updated = connection
+            .execute(
+                "UPDATE Record SET deleted = NULL WHERE id_record = ?1 AND deleted IS NOT NULL;",
+                params![id_record],
+            )
+            .map_err(Error::database("Failed to restore record"))?;
+        if updated == 0 {
+            return Err(Error::NotFound("Record is not in the trash"));
+        }
+        Ok(())
+    }
+
+    /// Deletes the record in the trash permanently with all its content, metadata and draft.
+    /// # Errors
+    /// If the record is not in the trash or cannot be deleted.
+    pub fn purge_record(&self, id_record: u64) -> Result<(), Error> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        let purged = purge_records(
+            &transaction,
+            "id_record = ?1 AND deleted IS NOT NULL",
+            params![id_record],
+        )
+        .map_err(Error::database("Failed to delete record"))?;
+        if purged == 0 {
+            return Err(Error::NotFound("Record is not in the trash"));
+        }
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))
+    }
+
+    pub fn delete_content(&self, content: Content) -> Result<(), Error> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        remove_content(&transaction, content.id())?;
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))
+    }
+
+    /// Deletes the metadata key of the record in the namespace. Missing key is not an error.
+    pub fn delete_record_metadata(
+        &self,
+        id_record: u64,
+        namespace: &str,
+        key: &str,
+    ) -> Result<(), Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        connection
+            .execute(
+                "DELETE FROM RecordMetadata WHERE id_record = ?1 AND namespace = ?2 AND key = ?3;",
+                params![id_record, namespace, key],
+            )
+            .map_err(Error::database("Failed to delete metadata"))?;
+        Ok(())
+    }
+
+    /// Discards the draft of the record, `id_record` is 0 for a new record.
+    pub fn discard_draft(&self, id_record: u64) -> Result<(), Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        connection
+            .execute(
+                "DELETE FROM Drafts WHERE id_record = ?1;",
+                params![id_record],
+            )
+            .map_err(Error::database("Failed to discard draft"))?;
+        Ok(())
+    }
+
+    /// Deletes all password hash breach status older than 24 hours. Nothing is deleted in read-only mode.
+    pub fn delete_data_breach_cache_older_24h(&self) -> Result<(), Error> {
+        if self.read_only.is_some() {
+            return Ok(());
+        }
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        connection
+            .execute(
+                "DELETE FROM DataBreachCache WHERE checked < datetime('now', '-1 day');",
+                [],
+            )
+            .map_err(Error::database("Failed to delete old breach status"))?;
+        Ok(())
+    }
+
+    /// Permanently deletes records that were moved to the trash more than 30 days ago. Nothing is deleted in read-only mode.
+    pub fn delete_trash_older_30_days(&self) -> Result<(), Error> {
+        if self.read_only.is_some() {
+            return Ok(());
+        }
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        purge_records(
+            &transaction,
+            "deleted IS NOT NULL AND julianday(deleted) < julianday('now', '-30 days')",
+            [],
+        )
+        .map_err(Error::database("Failed to empty trash"))?;
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))
+    }
+
+    /// Deletes previous values of every content except the last `keep_last`, see [`Database::get_content_history`]. Nothing is deleted in read-only mode.
+    pub fn prune_history(&self, keep_last: usize) -> Result<(), Error> {
+        if self.read_only.is_some() {
+            return Ok(());
+        }
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        connection
+            .execute(
+                "DELETE FROM ContentHistory WHERE id_version IN (
+                    SELECT id_version FROM (
+                        SELECT id_version, row_number() OVER (PARTITION BY id_content ORDER BY id_version DESC) AS version
+                        FROM ContentHistory
+                    ) WHERE version > ?1
+                );",
+                params![keep_last],
+            )
+            .map_err(Error::database("Failed to prune content history"))?;
+        Ok(())
+    }
+
+    /// Returns size of the database, size of its free pages and sizes before and after the last compaction.
+    pub fn stats(&self) -> Result<VaultStats, Error> {
+        let last_compaction = self
+            .get_setting(LAST_COMPACTION_SETTING)
+            .ok()
+            .and_then(|sizes| serde_json::from_str(sizes.expose_secret()).ok());
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let (size, free) = size(&connection)?;
+        Ok(VaultStats {
+            size,
+            free,
+            last_compaction,
+        })
+    }
+
+    /// Rebuilds the database file with `VACUUM`, so it shrinks after deletes and no free pages are left. Skipped in read-only mode or if the database is smaller than `threshold` bytes.
+    /// # Return
+    /// Sizes before and after the compaction, or `None` if it was skipped.
+    /// # Errors
+    /// If the database cannot be rebuilt.
+    pub fn compact(&self, threshold: u64) -> Result<Option<(u64, u64)>, Error> {
+        if self.read_only.is_some() {
+            return Ok(None);
+        }
+        let sizes = {
+            let connection = self
+                .connection
+                .lock()
+                .map_err(|_| "Failed to access database lock")?;
+            let (before, _) = size(&connection)?;
+            if before < threshold {
+                return Ok(None);
+            }
+            connection
+                .execute_batch("VACUUM;")
+                .map_err(Error::database("Failed to compact database"))?;
+            (before, size(&connection)?.0)
+        };
+        self.save_setting(
+            LAST_COMPACTION_SETTING,
+            &serde_json::to_string(&sizes).map_err(|_| "Failed to save database size")?,
+        )?;
+        Ok(Some(sizes))
+    }
+
+    /// Moves all committed changes from the write-ahead log into the database file and truncates the log, so the database file alone is complete and can be copied. Does nothing if the database is not in WAL mode.
+    /// # Errors
+    /// If the log cannot be checkpointed, e.g. because another connection is reading.
+    pub fn checkpoint(&self) -> Result<(), Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let busy: i64 = connection
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE);", [], |row| row.get(0))
+            .map_err(Error::database("Failed to checkpoint database"))?;
+        if busy != 0 {
+            return Err(Error::Other("Failed to checkpoint database"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn open_temp(directory: &TempDir, name: &str, password: &str) -> Database {
+        Database::open_path(password, &directory.path().join(name)).unwrap()
+    }
+
+    fn save_content(
+        database: &Database,
+        id_record: u64,
+        content: &mut Content,
+    ) -> Result<(), Error> {
+        write_content(&database.connection.lock().unwrap(), id_record, content)
+    }
+
+    fn save_login(database: &Database) -> (Record, Vec<Content>) {
+        let mut record = Record::new("Title".to_string(), "Subtitle".to_string(), Category::Login);
+        let mut content = vec![
+            Content::new(
+                "User".to_string(),
+                0,
+                true,
+                Value::Text(value::Text::new("user".to_string())),
+            ),
+            Content::new(
+                "Password".to_string(),
+                1,
+                true,
+                Value::Password(value::Password::new("password".to_string())),
+            ),
+        ];
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        let record = database.get_all_records().unwrap().remove(0);
+        (record, content)
+    }
+
+    #[test]
+    fn test_open_path_wrong_password() {
+        let directory = TempDir::new().unwrap();
+        drop(open_temp(&directory, "database", "password"));
+        let result = Database::open_path("wrong", &directory.path().join("database"));
+        assert_eq!(result.err(), Some(Error::InvalidPassword));
+    }
+    #[test]
+    fn test_open_with_derived_key() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database");
+        let database = Database::open_path("password", &path).unwrap();
+        database.save_setting("test", "value").unwrap();
+        drop(database);
+
+        let key = VaultSecret::Key(derived_key("password", &path).unwrap());
+        let database = Database::open_path_with_secret(&key, &path).unwrap();
+        assert_eq!(
+            database.get_setting("test").unwrap().expose_secret(),
+            "value"
+        );
+        let wrong = VaultSecret::Key(derived_key("wrong", &path).unwrap());
+        assert_eq!(
+            Database::open_path_with_secret(&wrong, &path).err(),
+            Some(Error::InvalidPassword)
+        );
+
+        database.change_key("new password").unwrap();
+        drop(database);
+        assert_eq!(
+            Database::open_path_with_secret(&key, &path).err(),
+            Some(Error::InvalidPassword)
+        );
+    }
+    #[test]
+    fn test_key_pragma() {
+        assert_eq!(
+            key_pragma("key", "password").as_str(),
+            "PRAGMA key = 'password';"
+        );
+        assert_eq!(
+            key_pragma("rekey", "it's'; DROP TABLE Record; --").as_str(),
+            "PRAGMA rekey = 'it''s''; DROP TABLE Record; --';"
+        );
+    }
+    #[test]
+    fn test_local_timestamps() {
+        use chrono::TimeZone;
+        // Timestamps saved before they were stored in UTC have the local offset, here across the end of daylight saving time
+        std::env::set_var("TZ", "CET-1CEST,M3.5.0,M10.5.0/3");
+        let before = chrono::Utc
+            .with_ymd_and_hms(2024, 10, 27, 0, 30, 0)
+            .unwrap()
+            .with_timezone(&chrono::Local);
+        let after = chrono::Utc
+            .with_ymd_and_hms(2024, 10, 27, 1, 10, 0)
+            .unwrap()
+            .with_timezone(&chrono::Local);
+        assert_eq!(before.to_rfc3339(), "2024-10-27T02:30:00+02:00");
+        assert_eq!(after.to_rfc3339(), "2024-10-27T02:10:00+01:00");
+
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        {
+            let connection = database.connection.lock().unwrap();
+            for (title, last_modified) in [("Before", before), ("After", after)] {
+                connection
+                    .execute(
+                        "INSERT INTO Record (title, subtitle, created, last_modified, category) VALUES (?1, '', ?2, ?2, 'Note');",
+                        params![title, last_modified],
+                    )
+                    .unwrap();
+            }
+        }
+        let mut record = Record::new("Saved".to_string(), String::new(), Category::Note);
+        record.set_created("2024-10-27T01:20:00Z".parse().unwrap());
+        database
+            .save_record_with_content(&mut record, &mut [])
+            .unwrap();
+        std::env::set_var("TZ", "EST5EDT,M3.2.0,M11.1.0");
+
+        let records = database
+            .get_records_window(
+                &listing::RecordFilter::default(),
+                listing::RecordSort {
+                    field: listing::SortField::Created,
+                    descending: false,
+                },
+                0,
+                10,
+            )
+            .unwrap();
+        let titles: Vec<&str> = records.iter().map(|record| record.title()).collect();
+        assert_eq!(titles, ["Before", "After", "Saved"]);
+        let created: Vec<String> = records
+            .iter()
+            .map(|record| serde_json::to_value(record).unwrap()["created"].to_string())
+            .collect();
+        assert_eq!(
+            created,
+            [
+                "\"2024-10-27T00:30:00Z\"",
+                "\"2024-10-27T01:10:00Z\"",
+                "\"2024-10-27T01:20:00Z\""
+            ]
+        );
+        assert_eq!(
+            records[1].created() - records[0].created(),
+            chrono::Duration::minutes(40)
+        );
+    }
+    #[test]
+    fn test_derived_key() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database");
+        let database = Database::open_path("password", &path).unwrap();
+        let salt = read_salt(&path).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        assert_eq!(unlock(&connection, "password"), Err(Error::InvalidPassword));
+        let connection = Connection::open(&path).unwrap();
+        unlock(&connection, &derive_key("password", &salt).unwrap()).unwrap();
+        drop(connection);
+
+        database.change_key("new password").unwrap();
+        drop(database);
+        let new_salt = read_salt(&path).unwrap();
+        assert_ne!(salt, new_salt);
+        let connection = Connection::open(&path).unwrap();
+        unlock(&connection, &derive_key("new password", &new_salt).unwrap()).unwrap();
+        assert_eq!(
+            Database::open_path("password", &path).err(),
+            Some(Error::InvalidPassword)
+        );
+    }
+    #[test]
+    fn test_migration_derived_key() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch("PRAGMA key = 'password'; create table Test (value text);")
+            .unwrap();
+        drop(connection);
+
+        assert_eq!(
+            Database::open_path("wrong", &path).err(),
+            Some(Error::InvalidPassword)
+        );
+        // Read-only databases can not be re-keyed
+        drop(Database::open_path_read_only("password", &path, "Test").unwrap());
+        let connection = Connection::open(&path).unwrap();
+        unlock(&connection, "password").unwrap();
+        drop(connection);
+
+        let database = Database::open_path("password", &path).unwrap();
+        database.save_setting("test", "value").unwrap();
+        drop(database);
+        let connection = Connection::open(&path).unwrap();
+        assert_eq!(unlock(&connection, "password"), Err(Error::InvalidPassword));
+        let database = Database::open_path("password", &path).unwrap();
+        assert_eq!(
+            database.get_setting("test").unwrap().expose_secret(),
+            "value"
+        );
+        drop(database);
+        assert_eq!(
+            Database::open_path_read_only("wrong", &path, "Test").err(),
+            Some(Error::InvalidPassword)
+        );
+    }
+    #[test]
+    fn test_open_path_special_password() {
+        let directory = TempDir::new().unwrap();
+        let passwords = ["it's", "say \"hi\"", "a;b", "'; PRAGMA key = 'x"];
+        for (index, password) in passwords.iter().enumerate() {
+            let path = directory.path().join(format!("database{index}"));
+            let database = Database::open_path(password, &path).unwrap();
+            database.save_setting("test", "value").unwrap();
+            drop(database);
+            let database = Database::open_path(password, &path).unwrap();
+            assert_eq!(
+                database.get_setting("test").unwrap().expose_secret(),
+                "value"
+            );
+
+            let new_password = passwords[(index + 1) % passwords.len()];
+            database.change_key(new_password).unwrap();
+            drop(database);
+            assert_eq!(
+                Database::open_path(password, &path).err(),
+                Some(Error::InvalidPassword)
+            );
+            let database = Database::open_path(new_password, &path).unwrap();
+            assert_eq!(
+                database.get_setting("test").unwrap().expose_secret(),
+                "value"
+            );
+        }
+    }
+    #[test]
+    fn test_open_path_normalized_password() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database");
+        drop(Database::open_path(decomposed, &path).unwrap());
+        drop(Database::open_path(composed, &path).unwrap());
+        drop(Database::open_path_read_only(decomposed, &path, "Test").unwrap());
+        assert!(Database::open_path_with_key(composed, &path).is_ok());
+
+        // Database created before normalization, with the decomposed password as typed
+        let legacy = directory.path().join("legacy");
+        drop(Database::open_path_with_key(decomposed, &legacy).unwrap());
+        assert_eq!(
+            Database::open_path_read_only(composed, &legacy, "Test").err(),
+            Some(Error::InvalidPassword)
+        );
+        drop(Database::open_path_read_only(decomposed, &legacy, "Test").unwrap());
+        assert!(Database::open_path_with_key(decomposed, &legacy).is_ok());
+        // Opening it writable re-keys it with the normalized password
+        drop(Database::open_path(decomposed, &legacy).unwrap());
+        assert_eq!(
+            Database::open_path_with_key(decomposed, &legacy).err(),
+            Some(Error::InvalidPassword)
+        );
+        drop(Database::open_path(composed, &legacy).unwrap());
+        assert_eq!(
+            Database::open_path("cafe", &legacy).err(),
+            Some(Error::InvalidPassword)
+        );
+    }
+    #[test]
+    fn test_custom_category_round_trip() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let json = r#"{"title":"GitHub","subtitle":"","category":"Passkey"}"#;
+        let mut record: Record = serde_json::from_str(json).unwrap();
+        assert_eq!(record.category(), &Category::Custom("Passkey".to_string()));
+        database
+            .save_record_with_content(&mut record, &mut [])
+            .unwrap();
+
+        let saved = database.get_all_records().unwrap().remove(0);
+        assert_eq!(saved.category(), &Category::Custom("Passkey".to_string()));
+        let json = serde_json::to_value(&saved).unwrap();
+        assert_eq!(json["category"], "Passkey");
+        assert_eq!(json["icon"], Category::Other.icon_hint());
+    }
+    #[test]
+    fn test_insert_record_copy() {
+        let directory = TempDir::new().unwrap();
+        let source = open_temp(&directory, "source", "source password");
+        let target = open_temp(&directory, "target", "target password");
+        let (record, content) = save_login(&source);
+
+        assert_eq!(target.insert_record_copy(&record, &content, &[]), Ok(true));
+
+        let copies = target.get_all_records().unwrap();
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[0].title(), record.title());
+        assert_eq!(copies[0].created(), record.created());
+        let copied_content = target.get_all_content_for_record(copies[0].id()).unwrap();
+        assert_eq!(copied_content.len(), content.len());
+        for (copy, original) in copied_content.iter().zip(content.iter()) {
+            assert_eq!(copy.label(), original.label());
+            assert_eq!(copy.value(), original.value());
+        }
+
+        assert_eq!(source.get_all_records().unwrap(), vec![record.clone()]);
+        let mut source_content = source.get_all_content_for_record(record.id()).unwrap();
+        for content in source_content.iter_mut() {
+            assert!(content.value_last_changed().is_some());
+            content.set_value_last_changed(None);
+        }
+        assert_eq!(source_content, content);
+    }
+    /// Subtitle and normalized columns of records and previews of content.
+    type DerivedColumns = (Vec<(String, String, String)>, Vec<Option<String>>);
+    /// Returns subtitle and normalized columns of all records and previews of all content, in the order of insertion.
+    fn derived_columns(database: &Database) -> DerivedColumns {
+        let connection = database.connection.lock().unwrap();
+        let records = connection
+            .prepare("SELECT subtitle, title_normalized, subtitle_normalized FROM Record ORDER BY id_record;")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        let previews = connection
+            .prepare("SELECT preview FROM Content ORDER BY id_content;")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        (records, previews)
+    }
+    /// Returns a note with a long text, its subtitle is derived from the first line and the text gets a preview.
+    fn long_note() -> (Record, Vec<Content>) {
+        let record = Record::new(
+            "Émile's Note".to_string(),
+            "Stale".to_string(),
+            Category::Note,
+        );
+        let content = vec![Content::new(
+            "Note".to_string(),
+            0,
+            true,
+            Value::LongText(value::LongText::new(format!(
+                "Första RAD\n{}",
+                "x".repeat(PREVIEW_LENGTH)
+            ))),
+        )];
+        (record, content)
+    }
+    #[test]
+    fn test_imports_derive_columns() {
+        let directory = TempDir::new().unwrap();
+        let saved = open_temp(&directory, "saved", "password");
+        let (mut record, mut content) = long_note();
+        saved
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        let expected = derived_columns(&saved);
+        assert_eq!(
+            expected,
+            (
+                vec![(
+                    "Första RAD".to_string(),
+                    "emile's note".to_string(),
+                    "forsta rad".to_string()
+                )],
+                vec![Some(format!(
+                    "Första RAD\n{}",
+                    "x".repeat(PREVIEW_LENGTH - 12)
+                ))]
+            )
+        );
+
+        let imported = open_temp(&directory, "imported", "password");
+        assert_eq!(imported.import_records(&[long_note()]), Ok(1));
+        assert_eq!(derived_columns(&imported), expected);
+
+        let copied = open_temp(&directory, "copied", "password");
+        let (record, content) = long_note();
+        assert_eq!(copied.insert_record_copy(&record, &content, &[]), Ok(true));
+        assert_eq!(derived_columns(&copied), expected);
+
+        let kept = open_temp(&directory, "kept", "password");
+        kept.save_setting(AUTO_SUBTITLE_SETTING, "false").unwrap();
+        assert_eq!(kept.import_records(&[long_note()]), Ok(1));
+        assert_eq!(derived_columns(&kept).0[0].0, "Stale");
+    }
+    #[test]
+    fn test_recompute_derived_columns() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (mut record, mut content) = long_note();
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        save_login(&database);
+        let expected = derived_columns(&database);
+        assert_eq!(database.recompute_derived_columns(), Ok(0));
+
+        database
+            .connection
+            .lock()
+            .unwrap()
+            .execute_batch(
+                "UPDATE Record SET subtitle = '', title_normalized = '', subtitle_normalized = '';
+                UPDATE Content SET preview = NULL;",
+            )
+            .unwrap();
+        assert_eq!(database.recompute_derived_columns(), Ok(3));
+        assert_eq!(derived_columns(&database), expected);
+        assert_eq!(database.get_all_records().unwrap()[1], record);
+    }
+    #[test]
+    fn test_stale_passwords() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (record, mut content) = save_login(&database);
+        assert!(database.get_stale_passwords(365, false).unwrap().is_empty());
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert_eq!(
+            database.get_stale_passwords(0, false).unwrap(),
+            [record.id()]
+        );
+
+        let year_ago = chrono::Utc::now() - chrono::Duration::days(400);
+        database
+            .connection
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE Content SET value_last_changed = ?1;",
+                params![year_ago],
+            )
+            .unwrap();
+        assert_eq!(
+            database.get_stale_passwords(365, false).unwrap(),
+            [record.id()]
+        );
+
+        // Saving the same value or changing other fields keeps the age
+        *content[0].value_mut() = Value::Text(value::Text::new("other user".to_string()));
+        save_content(&database, record.id(), &mut content[0]).unwrap();
+        save_content(&database, record.id(), &mut content[1]).unwrap();
+        assert_eq!(
+            database.get_stale_passwords(365, false).unwrap(),
+            [record.id()]
+        );
+
+        // Copies keep the age, imported records without it are new
+        let target = open_temp(&directory, "target", "password");
+        let loaded = database.get_all_content_for_record(record.id()).unwrap();
+        assert_eq!(loaded[1].value_last_changed(), Some(year_ago));
+        assert_eq!(target.insert_record_copy(&record, &loaded, &[]), Ok(true));
+        assert_eq!(target.get_stale_passwords(365, false).unwrap().len(), 1);
+        target
+            .import_records(&[(
+                Record::new("Imported".to_string(), String::new(), Category::Login),
+                vec![Content::new(
+                    "Password".to_string(),
+                    0,
+                    true,
+                    Value::Password(value::Password::new("imported".to_string())),
+                )],
+            )])
+            .unwrap();
+        assert_eq!(target.get_stale_passwords(365, false).unwrap().len(), 1);
+
+        *content[1].value_mut() = Value::Password(value::Password::new("new".to_string()));
+        save_content(&database, record.id(), &mut content[1]).unwrap();
+        assert!(database.get_stale_passwords(365, false).unwrap().is_empty());
+
+        database
+            .connection
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE Content SET value_last_changed = ?1;",
+                params![year_ago],
+            )
+            .unwrap();
+        database.delete_record(record.clone()).unwrap();
+        assert!(database.get_stale_passwords(365, false).unwrap().is_empty());
+    }
+    #[test]
+    fn test_acknowledge_finding() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (record, mut content) = save_login(&database);
+        assert_eq!(
+            database.acknowledge_finding(record.id() + 1, Finding::Stale, "note"),
+            Err(Error::NotFound("Record does not exist"))
+        );
+        assert_eq!(
+            database.acknowledge_finding(
+                record.id(),
+                Finding::Stale,
+                &"a".repeat(MAX_ACKNOWLEDGEMENT_NOTE_LENGTH + 1)
+            ),
+            Err(Error::Validation("Note is too long".into()))
+        );
+
+        database
+            .acknowledge_finding(record.id(), Finding::Stale, " Shared door code ")
+            .unwrap();
+        database
+            .acknowledge_finding(record.id(), Finding::Compromised, "")
+            .unwrap();
+        assert!(database.get_stale_passwords(0, false).unwrap().is_empty());
+        assert_eq!(
+            database.get_stale_passwords(0, true).unwrap(),
+            [record.id()]
+        );
+        let acknowledgements = database.get_acknowledgements().unwrap();
+        assert_eq!(acknowledgements.len(), 2);
+        assert_eq!(acknowledgements[1].finding, Finding::Stale);
+        assert_eq!(acknowledgements[1].note, "Shared door code");
+        let scan_all = |_: u64, _: &str| ControlFlow::Continue(());
+        assert_eq!(database.scan_passwords(false, 0, scan_all), Ok(0));
+        assert_eq!(database.scan_passwords(true, 0, scan_all), Ok(1));
+        assert_eq!(database.scan_passwords(true, record.id(), scan_all), Ok(0));
+        assert_eq!(
+            database.scan_passwords(true, 0, |_, _| ControlFlow::Break(())),
+            Ok(0)
+        );
+
+        // Saving the same password or other content keeps acknowledgements
+        *content[0].value_mut() = Value::Text(value::Text::new("other user".to_string()));
+        save_content(&database, record.id(), &mut content[0]).unwrap();
+        save_content(&database, record.id(), &mut content[1]).unwrap();
+        assert_eq!(database.get_acknowledgements().unwrap().len(), 2);
+
+        // Rotating the password clears them
+        *content[1].value_mut() = Value::Password(value::Password::new("rotated".to_string()));
+        save_content(&database, record.id(), &mut content[1]).unwrap();
+        assert!(database.get_acknowledgements().unwrap().is_empty());
+        assert_eq!(
+            database.get_stale_passwords(0, false).unwrap(),
+            [record.id()]
+        );
+
+        // So does restoring the previous password
+        database
+            .acknowledge_finding(record.id(), Finding::Compromised, "note")
+            .unwrap();
+        let version = database.get_content_history(content[1].id()).unwrap()[0].id;
+        database
+            .restore_content_version(content[1].id(), version)
+            .unwrap();
+        assert!(database.get_acknowledgements().unwrap().is_empty());
+
+        database
+            .acknowledge_finding(record.id(), Finding::Stale, "note")
+            .unwrap();
+        database
+            .revoke_acknowledgement(record.id(), Finding::Stale)
+            .unwrap();
+        assert_eq!(
+            database.revoke_acknowledgement(record.id(), Finding::Stale),
+            Err(Error::NotFound("Finding is not acknowledged"))
+        );
+    }
+    #[test]
+    fn test_insert_record_copy_duplicate() {
+        let directory = TempDir::new().unwrap();
+        let source = open_temp(&directory, "source", "source password");
+        let target = open_temp(&directory, "target", "target password");
+        let (record, content) = save_login(&source);
+
+        assert_eq!(target.insert_record_copy(&record, &content, &[]), Ok(true));
+        assert_eq!(target.insert_record_copy(&record, &content, &[]), Ok(false));
+        assert_eq!(target.get_all_records().unwrap().len(), 1);
+    }
+    #[test]
+    fn test_open_path_unchanged() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+        let directory = TempDir::new().unwrap();
+        let source = open_temp(&directory, "source", "source password");
+        let (record, content) = save_login(&source);
+        let user_version = |database: &Database| -> usize {
+            database
+                .connection
+                .lock()
+                .unwrap()
+                .query_row("PRAGMA user_version;", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        // Database created before normalization, with the decomposed password as typed
+        let path = directory.path().join("target");
+        drop(Database::open_path_with_key(decomposed, &path).unwrap());
+        let salt = read_salt(&path);
+        let target = Database::open_path_unchanged(decomposed, &path).unwrap();
+        assert_eq!(target.read_only_reason(), None);
+        assert_eq!(target.insert_record_copy(&record, &content, &[]), Ok(true));
+        drop(target);
+        // Not re-keyed with the normalized password
+        assert_eq!(read_salt(&path), salt);
+        assert_eq!(
+            Database::open_path_with_key(composed, &path).err(),
+            Some(Error::InvalidPassword)
+        );
+        let target = Database::open_path_read_only(decomposed, &path, "Test").unwrap();
+        assert_eq!(user_version(&target), migration::VERSION);
+        assert_eq!(target.get_all_records().unwrap().len(), 1);
+        drop(target);
+        assert!(Database::open_path_with_key(decomposed, &path).is_ok());
+
+        // Database of an older version is not migrated
+        let old = directory.path().join("old");
+        drop(open_temp(&directory, "old", "password"));
+        Connection::open(&old)
+            .and_then(|connection| {
+                unlock(
+                    &connection,
+                    derived_key("password", &old).unwrap().expose_secret(),
+                )
+                .unwrap();
+                connection.execute_batch("PRAGMA user_version = 1;")
+            })
+            .unwrap();
+        assert_eq!(
+            Database::open_path_unchanged("password", &old).err(),
+            Some(Error::Validation(
+                "Database has a different version, open it in the application first".into()
+            ))
+        );
+        assert!(VaultLock::path(&old).exists().not());
+        let database = Database::open_path_read_only("password", &old, "Test").unwrap();
+        assert_eq!(user_version(&database), 1);
+        assert_eq!(
+            Database::open_path_unchanged("wrong", &old).err(),
+            Some(Error::InvalidPassword)
+        );
+    }
+    #[test]
+    fn test_is_writable() {
+        let directory = TempDir::new().unwrap();
+        assert!(is_writable(directory.path()));
+        assert!(is_writable(
+            &directory.path().join("missing").join("nested")
+        ));
+        assert!(directory.path().join(".write_probe").exists().not());
+
+        let file = directory.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert!(is_writable(&file).not());
+        assert!(is_writable(&file.join("nested")).not());
+    }
+    #[cfg(unix)]
+    #[test]
+    fn test_open_data_path_read_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let directory = TempDir::new().unwrap();
+        let data = directory.path().join("data");
+        let path = data.join(DATABASE_FILE_NAME);
+        let secret = VaultSecret::Password(SecretString::new("password".to_string()));
+        let (record, _) = save_login(&Database::open_data_path(&secret, &path).unwrap());
+
+        let permissions = |mode: u32| {
+            fs::set_permissions(&data, fs::Permissions::from_mode(mode)).unwrap();
+        };
+        permissions(0o555);
+        // Root ignores permissions of the directory
+        if is_writable(&data) {
+            permissions(0o755);
+            return;
+        }
+        let database = Database::open_data_path(&secret, &path);
+        let missing = Database::open_data_path(&secret, &data.join("missing"));
+        permissions(0o755);
+
+        let database = database.unwrap();
+        assert_eq!(database.read_only_reason(), Some(READ_ONLY_DIRECTORY));
+        assert_eq!(
+            database.get_all_records().unwrap()[0].title(),
+            record.title()
+        );
+        assert!(database.delete_record(record).is_err());
+        assert_eq!(
+            missing.err(),
+            Some(Error::Other("Data directory is not writable"))
+        );
+        drop(database);
+        assert_eq!(
+            Database::open_data_path(&secret, &path)
+                .unwrap()
+                .read_only_reason(),
+            None
+        );
+    }
+    #[test]
+    fn test_open_path_read_only() {
+        let directory = TempDir::new().unwrap();
+        let (record, _) = save_login(&open_temp(&directory, "database", "password"));
+
+        let path = directory.path().join("database");
+        let database = Database::open_path_read_only("password", &path, "Read-only").unwrap();
+        assert_eq!(database.read_only_reason(), Some("Read-only"));
+        assert_eq!(
+            database.get_all_records().unwrap()[0].title(),
+            record.title()
+        );
+        assert!(database.delete_record(record).is_err());
+        assert!(database.add_data_breach_cache("hash", false).is_ok());
+
+        let result = Database::open_path_read_only("wrong", &path, "Read-only");
+        assert_eq!(result.err(), Some(Error::InvalidPassword));
+        let result = Database::open_path_read_only(
+            "password",
+            &directory.path().join("missing"),
+            "Read-only",
+        );
+        assert_eq!(result.err().unwrap(), "Failed to open database");
+    }
+    #[test]
+    fn test_get_records_window() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        for (title, category) in [
+            ("Mail", Category::Login),
+            ("Bank", Category::BankCard),
+            ("Shop", Category::Login),
+            ("Mailbox", Category::Login),
+        ] {
+            let mut record = Record::new(title.to_string(), "john".to_string(), category);
+            database
+                .save_record_with_content(&mut record, &mut [])
+                .unwrap();
+        }
+        let titles = |filter: &listing::RecordFilter, sort, start, count| {
+            database
+                .get_records_window(filter, sort, start, count)
+                .unwrap()
+                .iter()
+                .map(|record| record.title().to_string())
+                .collect::<Vec<String>>()
+        };
+        let all = listing::RecordFilter::default();
+        let sort = listing::RecordSort::default();
+        assert_eq!(
+            titles(&all, sort, 0, 10),
+            ["Bank", "Mail", "Mailbox", "Shop"]
+        );
+        assert_eq!(titles(&all, sort, 1, 2), ["Mail", "Mailbox"]);
+        assert!(titles(&all, sort, 4, 2).is_empty());
+        let descending = listing::RecordSort {
+            field: listing::SortField::Title,
+            descending: true,
+        };
+        assert_eq!(titles(&all, descending, 0, 2), ["Shop", "Mailbox"]);
+        assert_eq!(database.count_records(&all), Ok(4));
+
+        let logins = listing::RecordFilter {
+            category: Some(Category::Login),
+            query: "mail".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(titles(&logins, sort, 0, 10), ["Mail", "Mailbox"]);
+        assert_eq!(database.count_records(&logins), Ok(2));
+        let no_match = listing::RecordFilter {
+            category: Some(Category::Note),
+            ..Default::default()
+        };
+        assert_eq!(database.count_records(&no_match), Ok(0));
+    }
+    #[test]
+    fn test_records_window_filter() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let mut ids = Vec::new();
+        for (title, category) in [
+            ("Bank", Category::BankCard),
+            ("Mail", Category::Login),
+            ("Shop", Category::Login),
+            ("Diary", Category::Note),
+        ] {
+            let mut record = Record::new(title.to_string(), String::new(), category);
+            database
+                .save_record_with_content(&mut record, &mut [])
+                .unwrap();
+            ids.push(record.id());
+        }
+        database.set_record_favorite(ids[1], true).unwrap();
+        database.set_record_favorite(ids[2], true).unwrap();
+        database.add_tag(ids[1], "Work").unwrap();
+        database.add_tag(ids[1], "Mail").unwrap();
+        database.add_tag(ids[2], "Work").unwrap();
+        database.add_tag(ids[3], "Mail").unwrap();
+        database
+            .delete_record(database.get_record(ids[3]).unwrap().unwrap())
+            .unwrap();
+        let titles = |filter: listing::RecordFilter| {
+            assert_eq!(
+                database.count_records(&filter).unwrap() as usize,
+                database
+                    .get_records_window(&filter, listing::RecordSort::default(), 0, 10)
+                    .unwrap()
+                    .len()
+            );
+            database
+                .get_records_window(&filter, listing::RecordSort::default(), 0, 10)
+                .unwrap()
+                .iter()
+                .map(|record| record.title().to_string())
+                .collect::<Vec<String>>()
+        };
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect();
+
+        let favorites = listing::RecordFilter {
+            favorite: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(titles(favorites.clone()), ["Mail", "Shop"]);
+        let others = listing::RecordFilter {
+            favorite: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(titles(others), ["Bank"]);
+        let work = listing::RecordFilter {
+            tags: tags(&["work"]),
+            ..Default::default()
+        };
+        assert_eq!(titles(work), ["Mail", "Shop"]);
+        // All tags must match
+        let both = listing::RecordFilter {
+            tags: tags(&["Work", "MAIL"]),
+            ..Default::default()
+        };
+        assert_eq!(titles(both), ["Mail"]);
+        let unknown = listing::RecordFilter {
+            tags: tags(&["Work", "Home"]),
+            ..Default::default()
+        };
+        assert!(titles(unknown).is_empty());
+        let archived = listing::RecordFilter {
+            archived: true,
+            ..Default::default()
+        };
+        assert_eq!(titles(archived), ["Diary"]);
+        let archived_mail = listing::RecordFilter {
+            archived: true,
+            tags: tags(&["Mail"]),
+            ..Default::default()
+        };
+        assert_eq!(titles(archived_mail), ["Diary"]);
+        let combined = listing::RecordFilter {
+            category: Some(Category::Login),
+            favorite: Some(true),
+            tags: tags(&["Work"]),
+            query: "sh".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(titles(combined), ["Shop"]);
+    }
+    #[test]
+    fn test_get_records_page() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let mut ids = Vec::new();
+        for (title, category) in [
+            ("Mail", Category::Login),
+            ("Bank", Category::BankCard),
+            ("Shop", Category::Login),
+            ("Diary", Category::Note),
+        ] {
+            let mut record = Record::new(title.to_string(), String::new(), category);
+            database
+                .save_record_with_content(&mut record, &mut [])
+                .unwrap();
+            ids.push(record.id());
+        }
+        database
+            .delete_record(database.get_record(ids[3]).unwrap().unwrap())
+            .unwrap();
+        let titles = |offset, limit, sort| {
+            database
+                .get_records_page(offset, limit, sort)
+                .unwrap()
+                .iter()
+                .map(|record| record.title().to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            titles(0, 10, listing::SortBy::TitleAsc),
+            ["Bank", "Mail", "Shop"]
+        );
+        assert_eq!(titles(1, 1, listing::SortBy::TitleDesc), ["Mail"]);
+        assert_eq!(
+            titles(0, 10, listing::SortBy::LastModifiedDesc),
+            ["Shop", "Bank", "Mail"]
+        );
+        assert_eq!(
+            titles(0, 10, listing::SortBy::CreatedDesc),
+            ["Shop", "Bank", "Mail"]
+        );
+        assert_eq!(
+            titles(0, 10, listing::SortBy::Category),
+            ["Bank", "Mail", "Shop"]
+        );
+        assert!(titles(3, 10, listing::SortBy::TitleAsc).is_empty());
+        assert_eq!(
+            database.count_records(&listing::RecordFilter::default()),
+            Ok(3)
+        );
+
+        // All records keep favorites first
+        database.set_record_favorite(ids[0], true).unwrap();
+        let all: Vec<u64> = database
+            .get_all_records()
+            .unwrap()
+            .iter()
+            .map(Record::id)
+            .collect();
+        assert_eq!(all, [ids[0], ids[2], ids[1]]);
+    }
+    #[test]
+    fn test_count_records_since() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        // Saved with the offset of the time, across the daylight saving time change on 2026-03-29
+        for (title, timestamp) in [
+            ("Exactly a week", "2026-03-26 13:00:00+01:00"),
+            ("Second earlier", "2026-03-26 12:59:59+01:00"),
+            ("Summer time", "2026-03-26 13:30:00+02:00"),
+            ("After change", "2026-03-29 03:30:00+02:00"),
+            ("Month ago", "2026-03-03 12:00:00+00:00"),
+        ] {
+            let mut record = Record::new(title.to_string(), String::new(), Category::Other);
+            database
+                .save_record_with_content(&mut record, &mut [])
+                .unwrap();
+            database
+                .connection
+                .lock()
+                .unwrap()
+                .execute(
+                    "UPDATE Record SET created = ?1, last_modified = ?1 WHERE id_record = ?2;",
+                    params![timestamp, record.id()],
+                )
+                .unwrap();
+        }
+        let now: chrono::DateTime<chrono::Utc> = "2026-04-02T12:00:00Z".parse().unwrap();
+        let week = now - chrono::Duration::days(7);
+        assert_eq!(database.count_records_created_since(week), Ok(2));
+        assert_eq!(database.count_records_modified_since(week), Ok(2));
+        let month = now - chrono::Duration::days(30);
+        assert_eq!(database.count_records_created_since(month), Ok(5));
+        assert_eq!(
+            database.count_records_created_since(month + chrono::Duration::seconds(1)),
+            Ok(4)
+        );
+
+        let recent: Vec<String> = database
+            .recently_modified_records(2)
+            .unwrap()
+            .into_iter()
+            .map(|(_, title)| title)
+            .collect();
+        assert_eq!(recent, ["After change", "Exactly a week"]);
+
+        let connection = database.connection.lock().unwrap();
+        let plan: String = connection
+            .query_row(
+                "EXPLAIN QUERY PLAN SELECT count(*) FROM Record WHERE julianday(created) >= julianday(?1) AND deleted IS NULL;",
+                params![week],
+                |row| row.get(3),
+            )
+            .unwrap();
+        assert!(plan.contains("INDEX record_created"), "{plan}");
+    }
+    #[test]
+    fn test_records_window_query_plan() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let plan = |filter: &listing::RecordFilter, sort| {
+            let (where_clause, parameters) = database.listing_where_clause(filter);
+            let connection = database.connection.lock().unwrap();
+            let mut stmt = connection
+                .prepare(&format!(
+                    "EXPLAIN QUERY PLAN SELECT * FROM Record {where_clause} {};",
+                    listing::order_clause(sort, true)
+                ))
+                .unwrap();
+            let details: Result<Vec<String>> = stmt
+                .query_map(rusqlite::params_from_iter(parameters), |row| row.get(3))
+                .unwrap()
+                .collect();
+            details.unwrap().join("\n")
+        };
+        let sort = listing::RecordSort::default();
+        for query in ["", "mail"] {
+            let filter = listing::RecordFilter {
+                category: Some(Category::Login),
+                query: query.to_string(),
+                ..Default::default()
+            };
+            let plan = plan(&filter, sort);
+            assert!(
+                plan.contains("INDEX record_category_title (category=?)"),
+                "{plan}"
+            );
+            // Rows come out of the index sorted by title, only the id tiebreaker is sorted
+            assert!(!plan.contains("USE TEMP B-TREE FOR ORDER BY"), "{plan}");
+        }
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect();
+        for (favorite, archived, tags, query) in [
+            (Some(true), false, tags(&[]), ""),
+            (Some(false), true, tags(&[]), "mail"),
+            (None, false, tags(&["work"]), ""),
+            (None, true, tags(&["work", "mail"]), "mail"),
+            (Some(true), false, tags(&["work", "mail"]), ""),
+        ] {
+            let filter = listing::RecordFilter {
+                favorite,
+                archived,
+                tags,
+                query: query.to_string(),
+                ..Default::default()
+            };
+            let plan = plan(&filter, sort);
+            assert!(!plan.contains("SCAN Record"), "{plan}");
+            if favorite.is_some() {
+                assert!(
+                    plan.contains("INDEX record_favorite_title (favorite=?)"),
+                    "{plan}"
+                );
+                assert!(!plan.contains("USE TEMP B-TREE FOR ORDER BY"), "{plan}");
+            }
+            // Each tag is looked up by its name, then its records by the tag
+            for index in [
+                "sqlite_autoindex_Tag_1 (name=?)",
+                "record_tag_tag (id_tag=?)",
+            ] {
+                assert_eq!(plan.matches(index).count(), filter.tags.len(), "{plan}");
+            }
+        }
+    }
+    #[test]
+    fn test_normalize_search() {
+        assert_eq!(normalize_search("Müller"), "muller");
+        assert_eq!(normalize_search("Straße"), normalize_search("STRASSE"));
+        assert_eq!(normalize_search("Čeřovský Žluťoučký"), "cerovsky zlutoucky");
+        assert_eq!(normalize_search("İSTANBUL"), "istanbul");
+        assert_eq!(normalize_search("ışık"), normalize_search("IŞIK"));
+        assert_eq!(normalize_search("ﬁle"), "file");
+    }
+    #[test]
+    fn test_search_records_unicode() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        for (title, subtitle) in [
+            ("Müller", "Straße 1"),
+            ("Řidičák", "Čeněk"),
+            ("İstanbul", "ışık"),
+        ] {
+            let mut record = Record::new(title.to_string(), subtitle.to_string(), Category::Note);
+            database
+                .save_record_with_content(&mut record, &mut Vec::new())
+                .unwrap();
+        }
+        let titles = |query: &str| -> Vec<String> {
+            database
+                .search_records(query, false)
+                .unwrap()
+                .iter()
+                .map(|record| record.title().to_string())
+                .collect()
+        };
+        assert_eq!(titles("müller"), ["Müller"]);
+        assert_eq!(titles("MULLER"), ["Müller"]);
+        assert_eq!(titles("strasse"), ["Müller"]);
+        assert_eq!(titles("řidič"), ["Řidičák"]);
+        assert_eq!(titles("ridicak"), ["Řidičák"]);
+        assert_eq!(titles("ČENĚK"), ["Řidičák"]);
+        assert_eq!(titles("istanbul"), ["İstanbul"]);
+        assert_eq!(titles("ISIK"), ["İstanbul"]);
+        assert!(titles("mü%").is_empty());
+
+        let filter = listing::RecordFilter {
+            category: Some(Category::Note),
+            query: "MÜLLER".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(database.count_records(&filter).unwrap(), 1);
+    }
+    #[test]
+    fn test_migration_record_normalized() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "PRAGMA key = 'password';
+                create table Record (
+                    id_record integer primary key,
+                    title text not null,
+                    subtitle text not null,
+                    created datetime not null,
+                    last_modified datetime not null,
+                    category text not null
+                );
+                INSERT INTO Record (title, subtitle, created, last_modified, category)
+                VALUES ('Müller', 'Čeněk', datetime('now'), datetime('now'), 'Note');",
+            )
+            .unwrap();
+        drop(connection);
+
+        let database = Database::open_path("password", &path).unwrap();
+        assert_eq!(database.search_records("muller", false).unwrap().len(), 1);
+        assert_eq!(database.search_records("cenek", false).unwrap().len(), 1);
+    }
+    #[test]
+    fn test_derive_subtitle() {
+        let content = |label: &str, value: Value| Content::new(label.to_string(), 0, false, value);
+        let text = |text: &str| Value::Text(value::Text::new(text.to_string()));
+        let login = [
+            content("Website", text("https://example.com")),
+            content("User", text("  ")),
+            content(
+                "Recovery",
+                Value::Email(value::Email::new("john@example.com".to_string()).unwrap()),
+            ),
+        ];
+        assert_eq!(
+            derive_subtitle(&Category::Login, &login).as_deref(),
+            Some("john@example.com")
+        );
+        let login = [
+            content("User", text(" john ")),
+            content(
+                "Email",
+                Value::Email(value::Email::new("john@example.com".to_string()).unwrap()),
+            ),
+        ];
+        assert_eq!(
+            derive_subtitle(&Category::Login, &login).as_deref(),
+            Some("john")
+        );
+
+        let card = [content(
+            "Card number",
+            Value::BankCardNumber(
+                value::BankCardNumber::new("4111111111131234".to_string()).unwrap(),
+            ),
+        )];
+        assert_eq!(
+            derive_subtitle(&Category::BankCard, &card).as_deref(),
+            Some("•••• 1234")
+        );
+
+        let note = [content(
+            "Note",
+            Value::LongText(value::LongText::new(
+                "\nShopping list for the weekend trip to the mountains\nmilk".to_string(),
+            )),
+        )];
+        assert_eq!(
+            derive_subtitle(&Category::Note, &note).as_deref(),
+            Some("Shopping list for the weekend trip to th")
+        );
+
+        assert_eq!(derive_subtitle(&Category::Other, &login), None);
+        assert_eq!(derive_subtitle(&Category::BankCard, &login), None);
+        assert_eq!(derive_subtitle(&Category::Note, &[]), None);
+    }
+    #[test]
+    fn test_auto_subtitle() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let mut record = Record::new(
+            "Card".to_string(),
+            "4111111111131234".to_string(),
+            Category::BankCard,
+        );
+        let mut content = vec![Content::new(
+            "Card number".to_string(),
+            0,
+            true,
+            Value::BankCardNumber(
+                value::BankCardNumber::new("4111111111131234".to_string()).unwrap(),
+            ),
+        )];
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        assert_eq!(record.subtitle(), "•••• 1234");
+        assert_eq!(
+            database.get_all_records().unwrap()[0].subtitle(),
+            "•••• 1234"
+        );
+
+        database
+            .save_setting(AUTO_SUBTITLE_SETTING, "false")
+            .unwrap();
+        record.set_subtitle("Visa".to_string());
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        assert_eq!(database.get_all_records().unwrap()[0].subtitle(), "Visa");
+    }
+    #[test]
+    fn test_like_pattern() {
+        assert_eq!(like_pattern(" git "), "%git%");
+        assert_eq!(like_pattern("100%_a\\b"), "%100\\%\\_a\\\\b%");
+    }
+    #[test]
+    fn test_search_records() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        // The user is found only in deep search, so it must not be copied to the subtitle
+        database
+            .save_setting(AUTO_SUBTITLE_SETTING, "false")
+            .unwrap();
+        let (record, _) = save_login(&database);
+        let mut secret = Content::new(
+            "Recovery".to_string(),
+            2,
+            false,
+            Value::Password(value::Password::new("hunter".to_string())),
+        );
+        save_content(&database, record.id(), &mut secret).unwrap();
+        let mut card = Content::new(
+            "Card".to_string(),
+            3,
+            false,
+            Value::BankCardNumber(
+                value::BankCardNumber::new("4111111111111111".to_string()).unwrap(),
+            ),
+        );
+        save_content(&database, record.id(), &mut card).unwrap();
+
+        assert_eq!(database.search_records("TITLE", false).unwrap().len(), 1);
+        assert_eq!(database.search_records("subt", false).unwrap().len(), 1);
+        assert!(database.search_records("user", false).unwrap().is_empty());
+        assert_eq!(database.search_records("user", true).unwrap().len(), 1);
+        assert_eq!(database.search_records("recovery", true).unwrap().len(), 1);
+        assert!(database.search_records("hunter", true).unwrap().is_empty());
+        assert!(database.search_records("1111", true).unwrap().is_empty());
+        assert!(database.search_records("%", true).unwrap().is_empty());
+    }
+    #[test]
+    fn test_open_path_locked_by_another_process() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database");
+        drop(open_temp(&directory, "database", "password"));
+        assert!(VaultLock::path(&path).exists().not());
+
+        let now = chrono::Utc::now().to_rfc3339();
+        fs::write(
+            VaultLock::path(&path),
+            format!(r#"{{"pid":1,"acquired":"{now}","refreshed":"{now}"}}"#),
+        )
+        .unwrap();
+        let database = Database::open_path("password", &path).unwrap();
+        assert_eq!(
+            database.read_only_reason(),
+            Some("Database is opened by another process, changes can not be saved")
+        );
+    }
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("LongText", "short"), None);
+        assert_eq!(preview("Text", &"a".repeat(2000)), None);
+        let value = "a".repeat(2000);
+        assert_eq!(
+            preview("LongText", &value).unwrap().as_str(),
+            &value[..PREVIEW_LENGTH]
+        );
+        let value = format!("a{}", "č".repeat(1000));
+        let preview = preview("LongText", &value).unwrap();
+        assert_eq!(preview.len(), PREVIEW_LENGTH - 1);
+        assert!(value.starts_with(preview.as_str()));
+    }
+    #[test]
+    fn test_save_content_too_long() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (record, _) = save_login(&database);
+
+        let mut content = Content::new(
+            "Text".to_string(),
+            2,
+            false,
+            Value::Text(value::Text::new("a".repeat(4 * 1024 + 1))),
+        );
+        assert_eq!(
+            save_content(&database, record.id(), &mut content),
+            Err(Error::Validation("Value is too long".into()))
+        );
+        let mut content = Content::new(
+            "Note".to_string(),
+            2,
+            false,
+            Value::LongText(value::LongText::new("a".repeat(1024 * 1024 + 1))),
+        );
+        assert_eq!(
+            save_content(&database, record.id(), &mut content),
+            Err(Error::Validation("Value is too long".into()))
+        );
+        let mut content = Content::new(
+            "Note".to_string(),
+            2,
+            false,
+            Value::LongText(value::LongText::new("a".repeat(1024 * 1024))),
+        );
+        assert!(save_content(&database, record.id(), &mut content).is_ok());
+    }
+    #[test]
+    fn test_get_record_content() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (record, content) = save_login(&database);
+        let (other, _) = save_login(&database);
+
+        assert_eq!(
+            database
+                .get_record_content(content[1].id(), record.id())
+                .unwrap()
+                .value(),
+            content[1].value()
+        );
+        assert_eq!(
+            database.get_record_content(content[1].id(), other.id()),
+            Err(Error::NotFound(CONTENT_NOT_IN_RECORD))
+        );
+        assert_eq!(
+            database.get_record_content(0, record.id()),
+            Err(Error::NotFound(CONTENT_NOT_IN_RECORD))
+        );
+    }
+    #[test]
+    fn test_content_preview() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (record, _) = save_login(&database);
+        let value = "a".repeat(3000);
+        let mut note = Content::new(
+            "Note".to_string(),
+            2,
+            false,
+            Value::LongText(value::LongText::new(value.clone())),
+        );
+        save_content(&database, record.id(), &mut note).unwrap();
+
+        let mut content = database
+            .get_all_content_previews_for_record(record.id())
+            .unwrap();
+        assert!(content[0].truncated().not());
+        assert!(content[2].truncated());
+        assert_eq!(
+            content[2].value(),
+            &Value::LongText(value::LongText::new(value[..PREVIEW_LENGTH].to_string()))
+        );
+        assert_eq!(
+            save_content(&database, record.id(), &mut content[2]),
+            Err(Error::Validation("Full value was not loaded".into()))
+        );
+        assert_eq!(
+            database.get_content(note.id()).unwrap().value(),
+            note.value()
+        );
+    }
+    #[test]
+    fn test_migration_content_preview() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "PRAGMA key = 'password';
+                create table Content (
+                    id_content integer primary key,
+                    id_record integer not null,
+                    label text not null,
+                    position integer not null,
+                    required integer not null,
+                    kind text not null,
+                    value text not null
+                );",
+            )
+            .unwrap();
+        connection
+            .execute(
+                "INSERT INTO Content (id_record, label, position, required, kind, value) VALUES (1, 'Note', 0, 1, 'LongText', ?1);",
+                params!["a".repeat(2000)],
+            )
+            .unwrap();
+        drop(connection);
+
+        let database = Database::open_path("password", &path).unwrap();
+        let content = database.get_all_content_previews_for_record(1).unwrap();
+        assert!(content[0].truncated());
+        drop(database);
+        assert!(Database::open_path("password", &path).is_ok());
+    }
+    fn backdate_draft(database: &Database, id_record: u64, modifier: &str) {
+        database
+            .connection
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE Drafts SET saved = strftime('%Y-%m-%d %H:%M:%f', 'now', ?1) WHERE id_record = ?2;",
+                params![modifier, id_record],
+            )
+            .unwrap();
+    }
+    fn draft(database: &Database, id_record: u64) -> Option<String> {
+        database
+            .get_draft(id_record)
+            .unwrap()
+            .map(|draft| draft.expose_secret().to_string())
+    }
+    #[test]
+    fn test_save_draft_throttling() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+
+        assert_eq!(draft(&database, 1), None);
+        assert_eq!(database.save_draft(1, "first"), Ok(true));
+        assert_eq!(database.save_draft(1, "second"), Ok(false));
+        assert_eq!(database.save_draft(2, "other"), Ok(true));
+        assert_eq!(draft(&database, 1), Some("first".to_string()));
+
+        backdate_draft(&database, 1, "-5 seconds");
+        assert_eq!(database.save_draft(1, "third"), Ok(true));
+        assert_eq!(draft(&database, 1), Some("third".to_string()));
+
+        database.discard_draft(1).unwrap();
+        assert_eq!(draft(&database, 1), None);
+        assert_eq!(draft(&database, 2), Some("other".to_string()));
+    }
+    #[test]
+    fn test_save_record_discards_draft() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        database.save_draft(0, "new").unwrap();
+
+        let mut record = Record::new("Title".to_string(), "".to_string(), Category::Note);
+        let mut content = vec![Content::new(
+            "Note".to_string(),
+            0,
+            true,
+            Value::LongText(value::LongText::new("a".repeat(1024 * 1024 + 1))),
+        )];
+        assert_eq!(
+            database.save_record_with_content(&mut record, &mut content),
+            Err(Error::Validation("Value is too long".into()))
+        );
+        assert_eq!(record.id(), 0);
+        assert!(database.get_all_records().unwrap().is_empty());
+        assert_eq!(draft(&database, 0), Some("new".to_string()));
+
+        content = vec![Content::new(
+            "Note".to_string(),
+            0,
+            true,
+            Value::LongText(value::LongText::new("Note".to_string())),
+        )];
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        assert_eq!(draft(&database, 0), None);
+        assert_ne!(content[0].id(), 0);
+
+        backdate_draft(&database, record.id(), "-1 day");
+        database.save_draft(record.id(), "edit").unwrap();
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        assert_eq!(draft(&database, record.id()), None);
+
+        database.save_draft(record.id(), "edit").unwrap();
+        database.delete_record(record.clone()).unwrap();
+        assert_eq!(draft(&database, record.id()), None);
+    }
+    #[test]
+    fn test_save_record_replacing_content() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (mut record, mut content) = save_login(&database);
+        let password = content.remove(1);
+        *content[0].value_mut() = Value::Text(value::Text::new("admin".to_string()));
+        content.push(Content::new(
+            "Note".to_string(),
+            1,
+            false,
+            Value::Text(value::Text::new("note".to_string())),
+        ));
+
+        database
+            .save_record_replacing_content(&mut record, &mut content)
+            .unwrap();
+        assert_ne!(content[1].id(), 0);
+        let saved = database.get_all_content_for_record(record.id()).unwrap();
+        assert_eq!(
+            saved
+                .iter()
+                .map(|content| (content.id(), content.value()))
+                .collect::<Vec<_>>(),
+            content
+                .iter()
+                .map(|content| (content.id(), content.value()))
+                .collect::<Vec<_>>()
+        );
+        assert!(database
+            .get_content_history(password.id())
+            .unwrap()
+            .is_empty());
+
+        // Partial save keeps the other content
+        database
+            .save_record_with_content(&mut record, &mut content[..1])
+            .unwrap();
+        assert_eq!(
+            database
+                .get_all_content_for_record(record.id())
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+    #[test]
+    fn test_save_record_replacing_content_rollback() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (mut record, content) = save_login(&database);
+        database.save_draft(record.id(), "edit").unwrap();
+
+        let last_modified = record.last_modified();
+        let mut admin = Content::new(
+            "User".to_string(),
+            0,
+            true,
+            Value::Text(value::Text::new("admin".to_string())),
+        );
+        admin.set_id(content[0].id());
+        let mut submitted = vec![
+            admin,
+            Content::new(
+                "Text".to_string(),
+                1,
+                false,
+                Value::Text(value::Text::new("text".to_string())),
+            ),
+            Content::new(
+                "Note".to_string(),
+                2,
+                false,
+                Value::LongText(value::LongText::new("a".repeat(1024 * 1024 + 1))),
+            ),
+        ];
+        assert_eq!(
+            database.save_record_replacing_content(&mut record, &mut submitted),
+            Err(Error::Validation("Value is too long".into()))
+        );
+        assert_eq!(submitted[1].id(), 0);
+        assert_eq!(
+            database.get_all_records().unwrap()[0].last_modified(),
+            last_modified
+        );
+        let saved = database.get_all_content_for_record(record.id()).unwrap();
+        assert_eq!(
+            saved
+                .iter()
+                .map(|content| (content.id(), content.value()))
+                .collect::<Vec<_>>(),
+            content
+                .iter()
+                .map(|content| (content.id(), content.value()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(draft(&database, record.id()), Some("edit".to_string()));
+    }
+    #[test]
+    fn test_retention_drafts_older_7_days() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        database.save_draft(1, "old").unwrap();
+        database.save_draft(2, "recent").unwrap();
+        backdate_draft(&database, 1, "-8 days");
+        backdate_draft(&database, 2, "-6 days");
+
+        retention::run_retention(&database).unwrap();
+        assert_eq!(draft(&database, 1), None);
+        assert_eq!(draft(&database, 2), Some("recent".to_string()));
+    }
+    #[test]
+    fn test_find_records_for_url() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        for (title, url) in [
+            ("Example", "https://www.example.com/login"),
+            ("Other", "https://other.com"),
+        ] {
+            let mut record = Record::new(title.to_string(), "".to_string(), Category::Login);
+            let mut content = vec![Content::new(
+                "Website".to_string(),
+                0,
+                true,
+                Value::Url(value::Url::new(url.to_string()).unwrap()),
+            )];
+            database
+                .save_record_with_content(&mut record, &mut content)
+                .unwrap();
+        }
+
+        let records = database.find_records_for_url("http://Example.com").unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].title(), "Example");
+        assert!(database
+            .find_records_for_url("https://mail.example.com")
+            .unwrap()
+            .is_empty());
+        assert!(database.find_records_for_url("invalid").is_err());
+    }
+    #[test]
+    fn test_replace_prefix() {
+        let old = "https://sso.old.com";
+        let new = "https://sso.new.com";
+        for (url, replaced) in [
+            ("https://sso.old.com", Some("https://sso.new.com")),
+            (
+                "https://sso.old.com/login",
+                Some("https://sso.new.com/login"),
+            ),
+            (
+                "https://sso.old.com?next=1",
+                Some("https://sso.new.com?next=1"),
+            ),
+            (
+                "https://sso.old.com:8443/a",
+                Some("https://sso.new.com:8443/a"),
+            ),
+            ("https://sso.old.company.com", None),
+            ("https://sso.old.com.evil.org", None),
+            ("http://sso.old.com", None),
+        ] {
+            assert_eq!(replace_prefix(url, old, new).as_deref(), replaced, "{url}");
+        }
+        assert_eq!(
+            replace_prefix("http://a.com", "http://", "https://").as_deref(),
+            Some("https://a.com")
+        );
+        assert_eq!(replace_prefix("https://a.com", "http", "ftp"), None);
+    }
+    fn save_websites(database: &Database, title: &str, urls: &[&str]) -> u64 {
+        let mut record = Record::new(title.to_string(), "".to_string(), Category::Login);
+        let mut content: Vec<Content> = urls
+            .iter()
+            .enumerate()
+            .map(|(position, url)| {
+                Content::new(
+                    "Website".to_string(),
+                    position as u32,
+                    true,
+                    Value::Url(value::Url::new(url.to_string()).unwrap()),
+                )
+            })
+            .collect();
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        record.id()
+    }
+    fn websites(database: &Database, id_record: u64) -> Vec<String> {
+        database
+            .get_all_content_for_record(id_record)
+            .unwrap()
+            .iter()
+            .map(|content| content.value().to_secret_string().expose_secret().clone())
+            .collect()
+    }
+    #[test]
+    fn test_replace_url_prefix() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let mail = save_websites(
+            &database,
+            "Mail",
+            &["https://sso.old.com/mail", "https://sso.old.company.com"],
+        );
+        let wiki = save_websites(&database, "Wiki", &["https://sso.old.com"]);
+        let other = save_websites(&database, "Other", &["https://other.com"]);
+        let records = database.get_all_records().unwrap();
+
+        let dry_run = database
+            .replace_url_prefix("https://sso.old.com", "https://sso.new.com", true)
+            .unwrap();
+        let changed: Vec<(u64, &str, &str)> = dry_run
+            .iter()
+            .map(|replacement| {
+                (
+                    replacement.id_record,
+                    replacement.before.as_str(),
+                    replacement.after.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                (mail, "https://sso.old.com/mail", "https://sso.new.com/mail"),
+                (wiki, "https://sso.old.com", "https://sso.new.com"),
+            ]
+        );
+        assert_eq!(database.get_all_records().unwrap(), records);
+        assert_eq!(websites(&database, wiki), ["https://sso.old.com"]);
+
+        let applied = database
+            .replace_url_prefix("https://sso.old.com", "https://sso.new.com", false)
+            .unwrap();
+        assert_eq!(applied, dry_run);
+        assert_eq!(
+            websites(&database, mail),
+            ["https://sso.new.com/mail", "https://sso.old.company.com"]
+        );
+        assert_eq!(websites(&database, wiki), ["https://sso.new.com"]);
+        assert_eq!(websites(&database, other), ["https://other.com"]);
+        for record in database.get_all_records().unwrap() {
+            let before = records
+                .iter()
+                .find(|before| before.id() == record.id())
+                .unwrap();
+            assert_eq!(
+                record.last_modified() > before.last_modified(),
+                record.id() != other,
+                "{}",
+                record.title()
+            );
+        }
+        assert_eq!(
+            database.replace_url_prefix("https://sso.old.com", "https://sso.new.com", false),
+            Ok(Vec::new())
+        );
+        assert_eq!(
+            database.replace_url_prefix("", "https://sso.new.com", true),
+            Err(Error::Validation("Prefix can not be empty".into()))
+        );
+    }
+    #[test]
+    fn test_replace_url_prefix_rejected() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let first = save_websites(&database, "First", &["https://old.com/a"]);
+        let second = save_websites(
+            &database,
+            "Second",
+            &["https://old.com/b", "https://new.com/b"],
+        );
+        let records = database.get_all_records().unwrap();
+
+        assert_eq!(
+            database.replace_url_prefix("https://old.com", "not a url", false),
+            Err(Error::Validation("Replacement is not a valid URL".into()))
+        );
+        assert_eq!(
+            database.replace_url_prefix("https://old.com", "https://new.com", false),
+            Err(Error::Validation(
+                "Replacement would duplicate a website of the record".into()
+            ))
+        );
+        assert_eq!(database.get_all_records().unwrap(), records);
+        assert_eq!(websites(&database, first), ["https://old.com/a"]);
+        assert_eq!(
+            websites(&database, second),
+            ["https://old.com/b", "https://new.com/b"]
+        );
+    }
+    #[test]
+    fn test_secure_delete() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let secure_delete: i64 = database
+            .connection
+            .lock()
+            .unwrap()
+            .pragma_query_value(None, "secure_delete", |row| row.get(0))
+            .unwrap();
+        assert_eq!(secure_delete, 1);
+    }
+    #[test]
+    fn test_checkpoint() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        database.checkpoint().unwrap();
+        let mode: String = database
+            .connection
+            .lock()
+            .unwrap()
+            .query_row("PRAGMA journal_mode = WAL;", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+        let mut record = Record::new("Mail".to_string(), "john".to_string(), Category::Login);
+        database
+            .save_record_with_content(&mut record, &mut [])
+            .unwrap();
+
+        // Copy of the database file alone, like exports and cloud uploads do
+        let copy = |name: &str| {
+            let path = directory.path().join(name);
+            fs::copy(directory.path().join("database"), &path).unwrap();
+            Database::open_path("password", &path)
+                .unwrap()
+                .get_all_records()
+                .unwrap()
+                .len()
+        };
+        assert_eq!(copy("before"), 0);
+        database.checkpoint().unwrap();
+        assert_eq!(copy("after"), 1);
+        assert_eq!(
+            fs::metadata(directory.path().join("database-wal"))
+                .unwrap()
+                .len(),
+            0
+        );
+    }
+    #[test]
+    fn test_compact() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let mut records = Vec::new();
+        for index in 0..50 {
+            let mut record = Record::new(format!("Record {index}"), "".to_string(), Category::Note);
+            let mut content = vec![Content::new(
+                "Note".to_string(),
+                0,
+                true,
+                Value::LongText(value::LongText::new("a".repeat(4000))),
+            )];
+            database
+                .save_record_with_content(&mut record, &mut content)
+                .unwrap();
+            records.push(record);
+        }
+        for record in records {
+            let id = record.id();
+            database.delete_record(record).unwrap();
+            database.purge_record(id).unwrap();
+        }
+
+        let stats = database.stats().unwrap();
+        assert!(stats.free > 0);
+        assert_eq!(stats.last_compaction, None);
+        assert_eq!(database.compact(stats.size + 1).unwrap(), None);
+
+        let (before, after) = database.compact(stats.size).unwrap().unwrap();
+        assert_eq!(before, stats.size);
+        assert!(after < before);
+        let stats = database.stats().unwrap();
+        assert_eq!(stats.free, 0);
+        assert_eq!(stats.last_compaction, Some((before, after)));
+    }
+    #[test]
+    fn test_record_locked() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let mut record = Record::new("Seed".to_string(), "".to_string(), Category::Note);
+        let mut content = vec![Content::new(
+            "Phrase".to_string(),
+            0,
+            true,
+            Value::SensitiveText(value::SensitiveText::new("seed phrase".to_string())),
+        )];
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        assert!(!database.is_record_locked(record.id()).unwrap());
+
+        database.set_record_locked(record.id(), true).unwrap();
+        assert!(database.is_record_locked(record.id()).unwrap());
+        assert!(database.is_content_locked(content[0].id()).unwrap());
+        assert!(!database.is_content_locked(content[0].id() + 1).unwrap());
+        let records = database.get_all_records().unwrap();
+        assert_eq!(records[0].title(), "Seed");
+        assert!(records[0].locked());
+
+        // Saving the record from the frontend does not unlock it
+        record.set_locked(false);
+        database
+            .save_record_with_content(&mut record, &mut [])
+            .unwrap();
+        assert!(database.is_record_locked(record.id()).unwrap());
+
+        database.set_record_locked(record.id(), false).unwrap();
+        assert!(!database.is_record_locked(record.id()).unwrap());
+        assert_eq!(
+            database.set_record_locked(record.id() + 1, true),
+            Err(Error::NotFound("Record does not exist"))
+        );
+    }
+    #[test]
+    fn test_record_metadata() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (record, _) = save_login(&database);
+        let id = record.id();
+
+        database
+            .set_record_metadata(id, "ansible", "role", "web")
+            .unwrap();
+        database
+            .set_record_metadata(id, "ansible", "host", "example.com")
+            .unwrap();
+        database
+            .set_record_metadata(id, "cli", "role", "db")
+            .unwrap();
+        database
+            .set_record_metadata(id, "ansible", "role", "proxy")
+            .unwrap();
+        assert_eq!(
+            database.get_record_metadata(id, "ansible").unwrap(),
+            BTreeMap::from([
+                ("host".to_string(), "example.com".to_string()),
+                ("role".to_string(), "proxy".to_string())
+            ])
+        );
+        assert_eq!(
+            database.get_record_metadata(id, "cli").unwrap(),
+            BTreeMap::from([("role".to_string(), "db".to_string())])
+        );
+        assert!(database
+            .get_record_metadata(id, "other")
+            .unwrap()
+            .is_empty());
+
+        database
+            .delete_record_metadata(id, "ansible", "role")
+            .unwrap();
+        assert_eq!(
+            database.get_record_metadata(id, "ansible").unwrap().len(),
+            1
+        );
+        assert_eq!(database.get_record_metadata(id, "cli").unwrap().len(), 1);
+        assert_eq!(database.get_all_metadata_grouped().unwrap()[&id].len(), 2);
+        // Metadata is not content
+        assert_eq!(database.get_all_content_for_record(id).unwrap().len(), 2);
+    }
+    #[test]
+    fn test_record_metadata_invalid() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (record, _) = save_login(&database);
+        let id = record.id();
+
+        let value = "a".repeat(MAX_METADATA_LENGTH);
+        assert!(database
+            .set_record_metadata(id, "cli", "key", &value)
+            .is_ok());
+        assert_eq!(
+            database.set_record_metadata(id, "cli", "key", &format!("{value}a")),
+            Err(Error::Validation("Value is too long".into()))
+        );
+        assert_eq!(
+            database.set_record_metadata(id, " ", "key", "value"),
+            Err(Error::Validation(
+                "Namespace and key can not be empty".into()
+            ))
+        );
+        assert_eq!(
+            database.set_record_metadata(id + 1, "cli", "key", "value"),
+            Err(Error::NotFound("Record does not exist"))
+        );
+
+        database.delete_record(record).unwrap();
+        database.purge_record(id).unwrap();
+        assert!(database.get_record_metadata(id, "cli").unwrap().is_empty());
+        assert!(database.get_all_metadata_grouped().unwrap().is_empty());
+    }
+    fn backdate_deleted(database: &Database, id_record: u64, days: i64) {
+        database
+            .connection
+            .lock()
+            .unwrap()
+            .execute(
+                "UPDATE Record SET deleted = ?1 WHERE id_record = ?2;",
+                params![chrono::Utc::now() - chrono::Duration::days(days), id_record],
+            )
+            .unwrap();
+    }
+    #[test]
+    fn test_trash() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (record, _) = save_login(&database);
+        let id = record.id();
+        database
+            .set_record_metadata(id, "cli", "key", "value")
+            .unwrap();
+        database.save_draft(id, "draft").unwrap();
+
+        database.delete_record(record).unwrap();
+        assert!(database.get_all_records().unwrap().is_empty());
+        assert!(database.search_records("Title", true).unwrap().is_empty());
+        assert_eq!(
+            database
+                .count_records(&listing::RecordFilter::default())
+                .unwrap(),
+            0
+        );
+        let trashed = database.get_trashed_records().unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(draft(&database, id), None);
+        assert_eq!(
+            database.save_record_with_content(&mut trashed[0].clone(), &mut Vec::new()),
+            Err(Error::NotFound("Record does not exist"))
+        );
+        // Content stays until the record is purged
+        assert_eq!(database.get_all_content_for_record(id).unwrap().len(), 2);
+
+        database.restore_record(id).unwrap();
+        assert_eq!(database.get_all_records().unwrap().len(), 1);
+        assert!(database.get_trashed_records().unwrap().is_empty());
+        assert_eq!(
+            database.restore_record(id),
+            Err(Error::NotFound("Record is not in the trash"))
+        );
+        assert_eq!(
+            database.purge_record(id),
+            Err(Error::NotFound("Record is not in the trash"))
+        );
+
+        database.delete_record(trashed[0].clone()).unwrap();
+        database.purge_record(id).unwrap();
+        assert!(database.get_trashed_records().unwrap().is_empty());
+        assert!(database.get_all_content_for_record(id).unwrap().is_empty());
+        assert!(database.get_all_metadata_grouped().unwrap().is_empty());
+    }
+    /// Returns ids of records in the manual order.
+    fn manual_order(database: &Database) -> Vec<u64> {
+        let sort = listing::RecordSort {
+            field: listing::SortField::ManualOrder,
+            descending: false,
+        };
+        database
+            .get_records_window(&listing::RecordFilter::default(), sort, 0, u64::MAX)
+            .unwrap()
+            .iter()
+            .map(Record::id)
+            .collect()
+    }
+    #[test]
+    fn test_set_record_rank_between() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let [a, b, c, d] = [0; 4].map(|_| save_login(&database).0.id());
+        let records = database.get_all_records().unwrap();
+        assert_eq!(manual_order(&database), [a, b, c, d]);
+
+        // Unranked neighbors are ranked first, later records stay unranked
+        database
+            .set_record_rank_between(d, Some(a), Some(b))
+            .unwrap();
+        assert_eq!(manual_order(&database), [a, d, b, c]);
+        database.set_record_rank_between(c, None, Some(a)).unwrap();
+        assert_eq!(manual_order(&database), [c, a, d, b]);
+        database.set_record_rank_between(a, Some(b), None).unwrap();
+        assert_eq!(manual_order(&database), [c, d, b, a]);
+        // Moving does not modify the records
+        assert_eq!(database.get_all_records().unwrap(), records);
+
+        assert_eq!(
+            database.set_record_rank_between(a, Some(a), None),
+            Err(Error::Validation(
+                "Record can not be its own neighbor".into()
+            ))
+        );
+        assert_eq!(
+            database.set_record_rank_between(a, Some(9999), None),
+            Err(Error::NotFound("Record does not exist"))
+        );
+        assert_eq!(
+            database.set_record_rank_between(a, Some(b), Some(d)),
+            Err(Error::Validation(
+                "Previous record is not before the next one".into()
+            ))
+        );
+        assert_eq!(manual_order(&database), [c, d, b, a]);
+        database.delete_record(records[0].clone()).unwrap();
+        assert_eq!(
+            database.set_record_rank_between(records[0].id(), None, None),
+            Err(Error::NotFound("Record does not exist"))
+        );
+    }
+    #[test]
+    fn test_set_record_rank_repeated_below_top() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let top = save_login(&database).0.id();
+        let mut expected = vec![top, save_login(&database).0.id()];
+        database
+            .set_record_rank_between(expected[1], Some(top), None)
+            .unwrap();
+        // Halving the gap every time needs renormalization
+        for _ in 0..100 {
+            let id = save_login(&database).0.id();
+            database
+                .set_record_rank_between(id, Some(top), Some(expected[1]))
+                .unwrap();
+            expected.insert(1, id);
+        }
+        assert_eq!(manual_order(&database), expected);
+    }
+    #[test]
+    fn test_get_record() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (record, _) = save_login(&database);
+        save_login(&database);
+        assert_eq!(database.get_record(record.id()), Ok(Some(record.clone())));
+        assert_eq!(database.get_record(9999), Ok(None));
+
+        database.delete_record(record.clone()).unwrap();
+        assert_eq!(database.get_record(record.id()), Ok(None));
+    }
+    #[test]
+    fn test_bulk_delete_and_move() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (first, _) = save_login(&database);
+        let (second, _) = save_login(&database);
+        let (kept, _) = save_login(&database);
+        database.save_draft(first.id(), "draft").unwrap();
+        assert_eq!(database.delete_records(&[]), Ok(0));
+        assert_eq!(
+            database.move_records_to_category(&[], &Category::Note),
+            Ok(0)
+        );
+
+        assert_eq!(
+            database.move_records_to_category(&[first.id(), second.id()], &Category::Note),
+            Ok(2)
+        );
+        let categories: Vec<(u64, Category)> = database
+            .get_all_records()
+            .unwrap()
+            .iter()
+            .map(|record| (record.id(), record.category().clone()))
+            .collect();
+        assert!(categories.contains(&(first.id(), Category::Note)));
+        assert!(categories.contains(&(second.id(), Category::Note)));
+        assert!(categories.contains(&(kept.id(), Category::Login)));
+
+        assert_eq!(
+            database.delete_records(&[first.id(), second.id(), first.id()]),
+            Ok(2)
+        );
+        assert_eq!(database.get_all_records().unwrap().len(), 1);
+        assert_eq!(database.get_trashed_records().unwrap().len(), 2);
+        assert_eq!(draft(&database, first.id()), None);
+        // Content stays until the records are purged
+        assert_eq!(
+            database
+                .get_all_content_for_record(first.id())
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+    #[test]
+    fn test_bulk_rollback() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (first, _) = save_login(&database);
+        let (trashed, _) = save_login(&database);
+        database.delete_record(trashed.clone()).unwrap();
+        database.save_draft(first.id(), "draft").unwrap();
+
+        assert_eq!(
+            database.move_records_to_category(&[first.id(), trashed.id()], &Category::Note),
+            Err(Error::NotFound("Record does not exist"))
+        );
+        assert_eq!(
+            database.get_all_records().unwrap()[0].category(),
+            &Category::Login
+        );
+        assert_eq!(
+            database.delete_records(&[first.id(), 9999]),
+            Err(Error::NotFound("Record does not exist"))
+        );
+        assert_eq!(database.get_all_records().unwrap().len(), 1);
+        assert_eq!(draft(&database, first.id()), Some("draft".to_string()));
+    }
+    #[test]
+    fn test_delete_trash_older_30_days() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let save = |title: &str| {
+            let mut record = Record::new(title.to_string(), "".to_string(), Category::Note);
+            let mut content = vec![Content::new(
+                "Note".to_string(),
+                0,
+                true,
+                Value::LongText(value::LongText::new(title.to_string())),
+            )];
+            database
+                .save_record_with_content(&mut record, &mut content)
+                .unwrap();
+            record
+        };
+        let old = save("Old");
+        let recent = save("Recent");
+        let kept = save("Kept");
+        database.delete_record(old.clone()).unwrap();
+        database.delete_record(recent.clone()).unwrap();
+        backdate_deleted(&database, old.id(), 31);
+        backdate_deleted(&database, recent.id(), 29);
+
+        database.delete_trash_older_30_days().unwrap();
+        let trashed = database.get_trashed_records().unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert_eq!(trashed[0].id(), recent.id());
+        assert!(database
+            .get_all_content_for_record(old.id())
+            .unwrap()
+            .is_empty());
+        assert_eq!(database.get_all_records().unwrap()[0].id(), kept.id());
+    }
+    #[test]
+    fn test_record_favorite() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let mut ids = Vec::new();
+        for title in ["First", "Second", "Third"] {
+            let mut record = Record::new(title.to_string(), "".to_string(), Category::Note);
+            database
+                .save_record_with_content(&mut record, &mut Vec::new())
+                .unwrap();
+            ids.push(record.id());
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        }
+        let titles = |database: &Database| -> Vec<String> {
+            database
+                .get_all_records()
+                .unwrap()
+                .iter()
+                .map(|record| record.title().to_string())
+                .collect()
+        };
+        assert_eq!(titles(&database), ["Third", "Second", "First"]);
+
+        database.set_record_favorite(ids[0], true).unwrap();
+        assert_eq!(titles(&database), ["First", "Third", "Second"]);
+        let mut record = database.get_all_records().unwrap().remove(0);
+        assert!(record.favorite());
+
+        // Saving a record does not change the mark
+        record.set_favorite(false);
+        database
+            .save_record_with_content(&mut record, &mut Vec::new())
+            .unwrap();
+        assert!(database.get_all_records().unwrap()[0].favorite());
+
+        database.set_record_favorite(ids[0], false).unwrap();
+        assert_eq!(titles(&database), ["First", "Third", "Second"]);
+        assert!(!database.get_all_records().unwrap()[0].favorite());
+        assert_eq!(
+            database.set_record_favorite(ids[2] + 1, true),
+            Err(Error::NotFound("Record does not exist"))
+        );
+    }
+    #[test]
+    fn test_tags() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let mut ids = Vec::new();
+        for title in ["Mail", "Bank", "Recipe"] {
+            let mut record = Record::new(title.to_string(), "".to_string(), Category::Note);
+            database
+                .save_record_with_content(&mut record, &mut Vec::new())
+                .unwrap();
+            ids.push(record.id());
+        }
+        database.add_tag(ids[0], "work").unwrap();
+        database.add_tag(ids[1], " Work ").unwrap();
+        database.add_tag(ids[1], "finance").unwrap();
+        database.add_tag(ids[1], "finance").unwrap();
+
+        assert_eq!(database.get_all_tags().unwrap(), ["finance", "work"]);
+        assert_eq!(
+            database.get_tags_for_record(ids[1]).unwrap(),
+            ["finance", "work"]
+        );
+        let tagged: Vec<u64> = database
+            .get_records_by_tag("WORK")
+            .unwrap()
+            .iter()
+            .map(|record| record.id())
+            .collect();
+        assert_eq!(tagged.len(), 2);
+        assert!(tagged.contains(&ids[0]) && tagged.contains(&ids[1]));
+
+        let records = database.get_all_records_with_tags().unwrap();
+        assert_eq!(
+            records
+                .iter()
+                .map(|tagged| tagged.record.id())
+                .collect::<Vec<u64>>(),
+            database
+                .get_all_records()
+                .unwrap()
+                .iter()
+                .map(|record| record.id())
+                .collect::<Vec<u64>>()
+        );
+        let tags = |id: u64| {
+            records
+                .iter()
+                .find(|tagged| tagged.record.id() == id)
+                .unwrap()
+                .tags
+                .clone()
+        };
+        assert_eq!(tags(ids[0]), ["work"]);
+        assert_eq!(tags(ids[1]), ["finance", "work"]);
+        assert!(tags(ids[2]).is_empty());
+        let json = serde_json::to_value(&records[0]).unwrap();
+        assert!(json["title"].is_string());
+        assert!(json["tags"].is_array());
+
+        database.remove_tag(ids[1], "FINANCE").unwrap();
+        database.remove_tag(ids[1], "missing").unwrap();
+        assert_eq!(database.get_all_tags().unwrap(), ["work"]);
+    }
+    #[test]
+    fn test_tags_invalid() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (record, _) = save_login(&database);
+        let id = record.id();
+        assert_eq!(
+            database.add_tag(id, " "),
+            Err(Error::Validation("Tag can not be empty".into()))
+        );
+        assert_eq!(
+            database.add_tag(id, &"a".repeat(MAX_TAG_LENGTH + 1)),
+            Err(Error::Validation("Tag is too long".into()))
+        );
+        assert_eq!(
+            database.add_tag(id + 1, "work"),
+            Err(Error::NotFound("Record does not exist"))
+        );
+
+        database.add_tag(id, "work").unwrap();
+        database.delete_record(record).unwrap();
+        assert_eq!(
+            database.add_tag(id, "home"),
+            Err(Error::NotFound("Record does not exist"))
+        );
+        assert!(database.get_records_by_tag("work").unwrap().is_empty());
+        assert!(database.get_all_records_with_tags().unwrap().is_empty());
+
+        database.purge_record(id).unwrap();
+        assert!(database.get_tags_for_record(id).unwrap().is_empty());
+        assert!(database.get_all_tags().unwrap().is_empty());
+    }
+    fn set_password(
+        database: &Database,
+        record: &mut Record,
+        content: &mut [Content],
+        password: &str,
+    ) {
+        *content[1].value_mut() = Value::Password(value::Password::new(password.to_string()));
+        database.save_record_with_content(record, content).unwrap();
+    }
+    fn history(database: &Database, id_content: u64) -> Vec<String> {
+        database
+            .get_content_history(id_content)
+            .unwrap()
+            .iter()
+            .map(|version| version.value.expose_secret().to_string())
+            .collect()
+    }
+    #[test]
+    fn test_content_history() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (mut record, mut content) = save_login(&database);
+        let id_password = content[1].id();
+        assert!(history(&database, id_password).is_empty());
+
+        set_password(&database, &mut record, &mut content, "second");
+        set_password(&database, &mut record, &mut content, "second");
+        set_password(&database, &mut record, &mut content, "third");
+        assert_eq!(history(&database, id_password), ["second", "password"]);
+        let versions = database.get_content_history(id_password).unwrap();
+        assert_eq!(versions[0].kind, "Password");
+        assert!(versions[0].replaced >= versions[1].replaced);
+
+        // Not sensitive content has no history
+        *content[0].value_mut() = Value::Text(value::Text::new("john".to_string()));
+        set_password(&database, &mut record, &mut content, "third");
+        assert!(history(&database, content[0].id()).is_empty());
+
+        database
+            .restore_content_version(id_password, versions[1].id)
+            .unwrap();
+        assert_eq!(
+            database.get_content(id_password).unwrap().value(),
+            &Value::Password(value::Password::new("password".to_string()))
+        );
+        assert_eq!(history(&database, id_password), ["third", "second"]);
+        assert_eq!(
+            database.restore_content_version(content[0].id(), versions[0].id),
+            Err(Error::NotFound("Version does not exist"))
+        );
+
+        database.prune_history(1).unwrap();
+        assert_eq!(history(&database, id_password), ["third"]);
+
+        database.delete_content(content.remove(1)).unwrap();
+        assert!(history(&database, id_password).is_empty());
+    }
+    #[test]
+    fn test_content_history_purge() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let (mut record, mut content) = save_login(&database);
+        set_password(&database, &mut record, &mut content, "second");
+        assert_eq!(history(&database, content[1].id()).len(), 1);
+        let id = record.id();
+        database.delete_record(record).unwrap();
+        assert_eq!(history(&database, content[1].id()).len(), 1);
+        database.purge_record(id).unwrap();
+        assert!(history(&database, content[1].id()).is_empty());
+    }
+    #[test]
+    fn test_record_locked_read_only_old_schema() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "PRAGMA key = 'password';
+                create table Record (
+                    id_record integer primary key,
+                    title text not null,
+                    subtitle text not null,
+                    created datetime not null,
+                    last_modified datetime not null,
+                    category text not null
+                );
+                INSERT INTO Record (title, subtitle, created, last_modified, category)
+                VALUES ('Title', '', datetime('now'), datetime('now'), 'Note');",
+            )
+            .unwrap();
+        drop(connection);
+
+        let database = Database::open_path_read_only("password", &path, "Test").unwrap();
+        let records = database.get_all_records().unwrap();
+        assert_eq!(records[0].title(), "Title");
+        assert!(!records[0].locked());
+        assert!(!records[0].favorite());
+        assert!(database.get_all_records_with_tags().unwrap()[0]
+            .tags
+            .is_empty());
+        assert!(!database.is_record_locked(records[0].id()).unwrap());
+        assert!(database.get_trashed_records().unwrap().is_empty());
+        assert!(database.get_content_history(1).unwrap().is_empty());
+    }
+    #[test]
+    fn test_get_all_content_grouped() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let mut ids = Vec::new();
+        for index in 0..5 {
+            let mut record =
+                Record::new(format!("Record {index}"), "".to_string(), Category::Login);
+            let mut content: Vec<Content> = (0..index)
+                .map(|position| {
+                    Content::new(
+                        format!("Field {position}"),
+                        position,
+                        false,
+                        Value::Text(value::Text::new(format!("{index} {position}"))),
+                    )
+                })
+                .collect();
+            database
+                .save_record_with_content(&mut record, &mut content)
+                .unwrap();
+            ids.push(record.id());
+        }
+        // Content added later to an older record keeps the per-record order
+        let mut late = Content::new(
+            "Late".to_string(),
+            9,
+            false,
+            Value::Text(value::Text::new("late".to_string())),
+        );
+        save_content(&database, ids[1], &mut late).unwrap();
+
+        let mut grouped = database.get_all_content_grouped().unwrap();
+        assert!(!grouped.contains_key(&ids[0]));
+        assert_eq!(grouped.len(), 4);
+        for id in ids {
+            assert_eq!(
+                grouped.remove(&id).unwrap_or_default(),
+                database.get_all_content_for_record(id).unwrap()
+            );
+        }
+    }
+    #[test]
+    fn test_save_settings() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        database
+            .save_settings(&[("first", "1"), ("second", "2")])
+            .unwrap();
+        assert_eq!(database.get_setting("first").unwrap().expose_secret(), "1");
+        assert_eq!(database.get_setting("second").unwrap().expose_secret(), "2");
+
+        database
+            .connection
+            .lock()
+            .unwrap()
+            .execute_batch("CREATE TRIGGER fail BEFORE INSERT ON Settings WHEN NEW.name = 'fail' BEGIN SELECT RAISE(ABORT, 'fail'); END;")
+            .unwrap();
+        assert_eq!(
+            database
+                .save_settings(&[("first", "3"), ("fail", "4")])
+                .unwrap_err(),
+            "Failed to save setting"
+        );
+        assert_eq!(database.get_setting("first").unwrap().expose_secret(), "1");
+
+        database.delete_settings(&["first", "second"]).unwrap();
+        assert!(database.get_setting("first").is_err());
+        assert!(database.get_setting("second").is_err());
+    }
+    #[test]
+    fn test_mark_recovery_code_used() {
+        let directory = TempDir::new().unwrap();
+        let database = open_temp(&directory, "database", "password");
+        let mut record = Record::new("Title".to_string(), "".to_string(), Category::Login);
+        let mut content = vec![
+            Content::new(
+                "Recovery codes".to_string(),
+                0,
+                false,
+                Value::RecoveryCodes(
+                    value::RecoveryCodes::new(vec![
+                        ("first".to_string(), false),
+                        ("second".to_string(), false),
+                    ])
+                    .unwrap(),
+                ),
+            ),
+            Content::new(
+                "User".to_string(),
+                1,
+                true,
+                Value::Text(value::Text::new("john".to_string())),
+            ),
+        ];
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        let id = content[0].id();
+
+        database.mark_recovery_code_used(id, 1).unwrap();
+        assert_eq!(
+            database.mark_recovery_code_used(id, 2),
+            Err(Error::Other("Recovery code does not exist"))
+        );
+        assert_eq!(
+            database.mark_recovery_code_used(content[1].id(), 0),
+            Err(Error::Validation("Content is not recovery codes".into()))
+        );
+        let stored = database.get_content(id).unwrap();
+        let Value::RecoveryCodes(recovery_codes) = stored.value() else {
+            panic!("Content is not recovery codes");
+        };
+        assert!(recovery_codes.codes()[0].used().not());
+        assert!(recovery_codes.codes()[1].used());
+        assert_eq!(recovery_codes.codes()[1].code(), "second");
+        assert!(database.search_records("second", true).unwrap().is_empty());
     }
 }
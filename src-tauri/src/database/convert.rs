@@ -59,19 +59,41 @@ fn bank_card_number_from_database(value: String) -> Result<BankCardNumber, Error
         .map_err(|e| Error::InvalidColumnType(4, e.to_string(), rusqlite::types::Type::Text))
 }
 
+/// Helper function to convert a security question from the database to a QuestionAnswer struct.
+/// # Error
+/// Returns an error if the value cannot be converted to a QuestionAnswer.
+fn question_answer_from_database(mut value: String) -> Result<QuestionAnswer, Error> {
+    let result = QuestionAnswer::from_json(&value)
+        .map_err(|e| Error::InvalidColumnType(4, e.to_string(), rusqlite::types::Type::Text));
+    value.zeroize();
+    result
+}
+
+/// Helper function to convert recovery codes from the database to a RecoveryCodes struct.
+/// # Error
+/// Returns an error if the value cannot be converted to a RecoveryCodes.
+fn recovery_codes_from_database(mut value: String) -> Result<RecoveryCodes, Error> {
+    let result = RecoveryCodes::from_json(&value)
+        .map_err(|e| Error::InvalidColumnType(4, e.to_string(), rusqlite::types::Type::Text));
+    value.zeroize();
+    result
+}
+
 /// Helper function to convert a record from the database to a Record struct.
 fn record_from_database(
     id: u64,
     title: String,
     subtitle: String,
-    created: chrono::DateTime<chrono::Local>,
-    last_modified: chrono::DateTime<chrono::Local>,
+    created: chrono::DateTime<chrono::Utc>,
+    last_modified: chrono::DateTime<chrono::Utc>,
     category: Category,
+    locked: bool,
 ) -> Record {
     let mut record = Record::new(title, subtitle, category);
     record.set_id(id);
     record.set_created(created);
     record.set_last_modified(last_modified);
+    record.set_locked(locked);
     record
 }
 
@@ -79,14 +101,17 @@ fn record_from_database(
 /// # Error
 /// Returns an error if the row cannot be converted to a Record.
 pub fn row_to_record(row: &Row) -> Result<Record> {
-    Ok(record_from_database(
+    let mut record = record_from_database(
         row.get(0)?,
         row.get(1)?,
         row.get(2)?,
         row.get(3)?,
         row.get(4)?,
         Category::from_string(row.get(5)?),
-    ))
+        row.get(6)?,
+    );
+    record.set_favorite(row.get(7)?);
+    Ok(record)
 }
 
 /// Helper function to convert a row from the database to a Content struct.
@@ -112,6 +137,8 @@ pub fn row_to_content(row: &Row) -> Result<Content> {
         "Email" => Value::Email(email_from_database(value)?),
         "PhoneNumber" => Value::PhoneNumber(phone_number_from_database(value)?),
         "BankCardNumber" => Value::BankCardNumber(bank_card_number_from_database(value)?),
+        "QuestionAnswer" => Value::QuestionAnswer(question_answer_from_database(value)?),
+        "RecoveryCodes" => Value::RecoveryCodes(recovery_codes_from_database(value)?),
         _ => {
             id.zeroize();
             label.zeroize();
@@ -134,6 +161,15 @@ pub fn row_to_content(row: &Row) -> Result<Content> {
     Ok(content)
 }
 
+/// Helper function to convert a row with a preview from the database to a Content struct. The seventh column tells if the value is only a preview.
+/// # Error
+/// Returns an error if the row cannot be converted to a Content.
+pub fn row_to_content_preview(row: &Row) -> Result<Content> {
+    let mut content = row_to_content(row)?;
+    content.set_truncated(row.get(6)?);
+    Ok(content)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,18 +254,54 @@ mod tests {
         assert_eq!(result.unwrap().value(), "4702932172193242");
     }
     #[test]
+    fn test_question_answer_from_database_invalid() {
+        let result = question_answer_from_database(r#"{"question":"question"}"#.to_string());
+        assert!(result.is_err());
+    }
+    #[test]
+    fn test_question_answer_from_database_valid() {
+        let result = question_answer_from_database(
+            r#"{"question":"question","answer":"answer"}"#.to_string(),
+        );
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap().answer(), "answer");
+    }
+    #[test]
     fn test_record_from_database() {
         let record = record_from_database(
             1,
             "Title".to_string(),
             "Subtitle".to_string(),
-            chrono::Local::now(),
-            chrono::Local::now(),
+            chrono::Utc::now(),
+            chrono::Utc::now(),
             Category::Login,
+            true,
         );
         assert_eq!(record.id(), 1);
         assert_eq!(record.title(), "Title");
         assert_eq!(record.subtitle(), "Subtitle");
         assert_eq!(record.category(), &Category::Login);
+        assert!(record.locked());
+        assert!(!record.favorite());
+    }
+    #[test]
+    fn test_row_to_record() {
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+        let created = chrono::Utc::now() - chrono::Duration::days(1);
+        let last_modified = chrono::Utc::now();
+        let record = connection
+            .query_row(
+                "SELECT 1, 'Title', 'Subtitle', ?1, ?2, 'Login', 0, 1;",
+                rusqlite::params![created, last_modified],
+                row_to_record,
+            )
+            .unwrap();
+        assert_eq!(record.id(), 1);
+        assert_eq!(record.title(), "Title");
+        assert_eq!(record.category(), &Category::Login);
+        assert_eq!(record.created(), created);
+        assert_eq!(record.last_modified(), last_modified);
+        assert!(!record.locked());
+        assert!(record.favorite());
     }
 }
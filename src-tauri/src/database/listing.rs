@@ -0,0 +1,448 @@
+use super::model::Category;
+use super::{like_pattern, normalize_search};
+use rusqlite::types::Value;
+use serde::Deserialize;
+use std::ops::Not;
+
+/// Filter of the record list. Empty filter matches all records that are not in the trash. Conditions are combined with AND.
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecordFilter {
+    /// Only records of the category.
+    #[serde(default)]
+    pub category: Option<Category>,
+    /// Only favorite records if true, only the other ones if false.
+    #[serde(default)]
+    pub favorite: Option<bool>,
+    /// Records in the trash instead of the other ones. The trash keeps deleted records until they are purged, see [`super::Database::delete_record`].
+    #[serde(default)]
+    pub archived: bool,
+    /// Only records with all the tags. Tag names are compared case-insensitively.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Only records with the query in title or subtitle, case- and accent-insensitive ([`normalize_search`]). Blank query is ignored.
+    #[serde(default)]
+    pub query: String,
+}
+
+/// Columns and tables the filter is compiled for. A database opened read-only may miss those added by later migrations, see [`super::migration`].
+#[derive(Debug, Clone, Copy)]
+pub struct Schema {
+    /// Normalized title and subtitle. Without them, the query is matched with the original title and subtitle, case-insensitive only for ASCII.
+    pub normalized: bool,
+    /// Favorite column, without it no record is a favorite.
+    pub favorite: bool,
+    /// Deleted column, without it no record is in the trash.
+    pub deleted: bool,
+    /// Tag tables, without them no record has tags.
+    pub tags: bool,
+}
+
+/// Column the record list is sorted by.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Deserialize)]
+pub enum SortField {
+    #[default]
+    Title,
+    Created,
+    LastModified,
+    /// Category, then title.
+    Category,
+    /// Order arranged by the user, see [`rank_between`]. Records without a rank come after ranked ones.
+    ManualOrder,
+}
+
+/// Order of the record list. Records with equal values are ordered by id, so windows of the list never overlap.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecordSort {
+    #[serde(default)]
+    pub field: SortField,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// Preset orders of the paged record list, see [`super::Database::get_records_page`]. Each preset is a [`RecordSort`], so only the fixed SQL of [`order_clause`] is used.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Deserialize)]
+pub enum SortBy {
+    #[default]
+    TitleAsc,
+    TitleDesc,
+    LastModifiedDesc,
+    CreatedDesc,
+    Category,
+}
+
+impl From<SortBy> for RecordSort {
+    fn from(sort: SortBy) -> Self {
+        let (field, descending) = match sort {
+            SortBy::TitleAsc => (SortField::Title, false),
+            SortBy::TitleDesc => (SortField::Title, true),
+            SortBy::LastModifiedDesc => (SortField::LastModified, true),
+            SortBy::CreatedDesc => (SortField::Created, true),
+            SortBy::Category => (SortField::Category, false),
+        };
+        RecordSort { field, descending }
+    }
+}
+
+/// Builds the `WHERE` clause of the filter with its parameters for the schema. Values are always bound as parameters, only fixed SQL is put in the clause.
+pub fn where_clause(filter: &RecordFilter, schema: Schema) -> (String, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut parameters = Vec::new();
+    if let Some(category) = &filter.category {
+        parameters.push(Value::Text(category.as_str().to_string()));
+        conditions.push(format!("category = ?{}", parameters.len()));
+    }
+    match (filter.favorite, schema.favorite) {
+        (Some(favorite), true) => {
+            parameters.push(Value::Integer(favorite.into()));
+            conditions.push(format!("favorite = ?{}", parameters.len()));
+        }
+        (Some(true), false) => conditions.push("0".to_string()),
+        (Some(false), false) | (None, _) => {}
+    }
+    if filter.query.trim().is_empty().not() {
+        let (title, subtitle, pattern) = match schema.normalized {
+            true => (
+                "title_normalized",
+                "subtitle_normalized",
+                like_pattern(&normalize_search(&filter.query)),
+            ),
+            false => ("title", "subtitle", like_pattern(&filter.query)),
+        };
+        parameters.push(Value::Text(pattern));
+        let index = parameters.len();
+        conditions.push(format!(
+            "({title} LIKE ?{index} ESCAPE '\\' OR {subtitle} LIKE ?{index} ESCAPE '\\')"
+        ));
+    }
+    for tag in &filter.tags {
+        if schema.tags.not() {
+            conditions.push("0".to_string());
+            break;
+        }
+        parameters.push(Value::Text(tag.trim().to_string()));
+        conditions.push(format!(
+            "id_record IN (SELECT id_record FROM RecordTag JOIN Tag USING (id_tag) WHERE Tag.name = ?{})",
+            parameters.len()
+        ));
+    }
+    conditions.push(
+        match (schema.deleted, filter.archived) {
+            (true, false) => "deleted IS NULL",
+            (true, true) => "deleted IS NOT NULL",
+            (false, false) => "1",
+            (false, true) => "0",
+        }
+        .to_string(),
+    );
+    (format!("WHERE {}", conditions.join(" AND ")), parameters)
+}
+
+/// Builds the `ORDER BY` clause of the sort. Timestamps are compared as instants, because older ones are stored with the local UTC offset of the time they were saved. Without the `manual_rank` column, the manual order is the order of creation.
+pub fn order_clause(sort: RecordSort, manual_rank: bool) -> &'static str {
+    match (sort.field, sort.descending) {
+        (SortField::ManualOrder, false) if manual_rank => {
+            "ORDER BY manual_rank IS NULL, manual_rank, id_record"
+        }
+        (SortField::ManualOrder, true) if manual_rank => {
+            "ORDER BY manual_rank IS NULL, manual_rank DESC, id_record DESC"
+        }
+        (SortField::ManualOrder, false) => "ORDER BY id_record",
+        (SortField::ManualOrder, true) => "ORDER BY id_record DESC",
+        (SortField::Title, false) => "ORDER BY title, id_record",
+        (SortField::Title, true) => "ORDER BY title DESC, id_record DESC",
+        (SortField::Created, false) => "ORDER BY julianday(created), id_record",
+        (SortField::Created, true) => "ORDER BY julianday(created) DESC, id_record DESC",
+        (SortField::LastModified, false) => "ORDER BY julianday(last_modified), id_record",
+        (SortField::LastModified, true) => "ORDER BY julianday(last_modified) DESC, id_record DESC",
+        (SortField::Category, false) => "ORDER BY category, title, id_record",
+        (SortField::Category, true) => "ORDER BY category DESC, title DESC, id_record DESC",
+    }
+}
+
+/// Distance between ranks of neighboring records after [`renormalized_ranks`], and between a record moved to either end of the list and its neighbor.
+pub const RANK_STEP: f64 = 1024.0;
+
+/// Smallest distance between ranks of neighboring records. A record is not ranked closer to its neighbors, the ranks are renormalized instead.
+pub const MIN_RANK_GAP: f64 = 1e-6;
+
+/// Returns the rank of a record moved between records with the `previous` and `next` ranks, `None` meaning the record is the first or the last one. Fractional ranking: the rank is halfway between the neighbors, so no other record changes.
+/// # Return
+/// Returns `None` if the neighbors are too close to put a rank between them, see [`MIN_RANK_GAP`], or if `previous` is not less than `next`. The ranks need to be renormalized then.
+pub fn rank_between(previous: Option<f64>, next: Option<f64>) -> Option<f64> {
+    let rank = match (previous, next) {
+        (None, None) => RANK_STEP,
+        (Some(previous), None) => previous + RANK_STEP,
+        (None, Some(next)) => next - RANK_STEP,
+        (Some(previous), Some(next)) => {
+            if next - previous < 2.0 * MIN_RANK_GAP {
+                return None;
+            }
+            previous + (next - previous) / 2.0
+        }
+    };
+    // Far from zero, the gap can be lost to the precision of the float
+    let fits =
+        previous.is_none_or(|previous| previous < rank) && next.is_none_or(|next| rank < next);
+    fits.then_some(rank)
+}
+
+/// Returns evenly spaced ranks for `count` records in their current order, [`RANK_STEP`] apart.
+pub fn renormalized_ranks(count: usize) -> impl Iterator<Item = f64> {
+    (1..=count).map(|index| index as f64 * RANK_STEP)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Schema of a migrated database.
+    const CURRENT: Schema = Schema {
+        normalized: true,
+        favorite: true,
+        deleted: true,
+        tags: true,
+    };
+
+    #[test]
+    fn test_where_clause() {
+        let empty = ("WHERE deleted IS NULL".to_string(), Vec::new());
+        assert_eq!(where_clause(&RecordFilter::default(), CURRENT), empty);
+        let blank = RecordFilter {
+            query: "  ".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(where_clause(&blank, CURRENT), empty);
+
+        let category = RecordFilter {
+            category: Some(Category::BankCard),
+            ..Default::default()
+        };
+        assert_eq!(
+            where_clause(&category, CURRENT),
+            (
+                "WHERE category = ?1 AND deleted IS NULL".to_string(),
+                vec![Value::Text("BankCard".to_string())]
+            )
+        );
+
+        let both = RecordFilter {
+            category: Some(Category::Login),
+            query: "50%' OR 1=1 --".to_string(),
+            ..Default::default()
+        };
+        let (clause, parameters) = where_clause(
+            &both,
+            Schema {
+                normalized: false,
+                ..CURRENT
+            },
+        );
+        assert_eq!(
+            clause,
+            "WHERE category = ?1 AND (title LIKE ?2 ESCAPE '\\' OR subtitle LIKE ?2 ESCAPE '\\') AND deleted IS NULL"
+        );
+        assert_eq!(
+            parameters,
+            vec![
+                Value::Text("Login".to_string()),
+                Value::Text("%50\\%' OR 1=1 --%".to_string())
+            ]
+        );
+        let unicode = RecordFilter {
+            query: "Müller".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            where_clause(&unicode, CURRENT),
+            (
+                "WHERE (title_normalized LIKE ?1 ESCAPE '\\' OR subtitle_normalized LIKE ?1 ESCAPE '\\') AND deleted IS NULL"
+                    .to_string(),
+                vec![Value::Text("%muller%".to_string())]
+            )
+        );
+    }
+    #[test]
+    fn test_where_clause_favorite_archived_tags() {
+        let filter = RecordFilter {
+            category: Some(Category::Login),
+            favorite: Some(false),
+            archived: true,
+            tags: vec!["Work".to_string(), " x' OR 1=1 -- ".to_string()],
+            query: "mail".to_string(),
+        };
+        let (clause, parameters) = where_clause(&filter, CURRENT);
+        assert_eq!(
+            clause,
+            "WHERE category = ?1 AND favorite = ?2 AND (title_normalized LIKE ?3 ESCAPE '\\' OR subtitle_normalized LIKE ?3 ESCAPE '\\') \
+            AND id_record IN (SELECT id_record FROM RecordTag JOIN Tag USING (id_tag) WHERE Tag.name = ?4) \
+            AND id_record IN (SELECT id_record FROM RecordTag JOIN Tag USING (id_tag) WHERE Tag.name = ?5) \
+            AND deleted IS NOT NULL"
+        );
+        assert_eq!(
+            parameters,
+            vec![
+                Value::Text("Login".to_string()),
+                Value::Integer(0),
+                Value::Text("%mail%".to_string()),
+                Value::Text("Work".to_string()),
+                Value::Text("x' OR 1=1 --".to_string()),
+            ]
+        );
+
+        // Database opened read-only before the columns and tables were added
+        let old = Schema {
+            normalized: false,
+            favorite: false,
+            deleted: false,
+            tags: false,
+        };
+        assert_eq!(where_clause(&filter, old).0, "WHERE category = ?1 AND (title LIKE ?2 ESCAPE '\\' OR subtitle LIKE ?2 ESCAPE '\\') AND 0 AND 0");
+        let favorites = RecordFilter {
+            favorite: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            where_clause(&favorites, old),
+            ("WHERE 0 AND 1".to_string(), Vec::new())
+        );
+        assert_eq!(
+            where_clause(&favorites, CURRENT),
+            (
+                "WHERE favorite = ?1 AND deleted IS NULL".to_string(),
+                vec![Value::Integer(1)]
+            )
+        );
+    }
+    #[test]
+    fn test_deserialize() {
+        let filter: RecordFilter =
+            serde_json::from_str("{\"category\":\"Bank Card\",\"query\":\"mail\"}").unwrap();
+        assert_eq!(filter.category, Some(Category::BankCard));
+        let filter: RecordFilter = serde_json::from_str(
+            "{\"favorite\":true,\"archived\":true,\"tags\":[\"work\",\"home\"]}",
+        )
+        .unwrap();
+        assert_eq!(filter.favorite, Some(true));
+        assert!(filter.archived);
+        assert_eq!(filter.tags, ["work", "home"]);
+        assert_eq!(
+            serde_json::from_str::<RecordFilter>("{}").unwrap(),
+            RecordFilter::default()
+        );
+        assert!(serde_json::from_str::<RecordFilter>("{\"tag\":\"work\"}").is_err());
+
+        let sort: RecordSort =
+            serde_json::from_str("{\"field\":\"LastModified\",\"descending\":true}").unwrap();
+        assert_eq!(
+            order_clause(sort, true),
+            "ORDER BY julianday(last_modified) DESC, id_record DESC"
+        );
+        assert!(
+            serde_json::from_str::<RecordSort>("{\"field\":\"title; DROP TABLE Record\"}").is_err()
+        );
+    }
+    #[test]
+    fn test_sort_by() {
+        assert_eq!(
+            order_clause(SortBy::TitleDesc.into(), true),
+            "ORDER BY title DESC, id_record DESC"
+        );
+        assert_eq!(
+            order_clause(SortBy::Category.into(), true),
+            "ORDER BY category, title, id_record"
+        );
+        assert_eq!(
+            order_clause(SortBy::CreatedDesc.into(), true),
+            "ORDER BY julianday(created) DESC, id_record DESC"
+        );
+        assert_eq!(
+            serde_json::from_str::<SortBy>("\"LastModifiedDesc\"").unwrap(),
+            SortBy::LastModifiedDesc
+        );
+        assert!(serde_json::from_str::<SortBy>("\"title; DROP TABLE Record\"").is_err());
+    }
+    #[test]
+    fn test_order_clause_manual() {
+        let sort = RecordSort {
+            field: SortField::ManualOrder,
+            descending: false,
+        };
+        assert_eq!(
+            order_clause(sort, true),
+            "ORDER BY manual_rank IS NULL, manual_rank, id_record"
+        );
+        assert_eq!(order_clause(sort, false), "ORDER BY id_record");
+        assert_eq!(
+            serde_json::from_str::<RecordSort>("{\"field\":\"ManualOrder\"}").unwrap(),
+            sort
+        );
+    }
+    #[test]
+    fn test_rank_between() {
+        assert_eq!(rank_between(None, None), Some(RANK_STEP));
+        assert_eq!(rank_between(Some(1.0), None), Some(1.0 + RANK_STEP));
+        assert_eq!(rank_between(None, Some(1.0)), Some(1.0 - RANK_STEP));
+        assert_eq!(rank_between(Some(1.0), Some(2.0)), Some(1.5));
+        assert_eq!(rank_between(Some(-3.0), Some(-1.0)), Some(-2.0));
+        // Neighbors in the wrong order or too close
+        assert_eq!(rank_between(Some(2.0), Some(1.0)), None);
+        assert_eq!(rank_between(Some(1.0), Some(1.0)), None);
+        assert_eq!(rank_between(Some(1.0), Some(1.0 + MIN_RANK_GAP)), None);
+        // Gap lost to the precision of the float
+        assert_eq!(rank_between(Some(1e300), None), None);
+        assert_eq!(rank_between(Some(1e17), Some(1e17 + 16.0)), None);
+
+        assert_eq!(
+            renormalized_ranks(3).collect::<Vec<_>>(),
+            [RANK_STEP, 2.0 * RANK_STEP, 3.0 * RANK_STEP]
+        );
+        assert_eq!(renormalized_ranks(0).count(), 0);
+    }
+
+    /// Moves a new record to the index of the ranks like the database does, renormalizing the ranks if there is no room. Returns true if the ranks were renormalized.
+    fn insert(ranks: &mut Vec<f64>, index: usize) -> bool {
+        let between = |ranks: &Vec<f64>| {
+            rank_between(
+                index.checked_sub(1).map(|previous| ranks[previous]),
+                ranks.get(index).copied(),
+            )
+        };
+        let (rank, renormalized) = match between(ranks) {
+            Some(rank) => (rank, false),
+            None => {
+                *ranks = renormalized_ranks(ranks.len()).collect();
+                (between(ranks).unwrap(), true)
+            }
+        };
+        ranks.insert(index, rank);
+        renormalized
+    }
+
+    #[test]
+    fn test_repeated_insert_at_top() {
+        let mut ranks = Vec::new();
+        for _ in 0..10_000 {
+            assert!(insert(&mut ranks, 0).not());
+        }
+        assert!(ranks.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+    #[test]
+    fn test_repeated_insert_below_top() {
+        let mut ranks = renormalized_ranks(2).collect();
+        let mut renormalizations = 0;
+        for _ in 0..10_000 {
+            if insert(&mut ranks, 1) {
+                renormalizations += 1;
+            }
+        }
+        assert_eq!(ranks.len(), 10_002);
+        assert!(ranks
+            .windows(2)
+            .all(|pair| pair[1] - pair[0] >= MIN_RANK_GAP));
+        // Each renormalization makes room for about log2(RANK_STEP / MIN_RANK_GAP) halvings
+        assert!(renormalizations > 0);
+        assert!(renormalizations < 10_000 / 25, "{renormalizations}");
+    }
+}
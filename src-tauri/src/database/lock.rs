@@ -0,0 +1,245 @@
+use crate::error::Error;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+/// How often the lock file is refreshed while the lock is held.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Lock that was not refreshed for this long is considered stale, even if its process is still running (the pid could be reused).
+const STALE_AFTER: chrono::TimeDelta = chrono::TimeDelta::minutes(5);
+
+/// Content of the lock file. Lock files written with a local UTC offset are read as well.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct LockInfo {
+    pid: u32,
+    acquired: chrono::DateTime<chrono::Utc>,
+    refreshed: chrono::DateTime<chrono::Utc>,
+}
+
+impl LockInfo {
+    /// Lock is stale if its process is not running or if it was not refreshed for [`STALE_AFTER`].
+    fn is_stale(&self) -> bool {
+        is_alive(self.pid).not() || chrono::Utc::now() - self.refreshed > STALE_AFTER
+    }
+}
+
+/// Checks if the process with the given pid is running.
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks if the signal could be sent. EPERM means that the process exists but belongs to another user.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Processes are not checked on other platforms, the lock becomes stale only when it is not refreshed for [`STALE_AFTER`].
+#[cfg(not(unix))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+/// Shared state of the held lock. The refresh thread only holds a weak reference, so it stops when the lock is dropped.
+#[derive(Debug)]
+struct Held {
+    path: PathBuf,
+    acquired: chrono::DateTime<chrono::Utc>,
+}
+
+impl Held {
+    /// Checks if the lock file still belongs to this lock. The same process can acquire the lock again (e.g. when the database is reopened), in which case the older lock must not touch the file.
+    fn owns(&self, info: &LockInfo) -> bool {
+        info.pid == std::process::id() && info.acquired == self.acquired
+    }
+
+    fn refresh(&self) {
+        if let Some(mut info) = read(&self.path).filter(|info| self.owns(info)) {
+            info.refreshed = chrono::Utc::now();
+            write(&self.path, &info).unwrap_or_default();
+        }
+    }
+}
+
+impl Drop for Held {
+    fn drop(&mut self) {
+        if read(&self.path).is_some_and(|info| self.owns(&info)) {
+            fs::remove_file(&self.path).unwrap_or_default();
+        }
+    }
+}
+
+/// Advisory lock file next to the database. It marks the process that has the database opened for writing, so other processes can open it read-only instead of failing on "database is locked" errors. The file is removed when the lock is dropped.
+#[derive(Debug)]
+pub struct VaultLock {
+    _held: Arc<Held>,
+}
+
+impl VaultLock {
+    /// Returns path to the lock file of the database.
+    pub fn path(database_path: &Path) -> PathBuf {
+        let mut file_name = database_path.file_name().unwrap_or_default().to_owned();
+        file_name.push(".lock");
+        database_path.with_file_name(file_name)
+    }
+
+    /// Acquires the lock for the database. Stale locks are removed. The lock held by this process is taken over.
+    /// # Errors
+    /// Returns [`Error::Locked`] if the lock is held by another running process, or [`Error::Io`] if the lock file cannot be created
+    pub fn acquire(database_path: &Path) -> Result<VaultLock, Error> {
+        let path = VaultLock::path(database_path);
+        let now = chrono::Utc::now();
+        let info = LockInfo {
+            pid: std::process::id(),
+            acquired: now,
+            refreshed: now,
+        };
+
+        if let Some(current) = read(&path) {
+            if current.pid != info.pid && current.is_stale().not() {
+                return Err(Error::Locked);
+            }
+            fs::remove_file(&path).map_err(Error::io("Failed to remove stale lock"))?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .map_err(Error::io("Failed to create lock file"))?;
+        file.write_all(&serde_json::to_vec(&info).map_err(|_| "Failed to create lock file")?)
+            .map_err(Error::io("Failed to create lock file"))?;
+
+        let held = Arc::new(Held {
+            path,
+            acquired: now,
+        });
+        let weak = Arc::downgrade(&held);
+        std::thread::spawn(move || refresh_periodically(weak));
+
+        Ok(VaultLock { _held: held })
+    }
+}
+
+/// Refreshes the lock every [`REFRESH_INTERVAL`] until it is dropped.
+fn refresh_periodically(held: Weak<Held>) {
+    loop {
+        std::thread::sleep(REFRESH_INTERVAL);
+        match held.upgrade() {
+            Some(held) => held.refresh(),
+            None => return,
+        }
+    }
+}
+
+/// Reads the lock file. Returns `None` if it does not exist or cannot be parsed.
+fn read(path: &Path) -> Option<LockInfo> {
+    serde_json::from_slice(&fs::read(path).ok()?).ok()
+}
+
+fn write(path: &Path, info: &LockInfo) -> std::io::Result<()> {
+    fs::write(path, serde_json::to_vec(info)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Pid that is not used by any process, the maximum pid on Linux is 2^22.
+    const DEAD_PID: u32 = 99_999_999;
+
+    fn write_foreign(database_path: &Path, pid: u32, refreshed: chrono::DateTime<chrono::Utc>) {
+        let info = LockInfo {
+            pid,
+            acquired: refreshed,
+            refreshed,
+        };
+        write(&VaultLock::path(database_path), &info).unwrap();
+    }
+
+    #[test]
+    fn test_lock_path() {
+        assert_eq!(
+            VaultLock::path(Path::new("/data/database.password_manager")),
+            PathBuf::from("/data/database.password_manager.lock")
+        );
+    }
+    #[test]
+    fn test_lock_acquire_release() {
+        let directory = TempDir::new().unwrap();
+        let database_path = directory.path().join("database");
+        let lock = VaultLock::acquire(&database_path).unwrap();
+        let info = read(&VaultLock::path(&database_path)).unwrap();
+        assert_eq!(info.pid, std::process::id());
+        drop(lock);
+        assert!(VaultLock::path(&database_path).exists().not());
+    }
+    #[test]
+    fn test_lock_reacquire_same_process() {
+        let directory = TempDir::new().unwrap();
+        let database_path = directory.path().join("database");
+        let first = VaultLock::acquire(&database_path).unwrap();
+        let second = VaultLock::acquire(&database_path).unwrap();
+        drop(first);
+        assert!(VaultLock::path(&database_path).exists());
+        drop(second);
+        assert!(VaultLock::path(&database_path).exists().not());
+    }
+    #[test]
+    fn test_lock_held_by_another_process() {
+        let directory = TempDir::new().unwrap();
+        let database_path = directory.path().join("database");
+        write_foreign(&database_path, 1, chrono::Utc::now());
+        assert_eq!(
+            VaultLock::acquire(&database_path).err(),
+            Some(Error::Locked)
+        );
+        assert_eq!(read(&VaultLock::path(&database_path)).unwrap().pid, 1);
+    }
+    #[test]
+    fn test_lock_local_offset() {
+        let directory = TempDir::new().unwrap();
+        let database_path = directory.path().join("database");
+        let refreshed =
+            chrono::Utc::now().with_timezone(&chrono::FixedOffset::east_opt(7200).unwrap());
+        let timestamp = refreshed.to_rfc3339();
+        fs::write(
+            VaultLock::path(&database_path),
+            format!("{{\"pid\":1,\"acquired\":\"{timestamp}\",\"refreshed\":\"{timestamp}\"}}"),
+        )
+        .unwrap();
+        let info = read(&VaultLock::path(&database_path)).unwrap();
+        assert_eq!(info.refreshed, refreshed);
+        assert_eq!(
+            VaultLock::acquire(&database_path).err(),
+            Some(Error::Locked)
+        );
+    }
+    #[test]
+    fn test_lock_stale() {
+        let directory = TempDir::new().unwrap();
+        let database_path = directory.path().join("database");
+
+        write_foreign(&database_path, DEAD_PID, chrono::Utc::now());
+        assert!(VaultLock::acquire(&database_path).is_ok());
+
+        write_foreign(
+            &database_path,
+            1,
+            chrono::Utc::now() - STALE_AFTER - chrono::TimeDelta::minutes(1),
+        );
+        assert!(VaultLock::acquire(&database_path).is_ok());
+    }
+    #[test]
+    #[cfg(unix)]
+    fn test_is_alive() {
+        assert!(is_alive(std::process::id()));
+        assert!(is_alive(DEAD_PID).not());
+    }
+}
@@ -0,0 +1,211 @@
+use super::{normalize_search, preview};
+use crate::error::Error;
+use rusqlite::{params, Connection, Result, Transaction};
+
+/// Schema migrations in the order they are applied. After applying a migration, `user_version` of the database is set to its index + 1, so every migration runs exactly once.
+const MIGRATIONS: [fn(&Transaction) -> Result<()>; 15] = [
+    content_preview,
+    drafts,
+    record_locked,
+    record_list_index,
+    record_activity_index,
+    record_metadata,
+    record_deleted,
+    record_favorite,
+    tags,
+    content_history,
+    record_normalized,
+    content_value_last_changed,
+    acknowledgements,
+    record_manual_rank,
+    record_favorite_index,
+];
+
+/// Schema version of a database with all migrations applied.
+pub const VERSION: usize = MIGRATIONS.len();
+
+/// Adds preview column to the Content table and computes previews for existing long texts.
+fn content_preview(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch("ALTER TABLE Content ADD COLUMN preview text;")?;
+    let mut select = transaction
+        .prepare("SELECT id_content, kind, value FROM Content WHERE kind = 'LongText';")?;
+    let mut update =
+        transaction.prepare("UPDATE Content SET preview = ?1 WHERE id_content = ?2;")?;
+    let mut rows = select.query([])?;
+    while let Some(row) = rows.next()? {
+        let id_content: u64 = row.get(0)?;
+        let kind: String = row.get(1)?;
+        let value = zeroize::Zeroizing::new(row.get::<_, String>(2)?);
+        if let Some(preview) = preview(&kind, &value) {
+            update.execute(params![preview.as_str(), id_content])?;
+        }
+    }
+    Ok(())
+}
+
+/// Adds Drafts table for autosaved record edits. Record id 0 is used for a new record.
+fn drafts(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch(
+        "create table Drafts (
+            id_record integer primary key,
+            snapshot text not null,
+            saved text not null
+        );",
+    )
+}
+
+/// Adds locked column to the Record table, existing records are not locked.
+fn record_locked(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch("ALTER TABLE Record ADD COLUMN locked integer not null default 0;")
+}
+
+/// Adds index for the record list filtered by category and sorted by title, see [`super::listing`].
+fn record_list_index(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch("CREATE INDEX record_category_title ON Record (category, title);")
+}
+
+/// Adds indexes of record timestamps as instants, used by the activity summary. Timestamps are stored with the UTC offset of the time they were saved, so they are compared with `julianday`.
+fn record_activity_index(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch(
+        "CREATE INDEX record_created ON Record (julianday(created));
+        CREATE INDEX record_last_modified ON Record (julianday(last_modified));",
+    )
+}
+
+/// Adds RecordMetadata table for key-value metadata stored on records by integrations, see [`super::Database::set_record_metadata`].
+fn record_metadata(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch(
+        "create table RecordMetadata (
+            id_record integer not null,
+            namespace text not null,
+            key text not null,
+            value text not null,
+            primary key (id_record, namespace, key)
+        );",
+    )
+}
+
+/// Adds deleted column to the Record table, time when the record was moved to the trash. Existing records are not in the trash.
+fn record_deleted(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch("ALTER TABLE Record ADD COLUMN deleted datetime;")
+}
+
+/// Adds favorite column to the Record table, existing records are not favorites.
+fn record_favorite(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch("ALTER TABLE Record ADD COLUMN favorite integer not null default 0;")
+}
+
+/// Adds Tag table with case-insensitive unique names and RecordTag table linking tags to records, see [`super::Database::add_tag`].
+fn tags(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch(
+        "create table Tag (
+            id_tag integer primary key,
+            name text not null unique collate nocase
+        );
+        create table RecordTag (
+            id_record integer not null,
+            id_tag integer not null,
+            primary key (id_record, id_tag)
+        );
+        CREATE INDEX record_tag_tag ON RecordTag (id_tag);",
+    )
+}
+
+/// Adds ContentHistory table with previous values of sensitive content, see [`super::Database::get_content_history`].
+fn content_history(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch(
+        "create table ContentHistory (
+            id_version integer primary key,
+            id_content integer not null,
+            kind text not null,
+            value text not null,
+            replaced datetime not null
+        );
+        CREATE INDEX content_history_content ON ContentHistory (id_content);",
+    )
+}
+
+/// Adds title and subtitle of records normalized for search ([`normalize_search`]) and computes them for existing records.
+fn record_normalized(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch(
+        "ALTER TABLE Record ADD COLUMN title_normalized text not null default '';
+        ALTER TABLE Record ADD COLUMN subtitle_normalized text not null default '';",
+    )?;
+    let mut select = transaction.prepare("SELECT id_record, title, subtitle FROM Record;")?;
+    let mut update = transaction.prepare(
+        "UPDATE Record SET title_normalized = ?1, subtitle_normalized = ?2 WHERE id_record = ?3;",
+    )?;
+    let mut rows = select.query([])?;
+    while let Some(row) = rows.next()? {
+        let id_record: u64 = row.get(0)?;
+        let title: String = row.get(1)?;
+        let subtitle: String = row.get(2)?;
+        update.execute(params![
+            normalize_search(&title),
+            normalize_search(&subtitle),
+            id_record
+        ])?;
+    }
+    Ok(())
+}
+
+/// Adds the time of the last value change to the Content table, see [`super::Database::get_stale_passwords`]. Existing content gets the time of its last replaced value, or the last modification of its record if it has no history.
+fn content_value_last_changed(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch(
+        "ALTER TABLE Content ADD COLUMN value_last_changed datetime;
+        UPDATE Content SET value_last_changed = COALESCE(
+            (SELECT replaced FROM ContentHistory WHERE ContentHistory.id_content = Content.id_content ORDER BY id_version DESC LIMIT 1),
+            (SELECT last_modified FROM Record WHERE Record.id_record = Content.id_record)
+        );",
+    )
+}
+
+/// Adds Acknowledgement table with password audit findings the user accepted, see [`super::Database::acknowledge_finding`].
+fn acknowledgements(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch(
+        "create table Acknowledgement (
+            id_record integer not null,
+            finding text not null,
+            note text not null,
+            acknowledged datetime not null,
+            primary key (id_record, finding)
+        );",
+    )
+}
+
+/// Adds the rank of the record in the order arranged by the user, see [`super::Database::set_record_rank_between`]. Existing records have no rank.
+fn record_manual_rank(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch(
+        "ALTER TABLE Record ADD COLUMN manual_rank real;
+        CREATE INDEX record_manual_rank ON Record (manual_rank);",
+    )
+}
+
+/// Adds index for the record list filtered by favorites and sorted by title, see [`super::listing`].
+fn record_favorite_index(transaction: &Transaction) -> Result<()> {
+    transaction.execute_batch("CREATE INDEX record_favorite_title ON Record (favorite, title);")
+}
+
+/// Applies all migrations that were not applied yet, each in its own transaction.
+/// # Errors
+/// If any migration fails. The failed migration is rolled back.
+pub fn migrate(connection: &mut Connection) -> Result<(), Error> {
+    let version: usize = connection
+        .query_row("PRAGMA user_version;", [], |row| row.get(0))
+        .map_err(Error::database("Failed to get database version"))?;
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection
+            .transaction()
+            .map_err(Error::database("Failed to start transaction"))?;
+        migration(&transaction).map_err(Error::database("Failed to migrate database"))?;
+        transaction
+            .execute_batch(&format!("PRAGMA user_version = {};", index + 1))
+            .map_err(Error::database("Failed to set database version"))?;
+        transaction
+            .commit()
+            .map_err(Error::database("Failed to commit transaction"))?;
+    }
+
+    Ok(())
+}
@@ -2,7 +2,7 @@ pub mod value;
 
 use super::*;
 use rusqlite::types::FromSql;
-use serde::{Deserialize, Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::str::FromStr;
 use value::*;
 use zeroize::{Zeroize, ZeroizeOnDrop};
@@ -45,15 +45,15 @@ impl FromSql for SecretValue {
 }
 
 /// Record category
-#[derive(Debug, PartialEq, Clone, Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+///
+/// Categories unknown to this version are kept as [`Category::Custom`] with the original name, so records of a newer version survive a round-trip unchanged.
+#[derive(Debug, PartialEq, Clone, Zeroize, ZeroizeOnDrop)]
 pub enum Category {
     Login,
-    #[serde(rename(serialize = "Bank Card"))]
-    #[serde(alias = "Bank Card")]
     BankCard,
     Note,
-    #[serde(other)]
     Other,
+    Custom(String),
 }
 
 impl Category {
@@ -64,22 +64,65 @@ impl Category {
             "Login" => Category::Login,
             "BankCard" => Category::BankCard,
             "Note" => Category::Note,
-            _ => Category::Other,
+            "Other" => Category::Other,
+            custom => Category::Custom(custom.to_string()),
         }
     }
-    /// Converts a category to a string
+    /// Converts a category to a string, custom categories keep their original name
     pub fn as_str(&self) -> &str {
         match self {
             Category::Login => "Login",
             Category::BankCard => "BankCard",
             Category::Note => "Note",
             Category::Other => "Other",
+            Category::Custom(category) => category,
         }
     }
+    /// Returns name of the bundled icon of the category
+    pub fn icon_hint(&self) -> &'static str {
+        match self {
+            Category::Login => "globe",
+            Category::BankCard => "credit-card",
+            Category::Note => "note-sticky",
+            Category::Other | Category::Custom(_) => "briefcase",
+        }
+    }
+}
+
+impl Serialize for Category {
+    /// Serializes as [`Category::as_str`], except bank card which is "Bank Card".
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Category::BankCard => serializer.serialize_str("Bank Card"),
+            category => serializer.serialize_str(category.as_str()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Category {
+    /// Deserializes by [`Category::from_string`], also accepting "Bank Card".
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let category = SecretString::new(String::deserialize(deserializer)?);
+        Ok(match category.expose_secret().as_str() {
+            "Bank Card" => Category::BankCard,
+            category => Category::from_string(category.to_string()),
+        })
+    }
 }
 
 /// Represents a record in the database
-#[derive(Debug, PartialEq, Clone, Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+///
+/// Unknown fields are rejected in debug builds, so a misspelled field sent by the frontend is not silently dropped. Release builds ignore them.
+///
+/// Serialized with the icon hint of the category, see [`Category::icon_hint`], and the relative time of the last modification, see [`relative_time`]. Timestamps are serialized as RFC 3339 in UTC, timestamps with any offset are accepted.
+#[derive(Debug, PartialEq, Clone, Zeroize, ZeroizeOnDrop, Deserialize)]
+#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
 pub struct Record {
     #[serde(default)]
     id: u64,
@@ -87,11 +130,37 @@ pub struct Record {
     subtitle: String,
     category: Category,
     #[zeroize(skip)]
-    #[serde(default = "chrono::Local::now")]
-    created: chrono::DateTime<chrono::Local>,
+    #[serde(default = "chrono::Utc::now")]
+    created: chrono::DateTime<chrono::Utc>,
+    #[zeroize(skip)]
+    #[serde(default = "chrono::Utc::now")]
+    last_modified: chrono::DateTime<chrono::Utc>,
+    /// Content of a locked record can be viewed only shortly after the master password is verified again. Saving a record does not change it, see [`crate::database::Database::set_record_locked`].
+    #[serde(default)]
+    locked: bool,
+    /// Favorite records are listed first. Saving a record does not change it, see [`crate::database::Database::set_record_favorite`].
+    #[serde(default)]
+    favorite: bool,
+    /// Icon hint sent back by the frontend, ignored.
+    #[zeroize(skip)]
+    #[serde(default, rename = "icon", deserialize_with = "ignore_output")]
+    _icon: (),
+    /// Relative time sent back by the frontend, ignored.
     #[zeroize(skip)]
-    #[serde(default = "chrono::Local::now")]
-    last_modified: chrono::DateTime<chrono::Local>,
+    #[serde(
+        default,
+        rename = "last_modified_relative",
+        deserialize_with = "ignore_output"
+    )]
+    _last_modified_relative: (),
+}
+
+/// Accepts any value of a serialize-only field, such as the icon hint, the value is always derived when serializing.
+fn ignore_output<'de, D>(deserializer: D) -> std::result::Result<(), D::Error>
+where
+    D: Deserializer<'de>,
+{
+    serde::de::IgnoredAny::deserialize(deserializer).map(|_| ())
 }
 
 impl Record {
@@ -104,8 +173,12 @@ impl Record {
             title,
             subtitle,
             category,
-            created: chrono::Local::now(),
-            last_modified: chrono::Local::now(),
+            created: chrono::Utc::now(),
+            last_modified: chrono::Utc::now(),
+            locked: false,
+            favorite: false,
+            _icon: (),
+            _last_modified_relative: (),
         }
     }
     pub fn id(&self) -> u64 {
@@ -120,21 +193,90 @@ impl Record {
     pub fn category(&self) -> &Category {
         &self.category
     }
-    pub fn created(&self) -> chrono::DateTime<chrono::Local> {
+    pub fn created(&self) -> chrono::DateTime<chrono::Utc> {
         self.created
     }
-    pub fn last_modified(&self) -> chrono::DateTime<chrono::Local> {
+    pub fn last_modified(&self) -> chrono::DateTime<chrono::Utc> {
         self.last_modified
     }
+    pub fn locked(&self) -> bool {
+        self.locked
+    }
+    pub fn favorite(&self) -> bool {
+        self.favorite
+    }
     pub fn set_id(&mut self, id: u64) {
         self.id = id;
     }
-    pub fn set_created(&mut self, created: chrono::DateTime<chrono::Local>) {
+    pub fn set_subtitle(&mut self, subtitle: String) {
+        self.subtitle.zeroize();
+        self.subtitle = subtitle;
+    }
+    pub fn set_created(&mut self, created: chrono::DateTime<chrono::Utc>) {
         self.created = created;
     }
-    pub fn set_last_modified(&mut self, last_modified: chrono::DateTime<chrono::Local>) {
+    pub fn set_last_modified(&mut self, last_modified: chrono::DateTime<chrono::Utc>) {
         self.last_modified = last_modified;
     }
+    pub fn set_locked(&mut self, locked: bool) {
+        self.locked = locked;
+    }
+    pub fn set_favorite(&mut self, favorite: bool) {
+        self.favorite = favorite;
+    }
+}
+
+/// Serialized form of [`Record`]
+#[derive(Serialize)]
+struct RecordOutput<'a> {
+    id: u64,
+    title: &'a str,
+    subtitle: &'a str,
+    category: &'a Category,
+    icon: &'static str,
+    created: chrono::DateTime<chrono::Utc>,
+    last_modified: chrono::DateTime<chrono::Utc>,
+    last_modified_relative: String,
+    locked: bool,
+    favorite: bool,
+}
+
+/// Returns how long ago the time was, e.g. "3 days ago". It is computed from the elapsed time only, so it is the same in every timezone and across daylight saving changes. Times in the future, e.g. saved on a device with a clock ahead, are "just now".
+pub fn relative_time(
+    time: chrono::DateTime<chrono::Utc>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> String {
+    let elapsed = now - time;
+    let (count, unit) = match elapsed.num_days() {
+        _ if elapsed.num_minutes() < 1 => return "just now".to_string(),
+        0 if elapsed.num_hours() < 1 => (elapsed.num_minutes(), "minute"),
+        0 => (elapsed.num_hours(), "hour"),
+        days @ 1..=29 => (days, "day"),
+        days @ 30..=364 => (days / 30, "month"),
+        days => (days / 365, "year"),
+    };
+    format!("{count} {unit}{} ago", if count == 1 { "" } else { "s" })
+}
+
+impl Serialize for Record {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        RecordOutput {
+            id: self.id,
+            title: &self.title,
+            subtitle: &self.subtitle,
+            category: &self.category,
+            icon: self.category.icon_hint(),
+            created: self.created,
+            last_modified: self.last_modified,
+            last_modified_relative: relative_time(self.last_modified, chrono::Utc::now()),
+            locked: self.locked,
+            favorite: self.favorite,
+        }
+        .serialize(serializer)
+    }
 }
 
 /// Represents value of a content
@@ -152,6 +294,8 @@ pub enum Value {
     Email(Email),
     PhoneNumber(PhoneNumber),
     BankCardNumber(BankCardNumber),
+    QuestionAnswer(QuestionAnswer),
+    RecoveryCodes(RecoveryCodes),
 }
 
 impl ToSecretString for Value {
@@ -168,12 +312,78 @@ impl ToSecretString for Value {
             Value::Email(email) => email.to_secret_string(),
             Value::PhoneNumber(phone_number) => phone_number.to_secret_string(),
             Value::BankCardNumber(bank_card_number) => bank_card_number.to_secret_string(),
+            Value::QuestionAnswer(question_answer) => question_answer.to_secret_string(),
+            Value::RecoveryCodes(recovery_codes) => recovery_codes.to_secret_string(),
+        }
+    }
+}
+
+impl Value {
+    /// Returns maximum length of the value of the given kind in bytes. Long text can hold up to 1 MB, other kinds 4 KB.
+    pub fn max_length(kind: &str) -> usize {
+        match kind {
+            "LongText" => 1024 * 1024,
+            _ => 4 * 1024,
+        }
+    }
+
+    /// Returns the part of the value that is copied to the clipboard or revealed. For [`QuestionAnswer`] it is only the answer, for [`RecoveryCodes`] the codes on separate lines, for other values the whole value.
+    pub fn to_revealed_secret_string(&self) -> SecretString {
+        match &self {
+            Value::QuestionAnswer(question_answer) => {
+                SecretString::new(question_answer.answer().to_string())
+            }
+            Value::RecoveryCodes(recovery_codes) => SecretString::new(
+                recovery_codes
+                    .codes()
+                    .iter()
+                    .map(RecoveryCode::code)
+                    .collect::<Vec<&str>>()
+                    .join("\n"),
+            ),
+            _ => self.to_secret_string(),
+        }
+    }
+
+    /// Returns true for values that are masked until revealed, their reveals expire, see [`crate::reveal::RevealTimer`].
+    pub fn is_masked(&self) -> bool {
+        matches!(
+            self,
+            Value::SensitiveText(_)
+                | Value::Password(_)
+                | Value::TOTPSecret(_)
+                | Value::BankCardNumber(_)
+                | Value::QuestionAnswer(_)
+                | Value::RecoveryCodes(_)
+        )
+    }
+
+    /// Returns name of the bundled icon of the value kind
+    pub fn icon_hint(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "hashtag",
+            Value::Text(_) => "font",
+            Value::LongText(_) => "align-left",
+            Value::SensitiveText(_) => "eye-slash",
+            Value::Date(_) => "calendar",
+            Value::Password(_) => "key",
+            Value::TOTPSecret(_) => "clock",
+            Value::Url(_) => "globe",
+            Value::Email(_) => "envelope",
+            Value::PhoneNumber(_) => "phone",
+            Value::BankCardNumber(_) => "credit-card",
+            Value::QuestionAnswer(_) => "circle-question",
+            Value::RecoveryCodes(_) => "list-check",
         }
     }
 }
 
 /// Represents a content in the database
-#[derive(Debug, PartialEq, Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+///
+/// Unknown fields are passed to the flattened value, whose payloads reject them in debug builds and ignore them in release builds.
+///
+/// Serialized with the icon hint of the value kind, see [`Value::icon_hint`].
+#[derive(Debug, PartialEq, Zeroize, ZeroizeOnDrop, Deserialize)]
 pub struct Content {
     #[serde(default)]
     id: u64,
@@ -182,6 +392,17 @@ pub struct Content {
     required: bool,
     #[serde(flatten)]
     value: Value,
+    /// Value contains only a preview of the long text, see [`crate::database::PREVIEW_LENGTH`]. Serialized only when set.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    /// Icon hint sent back by the frontend, ignored so it is not passed to the value.
+    #[zeroize(skip)]
+    #[serde(default, rename = "icon", deserialize_with = "ignore_output")]
+    _icon: (),
+    /// Time the value was last changed. Loaded only when the content is copied to another database or an archive, so the age of passwords is kept. Never sent to or from the frontend.
+    #[zeroize(skip)]
+    #[serde(skip)]
+    value_last_changed: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl Content {
@@ -194,6 +415,9 @@ impl Content {
             position,
             required,
             value,
+            truncated: false,
+            _icon: (),
+            value_last_changed: None,
         }
     }
     pub fn kind(&self) -> &str {
@@ -209,6 +433,8 @@ impl Content {
             Value::Email(_) => "Email",
             Value::PhoneNumber(_) => "PhoneNumber",
             Value::BankCardNumber(_) => "BankCardNumber",
+            Value::QuestionAnswer(_) => "QuestionAnswer",
+            Value::RecoveryCodes(_) => "RecoveryCodes",
         }
     }
 
@@ -227,10 +453,60 @@ impl Content {
     pub fn value(&self) -> &Value {
         &self.value
     }
+    pub fn value_mut(&mut self) -> &mut Value {
+        &mut self.value
+    }
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+    pub fn value_last_changed(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.value_last_changed
+    }
     pub fn set_id(&mut self, id: u64) {
         self.id.zeroize();
         self.id = id;
     }
+    pub fn set_truncated(&mut self, truncated: bool) {
+        self.truncated = truncated;
+    }
+    pub fn set_value_last_changed(
+        &mut self,
+        value_last_changed: Option<chrono::DateTime<chrono::Utc>>,
+    ) {
+        self.value_last_changed = value_last_changed;
+    }
+}
+
+/// Serialized form of [`Content`]
+#[derive(Serialize)]
+struct ContentOutput<'a> {
+    id: u64,
+    label: &'a str,
+    position: u32,
+    required: bool,
+    #[serde(flatten)]
+    value: &'a Value,
+    icon: &'static str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+}
+
+impl Serialize for Content {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        ContentOutput {
+            id: self.id,
+            label: &self.label,
+            position: self.position,
+            required: self.required,
+            value: &self.value,
+            icon: self.value.icon_hint(),
+            truncated: self.truncated,
+        }
+        .serialize(serializer)
+    }
 }
 
 #[cfg(test)]
@@ -247,7 +523,7 @@ mod tests {
         assert_eq!(Category::from_string("Other".to_string()), Category::Other);
         assert_eq!(
             Category::from_string("Unknown".to_string()),
-            Category::Other
+            Category::Custom("Unknown".to_string())
         );
     }
     #[test]
@@ -256,6 +532,20 @@ mod tests {
         assert_eq!(Category::BankCard.as_str(), "BankCard");
         assert_eq!(Category::Note.as_str(), "Note");
         assert_eq!(Category::Other.as_str(), "Other");
+        assert_eq!(Category::Custom("Passkey".to_string()).as_str(), "Passkey");
+    }
+    #[test]
+    fn test_category_icon_hint() {
+        for category in [
+            Category::Login,
+            Category::BankCard,
+            Category::Note,
+            Category::Other,
+            Category::Custom("Passkey".to_string()),
+        ] {
+            assert!(category.icon_hint().is_empty().not(), "{category:?}");
+        }
+        assert_eq!(Category::BankCard.icon_hint(), "credit-card");
     }
     #[test]
     fn test_category_serialize() {
@@ -292,9 +582,33 @@ mod tests {
             Category::Note
         );
         assert_eq!(
-            serde_json::from_str::<Category>("\"Unknown\"").unwrap(),
+            serde_json::from_str::<Category>("\"Other\"").unwrap(),
             Category::Other
         );
+        assert_eq!(
+            serde_json::from_str::<Category>("\"Unknown\"").unwrap(),
+            Category::Custom("Unknown".to_string())
+        );
+    }
+    #[test]
+    fn test_category_round_trip() {
+        for name in [
+            "Login",
+            "Bank Card",
+            "Note",
+            "Other",
+            "Passkey",
+            "Wi-Fi Network",
+            "",
+        ] {
+            let json = serde_json::to_string(name).unwrap();
+            let category: Category = serde_json::from_str(&json).unwrap();
+            assert_eq!(serde_json::to_string(&category).unwrap(), json);
+            assert_eq!(
+                Category::from_string(category.as_str().to_string()),
+                category
+            );
+        }
     }
     #[test]
     fn test_new_record() {
@@ -304,7 +618,7 @@ mod tests {
         assert_eq!(record.subtitle(), "Subtitle");
         assert_eq!(record.category(), &Category::Login);
         record.set_id(1);
-        let now = chrono::Local::now();
+        let now = chrono::Utc::now();
         record.set_created(now);
         record.set_last_modified(now);
         assert_eq!(record.id(), 1);
@@ -318,12 +632,54 @@ mod tests {
         let last_modified = serde_json::to_string(&record.last_modified()).unwrap();
         assert_eq!(
             serde_json::to_string(&record).unwrap(),
-            format!("{{\"id\":0,\"title\":\"Title\",\"subtitle\":\"Subtitle\",\"category\":\"Login\",\"created\":{},\"last_modified\":{}}}",created,last_modified)
+            format!("{{\"id\":0,\"title\":\"Title\",\"subtitle\":\"Subtitle\",\"category\":\"Login\",\"icon\":\"globe\",\"created\":{},\"last_modified\":{},\"last_modified_relative\":\"just now\",\"locked\":false,\"favorite\":false}}",created,last_modified)
+        );
+        assert!(created.ends_with("Z\""));
+    }
+    #[test]
+    fn test_record_timestamps() {
+        let record = serde_json::from_str::<Record>(
+            "{\"title\":\"Title\",\"subtitle\":\"\",\"category\":\"Login\",\"created\":\"2024-10-27T02:30:00+02:00\",\"last_modified\":\"2024-10-27T02:10:00+01:00\",\"last_modified_relative\":\"3 days ago\"}",
+        )
+        .unwrap();
+        assert_eq!(record.created().to_rfc3339(), "2024-10-27T00:30:00+00:00");
+        assert_eq!(
+            record.last_modified().to_rfc3339(),
+            "2024-10-27T01:10:00+00:00"
         );
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["created"], "2024-10-27T00:30:00Z");
+        assert_eq!(json["last_modified"], "2024-10-27T01:10:00Z");
+        assert_eq!(serde_json::from_value::<Record>(json).unwrap(), record);
+    }
+    #[test]
+    fn test_relative_time() {
+        let now: chrono::DateTime<chrono::Utc> = "2024-03-31T12:00:00Z".parse().unwrap();
+        for (seconds, expected) in [
+            (-3600, "just now"),
+            (0, "just now"),
+            (59, "just now"),
+            (60, "1 minute ago"),
+            (59 * 60, "59 minutes ago"),
+            (3600, "1 hour ago"),
+            (23 * 3600, "23 hours ago"),
+            (24 * 3600, "1 day ago"),
+            (3 * 24 * 3600, "3 days ago"),
+            (30 * 24 * 3600, "1 month ago"),
+            (364 * 24 * 3600, "12 months ago"),
+            (365 * 24 * 3600, "1 year ago"),
+            (800 * 24 * 3600, "2 years ago"),
+        ] {
+            assert_eq!(
+                relative_time(now - chrono::Duration::seconds(seconds), now),
+                expected,
+                "{seconds}"
+            );
+        }
     }
     #[test]
     fn test_record_deserialize() {
-        let now = chrono::Local::now();
+        let now = chrono::Utc::now();
         let record = serde_json::from_str::<Record>(
             "{\"title\":\"Title\",\"subtitle\":\"Subtitle\",\"category\":\"Login\"}",
         );
@@ -362,10 +718,52 @@ mod tests {
         );
         assert_eq!(
             serde_json::to_string(&content).unwrap(),
-            "{\"id\":0,\"label\":\"Label\",\"position\":1,\"required\":true,\"kind\":\"Text\",\"value\":\"Text\"}"
+            "{\"id\":0,\"label\":\"Label\",\"position\":1,\"required\":true,\"kind\":\"Text\",\"value\":\"Text\",\"icon\":\"font\"}"
+        );
+    }
+    #[test]
+    fn test_value_icon_hint() {
+        let values = [
+            Value::Number(Number::new("1".to_string()).unwrap()),
+            Value::Text(Text::new("Text".to_string())),
+            Value::LongText(LongText::new("Text".to_string())),
+            Value::SensitiveText(SensitiveText::new("Text".to_string())),
+            Value::Date(Date::new("2021-01-01".to_string()).unwrap()),
+            Value::Password(Password::new("Password".to_string())),
+            Value::TOTPSecret(
+                TOTPSecret::new("rfffmaz4jsjq3qurwhzna2wljastmywv".to_string()).unwrap(),
+            ),
+            Value::Url(Url::new("https://example.com".to_string()).unwrap()),
+            Value::Email(Email::new("john@example.com".to_string()).unwrap()),
+            Value::PhoneNumber(PhoneNumber::new("+14152370800".to_string()).unwrap()),
+            Value::BankCardNumber(BankCardNumber::new("4111111111111111".to_string()).unwrap()),
+            Value::QuestionAnswer(
+                QuestionAnswer::new("Question".to_string(), "Answer".to_string()).unwrap(),
+            ),
+            Value::RecoveryCodes(RecoveryCodes::new(vec![("Code".to_string(), false)]).unwrap()),
+        ];
+        for value in &values {
+            assert!(value.icon_hint().is_empty().not(), "{value:?}");
+        }
+        assert_eq!(
+            Value::TOTPSecret(
+                TOTPSecret::new("rfffmaz4jsjq3qurwhzna2wljastmywv".to_string()).unwrap()
+            )
+            .icon_hint(),
+            "clock"
         );
     }
     #[test]
+    fn test_value_is_masked() {
+        assert!(Value::Password(Password::new("Password".to_string())).is_masked());
+        assert!(Value::BankCardNumber(
+            BankCardNumber::new("4111111111111111".to_string()).unwrap()
+        )
+        .is_masked());
+        assert!(!Value::Text(Text::new("Text".to_string())).is_masked());
+        assert!(!Value::LongText(LongText::new("Text".to_string())).is_masked());
+    }
+    #[test]
     fn test_content_deserialize() {
         let content = serde_json::from_str::<Content>(
             "{\"label\":\"Label\",\"position\":1,\"required\":true,\"kind\":\"Text\",\"value\":\"Text\"}",
@@ -378,4 +776,59 @@ mod tests {
         assert!(content.required());
         assert_eq!(content.value(), &Value::Text(Text::new("Text".to_string())));
     }
+    #[test]
+    fn test_deserialize_icon() {
+        // Frontend sends back records and content as it received them
+        let record = Record::new("Title".to_string(), "Subtitle".to_string(), Category::Login);
+        let json = serde_json::to_string(&record).unwrap();
+        assert_eq!(serde_json::from_str::<Record>(&json).unwrap(), record);
+        let content = Content::new(
+            "Label".to_string(),
+            1,
+            true,
+            Value::Text(Text::new("Text".to_string())),
+        );
+        let json = serde_json::to_value(&content).unwrap();
+        assert_eq!(json["icon"], "font");
+        assert_eq!(serde_json::from_value::<Content>(json).unwrap(), content);
+    }
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_record_deserialize_unknown_field() {
+        let record = serde_json::from_str::<Record>(
+            "{\"title\":\"Title\",\"subtitle\":\"Subtitle\",\"category\":\"Login\",\"categroy\":\"Note\"}",
+        );
+        assert!(record
+            .unwrap_err()
+            .to_string()
+            .contains("unknown field `categroy`"));
+    }
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_content_deserialize_unknown_field() {
+        for content in [
+            "{\"label\":\"Label\",\"position\":1,\"requried\":true,\"required\":true,\"kind\":\"Text\",\"value\":\"Text\"}",
+            "{\"label\":\"Label\",\"position\":1,\"required\":true,\"kind\":\"Password\",\"value\":\"Text\",\"valeu\":\"\"}",
+            "{\"label\":\"Label\",\"position\":1,\"required\":true,\"kind\":\"Url\",\"value\":\"https://example.com\",\"truncate\":true}",
+            "{\"label\":\"Label\",\"position\":1,\"required\":true,\"kind\":\"QuestionAnswer\",\"question\":\"Q\",\"answer\":\"A\",\"hint\":\"H\"}",
+        ] {
+            assert!(serde_json::from_str::<Content>(content).is_err(), "{content}");
+        }
+        assert!(serde_json::from_str::<Content>(
+            "{\"id\":1,\"label\":\"Label\",\"position\":1,\"required\":true,\"kind\":\"LongText\",\"value\":\"Text\",\"truncated\":true}",
+        )
+        .is_ok());
+    }
+    #[test]
+    #[cfg(not(debug_assertions))]
+    fn test_deserialize_unknown_field_release() {
+        let record = serde_json::from_str::<Record>(
+            "{\"title\":\"Title\",\"subtitle\":\"Subtitle\",\"category\":\"Login\",\"categroy\":\"Note\"}",
+        );
+        assert_eq!(record.unwrap().category(), &Category::Login);
+        let content = serde_json::from_str::<Content>(
+            "{\"label\":\"Label\",\"position\":1,\"required\":true,\"kind\":\"Url\",\"value\":\"https://example.com\",\"truncate\":true}",
+        );
+        assert!(content.unwrap().truncated().not());
+    }
 }
@@ -30,6 +30,7 @@ impl Number {
 
 /// Text value
 #[derive(Debug, PartialEq, Default, Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
 pub struct Text {
     value: String,
 }
@@ -46,6 +47,7 @@ impl Text {
 
 /// Long text value
 #[derive(Debug, PartialEq, Default, Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
 pub struct LongText {
     value: String,
 }
@@ -62,6 +64,7 @@ impl LongText {
 /// Sensitive text value
 /// This value is not serialized
 #[derive(Debug, PartialEq, Default, Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
 pub struct SensitiveText {
     #[serde(skip_serializing)]
     value: String,
@@ -102,6 +105,7 @@ impl Date {
 /// Password value
 /// This value is not serialized
 #[derive(Debug, PartialEq, Default, Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
 pub struct Password {
     #[serde(skip_serializing)]
     value: String,
@@ -168,6 +172,16 @@ impl Url {
     pub fn value(&self) -> &str {
         &self.value
     }
+    /// Returns lowercase host of the URL without the `www.` prefix. Values without a scheme, including IP addresses, are parsed as `https`.
+    pub fn host(&self) -> Option<String> {
+        let url = tauri::Url::parse(&self.value)
+            .ok()
+            .filter(|url| url.has_host())
+            .or_else(|| tauri::Url::parse(&format!("https://{}", self.value)).ok())
+            .or_else(|| tauri::Url::parse(&format!("https://[{}]", self.value)).ok())?;
+        let host = url.host_str()?.to_lowercase();
+        Some(host.strip_prefix("www.").unwrap_or(&host).to_string())
+    }
 }
 
 /// Email value
@@ -245,6 +259,190 @@ impl BankCardNumber {
     }
 }
 
+/// Security question with its answer
+/// Only the question is serialized, the answer is sensitive
+#[derive(Debug, PartialEq, Default, Zeroize, ZeroizeOnDrop, Serialize)]
+pub struct QuestionAnswer {
+    question: String,
+    #[serde(skip_serializing)]
+    answer: String,
+}
+
+/// Both parts of [`QuestionAnswer`]. Used for deserialization and for the value stored in the database.
+#[derive(Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+struct QuestionAnswerParts {
+    question: String,
+    answer: String,
+}
+
+impl QuestionAnswer {
+    /// Default label for the content with a security question
+    pub const DEFAULT_LABEL: &'static str = "Security question";
+
+    /// Create a new QuestionAnswer
+    /// # Errors
+    /// Returns an error if the question or the answer is empty
+    pub fn new(mut question: String, mut answer: String) -> Result<QuestionAnswer, &'static str> {
+        let error = if question.trim().is_empty() {
+            Some("Question can not be empty")
+        } else if answer.trim().is_empty() {
+            Some("Answer can not be empty")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            question.zeroize();
+            answer.zeroize();
+            return Err(error);
+        }
+
+        Ok(QuestionAnswer { question, answer })
+    }
+    /// Create a new QuestionAnswer from the stored JSON object with question and answer
+    /// # Errors
+    /// Returns an error if the value is not valid JSON object or if any part is empty
+    pub fn from_json(value: &str) -> Result<QuestionAnswer, &'static str> {
+        serde_json::from_str::<QuestionAnswer>(value).map_err(|_| "Invalid security question")
+    }
+    pub fn question(&self) -> &str {
+        &self.question
+    }
+    pub fn answer(&self) -> &str {
+        &self.answer
+    }
+}
+
+impl<'de> Deserialize<'de> for QuestionAnswer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut parts = QuestionAnswerParts::deserialize(deserializer)?;
+        QuestionAnswer::new(
+            std::mem::take(&mut parts.question),
+            std::mem::take(&mut parts.answer),
+        )
+        .map_err(de::Error::custom)
+    }
+}
+
+/// One-time recovery code
+/// Only the used flag is serialized, the code is sensitive
+#[derive(Debug, PartialEq, Default, Zeroize, ZeroizeOnDrop, Serialize)]
+pub struct RecoveryCode {
+    #[serde(skip_serializing)]
+    code: String,
+    used: bool,
+}
+
+impl RecoveryCode {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+    pub fn used(&self) -> bool {
+        self.used
+    }
+}
+
+/// Both parts of [`RecoveryCode`]. Used for deserialization and for the value stored in the database.
+#[derive(Zeroize, ZeroizeOnDrop, Serialize, Deserialize)]
+#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+struct RecoveryCodeParts {
+    code: String,
+    #[serde(default)]
+    used: bool,
+}
+
+/// Recovery codes given by a service, each of them can be used once
+#[derive(Debug, PartialEq, Default, Zeroize, ZeroizeOnDrop, Serialize)]
+pub struct RecoveryCodes {
+    codes: Vec<RecoveryCode>,
+}
+
+/// All codes of [`RecoveryCodes`]. Used for deserialization.
+#[derive(Deserialize)]
+#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+struct RecoveryCodesParts {
+    codes: Vec<RecoveryCodeParts>,
+}
+
+impl RecoveryCodes {
+    /// Setting whether copying a recovery code marks it as used, enabled unless set to `false`
+    pub const MARK_COPIED_SETTING: &'static str = "mark_copied_recovery_code";
+
+    /// Create new RecoveryCodes from codes with their used flags
+    /// # Errors
+    /// Returns an error if any code is empty or if the codes are not unique
+    pub fn new(mut codes: Vec<(String, bool)>) -> Result<RecoveryCodes, &'static str> {
+        let error = if codes.iter().any(|(code, _)| code.trim().is_empty()) {
+            Some("Recovery code can not be empty")
+        } else if codes
+            .iter()
+            .enumerate()
+            .any(|(index, (code, _))| codes[..index].iter().any(|(other, _)| other == code))
+        {
+            Some("Recovery codes must be unique")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            codes.iter_mut().for_each(|(code, _)| code.zeroize());
+            return Err(error);
+        }
+
+        Ok(RecoveryCodes {
+            codes: codes
+                .into_iter()
+                .map(|(code, used)| RecoveryCode { code, used })
+                .collect(),
+        })
+    }
+    /// Create new RecoveryCodes from the stored JSON array of objects with code and used flag
+    /// # Errors
+    /// Returns an error if the value is not valid JSON array or if the codes are not valid
+    pub fn from_json(value: &str) -> Result<RecoveryCodes, &'static str> {
+        let mut parts = serde_json::from_str::<Vec<RecoveryCodeParts>>(value)
+            .map_err(|_| "Invalid recovery codes")?;
+        RecoveryCodes::from_parts(&mut parts)
+    }
+    fn from_parts(parts: &mut [RecoveryCodeParts]) -> Result<RecoveryCodes, &'static str> {
+        RecoveryCodes::new(
+            parts
+                .iter_mut()
+                .map(|part| (std::mem::take(&mut part.code), part.used))
+                .collect(),
+        )
+    }
+    pub fn codes(&self) -> &[RecoveryCode] {
+        &self.codes
+    }
+    /// Returns index of the first code that was not used yet
+    pub fn next_unused(&self) -> Option<usize> {
+        self.codes.iter().position(|code| code.used.not())
+    }
+    /// Marks the code with the given index as used
+    /// # Errors
+    /// Returns an error if there is no code with the index
+    pub fn mark_used(&mut self, index: usize) -> Result<(), &'static str> {
+        self.codes
+            .get_mut(index)
+            .ok_or("Recovery code does not exist")?
+            .used = true;
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for RecoveryCodes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut parts = RecoveryCodesParts::deserialize(deserializer)?;
+        RecoveryCodes::from_parts(&mut parts.codes).map_err(de::Error::custom)
+    }
+}
+
 pub trait ToSecretString {
     fn to_secret_string(&self) -> SecretString;
 }
@@ -261,7 +459,35 @@ macro_rules! impl_to_secret_string {
 
 impl_to_secret_string!(for Number, Text, LongText, SensitiveText, Date, Password, TOTPSecret, Url, Email, PhoneNumber, BankCardNumber);
 
+impl ToSecretString for QuestionAnswer {
+    /// Convert value to SecretString with JSON object containing both question and answer
+    fn to_secret_string(&self) -> SecretString {
+        let parts = QuestionAnswerParts {
+            question: self.question.clone(),
+            answer: self.answer.clone(),
+        };
+        SecretString::new(serde_json::to_string(&parts).unwrap_or_default())
+    }
+}
+
+impl ToSecretString for RecoveryCodes {
+    /// Convert value to SecretString with JSON array containing all codes with their used flags
+    fn to_secret_string(&self) -> SecretString {
+        let parts: Vec<RecoveryCodeParts> = self
+            .codes
+            .iter()
+            .map(|code| RecoveryCodeParts {
+                code: code.code.clone(),
+                used: code.used,
+            })
+            .collect();
+        SecretString::new(serde_json::to_string(&parts).unwrap_or_default())
+    }
+}
+
 /// https://serde.rs/deserialize-struct.html
+///
+/// Unknown fields are rejected in debug builds and ignored in release builds, same as `#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]` on derived values.
 macro_rules! impl_deserialize {
     (for $($t:ty),+) => {
         $(impl<'de> Deserialize<'de> for $t {
@@ -269,7 +495,7 @@ macro_rules! impl_deserialize {
             where
                 D: Deserializer<'de>,
             {
-                enum Field { Value }
+                enum Field { Value, Ignore }
 
                 impl<'de> Deserialize<'de> for Field {
                     fn deserialize<D>(deserializer: D) -> Result<Field, D::Error>
@@ -291,7 +517,8 @@ macro_rules! impl_deserialize {
                             {
                                 match value {
                                     "value" => Ok(Field::Value),
-                                    _ => Err(de::Error::unknown_field(value, FIELDS)),
+                                    _ if cfg!(debug_assertions) => Err(de::Error::unknown_field(value, FIELDS)),
+                                    _ => Ok(Field::Ignore),
                                 }
                             }
                         }
@@ -334,6 +561,9 @@ macro_rules! impl_deserialize {
                                     }
                                     value = Some(map.next_value()?);
                                 }
+                                Field::Ignore => {
+                                    map.next_value::<de::IgnoredAny>()?;
+                                }
                             }
                         }
                         match value {
@@ -546,6 +776,23 @@ mod tests {
         assert_eq!(url.unwrap().value(), "2606:4700:4700::1111".to_string());
     }
     #[test]
+    fn test_url_host() {
+        let host = |value: &str| Url::new(value.to_string()).unwrap().host();
+        assert_eq!(
+            host("https://WWW.Example.com/login?next=1"),
+            Some("example.com".to_string())
+        );
+        assert_eq!(
+            host("http://accounts.example.com:8080"),
+            Some("accounts.example.com".to_string())
+        );
+        assert_eq!(host("1.1.1.1"), Some("1.1.1.1".to_string()));
+        assert_eq!(
+            host("2606:4700:4700::1111"),
+            Some("[2606:4700:4700::1111]".to_string())
+        );
+    }
+    #[test]
     fn test_url_deserialize_empty() {
         let url = serde_json::from_str::<Url>(r#"{}"#);
         assert!(url.is_err());
@@ -666,4 +913,122 @@ mod tests {
         assert!(bank_card_number.is_ok());
         assert_eq!(bank_card_number.unwrap().value(), "4702932172193242");
     }
+    #[test]
+    fn test_question_answer_empty() {
+        assert!(QuestionAnswer::new("".to_string(), "answer".to_string()).is_err());
+        assert!(QuestionAnswer::new("question".to_string(), " ".to_string()).is_err());
+    }
+    #[test]
+    fn test_question_answer_valid() {
+        let question_answer =
+            QuestionAnswer::new("question".to_string(), "answer".to_string()).unwrap();
+        assert_eq!(question_answer.question(), "question");
+        assert_eq!(question_answer.answer(), "answer");
+    }
+    #[test]
+    fn test_question_answer_serialize() {
+        let question_answer =
+            QuestionAnswer::new("question".to_string(), "answer".to_string()).unwrap();
+        let serialized = serde_json::to_string(&question_answer).unwrap();
+        assert_eq!(serialized, r#"{"question":"question"}"#);
+        let serialized = serde_json::to_string(&Value::QuestionAnswer(question_answer)).unwrap();
+        assert!(serialized.contains("answer").not());
+    }
+    #[test]
+    fn test_question_answer_deserialize() {
+        let question_answer = serde_json::from_str::<QuestionAnswer>(r#"{"question":"question"}"#);
+        assert!(question_answer.is_err());
+        let question_answer =
+            serde_json::from_str::<QuestionAnswer>(r#"{"question":"question","answer":""}"#);
+        assert!(question_answer.is_err());
+        let question_answer =
+            serde_json::from_str::<QuestionAnswer>(r#"{"question":"question","answer":"answer"}"#);
+        assert_eq!(question_answer.unwrap().answer(), "answer");
+    }
+    #[test]
+    fn test_question_answer_json() {
+        let question_answer =
+            QuestionAnswer::new("question".to_string(), "answer".to_string()).unwrap();
+        let stored = question_answer.to_secret_string();
+        assert_eq!(
+            QuestionAnswer::from_json(stored.expose_secret()),
+            Ok(question_answer)
+        );
+        assert!(QuestionAnswer::from_json("answer").is_err());
+    }
+    #[test]
+    fn test_recovery_codes_invalid() {
+        assert_eq!(
+            RecoveryCodes::new(vec![("a".to_string(), false), (" ".to_string(), false)]),
+            Err("Recovery code can not be empty")
+        );
+        assert_eq!(
+            RecoveryCodes::new(vec![("a".to_string(), false), ("a".to_string(), true)]),
+            Err("Recovery codes must be unique")
+        );
+        assert!(RecoveryCodes::new(Vec::new()).is_ok());
+    }
+    #[test]
+    fn test_recovery_codes_serialize() {
+        let recovery_codes = RecoveryCodes::new(vec![
+            ("first".to_string(), true),
+            ("second".to_string(), false),
+        ])
+        .unwrap();
+        let serialized = serde_json::to_string(&recovery_codes).unwrap();
+        assert_eq!(serialized, r#"{"codes":[{"used":true},{"used":false}]}"#);
+        let serialized = serde_json::to_string(&Value::RecoveryCodes(recovery_codes)).unwrap();
+        assert!(serialized.contains("first").not());
+    }
+    #[test]
+    fn test_recovery_codes_deserialize() {
+        let recovery_codes = serde_json::from_str::<RecoveryCodes>(r#"{"codes":[{"used":false}]}"#);
+        assert!(recovery_codes.is_err());
+        let recovery_codes = serde_json::from_str::<RecoveryCodes>(
+            r#"{"codes":[{"code":"first","used":true},{"code":"second"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(recovery_codes.codes()[0].code(), "first");
+        assert!(recovery_codes.codes()[0].used());
+        assert!(recovery_codes.codes()[1].used().not());
+        assert!(serde_json::from_str::<RecoveryCodes>(
+            r#"{"codes":[{"code":"first"},{"code":"first"}]}"#
+        )
+        .is_err());
+    }
+    #[test]
+    fn test_recovery_codes_json() {
+        let recovery_codes = RecoveryCodes::new(vec![
+            ("first".to_string(), true),
+            ("second".to_string(), false),
+        ])
+        .unwrap();
+        let stored = recovery_codes.to_secret_string();
+        assert_eq!(
+            stored.expose_secret(),
+            r#"[{"code":"first","used":true},{"code":"second","used":false}]"#
+        );
+        assert_eq!(
+            RecoveryCodes::from_json(stored.expose_secret()),
+            Ok(recovery_codes)
+        );
+        assert!(RecoveryCodes::from_json("first").is_err());
+    }
+    #[test]
+    fn test_recovery_codes_mark_used() {
+        let mut recovery_codes = RecoveryCodes::new(vec![
+            ("first".to_string(), false),
+            ("second".to_string(), false),
+        ])
+        .unwrap();
+        assert_eq!(recovery_codes.next_unused(), Some(0));
+        recovery_codes.mark_used(0).unwrap();
+        assert_eq!(recovery_codes.next_unused(), Some(1));
+        recovery_codes.mark_used(1).unwrap();
+        assert_eq!(recovery_codes.next_unused(), None);
+        assert_eq!(
+            recovery_codes.mark_used(2),
+            Err("Recovery code does not exist")
+        );
+    }
 }
@@ -0,0 +1,382 @@
+use super::Database;
+use crate::error::Error;
+use rusqlite::{params, Params};
+use secrecy::ExposeSecret;
+use std::ops::Not;
+use std::sync::Mutex;
+
+/// Maximum number of rows deleted by one statement. The database lock is released between batches, so other commands are not blocked for long.
+pub const BATCH_SIZE: u64 = 500;
+
+/// Retention policy of an auxiliary table. Rows older than the maximum age are deleted first, then the oldest rows over the maximum count. Both limits are settings, `0` disables the limit.
+pub struct Policy {
+    /// Name of the table.
+    pub table: &'static str,
+    /// Primary key of the table.
+    key: &'static str,
+    /// Column with the time the row was written, the oldest rows are deleted first.
+    time: &'static str,
+    /// Setting with the maximum number of rows.
+    pub max_rows_setting: &'static str,
+    /// Maximum number of rows when the setting is not set or not valid.
+    pub default_max_rows: u64,
+    /// Setting with the maximum age of rows in days.
+    pub max_days_setting: &'static str,
+    /// Maximum age of rows in days when the setting is not set or not valid.
+    pub default_max_days: u64,
+}
+
+/// Policies of the tables pruned by [`run_retention`].
+pub const POLICIES: [Policy; 2] = [
+    Policy {
+        table: "Drafts",
+        key: "id_record",
+        time: "saved",
+        max_rows_setting: "retention_drafts_max_rows",
+        default_max_rows: 100,
+        max_days_setting: "retention_drafts_max_days",
+        default_max_days: 7,
+    },
+    Policy {
+        table: "ContentHistory",
+        key: "id_version",
+        time: "replaced",
+        max_rows_setting: "retention_content_history_max_rows",
+        default_max_rows: 10_000,
+        max_days_setting: "retention_content_history_max_days",
+        default_max_days: 0,
+    },
+];
+
+impl Policy {
+    /// Returns the limit from the setting, or the default if it is not set or not valid.
+    fn limit(database: &Database, setting: &str, default: u64) -> u64 {
+        database
+            .get_setting(setting)
+            .ok()
+            .and_then(|value| value.expose_secret().parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// Returns the maximum number of rows from the settings, see [`Policy::max_rows_setting`].
+    pub fn max_rows(&self, database: &Database) -> u64 {
+        Policy::limit(database, self.max_rows_setting, self.default_max_rows)
+    }
+
+    /// Returns the maximum age of rows in days from the settings, see [`Policy::max_days_setting`].
+    pub fn max_days(&self, database: &Database) -> u64 {
+        Policy::limit(database, self.max_days_setting, self.default_max_days)
+    }
+}
+
+/// Rows of a table kept and deleted by [`run_retention`].
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct TableRetention {
+    pub table: &'static str,
+    pub kept: u64,
+    pub pruned: u64,
+}
+
+/// Prunes the tables by their [`POLICIES`] in batches of [`BATCH_SIZE`] rows. Nothing is deleted in read-only mode.
+/// # Return
+/// Returns rows kept and deleted in each table.
+/// # Errors
+/// If a table cannot be counted or pruned. Batches deleted before the error stay deleted.
+pub fn run_retention(database: &Database) -> Result<Vec<TableRetention>, Error> {
+    run_retention_in_batches(database, BATCH_SIZE)
+}
+
+/// Prunes the tables like [`run_retention`] with the given batch size.
+fn run_retention_in_batches(
+    database: &Database,
+    batch_size: u64,
+) -> Result<Vec<TableRetention>, Error> {
+    POLICIES
+        .iter()
+        .map(|policy| {
+            let pruned = if database.read_only.is_some() {
+                0
+            } else {
+                database.prune(policy, batch_size)?
+            };
+            Ok(TableRetention {
+                table: policy.table,
+                kept: database.count_rows(policy.table)?,
+                pruned,
+            })
+        })
+        .collect()
+}
+
+impl Database {
+    /// Returns the number of rows in the table, 0 if the table does not exist in a database opened read-only.
+    fn count_rows(&self, table: &str) -> Result<u64, Error> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| "Failed to access database lock")?;
+        let exists: bool = connection
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1);",
+                params![table],
+                |row| row.get(0),
+            )
+            .map_err(Error::database("Failed to count rows"))?;
+        if exists.not() {
+            return Ok(0);
+        }
+        connection
+            .query_row(&format!("SELECT count(*) FROM {table};"), [], |row| {
+                row.get(0)
+            })
+            .map_err(Error::database("Failed to count rows"))
+    }
+
+    /// Deletes rows of the table over the limits of the policy, the oldest first.
+    /// # Return
+    /// Returns the number of deleted rows.
+    fn prune(&self, policy: &Policy, batch_size: u64) -> Result<u64, Error> {
+        let Policy {
+            table, key, time, ..
+        } = policy;
+        let mut pruned = 0;
+        let max_days = policy.max_days(self);
+        if max_days > 0 {
+            let (deleted, _) = self.delete_in_batches(
+                &format!(
+                    "DELETE FROM {table} WHERE {key} IN (
+                        SELECT {key} FROM {table} WHERE julianday({time}) < julianday('now', ?1)
+                        ORDER BY julianday({time}), {key} LIMIT ?2
+                    );"
+                ),
+                (format!("-{max_days} days"), batch_size),
+                batch_size,
+            )?;
+            pruned += deleted;
+        }
+        let max_rows = policy.max_rows(self);
+        if max_rows > 0 {
+            let (deleted, _) = self.delete_in_batches(
+                &format!(
+                    "DELETE FROM {table} WHERE {key} IN (
+                        SELECT {key} FROM {table} ORDER BY julianday({time}), {key}
+                        LIMIT min(?2, max(0, (SELECT count(*) FROM {table}) - ?1))
+                    );"
+                ),
+                (max_rows, batch_size),
+                batch_size,
+            )?;
+            pruned += deleted;
+        }
+        Ok(pruned)
+    }
+
+    /// Executes the delete statement, which deletes at most `batch_size` rows, until it deletes fewer rows. The database lock is acquired for each batch separately.
+    /// # Return
+    /// Returns the number of deleted rows and executed batches.
+    fn delete_in_batches(
+        &self,
+        sql: &str,
+        params: impl Params + Clone,
+        batch_size: u64,
+    ) -> Result<(u64, u64), Error> {
+        let mut deleted = 0;
+        let mut batches = 0;
+        loop {
+            let batch = self
+                .connection
+                .lock()
+                .map_err(|_| "Failed to access database lock")?
+                .execute(sql, params.clone())
+                .map_err(Error::database("Failed to prune table"))? as u64;
+            deleted += batch;
+            batches += 1;
+            if batch < batch_size {
+                return Ok((deleted, batches));
+            }
+        }
+    }
+}
+
+/// Result of the last [`run_retention`] for tauri state, see [`crate::command::database::get_retention_stats`].
+#[derive(Default)]
+pub struct RetentionReport(Mutex<Vec<TableRetention>>);
+
+impl RetentionReport {
+    /// Replaces the result of the last run.
+    pub fn set(&self, retention: Vec<TableRetention>) {
+        if let Ok(mut guard) = self.0.lock() {
+            *guard = retention;
+        }
+    }
+
+    /// Returns rows currently kept in each table and rows deleted by the last run, 0 if it did not run in this session.
+    /// # Errors
+    /// If a table cannot be counted.
+    pub fn stats(&self, database: &Database) -> Result<Vec<TableRetention>, Error> {
+        let last = self
+            .0
+            .lock()
+            .map_err(|_| "Failed to access retention lock")?;
+        POLICIES
+            .iter()
+            .map(|policy| {
+                Ok(TableRetention {
+                    table: policy.table,
+                    kept: database.count_rows(policy.table)?,
+                    pruned: last
+                        .iter()
+                        .find(|retention| retention.table == policy.table)
+                        .map_or(0, |retention| retention.pruned),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn open(directory: &TempDir) -> Database {
+        Database::open_path("password", &directory.path().join("database")).unwrap()
+    }
+
+    /// Inserts drafts with the given ids, each saved the given number of hours ago.
+    fn seed_drafts(database: &Database, drafts: impl IntoIterator<Item = (u64, u64)>) {
+        let connection = database.connection.lock().unwrap();
+        for (id, hours) in drafts {
+            connection
+                .execute(
+                    "INSERT INTO Drafts (id_record, snapshot, saved) VALUES (?1, 'draft', strftime('%Y-%m-%d %H:%M:%f', 'now', ?2));",
+                    params![id, format!("-{hours} hours")],
+                )
+                .unwrap();
+        }
+    }
+
+    fn draft_ids(database: &Database) -> Vec<u64> {
+        let connection = database.connection.lock().unwrap();
+        let mut stmt = connection
+            .prepare("SELECT id_record FROM Drafts ORDER BY id_record;")
+            .unwrap();
+        let ids = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<u64>, _>>()
+            .unwrap();
+        ids
+    }
+
+    #[test]
+    fn test_run_retention() {
+        let directory = TempDir::new().unwrap();
+        let database = open(&directory);
+        // Two drafts are older than 7 days
+        seed_drafts(&database, (1..=30).map(|id| (id, id)));
+        seed_drafts(&database, [(31, 8 * 24), (32, 9 * 24)]);
+        database
+            .save_setting("retention_drafts_max_rows", "10")
+            .unwrap();
+        {
+            let connection = database.connection.lock().unwrap();
+            for version in 0..15 {
+                connection
+                    .execute(
+                        "INSERT INTO ContentHistory (id_content, kind, value, replaced) VALUES (1, 'Password', 'old', datetime('now', ?1));",
+                        params![format!("-{version} days")],
+                    )
+                    .unwrap();
+            }
+        }
+        database
+            .save_setting("retention_content_history_max_rows", "5")
+            .unwrap();
+
+        assert_eq!(
+            run_retention_in_batches(&database, 4).unwrap(),
+            vec![
+                TableRetention {
+                    table: "Drafts",
+                    kept: 10,
+                    pruned: 22
+                },
+                TableRetention {
+                    table: "ContentHistory",
+                    kept: 5,
+                    pruned: 10
+                }
+            ]
+        );
+        assert_eq!(draft_ids(&database), (1..=10).collect::<Vec<_>>());
+        assert_eq!(database.get_content_history(1).unwrap().len(), 5);
+        assert_eq!(
+            run_retention(&database).unwrap()[0],
+            TableRetention {
+                table: "Drafts",
+                kept: 10,
+                pruned: 0
+            }
+        );
+    }
+    #[test]
+    fn test_disabled_limits() {
+        let directory = TempDir::new().unwrap();
+        let database = open(&directory);
+        seed_drafts(&database, (1..=5).map(|id| (id, id * 24 * 30)));
+        database
+            .save_setting("retention_drafts_max_rows", "0")
+            .unwrap();
+        database
+            .save_setting("retention_drafts_max_days", "0")
+            .unwrap();
+        assert_eq!(POLICIES[0].max_rows(&database), 0);
+        assert_eq!(run_retention(&database).unwrap()[0].pruned, 0);
+        assert_eq!(draft_ids(&database).len(), 5);
+
+        database
+            .save_setting("retention_drafts_max_days", "invalid")
+            .unwrap();
+        assert_eq!(POLICIES[0].max_days(&database), 7);
+        assert_eq!(run_retention(&database).unwrap()[0].pruned, 5);
+    }
+    #[test]
+    fn test_delete_in_batches() {
+        let directory = TempDir::new().unwrap();
+        let database = open(&directory);
+        seed_drafts(&database, (1..=10).map(|id| (id, 1)));
+        let sql = "DELETE FROM Drafts WHERE id_record IN (SELECT id_record FROM Drafts ORDER BY id_record LIMIT ?1);";
+        assert_eq!(database.delete_in_batches(sql, [4], 4), Ok((10, 3)));
+        seed_drafts(&database, (1..=8).map(|id| (id, 1)));
+        // Last batch deletes nothing
+        assert_eq!(database.delete_in_batches(sql, [4], 4), Ok((8, 3)));
+        assert_eq!(database.delete_in_batches(sql, [4], 4), Ok((0, 1)));
+    }
+    #[test]
+    fn test_report_stats() {
+        let directory = TempDir::new().unwrap();
+        let database = open(&directory);
+        seed_drafts(&database, [(1, 1), (2, 8 * 24)]);
+        let report = RetentionReport::default();
+        assert_eq!(report.stats(&database).unwrap()[0].kept, 2);
+        assert_eq!(report.stats(&database).unwrap()[0].pruned, 0);
+
+        report.set(run_retention(&database).unwrap());
+        assert_eq!(
+            report.stats(&database).unwrap(),
+            vec![
+                TableRetention {
+                    table: "Drafts",
+                    kept: 1,
+                    pruned: 1
+                },
+                TableRetention {
+                    table: "ContentHistory",
+                    kept: 0,
+                    pruned: 0
+                }
+            ]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::borrow::Cow;
+
+/// Error of the database, the cloud and the commands. Sent to the frontend as an object with a machine-readable `code` and the user-facing `message`, see [`Error::code`]. The underlying error is kept as the source for logs, it is never sent to the frontend.
+///
+/// Errors of other modules are plain messages, they become [`Error::Other`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Password does not open the database.
+    #[error("Invalid password")]
+    InvalidPassword,
+    /// Record, content or another item does not exist.
+    #[error("{0}")]
+    NotFound(&'static str),
+    /// Value sent by the user is not valid.
+    #[error("{0}")]
+    Validation(Cow<'static, str>),
+    /// Locked record was requested and the master password was not verified recently, see [`crate::reauth`].
+    #[error("Enter the master password again")]
+    NeedsReauth,
+    /// Too many failed logins, with the remaining wait in seconds, see [`crate::throttle`].
+    #[error("Too many failed attempts, try again in {0} seconds")]
+    TooManyAttempts(u64),
+    /// Database is used by another process.
+    #[error("Database is locked by another process")]
+    Locked,
+    /// File operation failed.
+    #[error("{message}")]
+    Io {
+        message: &'static str,
+        #[source]
+        source: std::io::Error,
+    },
+    /// SQLite operation failed.
+    #[error("{message}")]
+    Database {
+        message: &'static str,
+        #[source]
+        source: rusqlite::Error,
+    },
+    /// Cloud storage did not respond or refused the request.
+    #[error("{0}")]
+    Network(&'static str),
+    /// Any other error.
+    #[error("{0}")]
+    Other(&'static str),
+}
+
+impl Error {
+    /// Returns the machine-readable code of the error, the name of the variant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::InvalidPassword => "InvalidPassword",
+            Error::NotFound(_) => "NotFound",
+            Error::Validation(_) => "Validation",
+            Error::NeedsReauth => "NeedsReauth",
+            Error::TooManyAttempts(_) => "TooManyAttempts",
+            Error::Locked => "Locked",
+            Error::Io { .. } => "Io",
+            Error::Database { .. } => "Database",
+            Error::Network(_) => "Network",
+            Error::Other(_) => "Other",
+        }
+    }
+
+    /// Returns the user-facing message of the error, the same as its [`std::fmt::Display`].
+    pub fn message(&self) -> Cow<'static, str> {
+        match self {
+            Error::InvalidPassword => Cow::Borrowed("Invalid password"),
+            Error::NotFound(message) | Error::Network(message) | Error::Other(message) => {
+                Cow::Borrowed(message)
+            }
+            Error::Validation(message) => message.clone(),
+            Error::NeedsReauth => Cow::Borrowed("Enter the master password again"),
+            Error::Locked => Cow::Borrowed("Database is locked by another process"),
+            Error::Io { message, .. } | Error::Database { message, .. } => Cow::Borrowed(message),
+            error @ Error::TooManyAttempts(_) => Cow::Owned(error.to_string()),
+        }
+    }
+
+    /// Returns a function mapping I/O errors to [`Error::Io`] with the message.
+    pub fn io(message: &'static str) -> impl Fn(std::io::Error) -> Error {
+        move |source| Error::Io { message, source }
+    }
+
+    /// Returns a function mapping database errors to [`Error::Database`] with the message. If the database is locked by another process, [`Error::Locked`] is returned instead, and [`Error::NotFound`] with the message if a query returned no rows.
+    pub fn database(message: &'static str) -> impl Fn(rusqlite::Error) -> Error {
+        move |source| match source.sqlite_error_code() {
+            Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => {
+                Error::Locked
+            }
+            _ if source == rusqlite::Error::QueryReturnedNoRows => Error::NotFound(message),
+            _ => Error::Database { message, source },
+        }
+    }
+}
+
+impl From<&'static str> for Error {
+    fn from(message: &'static str) -> Self {
+        Error::Other(message)
+    }
+}
+
+/// Errors are equal if they have the same code and message, sources are not compared.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        self.code() == other.code() && self.message() == other.message()
+    }
+}
+
+impl PartialEq<&str> for Error {
+    fn eq(&self, other: &&str) -> bool {
+        self.message() == *other
+    }
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut error = serializer.serialize_struct("Error", 2)?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.message())?;
+        error.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        assert_eq!(
+            serde_json::to_value(Error::InvalidPassword).unwrap(),
+            serde_json::json!({"code": "InvalidPassword", "message": "Invalid password"})
+        );
+        assert_eq!(
+            serde_json::to_value(Error::TooManyAttempts(30)).unwrap(),
+            serde_json::json!({"code": "TooManyAttempts", "message": "Too many failed attempts, try again in 30 seconds"})
+        );
+        assert_eq!(
+            serde_json::to_value(Error::NeedsReauth).unwrap(),
+            serde_json::json!({"code": "NeedsReauth", "message": "Enter the master password again"})
+        );
+        let io = Error::io("Failed to write file")(std::io::Error::other("disk full"));
+        assert_eq!(
+            serde_json::to_value(&io).unwrap(),
+            serde_json::json!({"code": "Io", "message": "Failed to write file"})
+        );
+        assert_eq!(
+            std::error::Error::source(&io).unwrap().to_string(),
+            "disk full"
+        );
+    }
+    #[test]
+    fn test_database() {
+        let busy = rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+            None,
+        );
+        assert_eq!(
+            Error::database("Failed to save record")(busy),
+            Error::Locked
+        );
+        let error = Error::database("Failed to save record")(rusqlite::Error::InvalidQuery);
+        assert_eq!(error.code(), "Database");
+        assert_eq!(error, "Failed to save record");
+        assert_eq!(
+            Error::database("Failed to get record")(rusqlite::Error::QueryReturnedNoRows),
+            Error::NotFound("Failed to get record")
+        );
+    }
+    #[test]
+    fn test_eq() {
+        assert_eq!(Error::from("Failed"), Error::Other("Failed"));
+        assert_ne!(Error::Other("Failed"), Error::Network("Failed"));
+        assert_ne!(Error::Other("Failed"), Error::Other("Other"));
+        assert_eq!(Error::NotFound("Record not found"), "Record not found");
+        assert_eq!(
+            Error::TooManyAttempts(5).message(),
+            Error::TooManyAttempts(5).to_string()
+        );
+        assert_eq!(Error::Locked.message(), Error::Locked.to_string());
+        assert_eq!(Error::NeedsReauth.message(), Error::NeedsReauth.to_string());
+    }
+}
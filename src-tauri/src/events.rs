@@ -0,0 +1,501 @@
+use crate::database::model::{Category, Record};
+use crate::login::LoginStage;
+use crate::session::SessionState;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use std::borrow::Cow;
+use tauri::{AppHandle, Manager, Runtime, Window};
+
+/// Names of all events emitted by the backend. Names are the strings the frontend listens to.
+#[derive(Debug, PartialEq, Clone, Copy, serde::Serialize)]
+pub enum EventName {
+    #[serde(rename = "settings")]
+    Settings,
+    #[serde(rename = "new_record")]
+    NewRecord,
+    #[serde(rename = "upload")]
+    Upload,
+    #[serde(rename = "single-instance")]
+    SingleInstance,
+    #[serde(rename = "import_progress")]
+    ImportProgress,
+    #[serde(rename = "transfer_progress")]
+    TransferProgress,
+    #[serde(rename = "re-mask")]
+    ReMask,
+    #[serde(rename = "session-state")]
+    SessionState,
+    #[serde(rename = "login_progress")]
+    LoginProgress,
+    #[serde(rename = "compromised_progress")]
+    CompromisedProgress,
+}
+
+impl EventName {
+    pub const ALL: [EventName; 10] = [
+        EventName::Settings,
+        EventName::NewRecord,
+        EventName::Upload,
+        EventName::SingleInstance,
+        EventName::ImportProgress,
+        EventName::TransferProgress,
+        EventName::ReMask,
+        EventName::SessionState,
+        EventName::LoginProgress,
+        EventName::CompromisedProgress,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventName::Settings => "settings",
+            EventName::NewRecord => "new_record",
+            EventName::Upload => "upload",
+            EventName::SingleInstance => "single-instance",
+            EventName::ImportProgress => "import_progress",
+            EventName::TransferProgress => "transfer_progress",
+            EventName::ReMask => "re-mask",
+            EventName::SessionState => "session-state",
+            EventName::LoginProgress => "login_progress",
+            EventName::CompromisedProgress => "compromised_progress",
+        }
+    }
+
+    /// Returns JSON schema of the event payload.
+    pub fn payload_schema(&self, generator: &mut SchemaGenerator) -> Schema {
+        match self {
+            EventName::Settings | EventName::Upload => generator.subschema_for::<()>(),
+            EventName::NewRecord => generator.subschema_for::<Record>(),
+            EventName::SingleInstance => generator.subschema_for::<SingleInstancePayload>(),
+            EventName::ImportProgress => generator.subschema_for::<ImportProgress>(),
+            EventName::TransferProgress => generator.subschema_for::<TransferProgress>(),
+            EventName::ReMask => generator.subschema_for::<ReMask>(),
+            EventName::SessionState => generator.subschema_for::<SessionStatePayload>(),
+            EventName::LoginProgress => generator.subschema_for::<LoginProgress>(),
+            EventName::CompromisedProgress => generator.subschema_for::<CompromisedProgress>(),
+        }
+    }
+}
+
+/// Payload of [`EventName::SingleInstance`], arguments and working directory of the second instance.
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct SingleInstancePayload {
+    pub args: Vec<String>,
+    pub cwd: String,
+}
+
+/// Payload of [`EventName::ImportProgress`], number of processed rows out of all rows.
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct ImportProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// Payload of [`EventName::TransferProgress`], number of transferred bytes out of all bytes.
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct TransferProgress {
+    pub transferred: u64,
+    pub total: u64,
+}
+
+/// Payload of [`EventName::ReMask`], token of the reveal that expired or was cancelled, see [`crate::reveal::RevealTimer`].
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct ReMask {
+    pub token: u64,
+}
+
+/// Payload of [`EventName::SessionState`], state the session moved to and when, see [`crate::session::transition`].
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct SessionStatePayload {
+    pub state: SessionState,
+    pub at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Payload of [`EventName::LoginProgress`], stage the login reached and the percentage of the download, see [`crate::login::run_stage`].
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct LoginProgress {
+    pub stage: LoginStage,
+    pub percent: Option<u8>,
+}
+
+/// Payload of [`EventName::CompromisedProgress`], number of checked distinct passwords out of all of them and ids of records found compromised since the previous event, see [`crate::command::database::get_compromised_records`].
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct CompromisedProgress {
+    pub checked: usize,
+    pub total: usize,
+    pub records: Vec<u64>,
+}
+
+/// Event with its payload.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Event {
+    /// Opens settings.
+    Settings,
+    /// Opens a new record of the category.
+    NewRecord(Category),
+    /// Starts database upload to the cloud.
+    Upload,
+    /// Second instance of the application was launched.
+    SingleInstance(SingleInstancePayload),
+    /// Import processed another row.
+    ImportProgress(ImportProgress),
+    /// One-off database transfer sent or received more bytes.
+    TransferProgress(TransferProgress),
+    /// Revealed value should be masked again.
+    ReMask(ReMask),
+    /// Vault was unlocked or is being locked.
+    SessionState(SessionStatePayload),
+    /// Login reached another stage or downloaded more of the cloud database.
+    LoginProgress(LoginProgress),
+    /// Search for compromised passwords checked more passwords.
+    CompromisedProgress(CompromisedProgress),
+}
+
+impl Event {
+    pub fn name(&self) -> EventName {
+        match self {
+            Event::Settings => EventName::Settings,
+            Event::NewRecord(_) => EventName::NewRecord,
+            Event::Upload => EventName::Upload,
+            Event::SingleInstance(_) => EventName::SingleInstance,
+            Event::ImportProgress(_) => EventName::ImportProgress,
+            Event::TransferProgress(_) => EventName::TransferProgress,
+            Event::ReMask(_) => EventName::ReMask,
+            Event::SessionState(_) => EventName::SessionState,
+            Event::LoginProgress(_) => EventName::LoginProgress,
+            Event::CompromisedProgress(_) => EventName::CompromisedProgress,
+        }
+    }
+
+    /// Returns the payload serialized to JSON.
+    pub fn payload(&self) -> serde_json::Value {
+        match self {
+            Event::Settings | Event::Upload => serde_json::Value::Null,
+            Event::NewRecord(category) => serde_json::to_value(Record::new(
+                "".to_string(),
+                "".to_string(),
+                category.clone(),
+            ))
+            .unwrap_or_default(),
+            Event::SingleInstance(payload) => serde_json::to_value(payload).unwrap_or_default(),
+            Event::ImportProgress(payload) => serde_json::to_value(payload).unwrap_or_default(),
+            Event::TransferProgress(payload) => serde_json::to_value(payload).unwrap_or_default(),
+            Event::ReMask(payload) => serde_json::to_value(payload).unwrap_or_default(),
+            Event::SessionState(payload) => serde_json::to_value(payload).unwrap_or_default(),
+            Event::LoginProgress(payload) => serde_json::to_value(payload).unwrap_or_default(),
+            Event::CompromisedProgress(payload) => {
+                serde_json::to_value(payload).unwrap_or_default()
+            }
+        }
+    }
+}
+
+/// Emits the event to the main window.
+pub fn emit_to_main(app_handle: &AppHandle, event: Event) -> tauri::Result<()> {
+    app_handle.emit_to("main", event.name().as_str(), event.payload())
+}
+
+/// Emits the event to the window only, e.g. progress of the login to the login window.
+pub fn emit_to_window<R: Runtime>(window: &Window<R>, event: Event) -> tauri::Result<()> {
+    window.emit(event.name().as_str(), event.payload())
+}
+
+/// Emits the event to all windows.
+pub fn emit_all<R: Runtime>(app_handle: &AppHandle<R>, event: Event) -> tauri::Result<()> {
+    app_handle.emit_all(event.name().as_str(), event.payload())
+}
+
+/// Event name with JSON schema of its payload, see [`EventName::payload_schema`].
+#[derive(Debug, serde::Serialize)]
+pub struct EventDescription {
+    name: EventName,
+    payload: Schema,
+}
+
+/// All events with JSON schemas of their payloads. Payload schemas refer to shared definitions in `$defs`.
+#[derive(Debug, serde::Serialize)]
+pub struct EventSchemas {
+    events: Vec<EventDescription>,
+    #[serde(rename = "$defs")]
+    definitions: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Returns all events with JSON schemas of their payloads.
+pub fn describe() -> EventSchemas {
+    let mut generator = SchemaGenerator::default();
+    let events = EventName::ALL
+        .iter()
+        .map(|name| EventDescription {
+            name: *name,
+            payload: name.payload_schema(&mut generator),
+        })
+        .collect();
+    EventSchemas {
+        events,
+        definitions: generator.take_definitions(true),
+    }
+}
+
+impl JsonSchema for Category {
+    fn schema_name() -> Cow<'static, str> {
+        "Category".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "description": "Unknown categories are kept as they are",
+            "examples": ["Login", "Bank Card", "Note", "Other"]
+        })
+    }
+}
+
+impl JsonSchema for Record {
+    fn schema_name() -> Cow<'static, str> {
+        "Record".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "integer", "minimum": 0 },
+                "title": { "type": "string" },
+                "subtitle": { "type": "string" },
+                "category": generator.subschema_for::<Category>(),
+                "icon": { "type": "string" },
+                "created": { "type": "string", "format": "date-time" },
+                "last_modified": { "type": "string", "format": "date-time" },
+                "last_modified_relative": { "type": "string" },
+                "locked": { "type": "boolean" },
+                "favorite": { "type": "boolean" }
+            },
+            "required": ["id", "title", "subtitle", "category", "icon", "created", "last_modified", "last_modified_relative", "locked", "favorite"]
+        })
+    }
+}
+
+impl JsonSchema for SingleInstancePayload {
+    fn schema_name() -> Cow<'static, str> {
+        "SingleInstancePayload".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "object",
+            "properties": {
+                "args": generator.subschema_for::<Vec<String>>(),
+                "cwd": { "type": "string" }
+            },
+            "required": ["args", "cwd"]
+        })
+    }
+}
+
+impl JsonSchema for ImportProgress {
+    fn schema_name() -> Cow<'static, str> {
+        "ImportProgress".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "object",
+            "properties": {
+                "processed": { "type": "integer", "minimum": 0 },
+                "total": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["processed", "total"]
+        })
+    }
+}
+
+impl JsonSchema for TransferProgress {
+    fn schema_name() -> Cow<'static, str> {
+        "TransferProgress".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "object",
+            "properties": {
+                "transferred": { "type": "integer", "minimum": 0 },
+                "total": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["transferred", "total"]
+        })
+    }
+}
+
+impl JsonSchema for ReMask {
+    fn schema_name() -> Cow<'static, str> {
+        "ReMask".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "object",
+            "properties": {
+                "token": { "type": "integer", "minimum": 0 }
+            },
+            "required": ["token"]
+        })
+    }
+}
+
+impl JsonSchema for SessionState {
+    fn schema_name() -> Cow<'static, str> {
+        "SessionState".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "enum": ["Unlocked", "Locked", "LockedByPanic"]
+        })
+    }
+}
+
+impl JsonSchema for SessionStatePayload {
+    fn schema_name() -> Cow<'static, str> {
+        "SessionStatePayload".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "object",
+            "properties": {
+                "state": generator.subschema_for::<SessionState>(),
+                "at": { "type": "string", "format": "date-time" }
+            },
+            "required": ["state", "at"]
+        })
+    }
+}
+
+impl JsonSchema for LoginStage {
+    fn schema_name() -> Cow<'static, str> {
+        "LoginStage".into()
+    }
+
+    fn json_schema(_generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "string",
+            "enum": ["ConnectingToCloud", "ComparingVersions", "Downloading", "OpeningVault"]
+        })
+    }
+}
+
+impl JsonSchema for LoginProgress {
+    fn schema_name() -> Cow<'static, str> {
+        "LoginProgress".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "object",
+            "properties": {
+                "stage": generator.subschema_for::<LoginStage>(),
+                "percent": { "type": ["integer", "null"], "minimum": 0, "maximum": 100 }
+            },
+            "required": ["stage", "percent"]
+        })
+    }
+}
+
+impl JsonSchema for CompromisedProgress {
+    fn schema_name() -> Cow<'static, str> {
+        "CompromisedProgress".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        json_schema!({
+            "type": "object",
+            "properties": {
+                "checked": { "type": "integer", "minimum": 0 },
+                "total": { "type": "integer", "minimum": 0 },
+                "records": generator.subschema_for::<Vec<u64>>()
+            },
+            "required": ["checked", "total", "records"]
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_name_serialize() {
+        for name in EventName::ALL {
+            assert_eq!(
+                serde_json::to_string(&name).unwrap(),
+                format!("\"{}\"", name.as_str())
+            );
+        }
+        assert_eq!(
+            EventName::ALL.map(|name| name.as_str()),
+            [
+                "settings",
+                "new_record",
+                "upload",
+                "single-instance",
+                "import_progress",
+                "transfer_progress",
+                "re-mask",
+                "session-state",
+                "login_progress",
+                "compromised_progress"
+            ]
+        );
+    }
+    #[test]
+    fn test_event_payload() {
+        assert_eq!(Event::Settings.payload(), serde_json::Value::Null);
+        let payload = Event::NewRecord(Category::BankCard).payload();
+        assert_eq!(payload["category"], "Bank Card");
+        assert_eq!(payload["icon"], "credit-card");
+        assert_eq!(payload["id"], 0);
+        assert_eq!(
+            Event::SingleInstance(SingleInstancePayload {
+                args: vec!["--hidden".to_string()],
+                cwd: "/".to_string(),
+            })
+            .payload(),
+            serde_json::json!({"args": ["--hidden"], "cwd": "/"})
+        );
+        assert_eq!(
+            Event::ReMask(ReMask { token: 3 }).payload(),
+            serde_json::json!({"token": 3})
+        );
+        let at = chrono::Utc::now();
+        assert_eq!(
+            Event::SessionState(SessionStatePayload {
+                state: SessionState::LockedByPanic,
+                at,
+            })
+            .payload(),
+            serde_json::json!({"state": "LockedByPanic", "at": at})
+        );
+        assert_eq!(
+            Event::LoginProgress(LoginProgress {
+                stage: LoginStage::Downloading,
+                percent: Some(40),
+            })
+            .payload(),
+            serde_json::json!({"stage": "Downloading", "percent": 40})
+        );
+    }
+    #[test]
+    fn test_describe() {
+        let schemas = serde_json::to_value(describe()).unwrap();
+        assert_eq!(
+            schemas["events"].as_array().unwrap().len(),
+            EventName::ALL.len()
+        );
+        assert_eq!(schemas["events"][1]["name"], "new_record");
+        assert_eq!(schemas["events"][1]["payload"]["$ref"], "#/$defs/Record");
+        assert_eq!(
+            schemas["$defs"]["Record"]["properties"]["category"]["$ref"],
+            "#/$defs/Category"
+        );
+        assert_eq!(schemas["events"][0]["payload"]["type"], "null");
+    }
+}
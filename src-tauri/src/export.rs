@@ -0,0 +1,499 @@
+use crate::database::model::value::ToSecretString;
+use crate::database::model::{Content, Record, Value};
+use crate::import::Entry;
+use secrecy::ExposeSecret;
+use std::collections::HashMap;
+use std::ops::Not;
+use zeroize::Zeroizing;
+
+/// Header of the exported CSV file.
+pub const HEADER: [&str; 6] = ["title", "subtitle", "category", "label", "kind", "value"];
+
+/// Pairs the records with their content for [`to_csv`]. Locked records are left out unless `include_locked` is set, like [`crate::archive::collect`].
+/// # Return
+/// Returns the exported records and the number of records that were left out because they are locked.
+pub fn collect(
+    records: Vec<Record>,
+    mut content: HashMap<u64, Vec<Content>>,
+    include_locked: bool,
+) -> (Vec<Entry>, usize) {
+    let mut entries = Vec::new();
+    let mut locked = 0;
+    for record in records {
+        if record.locked() && include_locked.not() {
+            locked += 1;
+            continue;
+        }
+        let content = content.remove(&record.id()).unwrap_or_default();
+        entries.push((record, content));
+    }
+    (entries, locked)
+}
+
+/// Serializes records to CSV with one row for every content, including passwords and TOTP secrets in plain text. Records without content get a single row with empty label, kind and value. Structured values are exported as JSON, like they are stored in the database.
+/// # Errors
+/// If a row cannot be written.
+pub fn to_csv(entries: &[Entry]) -> Result<Zeroizing<Vec<u8>>, &'static str> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(HEADER)
+        .map_err(|_| "Failed to write CSV")?;
+    for (record, content) in entries {
+        let fields = [
+            record.title(),
+            record.subtitle(),
+            record.category().as_str(),
+        ];
+        if content.is_empty() {
+            writer
+                .write_record(fields.iter().chain(&["", "", ""]))
+                .map_err(|_| "Failed to write CSV")?;
+        }
+        for content in content {
+            let value = content.value().to_secret_string();
+            writer
+                .write_record(fields.iter().chain(&[
+                    content.label(),
+                    content.kind(),
+                    value.expose_secret(),
+                ]))
+                .map_err(|_| "Failed to write CSV")?;
+        }
+    }
+    writer
+        .into_inner()
+        .map(Zeroizing::new)
+        .map_err(|_| "Failed to write CSV")
+}
+
+/// Header of the inventory CSV file.
+pub const INVENTORY_HEADER: [&str; 5] = ["title", "category", "urls", "usernames", "locked"];
+
+/// Labels of text content exported as usernames to the inventory, compared case-insensitively.
+const USERNAME_LABELS: [&str; 3] = ["user", "username", "login"];
+
+/// Record in the inventory, what accounts exist without any secrets. It has no field for values of sensitive kinds, only URLs, emails and text labeled as a username are taken from the content, see [`InventoryItem::new`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct InventoryItem {
+    pub title: String,
+    pub category: String,
+    pub urls: Vec<String>,
+    pub usernames: Vec<String>,
+    /// Locked records are listed with a marker and without anything from their content.
+    pub locked: bool,
+}
+
+impl InventoryItem {
+    /// Takes the title and category of the record, and URLs and usernames from its content unless it is locked. Values are taken by their kind, labels only select usernames among text values.
+    pub fn new(record: &Record, content: &[Content]) -> InventoryItem {
+        let mut urls = Vec::new();
+        let mut usernames = Vec::new();
+        if record.locked().not() {
+            for content in content {
+                match content.value() {
+                    Value::Url(url) => urls.push(url.value().to_string()),
+                    Value::Email(email) => usernames.push(email.value().to_string()),
+                    Value::Text(text)
+                        if USERNAME_LABELS.contains(&content.label().to_lowercase().as_str()) =>
+                    {
+                        usernames.push(text.value().to_string())
+                    }
+                    _ => {}
+                }
+            }
+        }
+        InventoryItem {
+            title: record.title().to_string(),
+            category: record.category().as_str().to_string(),
+            urls,
+            usernames,
+            locked: record.locked(),
+        }
+    }
+}
+
+/// Returns the footer of the inventory with the number of records.
+fn inventory_footer(items: &[InventoryItem]) -> String {
+    match items.len() {
+        1 => "1 record".to_string(),
+        count => format!("{count} records"),
+    }
+}
+
+/// Serializes the inventory to CSV with one row for every record, multiple URLs and usernames are separated by new lines. The last row is the footer with the number of records.
+/// # Errors
+/// If a row cannot be written.
+pub fn to_inventory_csv(items: &[InventoryItem]) -> Result<Vec<u8>, &'static str> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(INVENTORY_HEADER)
+        .map_err(|_| "Failed to write CSV")?;
+    for item in items {
+        writer
+            .write_record([
+                item.title.as_str(),
+                item.category.as_str(),
+                &item.urls.join("\n"),
+                &item.usernames.join("\n"),
+                if item.locked { "locked" } else { "" },
+            ])
+            .map_err(|_| "Failed to write CSV")?;
+    }
+    writer
+        .write_record([inventory_footer(items).as_str(), "", "", "", ""])
+        .map_err(|_| "Failed to write CSV")?;
+    writer.into_inner().map_err(|_| "Failed to write CSV")
+}
+
+/// Lines of text on one page of the inventory PDF.
+const PDF_LINES_PER_PAGE: usize = 60;
+
+/// Maximum number of characters of a line in the inventory PDF, longer lines are cut.
+const PDF_LINE_LENGTH: usize = 95;
+
+/// Escapes the line for a string in a PDF content stream. The standard Helvetica font is used with WinAnsi encoding, Latin-1 characters are written as octal escapes and other characters are replaced with `?`.
+fn pdf_string(line: &str) -> String {
+    let mut escaped = String::with_capacity(line.len());
+    for (index, character) in line.chars().enumerate() {
+        if index == PDF_LINE_LENGTH {
+            escaped.push_str("...");
+            break;
+        }
+        match character {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(character);
+            }
+            ' '..='~' => escaped.push(character),
+            '\u{A0}'..='\u{FF}' => escaped.push_str(&format!("\\{:03o}", character as u32)),
+            character if character.is_control() => escaped.push(' '),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+/// Renders the inventory as a printable A4 PDF with a line for every record and indented lines with its URLs and usernames. Locked records are marked. The last line is the footer with the number of records.
+pub fn to_inventory_pdf(items: &[InventoryItem]) -> Vec<u8> {
+    let mut lines = vec!["Inventory".to_string(), String::new()];
+    for item in items {
+        let marker = if item.locked { " [locked]" } else { "" };
+        lines.push(format!("{} ({}){marker}", item.title, item.category));
+        lines.extend(item.urls.iter().map(|url| format!("    URL: {url}")));
+        lines.extend(
+            item.usernames
+                .iter()
+                .map(|username| format!("    Username: {username}")),
+        );
+    }
+    lines.push(String::new());
+    lines.push(inventory_footer(items));
+
+    // Catalog, pages and font are the first three objects, then a page and its content for every page
+    let pages: Vec<&[String]> = lines.chunks(PDF_LINES_PER_PAGE).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..pages.len())
+                .map(|page| format!("{} 0 R", 4 + page * 2))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    ];
+    for (page, lines) in pages.iter().enumerate() {
+        let mut stream = "BT /F1 10 Tf 13 TL 50 800 Td\n".to_string();
+        for line in lines.iter() {
+            stream.push_str(&format!("({}) Tj T*\n", pdf_string(line)));
+        }
+        stream.push_str("ET");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            5 + page * 2
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{stream}\nendstream",
+            stream.len()
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", index + 1).as_bytes());
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::model::value;
+    use crate::database::model::Category;
+
+    #[test]
+    fn test_to_csv() {
+        let entries = vec![
+            (
+                Record::new("Mail".to_string(), "john".to_string(), Category::Login),
+                vec![
+                    Content::new(
+                        "Password".to_string(),
+                        0,
+                        true,
+                        Value::Password(value::Password::new("p,a\"ss".to_string())),
+                    ),
+                    Content::new(
+                        "Note".to_string(),
+                        1,
+                        false,
+                        Value::LongText(value::LongText::new("first\nsecond".to_string())),
+                    ),
+                ],
+            ),
+            (
+                Record::new("Empty".to_string(), String::new(), Category::BankCard),
+                Vec::new(),
+            ),
+        ];
+        let csv = to_csv(&entries).unwrap();
+        let mut reader = csv::Reader::from_reader(csv.as_slice());
+        assert_eq!(reader.headers().unwrap(), HEADER.as_slice());
+        let rows: Vec<Vec<String>> = reader
+            .records()
+            .map(|row| row.unwrap().iter().map(str::to_string).collect())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["Mail", "john", "Login", "Password", "Password", "p,a\"ss"],
+                vec!["Mail", "john", "Login", "Note", "LongText", "first\nsecond"],
+                vec!["Empty", "", "BankCard", "", "", ""],
+            ]
+        );
+    }
+    #[test]
+    fn test_collect() {
+        let mut mail = Record::new("Mail".to_string(), String::new(), Category::Login);
+        mail.set_id(1);
+        let mut bank = Record::new("Bank".to_string(), String::new(), Category::BankCard);
+        bank.set_id(2);
+        bank.set_locked(true);
+        let password = || {
+            vec![Content::new(
+                "Password".to_string(),
+                0,
+                true,
+                Value::Password(value::Password::new("secret".to_string())),
+            )]
+        };
+        let content = || HashMap::from([(1, password()), (2, password())]);
+
+        let (entries, locked) = collect(vec![mail.clone(), bank.clone()], content(), false);
+        assert_eq!(entries, vec![(mail.clone(), password())]);
+        assert_eq!(locked, 1);
+        let (entries, locked) = collect(vec![mail, bank], content(), true);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].1, password());
+        assert_eq!(locked, 0);
+    }
+    #[test]
+    fn test_to_csv_empty() {
+        assert_eq!(
+            to_csv(&[]).unwrap().as_slice(),
+            b"title,subtitle,category,label,kind,value\n"
+        );
+    }
+    fn inventory_entries() -> Vec<Entry> {
+        let mut locked = Record::new("Bank".to_string(), String::new(), Category::Login);
+        locked.set_locked(true);
+        vec![
+            (
+                Record::new("Mail".to_string(), "john".to_string(), Category::Login),
+                vec![
+                    Content::new(
+                        "User".to_string(),
+                        0,
+                        true,
+                        Value::Text(value::Text::new("john".to_string())),
+                    ),
+                    Content::new(
+                        "Password".to_string(),
+                        1,
+                        true,
+                        Value::Password(value::Password::new("secret".to_string())),
+                    ),
+                    Content::new(
+                        "Website".to_string(),
+                        2,
+                        false,
+                        Value::Url(value::Url::new("https://mail.com".to_string()).unwrap()),
+                    ),
+                    Content::new(
+                        "Note".to_string(),
+                        3,
+                        false,
+                        Value::Text(value::Text::new("not a username".to_string())),
+                    ),
+                ],
+            ),
+            (
+                locked,
+                vec![Content::new(
+                    "Website".to_string(),
+                    0,
+                    false,
+                    Value::Url(value::Url::new("https://bank.com".to_string()).unwrap()),
+                )],
+            ),
+        ]
+    }
+    #[test]
+    fn test_inventory_item() {
+        let items: Vec<InventoryItem> = inventory_entries()
+            .iter()
+            .map(|(record, content)| InventoryItem::new(record, content))
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                InventoryItem {
+                    title: "Mail".to_string(),
+                    category: "Login".to_string(),
+                    urls: vec!["https://mail.com".to_string()],
+                    usernames: vec!["john".to_string()],
+                    locked: false,
+                },
+                InventoryItem {
+                    title: "Bank".to_string(),
+                    category: "Login".to_string(),
+                    urls: Vec::new(),
+                    usernames: Vec::new(),
+                    locked: true,
+                },
+            ]
+        );
+    }
+    #[test]
+    fn test_inventory_sensitive_label() {
+        let record = Record::new("Crafted".to_string(), String::new(), Category::Other);
+        let content = [
+            Content::new(
+                "URL".to_string(),
+                0,
+                false,
+                Value::Password(value::Password::new("leaked-password".to_string())),
+            ),
+            Content::new(
+                "Username".to_string(),
+                1,
+                false,
+                Value::SensitiveText(value::SensitiveText::new("leaked-text".to_string())),
+            ),
+            Content::new(
+                "User".to_string(),
+                2,
+                false,
+                Value::TOTPSecret(
+                    value::TOTPSecret::new("RFFFMAZ4JSJQ3QURWHZNA2WLJASTMYWV".to_string()).unwrap(),
+                ),
+            ),
+        ];
+        let items = [InventoryItem::new(&record, &content)];
+        assert!(items[0].urls.is_empty());
+        assert!(items[0].usernames.is_empty());
+        for output in [to_inventory_csv(&items).unwrap(), to_inventory_pdf(&items)] {
+            let output = String::from_utf8_lossy(&output);
+            assert!(output.contains("Crafted"));
+            assert!(output.contains("leaked").not());
+            assert!(output.contains("RFFFMAZ4").not());
+        }
+    }
+    #[test]
+    fn test_to_inventory_csv() {
+        let items: Vec<InventoryItem> = inventory_entries()
+            .iter()
+            .map(|(record, content)| InventoryItem::new(record, content))
+            .collect();
+        let csv = to_inventory_csv(&items).unwrap();
+        let mut reader = csv::Reader::from_reader(csv.as_slice());
+        assert_eq!(reader.headers().unwrap(), INVENTORY_HEADER.as_slice());
+        let rows: Vec<Vec<String>> = reader
+            .records()
+            .map(|row| row.unwrap().iter().map(str::to_string).collect())
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["Mail", "Login", "https://mail.com", "john", ""],
+                vec!["Bank", "Login", "", "", "locked"],
+                vec!["2 records", "", "", "", ""],
+            ]
+        );
+        assert!(String::from_utf8(to_inventory_csv(&items[..1]).unwrap())
+            .unwrap()
+            .ends_with("1 record,,,,\n"));
+    }
+    #[test]
+    fn test_to_inventory_pdf() {
+        let items: Vec<InventoryItem> = (0..40)
+            .map(|index| InventoryItem {
+                title: format!("Record (é) {index}"),
+                category: "Login".to_string(),
+                urls: vec!["https://example.com".to_string()],
+                usernames: Vec::new(),
+                locked: index == 0,
+            })
+            .collect();
+        let pdf = to_inventory_pdf(&items);
+        let text = String::from_utf8(pdf.clone()).unwrap();
+        assert!(text.starts_with("%PDF-1.4\n"));
+        assert!(text.ends_with("%%EOF\n"));
+        // 84 lines are on two pages
+        assert!(text.contains("/Count 2"));
+        assert!(text.contains("(Record \\(\\351\\) 0 \\(Login\\) [locked]) Tj"));
+        assert!(text.contains("(    URL: https://example.com) Tj"));
+        assert!(text.contains("(40 records) Tj"));
+
+        // Offsets in the cross-reference table point to the objects
+        let xref = text.find("\nxref\n").unwrap() + 1;
+        assert_eq!(
+            text[text.rfind("startxref\n").unwrap() + 10..]
+                .lines()
+                .next()
+                .unwrap(),
+            xref.to_string()
+        );
+        for (index, entry) in text[xref..].lines().skip(3).take(7).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(text[offset..].starts_with(&format!("{} 0 obj", index + 1)));
+        }
+    }
+    #[test]
+    fn test_pdf_string() {
+        assert_eq!(pdf_string("a(b)\\c"), "a\\(b\\)\\\\c");
+        assert_eq!(pdf_string("Žluť\t"), "?lu? ");
+        assert_eq!(
+            pdf_string(&"a".repeat(100)),
+            format!("{}...", "a".repeat(95))
+        );
+    }
+}
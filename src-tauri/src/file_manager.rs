@@ -0,0 +1,212 @@
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+/// Location that can be revealed in the file manager. Paths are always resolved by the backend, the frontend can not pass arbitrary paths.
+#[derive(Debug, PartialEq, Clone, Copy, serde::Deserialize)]
+pub enum PathKind {
+    /// Database file in the app local data directory.
+    Database,
+    /// File of the last export in this session.
+    LastExport,
+}
+
+/// Path of the last export for tauri state. Lives only for the current session.
+#[derive(Default)]
+pub struct LastExport(Mutex<Option<PathBuf>>);
+
+impl LastExport {
+    /// Records path of a finished export.
+    pub fn set(&self, path: PathBuf) {
+        if let Ok(mut guard) = self.0.lock() {
+            *guard = Some(path);
+        }
+    }
+
+    pub fn get(&self) -> Option<PathBuf> {
+        self.0.lock().ok().and_then(|guard| guard.clone())
+    }
+}
+
+/// Checks if the path is inside the directory. Both paths are canonicalized, so symbolic links and `..` can not escape the directory.
+fn is_inside(path: &Path, directory: &Path) -> bool {
+    match (path.canonicalize(), directory.canonicalize()) {
+        (Ok(path), Ok(directory)) => path.starts_with(directory),
+        _ => false,
+    }
+}
+
+/// Resolves the kind to an existing path.
+/// - Database: must be inside the app data directory
+/// - LastExport: must be the recorded export path
+/// # Errors
+/// If the path is not known, does not exist or is not allowed
+pub fn resolve(
+    kind: PathKind,
+    database_path: Option<PathBuf>,
+    data_directory: Option<PathBuf>,
+    last_export: Option<PathBuf>,
+) -> Result<PathBuf, &'static str> {
+    let path = match kind {
+        PathKind::Database => {
+            let path = database_path.ok_or("Failed to get database path")?;
+            let directory = data_directory.ok_or("Failed to get data directory path")?;
+            if is_inside(&path, &directory).not() {
+                return Err("Path is not allowed");
+            }
+            path
+        }
+        PathKind::LastExport => last_export.ok_or("Nothing was exported yet")?,
+    };
+    if path.exists().not() {
+        return Err("File does not exist");
+    }
+    Ok(path)
+}
+
+/// Opens the file manager with the file selected.
+/// - macOS: `open -R`
+/// - Linux: `org.freedesktop.FileManager1.ShowItems` over D-Bus, if it fails the parent directory is opened by `xdg-open`
+/// # Errors
+/// If the file manager cannot be opened
+pub fn reveal(path: &Path) -> Result<(), &'static str> {
+    #[cfg(target_os = "macos")]
+    let status = Command::new("open").arg("-R").arg(path).status();
+
+    #[cfg(not(target_os = "macos"))]
+    let status = {
+        let uri = tauri::Url::from_file_path(path).map_err(|_| "Invalid path")?;
+        Command::new("dbus-send")
+            .args([
+                "--session",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+            ])
+            .arg(format!("array:string:{uri}"))
+            .arg("string:")
+            .status()
+            .ok()
+            .filter(|status| status.success())
+            .map(Ok)
+            .unwrap_or_else(|| {
+                Command::new("xdg-open")
+                    .arg(path.parent().ok_or("Invalid path")?)
+                    .status()
+                    .map_err(|_| "Failed to open file manager")
+            })
+    };
+
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        _ => Err("Failed to open file manager"),
+    }
+}
+
+/// Writes the data to the file atomically. Data is written to a temporary file in the same directory, which then replaces the file, so an interrupted write never leaves a partial file.
+/// # Errors
+/// If the temporary file cannot be written or renamed. The temporary file is removed in that case.
+pub fn atomic_write(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Invalid path"))?
+        .to_string_lossy();
+    let temporary = path.with_file_name(format!(".{name}.tmp"));
+    let result = std::fs::File::create(&temporary)
+        .and_then(|mut file| {
+            std::io::Write::write_all(&mut file, data)?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&temporary, path));
+    if result.is_err() {
+        std::fs::remove_file(&temporary).unwrap_or_default();
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_atomic_write() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("file.csv");
+        std::fs::write(&path, "old").unwrap();
+        atomic_write(&path, b"new").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 1);
+        assert!(atomic_write(&directory.path().join("missing").join("file"), b"new").is_err());
+    }
+    #[test]
+    fn test_path_kind_deserialize() {
+        assert_eq!(
+            serde_json::from_str::<PathKind>(r#""Database""#).unwrap(),
+            PathKind::Database
+        );
+        assert!(serde_json::from_str::<PathKind>(r#""/etc/passwd""#).is_err());
+    }
+    #[test]
+    fn test_resolve_database() {
+        let directory = TempDir::new().unwrap();
+        let data = directory.path().join("data");
+        std::fs::create_dir(&data).unwrap();
+        let database = data.join("database");
+        let outside = directory.path().join("database");
+
+        assert_eq!(
+            resolve(
+                PathKind::Database,
+                Some(database.clone()),
+                Some(data.clone()),
+                None
+            ),
+            Err("Path is not allowed")
+        );
+
+        std::fs::write(&database, "").unwrap();
+        std::fs::write(&outside, "").unwrap();
+        assert_eq!(
+            resolve(
+                PathKind::Database,
+                Some(database.clone()),
+                Some(data.clone()),
+                None
+            ),
+            Ok(database)
+        );
+        assert_eq!(
+            resolve(
+                PathKind::Database,
+                Some(data.join("..").join("database")),
+                Some(data),
+                None
+            ),
+            Err("Path is not allowed")
+        );
+    }
+    #[test]
+    fn test_resolve_last_export() {
+        let directory = TempDir::new().unwrap();
+        let export = directory.path().join("export");
+        let last_export = LastExport::default();
+
+        assert_eq!(
+            resolve(PathKind::LastExport, None, None, last_export.get()),
+            Err("Nothing was exported yet")
+        );
+        last_export.set(export.clone());
+        assert_eq!(
+            resolve(PathKind::LastExport, None, None, last_export.get()),
+            Err("File does not exist")
+        );
+        std::fs::write(&export, "").unwrap();
+        assert_eq!(
+            resolve(PathKind::LastExport, None, None, last_export.get()),
+            Ok(export)
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use crate::database::model::{value, Category, Content, Record, Value};
+use crate::database::Database;
+use crate::error::Error;
+use crate::import::Entry;
+use chrono::{Duration, TimeZone, Utc};
+use std::path::Path;
+
+/// What to generate in a fixture vault. The same spec always generates the same records.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct FixtureSpec {
+    /// Number of generated records.
+    pub records: usize,
+    /// Seed of the random number generator.
+    pub seed: u64,
+}
+
+impl Default for FixtureSpec {
+    fn default() -> Self {
+        FixtureSpec {
+            records: 50,
+            seed: 1,
+        }
+    }
+}
+
+/// Titles of generated records, including unicode and edge cases.
+const TITLES: [&str; 12] = [
+    "Mail",
+    "Bank",
+    "Pošta",
+    "Přihlášení do školy",
+    "日本語のサイト",
+    "Ελληνικά",
+    "שלום",
+    "Emoji 🔐🗝️",
+    "Title with \"quotes\" & <tags>",
+    "  Leading and trailing spaces  ",
+    "Zero\u{200B}width",
+    "Work",
+];
+
+/// Passwords that are known to be compromised.
+const COMPROMISED: [&str; 5] = ["123456", "password", "qwerty123", "letmein", "iloveyou"];
+
+/// Users of generated logins.
+const USERS: [&str; 5] = ["john", "jane.doe", "admin", "jiří", "user+tag"];
+
+/// Deterministic random number generator, SplitMix64. Not suitable for anything but fixtures.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Returns a number lower than `bound`.
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+    /// Returns `true` with probability of one in `n`.
+    fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+    fn string(&mut self, alphabet: &[u8], length: usize) -> String {
+        (0..length)
+            .map(|_| alphabet[self.below(alphabet.len())] as char)
+            .collect()
+    }
+    fn digits(&mut self, length: usize) -> String {
+        self.string(b"0123456789", length)
+    }
+}
+
+/// Returns a Visa card number with a valid Luhn check digit.
+fn card_number(rng: &mut Rng) -> String {
+    let mut number = format!("4{}", rng.digits(14));
+    let sum: u32 = number
+        .chars()
+        .rev()
+        .filter_map(|digit| digit.to_digit(10))
+        .enumerate()
+        .map(|(index, digit)| match index % 2 {
+            0 if digit * 2 > 9 => digit * 2 - 9,
+            0 => digit * 2,
+            _ => digit,
+        })
+        .sum();
+    number.push_str(&((10 - sum % 10) % 10).to_string());
+    number
+}
+
+fn login(rng: &mut Rng, title: &str) -> Result<Vec<Content>, &'static str> {
+    let user = rng.pick(&USERS);
+    let host = format!(
+        "{}.example.com",
+        rng.string(b"abcdefghijklmnopqrstuvwxyz", 6)
+    );
+    let password = if rng.one_in(4) {
+        rng.pick(&COMPROMISED).to_string()
+    } else {
+        rng.string(
+            b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!@#$%^&*",
+            16,
+        )
+    };
+    let mut content = vec![
+        Content::new(
+            "Website".to_string(),
+            0,
+            true,
+            Value::Url(value::Url::new(format!("https://{host}/login?next=%2F"))?),
+        ),
+        Content::new(
+            "User".to_string(),
+            1,
+            true,
+            Value::Text(value::Text::new(user.to_string())),
+        ),
+        Content::new(
+            "Password".to_string(),
+            2,
+            true,
+            Value::Password(value::Password::new(password)),
+        ),
+    ];
+    if rng.one_in(3) {
+        content.push(Content::new(
+            "TOTP".to_string(),
+            3,
+            false,
+            Value::TOTPSecret(value::TOTPSecret::new(
+                rng.string(b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567", 32),
+            )?),
+        ));
+    }
+    if rng.one_in(3) {
+        content.push(Content::new(
+            "Email".to_string(),
+            content.len() as u32,
+            false,
+            Value::Email(value::Email::new(format!(
+                "{}@example.com",
+                rng.string(b"abcdefghijklmnopqrstuvwxyz", 8)
+            ))?),
+        ));
+    }
+    if rng.one_in(4) {
+        content.push(Content::new(
+            value::QuestionAnswer::DEFAULT_LABEL.to_string(),
+            content.len() as u32,
+            false,
+            Value::QuestionAnswer(value::QuestionAnswer::new(
+                format!("First pet of {title}?"),
+                "Ferda Mravenec".to_string(),
+            )?),
+        ));
+    }
+    Ok(content)
+}
+
+fn bank_card(rng: &mut Rng) -> Result<Vec<Content>, &'static str> {
+    Ok(vec![
+        Content::new(
+            "Card holder".to_string(),
+            0,
+            true,
+            Value::Text(value::Text::new(rng.pick(&USERS).to_uppercase())),
+        ),
+        Content::new(
+            "Card number".to_string(),
+            1,
+            true,
+            Value::BankCardNumber(value::BankCardNumber::new(card_number(rng))?),
+        ),
+        Content::new(
+            "CVV".to_string(),
+            2,
+            true,
+            Value::Number(value::Number::new(rng.digits(3))?),
+        ),
+        Content::new(
+            "Expiration date".to_string(),
+            3,
+            true,
+            Value::Date(value::Date::new(format!(
+                "20{}-{:02}-01",
+                25 + rng.below(10),
+                1 + rng.below(12)
+            ))?),
+        ),
+        Content::new(
+            "PIN".to_string(),
+            4,
+            true,
+            Value::Number(value::Number::new(rng.digits(4))?),
+        ),
+    ])
+}
+
+fn note(rng: &mut Rng) -> Vec<Content> {
+    let text = match rng.below(4) {
+        0 => "Short note".to_string(),
+        1 => "Řádek s diakritikou\nSecond line 🙂\n\tIndented line".to_string(),
+        // Longer than the preview, so the note is loaded truncated
+        2 => "Lorem ipsum dolor sit amet. ".repeat(200),
+        _ => String::new(),
+    };
+    vec![Content::new(
+        "Note".to_string(),
+        0,
+        true,
+        Value::LongText(value::LongText::new(text)),
+    )]
+}
+
+fn other(rng: &mut Rng) -> Result<Vec<Content>, &'static str> {
+    Ok(vec![
+        Content::new(
+            "Phone".to_string(),
+            0,
+            false,
+            Value::PhoneNumber(value::PhoneNumber::new(format!(
+                "+1415237{}",
+                rng.digits(4)
+            ))?),
+        ),
+        Content::new(
+            "Number".to_string(),
+            1,
+            false,
+            Value::Number(value::Number::new(
+                [i64::MIN, -1, 0, i64::MAX][rng.below(4)].to_string(),
+            )?),
+        ),
+        Content::new(
+            "Recovery codes".to_string(),
+            2,
+            false,
+            Value::SensitiveText(value::SensitiveText::new(format!(
+                "{} {}",
+                rng.digits(8),
+                rng.digits(8)
+            ))),
+        ),
+        Content::new(
+            "Date".to_string(),
+            3,
+            false,
+            Value::Date(value::Date::new(
+                ["1970-01-01", "2000-02-29", "2099-12-31"][rng.below(3)].to_string(),
+            )?),
+        ),
+    ])
+}
+
+/// Generates records with content as described by the spec. Records are spread across all categories, some logins have compromised passwords or TOTP secrets. Creation times are derived from the seed, not from the current time.
+/// # Errors
+/// If a generated value is not valid.
+pub fn generate(spec: FixtureSpec) -> Result<Vec<Entry>, &'static str> {
+    let mut rng = Rng(spec.seed);
+    let start = Utc
+        .with_ymd_and_hms(2024, 1, 1, 12, 0, 0)
+        .single()
+        .ok_or("Invalid start date")?;
+    let mut entries = Vec::with_capacity(spec.records);
+    for index in 0..spec.records {
+        let category = [
+            Category::Login,
+            Category::BankCard,
+            Category::Note,
+            Category::Other,
+        ][rng.below(4)]
+        .clone();
+        let title = if rng.one_in(20) {
+            "Very long title ".repeat(20)
+        } else {
+            format!("{} {index}", rng.pick(&TITLES))
+        };
+        let (subtitle, content) = match category {
+            Category::Login => (rng.pick(&USERS).to_string(), login(&mut rng, &title)?),
+            Category::BankCard => (format!("**** {}", rng.digits(4)), bank_card(&mut rng)?),
+            Category::Note => (String::new(), note(&mut rng)),
+            Category::Other | Category::Custom(_) => (String::new(), other(&mut rng)?),
+        };
+        let mut record = Record::new(title, subtitle, category);
+        let created = start + Duration::hours(index as i64);
+        record.set_created(created);
+        record.set_last_modified(created + Duration::minutes(rng.below(60 * 24 * 30) as i64));
+        entries.push((record, content));
+    }
+    Ok(entries)
+}
+
+/// Creates a new vault with records generated by [`generate`].
+/// # Return
+/// Returns the number of created records.
+/// # Errors
+/// If the file already exists, or if the vault cannot be created or filled.
+pub fn create_fixture_vault(
+    path: &Path,
+    password: &str,
+    spec: FixtureSpec,
+) -> Result<usize, Error> {
+    if path.exists() {
+        return Err(Error::Other("File already exists"));
+    }
+    let entries = generate(spec)?;
+    Database::open_path(password, path)?.import_records(&entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_generate_deterministic() {
+        let spec = FixtureSpec {
+            records: 40,
+            seed: 7,
+        };
+        let first = generate(spec).unwrap();
+        let second = generate(spec).unwrap();
+        assert_eq!(first, second);
+        assert_ne!(
+            first,
+            generate(FixtureSpec {
+                records: 40,
+                seed: 8
+            })
+            .unwrap()
+        );
+        for category in [
+            Category::Login,
+            Category::BankCard,
+            Category::Note,
+            Category::Other,
+        ] {
+            assert!(
+                first
+                    .iter()
+                    .any(|(record, _)| record.category() == &category),
+                "{category:?}"
+            );
+        }
+    }
+    #[test]
+    fn test_card_number() {
+        let mut rng = Rng(3);
+        for _ in 0..100 {
+            assert!(value::BankCardNumber::new(card_number(&mut rng)).is_ok());
+        }
+    }
+    #[test]
+    fn test_create_fixture_vault() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database");
+        let spec = FixtureSpec::default();
+        assert_eq!(
+            create_fixture_vault(&path, "password", spec),
+            Ok(spec.records)
+        );
+        assert_eq!(
+            create_fixture_vault(&path, "password", spec),
+            Err(Error::Other("File already exists"))
+        );
+
+        let database = Database::open_path_read_only("password", &path, "Fixture").unwrap();
+        let records = database.get_all_records().unwrap();
+        assert_eq!(records.len(), spec.records);
+        let content = database.get_all_content_grouped().unwrap();
+        assert!(content
+            .values()
+            .flatten()
+            .any(|content| matches!(content.value(), Value::TOTPSecret(_))));
+        assert!(content
+            .values()
+            .flatten()
+            .any(|content| match content.value() {
+                Value::Password(password) => COMPROMISED.contains(&password.value()),
+                _ => false,
+            }));
+    }
+}
@@ -0,0 +1,115 @@
+use crate::database::model::SecretValue;
+use secrecy::SecretString;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Generated password with the time of generation.
+#[derive(Clone, serde::Serialize)]
+pub struct GeneratedPassword {
+    value: SecretValue,
+    generated: chrono::DateTime<chrono::Utc>,
+}
+
+/// History of generated passwords for tauri state. Lives only for the current session, nothing is written to disk. Values are zeroized when they are removed: removed entries are dropped right away and [`SecretValue`] holds a [`SecretString`], which zeroizes the password on drop.
+pub struct GeneratorHistory {
+    entries: Mutex<VecDeque<GeneratedPassword>>,
+    capacity: usize,
+}
+
+impl GeneratorHistory {
+    /// Creates a new history holding at most `capacity` passwords. The history always holds at least the last password, a capacity of 0 is raised to 1.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        GeneratorHistory {
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+        }
+    }
+
+    /// Adds a password to the history. If the history is full, the oldest password is removed.
+    pub fn add(&self, value: &str) {
+        if let Ok(mut guard) = self.entries.lock() {
+            if guard.len() == self.capacity {
+                guard.pop_back();
+            }
+            guard.push_front(GeneratedPassword {
+                value: SecretValue::new(SecretString::new(value.to_string())),
+                generated: chrono::Utc::now(),
+            });
+        }
+    }
+
+    /// Returns all passwords in the history, the most recent first.
+    pub fn get_all(&self) -> Vec<GeneratedPassword> {
+        self.entries
+            .lock()
+            .map(|guard| guard.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Removes all passwords from the history.
+    pub fn clear(&self) {
+        if let Ok(mut guard) = self.entries.lock() {
+            guard.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_generator_history_capacity() {
+        let history = GeneratorHistory::new(3);
+        for value in ["1", "2", "3", "4", "5"] {
+            history.add(value);
+        }
+        let entries = history.get_all();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].value.expose_secret(), "5");
+        assert_eq!(entries[2].value.expose_secret(), "3");
+    }
+    #[test]
+    fn test_generator_history_order() {
+        let history = GeneratorHistory::new(10);
+        history.add("first");
+        history.add("second");
+        let entries = history.get_all();
+        assert_eq!(entries[0].value.expose_secret(), "second");
+        assert_eq!(entries[1].value.expose_secret(), "first");
+        assert!(entries[0].generated >= entries[1].generated);
+    }
+    #[test]
+    fn test_generator_history_clear() {
+        let history = GeneratorHistory::new(10);
+        history.add("password");
+        history.clear();
+        assert!(history.get_all().is_empty());
+        history.add("new");
+        assert_eq!(history.get_all().len(), 1);
+    }
+    #[test]
+    fn test_generator_history_zero_capacity() {
+        let history = GeneratorHistory::new(0);
+        for value in ["1", "2", "3"] {
+            history.add(value);
+        }
+        let entries = history.get_all();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].value.expose_secret(), "3");
+    }
+    #[test]
+    fn test_generator_history_drops_removed() {
+        let stored = |history: &GeneratorHistory| history.entries.lock().unwrap().len();
+        let history = GeneratorHistory::new(2);
+        for value in ["1", "2", "3"] {
+            history.add(value);
+        }
+        // Evicted and cleared values are not kept anywhere, so they are zeroized by dropping
+        assert_eq!(stored(&history), 2);
+        drop(history.get_all());
+        assert_eq!(stored(&history), 2);
+        history.clear();
+        assert_eq!(stored(&history), 0);
+    }
+}
@@ -0,0 +1,672 @@
+//! Test harness invoking commands through IPC, the same way the frontend calls them, on a mock app without a webview.
+//!
+//! Only commands that do not take [`tauri::AppHandle`] or [`tauri::Window`] can be invoked, those are bound to the real runtime.
+use crate::command::authentication::{__cmd__get_session_state, get_session_state};
+use crate::command::database::*;
+use crate::command::import::*;
+use crate::command::metrics::*;
+use crate::command::totp::{
+    __cmd__export_totp_bundle, __cmd__get_totp_code, __cmd__import_totp_bundle, export_totp_bundle,
+    get_totp_code, import_totp_bundle,
+};
+use crate::command::validation::*;
+use crate::database::Database;
+use crate::file_manager::LastExport;
+use crate::metrics::{self, CommandMetrics};
+use crate::reauth::Reauthentication;
+use crate::reveal::RevealTimer;
+use crate::session::Session;
+use crate::totp::TOTPManager;
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+use tauri::{App, Manager, State, Window};
+use tempfile::TempDir;
+
+/// Command which only blocks for the given time, to test [`metrics::traced`] with a slow command.
+#[tauri::command]
+fn sleep(milliseconds: u64) {
+    std::thread::sleep(Duration::from_millis(milliseconds));
+}
+
+/// Mock app with the state managed by [`crate::run`] and a database in a temporary directory.
+pub struct Harness {
+    app: App<MockRuntime>,
+    window: Window<MockRuntime>,
+    _directory: TempDir,
+}
+
+impl Harness {
+    /// Creates the mock app with a new database.
+    pub fn new() -> Harness {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        let app = mock_builder()
+            .manage(database)
+            .manage(TOTPManager::new(50))
+            .manage(Reauthentication::default())
+            .manage(RevealTimer::default())
+            .manage(Session::default())
+            .manage(CommandMetrics::default())
+            .manage(LastExport::default())
+            .invoke_handler(metrics::traced(tauri::generate_handler![
+                search_records,
+                get_records_window,
+                count_records,
+                import_csv,
+                import_csv_mapped,
+                import_bitwarden,
+                import_keepass,
+                import_encrypted_archive,
+                import_notes_encrypted,
+                import_totp_bundle,
+                export_encrypted_archive,
+                export_notes_encrypted,
+                export_totp_bundle,
+                get_content_value,
+                get_totp_code,
+                cancel_reveal,
+                save_record,
+                get_record,
+                save_draft,
+                get_draft,
+                set_record_locked,
+                delete_record,
+                delete_content,
+                validate,
+                get_session_state,
+                get_command_metrics,
+                reset_command_metrics,
+                sleep,
+            ]))
+            .build(mock_context(noop_assets()))
+            .unwrap();
+        let window = app.get_window("main").unwrap();
+        Harness {
+            app,
+            window,
+            _directory: directory,
+        }
+    }
+
+    /// Invokes the command with the arguments, which must be a JSON object as sent by the frontend.
+    /// # Errors
+    /// Returns the error of the command.
+    pub fn invoke<T: DeserializeOwned>(
+        &self,
+        command: &str,
+        args: serde_json::Value,
+    ) -> Result<T, serde_json::Value> {
+        let mut payload = serde_json::json!({
+            "cmd": command,
+            "callback": 0,
+            "error": 1,
+            // Key of the mock app, newer versions of Tauri reject messages without it
+            "__TAURI_INVOKE_KEY__": "__invoke-key__",
+        });
+        payload
+            .as_object_mut()
+            .unwrap()
+            .extend(args.as_object().cloned().unwrap_or_default());
+        tauri::test::get_ipc_response::<serde_json::Value>(
+            &self.window,
+            serde_json::from_value(payload).unwrap(),
+        )
+        .map(|value| serde_json::from_value(value).unwrap())
+    }
+
+    /// Database managed by the app, to prepare or check data without going through commands.
+    pub fn database(&self) -> State<'_, Database> {
+        self.app.state::<Database>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hooks::HookEvent;
+    use serde_json::json;
+
+    fn login(harness: &Harness, title: &str, password: &str) -> u64 {
+        harness
+            .invoke(
+                "save_record",
+                json!({
+                    "payload": json!({
+                        "record": {"title": title, "subtitle": "john", "category": "Login"},
+                        "content": [
+                            {"label": "User", "position": 0, "required": true, "kind": "Text", "value": "john"},
+                            {"label": "Password", "position": 1, "required": true, "kind": "Password", "value": password}
+                        ]
+                    })
+                    .to_string()
+                }),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_save_record() {
+        let harness = Harness::new();
+        let id = login(&harness, "Mail", "secret");
+        let found: serde_json::Value = harness
+            .invoke("search_records", json!({"query": "Mail", "deep": false}))
+            .unwrap();
+        let record = &found[0]["record"];
+        assert_eq!(record["id"], id);
+        assert_eq!(record["title"], "Mail");
+        assert_eq!(record["icon"], "globe");
+        assert!(record.get("password").is_none());
+        // Record is sent only as a JSON string
+        assert!(harness
+            .invoke::<u64>(
+                "save_record",
+                json!({"record": {"title": "Bank", "subtitle": "", "category": "Login"}, "content": []}),
+            )
+            .is_err());
+    }
+    #[test]
+    fn test_save_record_stale_id() {
+        let harness = Harness::new();
+        let id = login(&harness, "Mail", "secret");
+        let record = harness.database().get_all_records().unwrap().remove(0);
+        let content = harness.database().get_all_content_for_record(id).unwrap();
+        harness.database().delete_record(record).unwrap();
+
+        // Record was deleted in another window while it was edited
+        let result: Result<u64, _> = harness.invoke(
+            "save_record",
+            json!({
+                "payload": json!({
+                    "record": {"id": id, "title": "Mail", "subtitle": "john", "category": "Login"},
+                    "content": [
+                        {"label": "User", "position": 0, "required": true, "kind": "Text", "value": "john"}
+                    ]
+                })
+                .to_string()
+            }),
+        );
+        assert_eq!(
+            result,
+            Err(json!({"code": "NotFound", "message": "Record does not exist"}))
+        );
+        assert!(harness.database().get_all_records().unwrap().is_empty());
+        assert_eq!(
+            harness.database().get_all_content_for_record(id).unwrap(),
+            content
+        );
+    }
+    #[test]
+    fn test_delete_record() {
+        let harness = Harness::new();
+        login(&harness, "Mail", "secret");
+        let id = login(&harness, "Bank", "secret");
+        let found: serde_json::Value = harness
+            .invoke("search_records", json!({"query": "Bank", "deep": false}))
+            .unwrap();
+        let record = found[0]["record"].clone();
+        assert_eq!(record["id"], id);
+        // Frontend sends back the record as it received it
+        harness
+            .invoke::<()>("delete_record", json!({ "record": record }))
+            .unwrap();
+        let records = harness.database().get_all_records().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].title(), "Mail");
+    }
+    #[test]
+    fn test_get_record() {
+        let harness = Harness::new();
+        let id = login(&harness, "Mail", "secret");
+        let record: serde_json::Value = harness.invoke("get_record", json!({"id": id})).unwrap();
+        assert_eq!(record["id"], id);
+        assert_eq!(record["title"], "Mail");
+        // Missing record is not an error
+        assert_eq!(
+            harness.invoke::<serde_json::Value>("get_record", json!({"id": id + 1})),
+            Ok(serde_json::Value::Null)
+        );
+    }
+    #[test]
+    fn test_get_content_value_locked() {
+        let harness = Harness::new();
+        let id = login(&harness, "Mail", "secret");
+        let password = harness.database().get_all_content_for_record(id).unwrap()[1].id();
+        let revealed: serde_json::Value = harness
+            .invoke("get_content_value", json!({ "id": password }))
+            .unwrap();
+        assert_eq!(revealed["value"], "secret");
+        assert!(revealed["reveal_token"].is_u64());
+        assert_eq!(
+            revealed["reveal_seconds"],
+            crate::reveal::DEFAULT_DURATION.as_secs()
+        );
+        assert!(harness
+            .app
+            .state::<RevealTimer>()
+            .is_pending(revealed["reveal_token"].as_u64().unwrap()));
+
+        harness
+            .invoke::<()>("set_record_locked", json!({"id": id, "locked": true}))
+            .unwrap();
+        // Locking masks the revealed password right away
+        assert!(!harness
+            .app
+            .state::<RevealTimer>()
+            .is_pending(revealed["reveal_token"].as_u64().unwrap()));
+        assert_eq!(
+            harness.invoke::<serde_json::Value>("get_content_value", json!({ "id": password })),
+            Err(json!(crate::error::Error::NeedsReauth))
+        );
+        assert_eq!(
+            harness.invoke::<()>("set_record_locked", json!({"id": id, "locked": false})),
+            Err(json!(crate::error::Error::NeedsReauth))
+        );
+    }
+    #[test]
+    fn test_cancel_reveal() {
+        let harness = Harness::new();
+        let id = login(&harness, "Mail", "secret");
+        let content = harness.database().get_all_content_for_record(id).unwrap();
+        // Plain values are not masked, so they have no reveal
+        let user: serde_json::Value = harness
+            .invoke("get_content_value", json!({ "id": content[0].id() }))
+            .unwrap();
+        assert!(user["reveal_token"].is_null());
+
+        let password: serde_json::Value = harness
+            .invoke("get_content_value", json!({ "id": content[1].id() }))
+            .unwrap();
+        let token = password["reveal_token"].as_u64().unwrap();
+        harness
+            .invoke::<()>("cancel_reveal", json!({ "token": token }))
+            .unwrap();
+        assert!(!harness.app.state::<RevealTimer>().is_pending(token));
+    }
+    #[test]
+    fn test_expected_record_id() {
+        let harness = Harness::new();
+        let mail = login(&harness, "Mail", "secret");
+        let bank = login(&harness, "Bank", "other");
+        let password = harness.database().get_all_content_for_record(mail).unwrap()[1].id();
+        let not_in_record =
+            json!({"code": "NotFound", "message": crate::database::CONTENT_NOT_IN_RECORD});
+
+        let revealed: serde_json::Value = harness
+            .invoke(
+                "get_content_value",
+                json!({ "id": password, "expected_record_id": mail }),
+            )
+            .unwrap();
+        assert_eq!(revealed["value"], "secret");
+        assert_eq!(
+            harness.invoke::<serde_json::Value>(
+                "get_content_value",
+                json!({ "id": password, "expected_record_id": bank })
+            ),
+            Err(not_in_record.clone())
+        );
+
+        harness
+            .app
+            .state::<TOTPManager>()
+            .add_secret(
+                password,
+                mail,
+                "RFFFMAZ4JSJQ3QURWHZNA2WLJASTMYWV".to_string(),
+            )
+            .unwrap();
+        assert!(harness
+            .invoke::<serde_json::Value>("get_totp_code", json!({ "id": password }))
+            .is_ok());
+        assert!(harness
+            .invoke::<serde_json::Value>(
+                "get_totp_code",
+                json!({ "id": password, "expected_record_id": mail })
+            )
+            .is_ok());
+        assert_eq!(
+            harness.invoke::<serde_json::Value>(
+                "get_totp_code",
+                json!({ "id": password, "expected_record_id": bank })
+            ),
+            Err(not_in_record)
+        );
+    }
+    #[test]
+    fn test_search_records() {
+        let harness = Harness::new();
+        login(&harness, "Mail", "secret");
+        login(&harness, "Bank", "secret");
+        let found: serde_json::Value = harness
+            .invoke("search_records", json!({"query": "mai", "deep": false}))
+            .unwrap();
+        assert_eq!(found.as_array().unwrap().len(), 1);
+        assert_eq!(found[0]["record"]["title"], "Mail");
+        // Password values are never searched
+        let found: serde_json::Value = harness
+            .invoke("search_records", json!({"query": "secret", "deep": true}))
+            .unwrap();
+        assert!(found.as_array().unwrap().is_empty());
+    }
+    #[test]
+    fn test_get_records_window() {
+        let harness = Harness::new();
+        for title in ["Mail", "Bank", "Shop"] {
+            login(&harness, title, "secret");
+        }
+        let filter = json!({"category": "Login", "query": ""});
+        assert_eq!(
+            harness.invoke::<u64>("count_records", json!({ "filter": filter })),
+            Ok(3)
+        );
+        let window: serde_json::Value = harness
+            .invoke(
+                "get_records_window",
+                json!({"filter": filter, "sort": {"field": "Title"}, "start": 1, "count": 5}),
+            )
+            .unwrap();
+        assert_eq!(window.as_array().unwrap().len(), 2);
+        assert_eq!(window[0]["title"], "Mail");
+        // Unknown filters are rejected, not ignored
+        assert!(harness
+            .invoke::<u64>("count_records", json!({"filter": {"tag": "work"}}))
+            .is_err());
+    }
+    #[test]
+    fn test_import_csv() {
+        let harness = Harness::new();
+        let path = harness._directory.path().join("import.csv");
+        std::fs::write(
+            &path,
+            "title,category,website,username,password,notes\nMail,Login,https://example.com,john,secret,\nBroken,Login,not a url,john,secret,\n",
+        )
+        .unwrap();
+        let summary: serde_json::Value = harness
+            .invoke("import_csv", json!({ "path": path }))
+            .unwrap();
+        assert_eq!(summary["imported"], 1);
+        assert_eq!(summary["failed"], json!([[3, "Invalid URL"]]));
+        let skipped = harness._directory.path().join("import.skipped.csv");
+        assert_eq!(summary["skipped_file"], json!(skipped));
+        assert!(std::fs::read_to_string(skipped)
+            .unwrap()
+            .ends_with("Broken,Login,not a url,john,secret,,Invalid URL\n"));
+        assert_eq!(
+            harness.invoke::<u64>("count_records", json!({"filter": {}})),
+            Ok(1)
+        );
+
+        std::fs::write(&path, "name,password\nMail,secret\n").unwrap();
+        assert!(harness
+            .invoke::<serde_json::Value>("import_csv", json!({ "path": path }))
+            .is_err());
+    }
+    #[test]
+    fn test_draft() {
+        let harness = Harness::new();
+        assert_eq!(
+            harness.invoke::<Option<String>>("get_draft", json!({"id": 0})),
+            Ok(None)
+        );
+        assert_eq!(
+            harness.invoke::<bool>("save_draft", json!({"id": 0, "snapshot": "{}"})),
+            Ok(true)
+        );
+        assert_eq!(
+            harness.invoke::<Option<String>>("get_draft", json!({"id": 0})),
+            Ok(Some("{}".to_string()))
+        );
+        // Saving a new record discards the draft of a new record
+        login(&harness, "Mail", "secret");
+        assert_eq!(
+            harness.invoke::<Option<String>>("get_draft", json!({"id": 0})),
+            Ok(None)
+        );
+    }
+    #[test]
+    fn test_validate() {
+        let harness = Harness::new();
+        assert_eq!(
+            harness.invoke::<Option<String>>(
+                "validate",
+                json!({"kind": "Email", "value": "john@example.com"})
+            ),
+            Ok(None)
+        );
+        assert_eq!(
+            harness.invoke::<Option<String>>("validate", json!({"kind": "Number", "value": "1.5"})),
+            Ok(Some("Invalid number".to_string()))
+        );
+    }
+    /// Log output shared with the test.
+    #[derive(Clone, Default)]
+    struct Captured(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buffer);
+            Ok(buffer.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_command_metrics() {
+        let harness = Harness::new();
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            harness
+                .invoke::<()>("sleep", json!({"milliseconds": 150}))
+                .unwrap();
+            harness
+                .invoke::<()>("sleep", json!({"milliseconds": 0}))
+                .unwrap();
+        });
+
+        let metrics: serde_json::Value = harness.invoke("get_command_metrics", json!({})).unwrap();
+        let sleep = &metrics["sleep"];
+        assert_eq!(sleep["calls"], 2);
+        assert!(sleep["max_ms"].as_f64().unwrap() >= 150.0);
+        assert_eq!(sleep["histogram"][0], 1);
+        assert_eq!(sleep["histogram"][5], 1);
+
+        let log = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(log.lines().count(), 1);
+        assert!(log.contains("WARN"));
+        assert!(log.contains("Slow command"));
+        assert!(log.contains("command=\"sleep\"") || log.contains("command=sleep"));
+        assert!(log.contains("duration_ms="));
+        assert!(!log.contains("milliseconds"));
+
+        harness
+            .invoke::<()>("reset_command_metrics", json!({}))
+            .unwrap();
+        let metrics: serde_json::Value = harness.invoke("get_command_metrics", json!({})).unwrap();
+        assert!(metrics.get("sleep").is_none());
+    }
+    #[test]
+    fn test_get_session_state() {
+        let harness = Harness::new();
+        let session: serde_json::Value = harness.invoke("get_session_state", json!({})).unwrap();
+        assert_eq!(session["state"], "Locked");
+
+        crate::session::transition(
+            &harness.app.handle(),
+            crate::session::SessionState::Unlocked,
+        );
+        let session: serde_json::Value = harness.invoke("get_session_state", json!({})).unwrap();
+        assert_eq!(session["state"], "Unlocked");
+        assert!(session["at"].is_string());
+    }
+    /// Every exporter and importer that can be invoked here runs its hook. The menu export of the database file is checked through [`crate::window::menu::event::database_exported`], exports to CSV and the browser import ask for a file or take the app handle and trigger the hook the same way.
+    #[cfg(unix)]
+    #[test]
+    fn test_hooks_of_exports_and_imports() {
+        let harness = Harness::new();
+        let directory = harness._directory.path();
+        let output = directory.join("hooks.jsonl");
+        let command_line = format!(
+            r#"/bin/sh -c 'cat >> "$0"; echo >> "$0"' '{}'"#,
+            output.display()
+        );
+        crate::hooks::save(
+            &harness.database(),
+            &[HookEvent::ExportFinished, HookEvent::ImportFinished]
+                .into_iter()
+                .map(|event| (event, command_line.clone()))
+                .collect(),
+        )
+        .unwrap();
+        login(&harness, "Mail", "secret");
+
+        // Hooks run in the background, each one is waited for before the next is started
+        let mut fired = 0;
+        let mut next_payload = || {
+            fired += 1;
+            let start = std::time::Instant::now();
+            loop {
+                let lines = std::fs::read_to_string(&output).unwrap_or_default();
+                if lines.lines().count() == fired && lines.ends_with('\n') {
+                    let payload: serde_json::Value =
+                        serde_json::from_str(lines.lines().last().unwrap()).unwrap();
+                    return (
+                        payload["event"].clone(),
+                        payload["format"].clone(),
+                        payload["records"].clone(),
+                    );
+                }
+                assert!(
+                    start.elapsed() < Duration::from_secs(10),
+                    "Hook did not run"
+                );
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        };
+
+        let archive = directory.join("export.archive");
+        let bundle = directory.join("export.bundle");
+        let passphrase = "correct horse battery staple";
+        let _: serde_json::Value = harness
+            .invoke(
+                "export_encrypted_archive",
+                json!({"path": archive, "passphrase": passphrase, "include_locked": false, "include_metadata": false}),
+            )
+            .unwrap();
+        assert_eq!(
+            next_payload(),
+            (json!("export-finished"), json!("archive"), json!(1))
+        );
+        let _: serde_json::Value = harness
+            .invoke(
+                "export_totp_bundle",
+                json!({"path": bundle, "passphrase": passphrase, "include_locked": false}),
+            )
+            .unwrap();
+        assert_eq!(
+            next_payload(),
+            (json!("export-finished"), json!("totp-bundle"), json!(0))
+        );
+        let _: serde_json::Value = harness
+            .invoke(
+                "export_notes_encrypted",
+                json!({"directory": directory, "passphrase": passphrase, "include_locked": false}),
+            )
+            .unwrap();
+        assert_eq!(
+            next_payload(),
+            (json!("export-finished"), json!("notes"), json!(0))
+        );
+        crate::window::menu::event::database_exported(
+            &harness.app.handle(),
+            directory.join("copy"),
+        );
+        assert_eq!(
+            next_payload(),
+            (json!("export-finished"), json!("database"), json!(null))
+        );
+
+        let csv = directory.join("import.csv");
+        std::fs::write(
+            &csv,
+            "title,category,website,username,password,notes\nBank,Login,,john,secret,\n",
+        )
+        .unwrap();
+        let _: serde_json::Value = harness
+            .invoke("import_csv", json!({ "path": csv }))
+            .unwrap();
+        assert_eq!(
+            next_payload(),
+            (json!("import-finished"), json!("csv"), json!(1))
+        );
+        let _: serde_json::Value = harness
+            .invoke(
+                "import_csv_mapped",
+                json!({"path": csv, "mapping": {"title": "title", "password": "password"}, "keep_unmapped": false}),
+            )
+            .unwrap();
+        assert_eq!(
+            next_payload(),
+            (json!("import-finished"), json!("csv"), json!(1))
+        );
+        let bitwarden = directory.join("bitwarden.json");
+        std::fs::write(&bitwarden, crate::import::bitwarden::tests::EXPORT).unwrap();
+        let _: serde_json::Value = harness
+            .invoke("import_bitwarden", json!({ "path": bitwarden }))
+            .unwrap();
+        assert_eq!(
+            next_payload(),
+            (json!("import-finished"), json!("bitwarden"), json!(4))
+        );
+        let keepass = directory.join("database.kdbx");
+        crate::import::keepass::tests::write(&keepass, "password");
+        let _: serde_json::Value = harness
+            .invoke(
+                "import_keepass",
+                json!({"path": keepass, "password": "password"}),
+            )
+            .unwrap();
+        assert_eq!(
+            next_payload(),
+            (json!("import-finished"), json!("keepass"), json!(3))
+        );
+        let _: serde_json::Value = harness
+            .invoke(
+                "import_encrypted_archive",
+                json!({"path": archive, "passphrase": passphrase}),
+            )
+            .unwrap();
+        // Mail from the archive is already in the vault
+        assert_eq!(
+            next_payload(),
+            (json!("import-finished"), json!("archive"), json!(0))
+        );
+        let _: serde_json::Value = harness
+            .invoke(
+                "import_totp_bundle",
+                json!({"path": bundle, "passphrase": passphrase}),
+            )
+            .unwrap();
+        assert_eq!(
+            next_payload(),
+            (json!("import-finished"), json!("totp-bundle"), json!(0))
+        );
+        let _: serde_json::Value = harness
+            .invoke(
+                "import_notes_encrypted",
+                json!({"directory": directory, "passphrase": passphrase}),
+            )
+            .unwrap();
+        assert_eq!(
+            next_payload(),
+            (json!("import-finished"), json!("notes"), json!(0))
+        );
+    }
+}
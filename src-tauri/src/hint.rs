@@ -0,0 +1,151 @@
+use crate::database::{normalize_search, DATABASE_FILE_NAME};
+use crate::error::Error;
+use crate::file_manager::atomic_write;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+/// Number of failed login attempts after which the hint is shown, unless set otherwise with the hint.
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+
+/// Maximum length of the hint in characters.
+pub const MAX_HINT_LENGTH: usize = 100;
+
+/// Warning returned whenever a hint is saved.
+pub const UNENCRYPTED_WARNING: &str =
+    "The hint is stored unencrypted next to the vault, anyone with access to the file can read it";
+
+/// Master password hint with the number of failed login attempts before it is shown. Stored as plain JSON next to the database, it must be readable before the vault is unlocked.
+#[derive(Debug, PartialEq, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Hint {
+    pub hint: String,
+    pub after_attempts: u32,
+}
+
+/// Returns path of the hint file next to the database file.
+pub fn path(database_path: &Path) -> PathBuf {
+    database_path.with_file_name(format!("{DATABASE_FILE_NAME}.hint"))
+}
+
+/// Checks that the hint can be saved for the password: it is not empty, not too long and it does not reveal the password. Comparison ignores case and accents, see [`normalize_search`].
+/// # Errors
+/// Returns [`Error::Validation`] if the hint is empty, too long, contains the password or is a part of it.
+pub fn check(hint: &str, password: &str) -> Result<(), Error> {
+    let hint = normalize_search(hint.trim());
+    if hint.is_empty() {
+        return Err(Error::Validation("Hint can not be empty".into()));
+    }
+    if hint.chars().count() > MAX_HINT_LENGTH {
+        return Err(Error::Validation("Hint is too long".into()));
+    }
+    let password = normalize_search(password);
+    if hint.contains(password.as_str()) || password.contains(hint.as_str()) {
+        return Err(Error::Validation(
+            "Hint must not contain the password or a part of it".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Saves the hint next to the database, replacing the previous one.
+/// # Errors
+/// Returns [`Error::Io`] if the hint file cannot be written.
+pub fn save(database_path: &Path, hint: &Hint) -> Result<(), Error> {
+    let json = serde_json::to_vec(hint).map_err(|_| "Failed to save hint")?;
+    atomic_write(&path(database_path), &json).map_err(Error::io("Failed to save hint"))
+}
+
+/// Loads the hint saved next to the database. Missing or unreadable hint file means there is no hint.
+pub fn load(database_path: &Path) -> Option<Hint> {
+    std::fs::read(path(database_path))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+}
+
+/// Removes the hint, if there is one.
+/// # Errors
+/// Returns [`Error::Io`] if the hint file exists and cannot be removed.
+pub fn clear(database_path: &Path) -> Result<(), Error> {
+    let path = path(database_path);
+    if path.exists().not() {
+        return Ok(());
+    }
+    std::fs::remove_file(path).map_err(Error::io("Failed to remove hint"))
+}
+
+/// Returns the hint if there were at least as many failed logins as it requires, see [`crate::throttle::FailedLogins`].
+pub fn shown(hint: &Hint, failures: u32) -> Option<&str> {
+    (failures >= hint.after_attempts).then_some(hint.hint.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check() {
+        let password = "Correct Horse Battery";
+        assert_eq!(check("favourite animal and a battery", password), Ok(()));
+        for hint in [
+            "Correct Horse Battery",
+            "correct horse battery",
+            "my password is CORRECT HORSE BATTERY!",
+            "Horse",
+            "hórse battery",
+        ] {
+            assert_eq!(
+                check(hint, password),
+                Err(Error::Validation(
+                    "Hint must not contain the password or a part of it".into()
+                )),
+                "{hint}"
+            );
+        }
+        assert_eq!(
+            check("   ", password),
+            Err(Error::Validation("Hint can not be empty".into()))
+        );
+        assert_eq!(
+            check(&"a".repeat(MAX_HINT_LENGTH + 1), password),
+            Err(Error::Validation("Hint is too long".into()))
+        );
+    }
+    #[test]
+    fn test_save_load_clear() {
+        let directory = TempDir::new().unwrap();
+        let database_path = directory.path().join(DATABASE_FILE_NAME);
+        assert_eq!(load(&database_path), None);
+        assert_eq!(clear(&database_path), Ok(()));
+
+        let hint = Hint {
+            hint: "first pet".to_string(),
+            after_attempts: DEFAULT_ATTEMPTS,
+        };
+        save(&database_path, &hint).unwrap();
+        assert_eq!(load(&database_path), Some(hint.clone()));
+        clear(&database_path).unwrap();
+        assert_eq!(load(&database_path), None);
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 0);
+
+        let missing = directory.path().join("missing").join(DATABASE_FILE_NAME);
+        let error = save(&missing, &hint).unwrap_err();
+        assert_eq!(error.code(), "Io");
+        assert_eq!(error, "Failed to save hint");
+    }
+    #[test]
+    fn test_shown_after_attempts() {
+        let hint = Hint {
+            hint: "first pet".to_string(),
+            after_attempts: 3,
+        };
+        assert_eq!(shown(&hint, 0), None);
+        assert_eq!(shown(&hint, 2), None);
+        assert_eq!(shown(&hint, 3), Some("first pet"));
+        assert_eq!(shown(&hint, 10), Some("first pet"));
+        let immediate = Hint {
+            after_attempts: 0,
+            ..hint
+        };
+        assert_eq!(shown(&immediate, 0), Some("first pet"));
+    }
+}
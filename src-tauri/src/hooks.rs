@@ -0,0 +1,403 @@
+use crate::database::Database;
+use crate::error::Error;
+use secrecy::ExposeSecret;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::ops::Not;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Setting with the hooks as a JSON object of event names and command lines. No program is run unless it is set.
+pub const HOOKS_SETTING: &str = "hooks";
+
+/// Longest time a hook may run before it is killed.
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum number of characters of the hook output kept for the log.
+pub const MAX_OUTPUT_LENGTH: usize = 4096;
+
+/// Environment variables passed to hooks, all others are cleared so nothing from the application reaches the program.
+const INHERITED_VARIABLES: [&str; 9] = [
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "USERPROFILE",
+    "SYSTEMROOT",
+];
+
+/// Events a hook can be set for.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    /// Database was uploaded to the cloud.
+    SyncCompleted,
+    /// Vault is being locked, see [`crate::session::lock`].
+    VaultLocked,
+    /// Records were exported to a file.
+    ExportFinished,
+    /// Records were imported from a file.
+    ImportFinished,
+}
+
+/// Data of the event written to the standard input of the hook as JSON. Only non-sensitive data: fields are numbers and fixed strings, never values of the vault or paths chosen by the user.
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct HookPayload {
+    pub event: HookEvent,
+    pub at: chrono::DateTime<chrono::Utc>,
+    /// Number of exported or imported records.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub records: Option<usize>,
+    /// Format of the export or import, e.g. `csv`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<&'static str>,
+}
+
+impl HookPayload {
+    /// Creates a payload of the event without records and format.
+    pub fn new(event: HookEvent) -> HookPayload {
+        HookPayload {
+            event,
+            at: chrono::Utc::now(),
+            records: None,
+            format: None,
+        }
+    }
+}
+
+/// Exit code and output of a finished hook. Output is cut to [`MAX_OUTPUT_LENGTH`].
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct HookOutput {
+    /// Exit code, missing if the program was terminated by a signal.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Splits the command line into the program and its arguments without a shell. Arguments are separated by whitespace, single quotes keep everything literally, double quotes keep whitespace and a backslash escapes the next character outside single quotes.
+/// # Errors
+/// Returns [`Error::Validation`] if a quote is not closed, the line ends with a backslash or there is no program.
+pub fn split(command_line: &str) -> Result<Vec<String>, Error> {
+    let invalid = |message: &'static str| Error::Validation(message.into());
+    let mut arguments = Vec::new();
+    let mut current: Option<String> = None;
+    let mut characters = command_line.chars();
+    while let Some(character) = characters.next() {
+        match character {
+            '\'' => {
+                let argument = current.get_or_insert_with(String::new);
+                loop {
+                    match characters.next() {
+                        Some('\'') => break,
+                        Some(character) => argument.push(character),
+                        None => return Err(invalid("Quote is not closed")),
+                    }
+                }
+            }
+            '"' => {
+                let argument = current.get_or_insert_with(String::new);
+                loop {
+                    match characters.next() {
+                        Some('"') => break,
+                        Some('\\') => argument.push(
+                            characters
+                                .next()
+                                .ok_or_else(|| invalid("Quote is not closed"))?,
+                        ),
+                        Some(character) => argument.push(character),
+                        None => return Err(invalid("Quote is not closed")),
+                    }
+                }
+            }
+            '\\' => current.get_or_insert_with(String::new).push(
+                characters
+                    .next()
+                    .ok_or_else(|| invalid("Command ends with a backslash"))?,
+            ),
+            character if character.is_whitespace() => arguments.extend(current.take()),
+            character => current.get_or_insert_with(String::new).push(character),
+        }
+    }
+    arguments.extend(current);
+    if arguments.is_empty() {
+        return Err(invalid("Command can not be empty"));
+    }
+    Ok(arguments)
+}
+
+/// Checks that the command line can be saved as a hook: it can be split ([`split`]) and the program is an absolute path to an existing file, so it does not depend on the search path.
+/// # Errors
+/// Returns [`Error::Validation`] if the command line is not valid or the program does not exist.
+pub fn validate(command_line: &str) -> Result<Vec<String>, Error> {
+    let arguments = split(command_line)?;
+    let program = Path::new(&arguments[0]);
+    if program.is_absolute().not() {
+        return Err(Error::Validation("Program must be an absolute path".into()));
+    }
+    if program.is_file().not() {
+        return Err(Error::Validation("Program does not exist".into()));
+    }
+    Ok(arguments)
+}
+
+/// Loads the hooks from the settings, see [`HOOKS_SETTING`]. Missing or invalid setting means there are no hooks.
+pub fn load(database: &Database) -> BTreeMap<HookEvent, String> {
+    database
+        .get_setting(HOOKS_SETTING)
+        .ok()
+        .and_then(|hooks| serde_json::from_str(hooks.expose_secret()).ok())
+        .unwrap_or_default()
+}
+
+/// Saves the hooks to the settings, removing the setting if there are none.
+/// # Errors
+/// If the setting cannot be saved.
+pub fn save(database: &Database, hooks: &BTreeMap<HookEvent, String>) -> Result<(), Error> {
+    if hooks.is_empty() {
+        return database.delete_settings(&[HOOKS_SETTING]);
+    }
+    let json = serde_json::to_string(hooks).map_err(|_| "Failed to save hooks")?;
+    database.save_setting(HOOKS_SETTING, &json)
+}
+
+/// Starts the program with the payload as JSON on its standard input. The environment is cleared except [`INHERITED_VARIABLES`].
+/// # Errors
+/// Returns [`Error::Io`] if the program cannot be started.
+pub fn start(arguments: &[String], payload: &HookPayload) -> Result<Child, Error> {
+    let (program, arguments) = arguments
+        .split_first()
+        .ok_or_else(|| Error::Validation("Command can not be empty".into()))?;
+    let json = serde_json::to_vec(payload).map_err(|_| "Failed to write hook input")?;
+    let mut command = Command::new(program);
+    command
+        .args(arguments)
+        .env_clear()
+        .envs(
+            INHERITED_VARIABLES
+                .iter()
+                .filter_map(|name| std::env::var_os(name).map(|value| (name, value))),
+        )
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let mut child = command.spawn().map_err(Error::io("Failed to start hook"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The program may exit without reading its input, that is not an error
+        stdin.write_all(&json).unwrap_or_default();
+    }
+    Ok(child)
+}
+
+/// Reads the whole output on a new thread, so the program is not blocked by a full pipe.
+fn read_output(output: Option<impl Read + Send + 'static>) -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    if let Some(mut output) = output {
+        std::thread::spawn(move || {
+            let mut bytes = Vec::new();
+            output.read_to_end(&mut bytes).unwrap_or_default();
+            let text = String::from_utf8_lossy(&bytes);
+            sender
+                .send(text.chars().take(MAX_OUTPUT_LENGTH).collect())
+                .unwrap_or_default();
+        });
+    }
+    receiver
+}
+
+/// Waits for the started program to finish and collects its output. The program is killed if it does not finish within the timeout.
+/// # Errors
+/// If the program does not finish in time, or [`Error::Io`] if waiting for it fails.
+pub fn wait(mut child: Child, timeout: Duration) -> Result<HookOutput, Error> {
+    let stdout = read_output(child.stdout.take());
+    let stderr = read_output(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(Error::io("Failed to wait for hook"))?
+        {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            child.kill().unwrap_or_default();
+            child.wait().unwrap_or_default();
+            return Err(Error::Other("Hook did not finish in time"));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    // Output stays open while processes started by the hook run, it is not waited for long
+    let received = |receiver: mpsc::Receiver<String>| {
+        receiver
+            .recv_timeout(Duration::from_secs(1))
+            .unwrap_or_default()
+    };
+    Ok(HookOutput {
+        code: status.code(),
+        stdout: received(stdout),
+        stderr: received(stderr),
+    })
+}
+
+/// Runs the hook of the event if one is set. The program is started right away, so it runs even if the application restarts next, and it is waited for on a new thread. The result is written to the log.
+pub fn trigger(database: &Database, payload: HookPayload) {
+    let Some(command_line) = load(database).remove(&payload.event) else {
+        return;
+    };
+    let event = payload.event;
+    let child = split(&command_line).and_then(|arguments| start(&arguments, &payload));
+    std::thread::spawn(
+        move || match child.and_then(|child| wait(child, HOOK_TIMEOUT)) {
+            Ok(output) if output.code == Some(0) => {
+                tracing::info!(
+                    ?event,
+                    stdout = output.stdout,
+                    stderr = output.stderr,
+                    "Hook finished"
+                )
+            }
+            Ok(output) => tracing::warn!(
+                ?event,
+                code = output.code,
+                stdout = output.stdout,
+                stderr = output.stderr,
+                "Hook failed"
+            ),
+            Err(error) => tracing::warn!(?event, %error, "Hook failed"),
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn arguments(arguments: &[&str]) -> Vec<String> {
+        arguments
+            .iter()
+            .map(|argument| argument.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            split("/usr/bin/backup --to /mnt/backup").unwrap(),
+            ["/usr/bin/backup", "--to", "/mnt/backup"]
+        );
+        assert_eq!(
+            split(r#"  "/opt/My Scripts/backup.sh"   'a b' c\ d "e\"f" '' x"y"z "#).unwrap(),
+            ["/opt/My Scripts/backup.sh", "a b", "c d", "e\"f", "", "xyz"]
+        );
+        assert_eq!(
+            split("/bin/echo '$HOME; rm -rf /' `id` |").unwrap(),
+            ["/bin/echo", "$HOME; rm -rf /", "`id`", "|"]
+        );
+        let invalid = |message: &'static str| Err(Error::Validation(message.into()));
+        assert_eq!(split("/bin/echo 'a"), invalid("Quote is not closed"));
+        assert_eq!(split("/bin/echo \"a"), invalid("Quote is not closed"));
+        assert_eq!(
+            split("/bin/echo a\\"),
+            invalid("Command ends with a backslash")
+        );
+        assert_eq!(split("   "), invalid("Command can not be empty"));
+    }
+    #[test]
+    fn test_validate() {
+        let directory = tempfile::TempDir::new().unwrap();
+        let program = directory.path().join("backup");
+        std::fs::write(&program, "").unwrap();
+        let command_line = format!("'{}' --quiet", program.display());
+        assert_eq!(
+            validate(&command_line).unwrap(),
+            [program.display().to_string(), "--quiet".to_string()]
+        );
+        assert_eq!(
+            validate("backup --quiet"),
+            Err(Error::Validation("Program must be an absolute path".into()))
+        );
+        let missing = format!("'{}'", directory.path().join("missing").display());
+        assert_eq!(
+            validate(&missing),
+            Err(Error::Validation("Program does not exist".into()))
+        );
+    }
+    #[test]
+    fn test_payload() {
+        let payload = HookPayload {
+            records: Some(3),
+            format: Some("csv"),
+            ..HookPayload::new(HookEvent::ExportFinished)
+        };
+        let json = serde_json::to_value(&payload).unwrap();
+        assert_eq!(json["event"], "export-finished");
+        assert_eq!(json["records"], 3);
+        assert_eq!(json["format"], "csv");
+        let json = serde_json::to_value(HookPayload::new(HookEvent::SyncCompleted)).unwrap();
+        assert_eq!(
+            json.as_object().unwrap().keys().collect::<Vec<_>>(),
+            ["event", "at"]
+        );
+    }
+    #[cfg(unix)]
+    #[test]
+    fn test_run_hook() {
+        let payload = HookPayload::new(HookEvent::SyncCompleted);
+        let child = start(&arguments(&["/bin/cat"]), &payload).unwrap();
+        let output = wait(child, HOOK_TIMEOUT).unwrap();
+        assert_eq!(output.code, Some(0));
+        assert_eq!(output.stdout, serde_json::to_string(&payload).unwrap());
+
+        let child = start(
+            &arguments(&["/bin/sh", "-c", "echo failed >&2; exit 3"]),
+            &payload,
+        )
+        .unwrap();
+        let output = wait(child, HOOK_TIMEOUT).unwrap();
+        assert_eq!(output.code, Some(3));
+        assert_eq!(output.stderr, "failed\n");
+
+        assert_eq!(
+            start(&arguments(&["/nonexistent/hook"]), &payload)
+                .unwrap_err()
+                .code(),
+            "Io"
+        );
+    }
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_hook_timeout() {
+        let payload = HookPayload::new(HookEvent::VaultLocked);
+        let child = start(&arguments(&["/bin/sleep", "10"]), &payload).unwrap();
+        let id = child.id();
+        let start = Instant::now();
+        assert_eq!(
+            wait(child, Duration::from_millis(200)).err(),
+            Some(Error::Other("Hook did not finish in time"))
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // Killed and reaped
+        assert!(Path::new(&format!("/proc/{id}")).exists().not());
+    }
+    #[cfg(unix)]
+    #[test]
+    fn test_hook_environment() {
+        std::env::set_var("PASSWORD_MANAGER_TEST_SECRET", "hunter2");
+        let payload = HookPayload::new(HookEvent::ImportFinished);
+        let child = start(&arguments(&["/usr/bin/env"]), &payload).unwrap();
+        let output = wait(child, HOOK_TIMEOUT).unwrap();
+        std::env::remove_var("PASSWORD_MANAGER_TEST_SECRET");
+        assert_eq!(output.code, Some(0));
+        assert!(output.stdout.contains("hunter2").not());
+        for line in output.stdout.lines() {
+            let name = line.split('=').next().unwrap();
+            assert!(INHERITED_VARIABLES.contains(&name), "{name} is inherited");
+        }
+    }
+}
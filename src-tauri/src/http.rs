@@ -0,0 +1,16 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Timeout of every request made with [`client`].
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Returns the HTTP client shared by the application. Requests time out after [`REQUEST_TIMEOUT`], so a server that never responds can not block a feature forever.
+pub fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default()
+    })
+}
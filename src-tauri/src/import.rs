@@ -0,0 +1,245 @@
+pub mod bitwarden;
+pub mod browser;
+pub mod generic;
+pub mod keepass;
+
+use crate::database::model::value;
+use crate::database::model::{Category, Content, Record, Value};
+use crate::database::Database;
+use crate::error::Error;
+use crate::file_manager::atomic_write;
+use generic::Table;
+use secrecy::ExposeSecret;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+/// Setting which disables writing the file with rows that were not imported, see [`write_skipped_rows`]. Enabled unless set to `false`.
+pub const SKIPPED_FILE_SETTING: &str = "import_skipped_file";
+
+/// Record with its content, ready to be imported.
+pub type Entry = (Record, Vec<Content>);
+
+/// Records converted from an import file.
+pub struct Converted {
+    pub records: Vec<Entry>,
+    /// Rows that failed to convert, with the line number and reason.
+    pub failed: Vec<(usize, String)>,
+}
+
+/// Result of an import.
+#[derive(Clone, serde::Serialize)]
+pub struct ImportSummary {
+    /// Number of imported records.
+    pub imported: usize,
+    /// Number of existing records that were updated.
+    pub updated: usize,
+    /// Rows that were not imported because the record already exists, with the line number and reason.
+    pub skipped: Vec<(usize, String)>,
+    /// Rows that were skipped, with the line number and reason.
+    pub failed: Vec<(usize, String)>,
+    /// File with the rows that were not imported, next to the imported file.
+    pub skipped_file: Option<PathBuf>,
+}
+
+impl ImportSummary {
+    /// Returns skipped and failed rows, ordered by line.
+    fn not_imported(&self) -> Vec<&(usize, String)> {
+        let mut rows: Vec<&(usize, String)> = self.skipped.iter().chain(&self.failed).collect();
+        rows.sort_by_key(|(line, _)| *line);
+        rows
+    }
+}
+
+/// Validates the value, falling back to text if it is not valid, so no value is lost. Sensitive values fall back to sensitive text.
+fn validated(
+    value: &str,
+    sensitive: bool,
+    new: impl FnOnce(String) -> Result<Value, &'static str>,
+) -> Value {
+    new(value.to_string()).unwrap_or_else(|_| {
+        if sensitive {
+            Value::SensitiveText(value::SensitiveText::new(value.to_string()))
+        } else {
+            Value::Text(value::Text::new(value.to_string()))
+        }
+    })
+}
+
+/// Converts a TOTP value, which is either a secret or an `otpauth://` URI.
+fn totp(value: String) -> Result<Value, &'static str> {
+    let secret = if value.starts_with("otpauth://") {
+        totp_rs::TOTP::from_url_unchecked(&value)
+            .map_err(|_| "Invalid OTP Secret")?
+            .get_secret_base32()
+    } else {
+        value
+    };
+    value::TOTPSecret::new(secret).map(Value::TOTPSecret)
+}
+
+/// Returns whether the file with rows that were not imported is written, see [`SKIPPED_FILE_SETTING`].
+pub fn skipped_file_enabled(database: &Database) -> bool {
+    database
+        .get_setting(SKIPPED_FILE_SETTING)
+        .map_or(true, |value| value.expose_secret() != "false")
+}
+
+/// Returns the path of the file with rows that were not imported, `name.skipped.extension` next to the imported file.
+fn skipped_path(source: &Path, extension: &str) -> Result<PathBuf, Error> {
+    let name = source
+        .file_stem()
+        .ok_or_else(|| Error::Validation("Invalid path".into()))?
+        .to_string_lossy();
+    Ok(source.with_file_name(format!("{name}.skipped.{extension}")))
+}
+
+/// Writes rows of a CSV file that were not imported to `name.skipped.csv` next to it, with the original values and an error column, so they can be fixed and imported again.
+/// # Return
+/// Returns the path of the file, `None` if all rows were imported.
+/// # Errors
+/// Returns [`Error::Io`] if the file cannot be written.
+pub fn write_skipped_rows(
+    source: &Path,
+    table: &Table,
+    summary: &ImportSummary,
+) -> Result<Option<PathBuf>, Error> {
+    let not_imported = summary.not_imported();
+    if not_imported.is_empty() {
+        return Ok(None);
+    }
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(table.headers().iter().map(String::as_str).chain(["error"]))
+        .map_err(|_| "Failed to write CSV")?;
+    for (line, error) in not_imported {
+        let Some(row) = table.rows().iter().find(|row| row.line() == *line) else {
+            continue;
+        };
+        writer
+            .write_record(
+                row.values()
+                    .iter()
+                    .map(String::as_str)
+                    .chain([error.as_str()]),
+            )
+            .map_err(|_| "Failed to write CSV")?;
+    }
+    let data = Zeroizing::new(writer.into_inner().map_err(|_| "Failed to write CSV")?);
+    let path = skipped_path(source, "csv")?;
+    atomic_write(&path, &data).map_err(Error::io("Failed to write file"))?;
+    Ok(Some(path))
+}
+
+/// Writes positions of entries that were not imported with the reason to `name.skipped.json` next to the imported file. Used for formats that are encrypted or structured, only positions and errors are written, never the values.
+/// # Return
+/// Returns the path of the file, `None` if all entries were imported.
+/// # Errors
+/// Returns [`Error::Io`] if the file cannot be written.
+pub fn write_skipped_positions(
+    source: &Path,
+    summary: &ImportSummary,
+) -> Result<Option<PathBuf>, Error> {
+    let not_imported: Vec<serde_json::Value> = summary
+        .not_imported()
+        .into_iter()
+        .map(|(position, error)| serde_json::json!({"position": position, "error": error}))
+        .collect();
+    if not_imported.is_empty() {
+        return Ok(None);
+    }
+    let data = serde_json::to_vec_pretty(&not_imported).map_err(|_| "Failed to write file")?;
+    let path = skipped_path(source, "json")?;
+    atomic_write(&path, &data).map_err(Error::io("Failed to write file"))?;
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn summary(skipped: Vec<(usize, String)>, failed: Vec<(usize, String)>) -> ImportSummary {
+        ImportSummary {
+            imported: 0,
+            updated: 0,
+            skipped,
+            failed,
+            skipped_file: None,
+        }
+    }
+
+    #[test]
+    fn test_write_skipped_rows() {
+        let directory = TempDir::new().unwrap();
+        let source = directory.path().join("passwords.csv");
+        let table = generic::parse(
+            "title,url,password,totp\nMail,https://example.com,secret,\nBroken,not a url,secret,\n,,secret,\nBank,,\"se,cret\",invalid\n",
+        )
+        .unwrap();
+        let mapping = generic::guess_mapping(table.headers());
+        let Converted { failed, .. } = generic::to_records(&table, &mapping, false).unwrap();
+        let path = write_skipped_rows(&source, &table, &summary(Vec::new(), failed))
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, directory.path().join("passwords.skipped.csv"));
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "title,url,password,totp,error\n\
+            Broken,not a url,secret,,Invalid URL\n\
+            ,,secret,,Missing title\n\
+            Bank,,\"se,cret\",invalid,Invalid OTP Secret\n"
+        );
+
+        let all_imported = generic::parse("title\nMail\n").unwrap();
+        let other = directory.path().join("other.csv");
+        assert_eq!(
+            write_skipped_rows(&other, &all_imported, &summary(Vec::new(), Vec::new())),
+            Ok(None)
+        );
+        assert!(!directory.path().join("other.skipped.csv").exists());
+    }
+    #[test]
+    fn test_write_skipped_positions_bitwarden() {
+        let directory = TempDir::new().unwrap();
+        let source = directory.path().join("bitwarden.json");
+        let export = bitwarden::parse(
+            r#"{"items": [
+                {"type": 1, "name": "Mail", "login": {"username": "john", "password": "hunter2"}},
+                {"type": 4, "name": "Passport", "notes": "P123456", "identity": {"passportNumber": "P123456"}}
+            ]}"#,
+        )
+        .unwrap();
+        let Converted { failed, .. } = bitwarden::to_records(&export);
+        let path = write_skipped_positions(&source, &summary(Vec::new(), failed))
+            .unwrap()
+            .unwrap();
+        assert_eq!(path, directory.path().join("bitwarden.skipped.json"));
+        let written = std::fs::read_to_string(&path).unwrap();
+        let positions: Vec<HashMap<String, serde_json::Value>> =
+            serde_json::from_str(&written).unwrap();
+        assert_eq!(
+            positions,
+            vec![HashMap::from([
+                ("position".to_string(), serde_json::json!(2)),
+                (
+                    "error".to_string(),
+                    serde_json::json!("Item type is not supported")
+                ),
+            ])]
+        );
+        for value in ["hunter2", "P123456", "Passport", "john"] {
+            assert!(!written.contains(value), "{value}");
+        }
+    }
+    #[test]
+    fn test_skipped_file_setting() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        assert!(skipped_file_enabled(&database));
+        database
+            .save_setting(SKIPPED_FILE_SETTING, "false")
+            .unwrap();
+        assert!(!skipped_file_enabled(&database));
+    }
+}
@@ -0,0 +1,367 @@
+use super::*;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Item types of a Bitwarden export.
+const LOGIN: u8 = 1;
+const SECURE_NOTE: u8 = 2;
+const CARD: u8 = 3;
+
+/// Custom field type of hidden values.
+const HIDDEN_FIELD: u8 = 1;
+
+/// Unencrypted JSON export of a Bitwarden vault. Values are zeroized on drop.
+#[derive(Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct Export {
+    #[serde(default)]
+    encrypted: bool,
+    #[serde(default)]
+    items: Vec<Item>,
+}
+
+#[derive(Deserialize, Zeroize)]
+struct Item {
+    #[serde(rename = "type")]
+    kind: u8,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    fields: Option<Vec<Field>>,
+    #[serde(default)]
+    login: Option<Login>,
+    #[serde(default)]
+    card: Option<Card>,
+}
+
+#[derive(Deserialize, Zeroize)]
+struct Field {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    value: Option<String>,
+    #[serde(rename = "type", default)]
+    kind: u8,
+}
+
+#[derive(Deserialize, Zeroize)]
+struct Login {
+    #[serde(default)]
+    uris: Option<Vec<Uri>>,
+    #[serde(default)]
+    username: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    totp: Option<String>,
+}
+
+#[derive(Deserialize, Zeroize)]
+struct Uri {
+    #[serde(default)]
+    uri: Option<String>,
+}
+
+#[derive(Deserialize, Zeroize)]
+#[serde(rename_all = "camelCase")]
+struct Card {
+    #[serde(default)]
+    cardholder_name: Option<String>,
+    #[serde(default)]
+    brand: Option<String>,
+    #[serde(default)]
+    number: Option<String>,
+    #[serde(default)]
+    exp_month: Option<String>,
+    #[serde(default)]
+    exp_year: Option<String>,
+    #[serde(default)]
+    code: Option<String>,
+}
+
+/// Returns the trimmed value, `None` if it is missing or blank.
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Parses a Bitwarden JSON export.
+/// # Errors
+/// If the data is not a Bitwarden export or the export is encrypted.
+pub fn parse(data: &str) -> Result<Export, &'static str> {
+    let export: Export = serde_json::from_str(data).map_err(|_| "Not a Bitwarden export")?;
+    if export.encrypted {
+        return Err("Encrypted Bitwarden exports are not supported");
+    }
+    Ok(export)
+}
+
+/// Reads and parses a Bitwarden JSON export ([`parse`]).
+/// # Errors
+/// If the file cannot be read or parsed.
+pub fn read(path: &Path) -> Result<Export, &'static str> {
+    let data = Zeroizing::new(fs::read_to_string(path).map_err(|_| "Failed to read file")?);
+    parse(&data)
+}
+
+/// Converts a single item to a record with content. Login, card and secure note items follow the default layout of their category. Values that fail validation are kept as text, see [`validated`].
+/// # Errors
+/// If the item type is not supported.
+fn item_to_record(item: &Item) -> Result<Entry, &'static str> {
+    let mut content: Vec<Content> = Vec::new();
+    let mut push = |label: &str, required: bool, value: Value| {
+        let position = content.len() as u32;
+        content.push(Content::new(label.to_string(), position, required, value));
+    };
+
+    let (category, subtitle) = match item.kind {
+        LOGIN => {
+            let login = item.login.as_ref();
+            let uris: Vec<&str> = login
+                .and_then(|login| login.uris.as_ref())
+                .map(|uris| uris.iter().filter_map(|uri| non_empty(&uri.uri)).collect())
+                .unwrap_or_default();
+            let username = login.and_then(|login| non_empty(&login.username));
+            for (index, uri) in uris.iter().enumerate() {
+                let label = match index {
+                    0 => "Website".to_string(),
+                    _ => format!("Website {}", index + 1),
+                };
+                push(
+                    &label,
+                    index == 0,
+                    validated(uri, false, |uri| value::Url::new(uri).map(Value::Url)),
+                );
+            }
+            if let Some(username) = username {
+                push(
+                    "User",
+                    true,
+                    Value::Text(value::Text::new(username.to_string())),
+                );
+            }
+            if let Some(password) = login.and_then(|login| non_empty(&login.password)) {
+                push(
+                    "Password",
+                    true,
+                    Value::Password(value::Password::new(password.to_string())),
+                );
+            }
+            if let Some(secret) = login.and_then(|login| non_empty(&login.totp)) {
+                push("TOTP", false, validated(secret, true, totp));
+            }
+            (Category::Login, username.unwrap_or_default().to_string())
+        }
+        CARD => {
+            let card = item.card.as_ref();
+            let get =
+                |field: fn(&Card) -> &Option<String>| card.and_then(|card| non_empty(field(card)));
+            if let Some(holder) = get(|card| &card.cardholder_name) {
+                push(
+                    "Card holder",
+                    true,
+                    Value::Text(value::Text::new(holder.to_string())),
+                );
+            }
+            if let Some(number) = get(|card| &card.number) {
+                push(
+                    "Card number",
+                    true,
+                    validated(number, true, |number| {
+                        value::BankCardNumber::new(number.replace(' ', ""))
+                            .map(Value::BankCardNumber)
+                    }),
+                );
+            }
+            if let Some(code) = get(|card| &card.code) {
+                push(
+                    "CVV",
+                    true,
+                    validated(code, true, |code| {
+                        value::Number::new(code).map(Value::Number)
+                    }),
+                );
+            }
+            match (get(|card| &card.exp_month), get(|card| &card.exp_year)) {
+                (Some(month), Some(year)) => {
+                    let date = validated(&format!("{month}/{year}"), false, |_| {
+                        let month = month.parse::<u32>().map_err(|_| "Invalid date")?;
+                        value::Date::new(format!("{year}-{month:02}-01")).map(Value::Date)
+                    });
+                    push("Expiration date", true, date);
+                }
+                (Some(date), None) | (None, Some(date)) => push(
+                    "Expiration date",
+                    true,
+                    Value::Text(value::Text::new(date.to_string())),
+                ),
+                (None, None) => {}
+            }
+            (
+                Category::BankCard,
+                get(|card| &card.brand).unwrap_or_default().to_string(),
+            )
+        }
+        SECURE_NOTE => (Category::Note, String::new()),
+        _ => return Err("Item type is not supported"),
+    };
+
+    if let Some(notes) = non_empty(&item.notes) {
+        push(
+            "Note",
+            category == Category::Note,
+            Value::LongText(value::LongText::new(notes.to_string())),
+        );
+    }
+    for field in item.fields.iter().flatten() {
+        let Some(value) = non_empty(&field.value) else {
+            continue;
+        };
+        let label = non_empty(&field.name).unwrap_or("Field");
+        let value = match field.kind {
+            HIDDEN_FIELD => Value::SensitiveText(value::SensitiveText::new(value.to_string())),
+            _ => Value::Text(value::Text::new(value.to_string())),
+        };
+        push(label, false, value);
+    }
+
+    let title = non_empty(&item.name).unwrap_or("Untitled").to_string();
+    Ok((Record::new(title, subtitle, category), content))
+}
+
+/// Converts login, card and secure note items of the export to records. Other items, e.g. identities, are reported as failed with their position in the export, starting at 1.
+pub fn to_records(export: &Export) -> Converted {
+    let mut records = Vec::with_capacity(export.items.len());
+    let mut failed = Vec::new();
+    for (index, item) in export.items.iter().enumerate() {
+        match item_to_record(item) {
+            Ok(record) => records.push(record),
+            Err(error) => failed.push((index + 1, error.to_string())),
+        }
+    }
+    Converted { records, failed }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) const EXPORT: &str = r#"{
+        "encrypted": false,
+        "folders": [],
+        "items": [
+            {
+                "id": "1", "type": 1, "name": "Mail", "notes": "old account", "favorite": false,
+                "fields": [
+                    {"name": "PIN", "value": "1234", "type": 1},
+                    {"name": "Linked", "value": null, "type": 3}
+                ],
+                "login": {
+                    "uris": [{"match": null, "uri": "https://mail.example.com"}, {"match": null, "uri": "not a url"}],
+                    "username": "john",
+                    "password": "secret",
+                    "totp": "otpauth://totp/Mail:john?secret=JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP&issuer=Mail"
+                }
+            },
+            {
+                "id": "2", "type": 3, "name": "Visa", "notes": null,
+                "card": {
+                    "cardholderName": "John Doe", "brand": "Visa", "number": "4111 1111 1111 1111",
+                    "expMonth": "7", "expYear": "2030", "code": "123"
+                }
+            },
+            {"id": "3", "type": 2, "name": "Recipe", "notes": "flour\nwater", "secureNote": {"type": 0}},
+            {"id": "4", "type": 4, "name": "Me", "identity": {"firstName": "John"}},
+            {"id": "5", "type": 3, "name": "Broken card", "card": {"number": "1234", "expMonth": "13", "expYear": "2030"}}
+        ]
+    }"#;
+
+    fn labels(content: &[Content]) -> Vec<&str> {
+        content.iter().map(|content| content.label()).collect()
+    }
+
+    #[test]
+    fn test_to_records() {
+        let Converted { records, failed } = to_records(&parse(EXPORT).unwrap());
+        assert_eq!(records.len(), 4);
+        assert_eq!(failed, vec![(4, "Item type is not supported".to_string())]);
+
+        let (record, content) = &records[0];
+        assert_eq!(record.title(), "Mail");
+        assert_eq!(record.subtitle(), "john");
+        assert_eq!(record.category(), &Category::Login);
+        assert_eq!(
+            labels(content),
+            vec![
+                "Website",
+                "Website 2",
+                "User",
+                "Password",
+                "TOTP",
+                "Note",
+                "PIN"
+            ]
+        );
+        assert!(matches!(content[0].value(), Value::Url(_)));
+        assert_eq!(
+            content[1].value(),
+            &Value::Text(value::Text::new("not a url".to_string()))
+        );
+        assert_eq!(
+            content[4].value(),
+            &Value::TOTPSecret(
+                value::TOTPSecret::new("JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP".to_string()).unwrap()
+            )
+        );
+        assert!(matches!(content[6].value(), Value::SensitiveText(_)));
+
+        let (record, content) = &records[1];
+        assert_eq!(record.category(), &Category::BankCard);
+        assert_eq!(record.subtitle(), "Visa");
+        assert_eq!(
+            labels(content),
+            vec!["Card holder", "Card number", "CVV", "Expiration date"]
+        );
+        assert!(matches!(content[1].value(), Value::BankCardNumber(_)));
+        assert!(matches!(content[2].value(), Value::Number(_)));
+        assert_eq!(
+            content[3].value(),
+            &Value::Date(value::Date::new("2030-07-01".to_string()).unwrap())
+        );
+
+        let (record, content) = &records[2];
+        assert_eq!(record.category(), &Category::Note);
+        assert_eq!(labels(content), vec!["Note"]);
+        assert!(content[0].required());
+    }
+    #[test]
+    fn test_invalid_values_fall_back_to_text() {
+        let Converted { records, .. } = to_records(&parse(EXPORT).unwrap());
+        let (_, content) = &records[3];
+        assert_eq!(
+            content[0].value(),
+            &Value::SensitiveText(value::SensitiveText::new("1234".to_string()))
+        );
+        assert_eq!(
+            content[1].value(),
+            &Value::Text(value::Text::new("13/2030".to_string()))
+        );
+    }
+    #[test]
+    fn test_parse_invalid() {
+        assert_eq!(
+            parse(r#"{"encrypted": true, "items": []}"#).err(),
+            Some("Encrypted Bitwarden exports are not supported")
+        );
+        assert_eq!(parse("title,url").err(), Some("Not a Bitwarden export"));
+        assert_eq!(
+            parse(r#"{"items": [{"name": "Missing type"}]}"#).err(),
+            Some("Not a Bitwarden export")
+        );
+    }
+}
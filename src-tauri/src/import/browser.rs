@@ -0,0 +1,421 @@
+use super::generic::Table;
+use super::*;
+use chrono::{DateTime, TimeZone, Utc};
+use secrecy::{ExposeSecret, SecretString};
+use std::ops::Not;
+
+/// What to do with an imported login when the vault has the same login with a different password.
+#[derive(Debug, PartialEq, Clone, Copy, serde::Deserialize)]
+pub enum ConflictPolicy {
+    /// Keep the stored password.
+    Skip,
+    /// Update the stored password, unless it was changed after the imported one.
+    Overwrite,
+    /// Import the login as a new record.
+    Duplicate,
+}
+
+/// Login from a browser password export.
+pub struct BrowserLogin {
+    line: usize,
+    name: Option<String>,
+    url: String,
+    username: String,
+    password: SecretString,
+    /// When the password was last changed. Only Firefox exports it.
+    changed: Option<DateTime<Utc>>,
+    /// Note of the login. Only newer versions of Chrome export it.
+    note: Option<String>,
+}
+
+impl BrowserLogin {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+    pub fn password(&self) -> &SecretString {
+        &self.password
+    }
+}
+
+/// Logins read from a browser export.
+pub struct Logins {
+    pub logins: Vec<BrowserLogin>,
+    /// Rows that are not valid, with the line number and reason.
+    pub failed: Vec<(usize, String)>,
+}
+
+/// Login already stored in the vault, compared with imported logins.
+pub struct ExistingLogin {
+    id_record: u64,
+    username: String,
+    password: Option<SecretString>,
+    modified: DateTime<Utc>,
+}
+
+impl ExistingLogin {
+    /// Reads the login from the record content. Username is taken from the content labeled "User", or from the subtitle if there is none. Password is the first password of the record.
+    pub fn new(record: &Record, content: &[Content]) -> ExistingLogin {
+        let username = content
+            .iter()
+            .find_map(|content| match content.value() {
+                Value::Text(text) if content.label() == "User" => Some(text.value().to_string()),
+                Value::Email(email) if content.label() == "User" => Some(email.value().to_string()),
+                _ => None,
+            })
+            .unwrap_or_else(|| record.subtitle().to_string());
+        let password = content.iter().find_map(|content| match content.value() {
+            Value::Password(password) => Some(SecretString::new(password.value().to_string())),
+            _ => None,
+        });
+        ExistingLogin {
+            id_record: record.id(),
+            username,
+            password,
+            modified: record.last_modified(),
+        }
+    }
+}
+
+/// What to do with an imported login, see [`decide`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum Decision {
+    /// Import as a new record.
+    Insert,
+    /// Do not import, with the reason reported to the user.
+    Skip(&'static str),
+    /// Update the password of the existing record.
+    Update(u64),
+}
+
+/// Decides what to do with an imported login, based on logins stored for the same website. Without `dedupe` every login is inserted.
+/// - No stored login with the same username: insert
+/// - Stored login with the same username and password: skip
+/// - Stored login with the same username and a different password: follow the policy. Overwrite updates the most recently modified login, but only if the imported password is newer or its change time is unknown.
+pub fn decide(
+    login: &BrowserLogin,
+    existing: &[ExistingLogin],
+    dedupe: bool,
+    policy: ConflictPolicy,
+) -> Decision {
+    if dedupe.not() {
+        return Decision::Insert;
+    }
+    let matching: Vec<&ExistingLogin> = existing
+        .iter()
+        .filter(|existing| existing.username == login.username)
+        .collect();
+    if matching.is_empty() {
+        return Decision::Insert;
+    }
+    if matching.iter().any(|existing| {
+        existing
+            .password
+            .as_ref()
+            .is_some_and(|password| password.expose_secret() == login.password.expose_secret())
+    }) {
+        return Decision::Skip("Login already exists");
+    }
+    match policy {
+        ConflictPolicy::Skip => Decision::Skip("Login exists with a different password"),
+        ConflictPolicy::Duplicate => Decision::Insert,
+        ConflictPolicy::Overwrite => {
+            let Some(newest) = matching.iter().max_by_key(|existing| existing.modified) else {
+                return Decision::Insert;
+            };
+            match login.changed {
+                Some(changed) if changed <= newest.modified => {
+                    Decision::Skip("Stored password is newer")
+                }
+                _ => Decision::Update(newest.id_record),
+            }
+        }
+    }
+}
+
+/// Returns index of the column with the header, case-insensitive.
+fn column(table: &Table, header: &str) -> Option<usize> {
+    table
+        .headers()
+        .iter()
+        .position(|other| other.eq_ignore_ascii_case(header))
+}
+
+/// Browser that exported the passwords.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BrowserFormat {
+    /// `name,url,username,password`, newer versions add `note`.
+    Chrome,
+    /// `url,username,password` followed by Firefox columns, e.g. `guid` and `timePasswordChanged`.
+    Firefox,
+}
+
+/// Detects the browser from the header. Both exports have url, username and password columns, only Chrome has a name column.
+pub fn detect(table: &Table) -> Option<BrowserFormat> {
+    ["url", "username", "password"]
+        .iter()
+        .all(|header| column(table, header).is_some())
+        .then(|| match column(table, "name") {
+            Some(_) => BrowserFormat::Chrome,
+            None => BrowserFormat::Firefox,
+        })
+}
+
+/// Reads logins from a browser password export ([`detect`]). Chrome exports also name and note of the login, Firefox the time when the password was last changed.
+/// # Errors
+/// If the table is not a browser export.
+pub fn to_logins(table: &Table) -> Result<Logins, &'static str> {
+    let format = detect(table).ok_or("File is not a browser password export")?;
+    let (url, username, password) = (
+        column(table, "url"),
+        column(table, "username"),
+        column(table, "password"),
+    );
+    let (name, note, changed) = match format {
+        BrowserFormat::Chrome => (column(table, "name"), column(table, "note"), None),
+        BrowserFormat::Firefox => (None, None, column(table, "timePasswordChanged")),
+    };
+
+    let mut logins = Vec::with_capacity(table.rows().len());
+    let mut failed = Vec::new();
+    for row in table.rows() {
+        let get = |index: Option<usize>| {
+            index
+                .and_then(|index| row.values().get(index))
+                .map(|value| value.trim())
+                .filter(|value| value.is_empty().not())
+        };
+        let Some(url) = get(url) else {
+            failed.push((row.line(), "Missing URL".to_string()));
+            continue;
+        };
+        let Some(password) = get(password) else {
+            failed.push((row.line(), "Missing password".to_string()));
+            continue;
+        };
+        logins.push(BrowserLogin {
+            line: row.line(),
+            name: get(name).map(str::to_string),
+            url: url.to_string(),
+            username: get(username).unwrap_or_default().to_string(),
+            password: SecretString::new(password.to_string()),
+            changed: get(changed)
+                .and_then(|changed| changed.parse::<i64>().ok())
+                .and_then(|changed| Utc.timestamp_millis_opt(changed).single()),
+            note: get(note).map(str::to_string),
+        });
+    }
+    Ok(Logins { logins, failed })
+}
+
+/// Converts the login to a login record with the default login layout, followed by the note if there is one. Title is the name of the login or the host of the website.
+/// # Errors
+/// If the URL is not valid.
+pub fn to_record(login: &BrowserLogin) -> Result<Entry, &'static str> {
+    let url = value::Url::new(login.url.clone())?;
+    let title = login
+        .name
+        .clone()
+        .or_else(|| url.host())
+        .unwrap_or_else(|| login.url.clone());
+    let record = Record::new(title, login.username.clone(), Category::Login);
+    let mut content = vec![
+        Content::new("Website".to_string(), 0, true, Value::Url(url)),
+        Content::new(
+            "User".to_string(),
+            1,
+            true,
+            Value::Text(value::Text::new(login.username.clone())),
+        ),
+        Content::new(
+            "Password".to_string(),
+            2,
+            true,
+            Value::Password(value::Password::new(
+                login.password.expose_secret().to_string(),
+            )),
+        ),
+    ];
+    if let Some(note) = &login.note {
+        content.push(Content::new(
+            "Note".to_string(),
+            3,
+            false,
+            Value::LongText(value::LongText::new(note.clone())),
+        ));
+    }
+    Ok((record, content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn login(username: &str, password: &str, changed: Option<DateTime<Utc>>) -> BrowserLogin {
+        BrowserLogin {
+            line: 2,
+            name: None,
+            url: "https://example.com".to_string(),
+            username: username.to_string(),
+            password: SecretString::new(password.to_string()),
+            changed,
+            note: None,
+        }
+    }
+
+    fn existing(
+        id_record: u64,
+        username: &str,
+        password: &str,
+        modified: DateTime<Utc>,
+    ) -> ExistingLogin {
+        ExistingLogin {
+            id_record,
+            username: username.to_string(),
+            password: Some(SecretString::new(password.to_string())),
+            modified,
+        }
+    }
+
+    #[test]
+    fn test_decide() {
+        let now = Utc::now();
+        let newer = Some((now + Duration::days(1)).with_timezone(&Utc));
+        let older = Some((now - Duration::days(1)).with_timezone(&Utc));
+        let stored = [existing(1, "john", "old", now)];
+        let policies = [
+            ConflictPolicy::Skip,
+            ConflictPolicy::Overwrite,
+            ConflictPolicy::Duplicate,
+        ];
+
+        for policy in policies {
+            assert_eq!(
+                decide(&login("john", "new", newer), &stored, false, policy),
+                Decision::Insert
+            );
+            assert_eq!(
+                decide(&login("john", "new", newer), &[], true, policy),
+                Decision::Insert
+            );
+            assert_eq!(
+                decide(&login("jane", "old", newer), &stored, true, policy),
+                Decision::Insert
+            );
+            assert_eq!(
+                decide(&login("john", "old", newer), &stored, true, policy),
+                Decision::Skip("Login already exists")
+            );
+        }
+
+        let cases = [
+            (
+                ConflictPolicy::Skip,
+                newer,
+                Decision::Skip("Login exists with a different password"),
+            ),
+            (
+                ConflictPolicy::Skip,
+                None,
+                Decision::Skip("Login exists with a different password"),
+            ),
+            (ConflictPolicy::Duplicate, older, Decision::Insert),
+            (ConflictPolicy::Duplicate, None, Decision::Insert),
+            (ConflictPolicy::Overwrite, newer, Decision::Update(1)),
+            (ConflictPolicy::Overwrite, None, Decision::Update(1)),
+            (
+                ConflictPolicy::Overwrite,
+                older,
+                Decision::Skip("Stored password is newer"),
+            ),
+        ];
+        for (policy, changed, decision) in cases {
+            assert_eq!(
+                decide(&login("john", "new", changed), &stored, true, policy),
+                decision,
+                "{policy:?} {changed:?}"
+            );
+        }
+    }
+    #[test]
+    fn test_decide_overwrite_newest() {
+        let now = Utc::now();
+        let stored = [
+            existing(1, "john", "first", now - Duration::days(2)),
+            existing(2, "john", "second", now),
+            existing(3, "jane", "third", now + Duration::days(1)),
+        ];
+        assert_eq!(
+            decide(
+                &login("john", "new", None),
+                &stored,
+                true,
+                ConflictPolicy::Overwrite
+            ),
+            Decision::Update(2)
+        );
+        assert_eq!(
+            decide(
+                &login("john", "first", None),
+                &stored,
+                true,
+                ConflictPolicy::Overwrite
+            ),
+            Decision::Skip("Login already exists")
+        );
+    }
+    #[test]
+    fn test_to_logins_chrome() {
+        let table = generic::parse(
+            "name,url,username,password\nMail,https://mail.example.com,john,secret\n,https://example.com,,secret\nBroken,https://example.com,john,\n",
+        )
+        .unwrap();
+        let Logins { logins, failed } = to_logins(&table).unwrap();
+        assert_eq!(logins.len(), 2);
+        assert_eq!(failed, vec![(4, "Missing password".to_string())]);
+        assert_eq!(logins[0].name.as_deref(), Some("Mail"));
+        assert_eq!(logins[1].username, "");
+        assert!(logins[0].changed.is_none());
+
+        let (record, content) = to_record(&logins[1]).unwrap();
+        assert_eq!(record.title(), "example.com");
+        assert_eq!(record.category(), &Category::Login);
+        assert_eq!(content.len(), 3);
+    }
+    #[test]
+    fn test_to_logins_chrome_note() {
+        let table = generic::parse(
+            "name,url,username,password,note\nMail,https://mail.example.com,john,secret,\"PIN 1234\"\n",
+        )
+        .unwrap();
+        assert_eq!(detect(&table), Some(BrowserFormat::Chrome));
+        let Logins { logins, .. } = to_logins(&table).unwrap();
+        let (_, content) = to_record(&logins[0]).unwrap();
+        let labels: Vec<&str> = content.iter().map(|content| content.label()).collect();
+        assert_eq!(labels, vec!["Website", "User", "Password", "Note"]);
+        assert_eq!(
+            content[3].value(),
+            &Value::LongText(value::LongText::new("PIN 1234".to_string()))
+        );
+    }
+    #[test]
+    fn test_to_logins_firefox() {
+        let table = generic::parse(
+            "\"url\",\"username\",\"password\",\"httpRealm\",\"formActionOrigin\",\"guid\",\"timeCreated\",\"timeLastUsed\",\"timePasswordChanged\"\n\"https://example.com\",\"john\",\"secret\",,\"https://example.com\",\"{guid}\",\"1700000000000\",\"1700000000000\",\"1710000000000\"\n",
+        )
+        .unwrap();
+        assert_eq!(detect(&table), Some(BrowserFormat::Firefox));
+        let Logins { logins, failed } = to_logins(&table).unwrap();
+        assert!(failed.is_empty());
+        assert_eq!(
+            logins[0].changed,
+            Utc.timestamp_millis_opt(1_710_000_000_000).single()
+        );
+        let table = generic::parse("title,password\nMail,secret\n").unwrap();
+        assert_eq!(detect(&table), None);
+        assert!(to_logins(&table).is_err());
+    }
+}
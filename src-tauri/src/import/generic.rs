@@ -0,0 +1,516 @@
+use super::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Number of data rows included in [`Preview`].
+const SAMPLE_ROWS: usize = 5;
+
+/// Placeholder for sensitive values in [`Preview`].
+const MASK: &str = "••••••••";
+
+/// Record fields that CSV columns can be mapped to.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Field {
+    Title,
+    Url,
+    Username,
+    Password,
+    Notes,
+    Category,
+    Totp,
+}
+
+impl Field {
+    /// Converts a field name used in mappings to a field.
+    pub fn from_name(name: &str) -> Option<Field> {
+        match name {
+            "title" => Some(Field::Title),
+            "url" => Some(Field::Url),
+            "username" => Some(Field::Username),
+            "password" => Some(Field::Password),
+            "notes" => Some(Field::Notes),
+            "category" => Some(Field::Category),
+            "totp" => Some(Field::Totp),
+            _ => None,
+        }
+    }
+
+    /// Converts a field to the name used in mappings.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Field::Title => "title",
+            Field::Url => "url",
+            Field::Username => "username",
+            Field::Password => "password",
+            Field::Notes => "notes",
+            Field::Category => "category",
+            Field::Totp => "totp",
+        }
+    }
+
+    /// Guesses the field based on a column header.
+    fn guess(header: &str) -> Option<Field> {
+        let header = header.trim().to_lowercase();
+        let contains = |keywords: &[&str]| keywords.iter().any(|keyword| header.contains(keyword));
+        if contains(&["totp", "otp", "2fa"]) {
+            Some(Field::Totp)
+        } else if contains(&["pass"]) {
+            Some(Field::Password)
+        } else if contains(&["url", "uri", "website", "site"]) {
+            Some(Field::Url)
+        } else if contains(&["user", "login", "email"]) {
+            Some(Field::Username)
+        } else if contains(&["note", "comment", "extra"]) {
+            Some(Field::Notes)
+        } else if contains(&["category", "type"]) {
+            Some(Field::Category)
+        } else if contains(&["title", "name", "account"]) {
+            Some(Field::Title)
+        } else {
+            None
+        }
+    }
+}
+
+/// Single data row of a CSV file.
+#[derive(Zeroize)]
+pub struct Row {
+    /// Line of the file on which the row starts.
+    line: usize,
+    values: Vec<String>,
+}
+
+/// CSV file loaded into memory. Values are zeroized on drop.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Row>,
+}
+
+impl Row {
+    pub fn line(&self) -> usize {
+        self.line
+    }
+    pub fn values(&self) -> &[String] {
+        &self.values
+    }
+}
+
+impl Table {
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+}
+
+/// Header, first rows and guessed mapping of a CSV file for the user to confirm.
+#[derive(Clone, serde::Serialize)]
+pub struct Preview {
+    headers: Vec<String>,
+    sample: Vec<Vec<String>>,
+    mapping: HashMap<String, String>,
+}
+
+/// Detects the delimiter based on the first line. Supported delimiters are comma, semicolon and tab, comma is the default.
+fn detect_delimiter(data: &str) -> u8 {
+    let mut quoted = false;
+    let mut counts = [(b',', 0), (b';', 0), (b'\t', 0)];
+    for character in data.lines().next().unwrap_or_default().bytes() {
+        if character == b'"' {
+            quoted = !quoted;
+        } else if !quoted {
+            if let Some((_, count)) = counts
+                .iter_mut()
+                .find(|(delimiter, _)| *delimiter == character)
+            {
+                *count += 1;
+            }
+        }
+    }
+    counts
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .filter(|(_, count)| *count > 0)
+        .map_or(b',', |(delimiter, _)| *delimiter)
+}
+
+/// Parses CSV data. The first row is used as a header. Handles UTF-8 BOM, quoted values with newlines and detects the delimiter ([`detect_delimiter`]).
+/// # Errors
+/// If the data is not valid CSV or has no header.
+pub fn parse(data: &str) -> Result<Table, &'static str> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(detect_delimiter(data))
+        .flexible(true)
+        .from_reader(data.as_bytes());
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|_| "Failed to read header")?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+    if headers.iter().all(|header| header.is_empty()) {
+        return Err("Missing header");
+    }
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|_| "Failed to parse CSV")?;
+        rows.push(Row {
+            line: record
+                .position()
+                .map(|position| position.line() as usize)
+                .unwrap_or_default(),
+            values: record.iter().map(|value| value.to_string()).collect(),
+        });
+    }
+
+    Ok(Table { headers, rows })
+}
+
+/// Reads and parses a CSV file ([`parse`]).
+/// # Errors
+/// If the file cannot be read or parsed.
+pub fn read(path: &Path) -> Result<Table, &'static str> {
+    let data = Zeroizing::new(fs::read_to_string(path).map_err(|_| "Failed to read file")?);
+    parse(&data)
+}
+
+/// Guesses mapping of column headers to field names. Every field is mapped at most once, to the first matching column.
+pub fn guess_mapping(headers: &[String]) -> HashMap<String, String> {
+    let mut mapping: HashMap<String, String> = HashMap::with_capacity(headers.len());
+    for header in headers {
+        if let Some(field) = Field::guess(header) {
+            if mapping.values().all(|name| name != field.as_str()) {
+                mapping.insert(header.clone(), field.as_str().to_string());
+            }
+        }
+    }
+    mapping
+}
+
+/// Returns headers, first [`SAMPLE_ROWS`] rows and guessed mapping of the table. Values of columns guessed as passwords or TOTP secrets are masked.
+pub fn preview(table: &Table) -> Preview {
+    let mapping = guess_mapping(&table.headers);
+    let masked: Vec<bool> = table
+        .headers
+        .iter()
+        .map(|header| {
+            mapping
+                .get(header)
+                .and_then(|name| Field::from_name(name))
+                .is_some_and(|field| matches!(field, Field::Password | Field::Totp))
+        })
+        .collect();
+    let sample = table
+        .rows
+        .iter()
+        .take(SAMPLE_ROWS)
+        .map(|row| {
+            row.values
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    if masked.get(index).copied().unwrap_or_default() && !value.is_empty() {
+                        MASK.to_string()
+                    } else {
+                        value.clone()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    Preview {
+        headers: table.headers.clone(),
+        sample,
+        mapping,
+    }
+}
+
+/// Headers of the generic CSV format with the fields of their columns.
+pub const GENERIC_FORMAT: [(&str, Field); 6] = [
+    ("title", Field::Title),
+    ("category", Field::Category),
+    ("website", Field::Url),
+    ("username", Field::Username),
+    ("password", Field::Password),
+    ("notes", Field::Notes),
+];
+
+/// Returns the mapping of a file in the generic format ([`GENERIC_FORMAT`]). Headers are matched case-insensitively, columns may be in any order and may be missing, except for the title.
+/// # Errors
+/// If the file has no title column.
+pub fn generic_mapping(headers: &[String]) -> Result<HashMap<String, String>, &'static str> {
+    let mapping: HashMap<String, String> = headers
+        .iter()
+        .filter_map(|header| {
+            GENERIC_FORMAT
+                .iter()
+                .find(|(name, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, field)| (header.clone(), field.as_str().to_string()))
+        })
+        .collect();
+    if mapping.values().all(|name| name != Field::Title.as_str()) {
+        return Err("Missing title column");
+    }
+    Ok(mapping)
+}
+
+/// Converts a single row to a record with content. Content follows the default layout of the category.
+/// # Errors
+/// If a value is not valid for its field or the row has no title.
+fn row_to_record(
+    headers: &[String],
+    columns: &[Option<Field>],
+    values: &[String],
+    keep_unmapped: bool,
+) -> Result<Entry, &'static str> {
+    let get = |field: Field| {
+        columns
+            .iter()
+            .position(|column| *column == Some(field))
+            .and_then(|index| values.get(index))
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+    };
+
+    let category = match get(Field::Category) {
+        Some(category) => Category::from_string(category.replace(' ', "")),
+        None if [Field::Url, Field::Username, Field::Password]
+            .into_iter()
+            .any(|field| get(field).is_some()) =>
+        {
+            Category::Login
+        }
+        None if get(Field::Notes).is_some() => Category::Note,
+        None => Category::Other,
+    };
+
+    let title = get(Field::Title)
+        .or(get(Field::Url))
+        .or(get(Field::Username))
+        .ok_or("Missing title")?;
+    let record = Record::new(
+        title.to_string(),
+        get(Field::Username).unwrap_or_default().to_string(),
+        category.clone(),
+    );
+
+    let mut content: Vec<Content> = Vec::with_capacity(values.len());
+    let mut push = |label: &str, required: bool, value: Value| {
+        let position = content.len() as u32;
+        content.push(Content::new(label.to_string(), position, required, value));
+    };
+
+    if let Some(url) = get(Field::Url) {
+        push(
+            "Website",
+            true,
+            Value::Url(value::Url::new(url.to_string())?),
+        );
+    }
+    if let Some(username) = get(Field::Username) {
+        push(
+            "User",
+            true,
+            Value::Text(value::Text::new(username.to_string())),
+        );
+    }
+    if let Some(password) = get(Field::Password) {
+        push(
+            "Password",
+            true,
+            Value::Password(value::Password::new(password.to_string())),
+        );
+    }
+    if let Some(totp) = get(Field::Totp) {
+        push(
+            "TOTP",
+            false,
+            Value::TOTPSecret(value::TOTPSecret::new(totp.to_string())?),
+        );
+    }
+    if let Some(notes) = get(Field::Notes) {
+        push(
+            "Note",
+            category == Category::Note,
+            Value::LongText(value::LongText::new(notes.to_string())),
+        );
+    }
+    if keep_unmapped {
+        for (index, header) in headers.iter().enumerate() {
+            let Some(value) = values.get(index).map(|value| value.trim()) else {
+                continue;
+            };
+            if columns[index].is_none() && !value.is_empty() {
+                push(
+                    header,
+                    false,
+                    Value::Text(value::Text::new(value.to_string())),
+                );
+            }
+        }
+    }
+
+    Ok((record, content))
+}
+
+/// Converts rows of the table to records based on the mapping of column headers to field names ([`Field::from_name`]). Unmapped columns are added as extra text content if `keep_unmapped` is set.
+/// # Errors
+/// If the mapping contains unknown field names or maps a field more than once.
+pub fn to_records(
+    table: &Table,
+    mapping: &HashMap<String, String>,
+    keep_unmapped: bool,
+) -> Result<Converted, &'static str> {
+    let mut columns: Vec<Option<Field>> = Vec::with_capacity(table.headers.len());
+    for header in &table.headers {
+        let field = match mapping.get(header) {
+            Some(name) if name.is_empty() => None,
+            Some(name) => Some(Field::from_name(name).ok_or("Unknown field in mapping")?),
+            None => None,
+        };
+        if field.is_some() && columns.contains(&field) {
+            return Err("Field is mapped more than once");
+        }
+        columns.push(field);
+    }
+
+    let mut records = Vec::with_capacity(table.rows.len());
+    let mut failed = Vec::new();
+    for row in &table.rows {
+        match row_to_record(&table.headers, &columns, &row.values, keep_unmapped) {
+            Ok(record) => records.push(record),
+            Err(error) => failed.push((row.line, error.to_string())),
+        }
+    }
+
+    Ok(Converted { records, failed })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_quoted_newline() {
+        let table =
+            parse("title,notes\n\"Bank\",\"first line\nsecond line\"\nMail,short\n").unwrap();
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0].values[1], "first line\nsecond line");
+        assert_eq!(table.rows[1].line, 4);
+    }
+    #[test]
+    fn test_parse_bom() {
+        let table = parse("\u{feff}title,url\nMail,https://example.com\n").unwrap();
+        assert_eq!(table.headers, vec!["title", "url"]);
+    }
+    #[test]
+    fn test_parse_semicolon() {
+        let table = parse("title;url;notes\nMail;https://example.com;\"a, b\"\n").unwrap();
+        assert_eq!(table.headers, vec!["title", "url", "notes"]);
+        assert_eq!(table.rows[0].values[2], "a, b");
+    }
+    #[test]
+    fn test_detect_delimiter() {
+        assert_eq!(detect_delimiter("a,b,c"), b',');
+        assert_eq!(detect_delimiter("a;b;c"), b';');
+        assert_eq!(detect_delimiter("a\tb\tc"), b'\t');
+        assert_eq!(detect_delimiter("\"a;b\",c"), b',');
+        assert_eq!(detect_delimiter(""), b',');
+    }
+    #[test]
+    fn test_guess_mapping() {
+        let headers: Vec<String> = [
+            "Name",
+            "Login URI",
+            "Login",
+            "Password",
+            "TOTP",
+            "Notes",
+            "Folder",
+        ]
+        .iter()
+        .map(|header| header.to_string())
+        .collect();
+        let mapping = guess_mapping(&headers);
+        assert_eq!(mapping.get("Name").unwrap(), "title");
+        assert_eq!(mapping.get("Login URI").unwrap(), "url");
+        assert_eq!(mapping.get("Login").unwrap(), "username");
+        assert_eq!(mapping.get("Password").unwrap(), "password");
+        assert_eq!(mapping.get("TOTP").unwrap(), "totp");
+        assert_eq!(mapping.get("Notes").unwrap(), "notes");
+        assert!(!mapping.contains_key("Folder"));
+    }
+    #[test]
+    fn test_preview_masks_passwords() {
+        let table = parse("title,password\nMail,secret\n").unwrap();
+        let preview = preview(&table);
+        assert_eq!(preview.sample[0], vec!["Mail", MASK]);
+    }
+    #[test]
+    fn test_to_records() {
+        let table = parse(
+            "title,url,user,password,group\nMail,https://example.com,john,secret,Work\nBroken,invalid url,john,secret,\n",
+        )
+        .unwrap();
+        let mapping = guess_mapping(&table.headers);
+        let Converted { records, failed } = to_records(&table, &mapping, true).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(failed, vec![(3, "Invalid URL".to_string())]);
+
+        let (record, content) = &records[0];
+        assert_eq!(record.title(), "Mail");
+        assert_eq!(record.subtitle(), "john");
+        assert_eq!(record.category(), &Category::Login);
+        let labels: Vec<&str> = content.iter().map(|content| content.label()).collect();
+        assert_eq!(labels, vec!["Website", "User", "Password", "group"]);
+        assert_eq!(
+            content[2].value(),
+            &Value::Password(value::Password::new("secret".to_string()))
+        );
+    }
+    #[test]
+    fn test_generic_mapping() {
+        let table = parse(
+            "Title,category,website,username,password,notes,folder\nMail,Login,https://example.com,john,secret,,Work\nBroken,Login,invalid url,john,secret,,\n,Note,,,,text,\nCard,Bank Card,,,,,\n",
+        )
+        .unwrap();
+        let mapping = generic_mapping(&table.headers).unwrap();
+        assert_eq!(mapping.get("Title").unwrap(), "title");
+        assert_eq!(mapping.get("website").unwrap(), "url");
+        assert!(!mapping.contains_key("folder"));
+
+        let Converted { records, failed } = to_records(&table, &mapping, true).unwrap();
+        assert_eq!(
+            failed,
+            vec![
+                (3, "Invalid URL".to_string()),
+                (4, "Missing title".to_string())
+            ]
+        );
+        assert_eq!(records.len(), 2);
+        let labels: Vec<&str> = records[0].1.iter().map(|content| content.label()).collect();
+        assert_eq!(labels, vec!["Website", "User", "Password", "folder"]);
+        assert_eq!(records[1].0.category(), &Category::BankCard);
+
+        let headers = vec!["website".to_string(), "password".to_string()];
+        assert_eq!(generic_mapping(&headers), Err("Missing title column"));
+    }
+    #[test]
+    fn test_to_records_invalid_mapping() {
+        let table = parse("a,b\n1,2\n").unwrap();
+        let mapping = HashMap::from([("a".to_string(), "unknown".to_string())]);
+        assert!(to_records(&table, &mapping, false).is_err());
+        let mapping = HashMap::from([
+            ("a".to_string(), "title".to_string()),
+            ("b".to_string(), "title".to_string()),
+        ]);
+        assert!(to_records(&table, &mapping, false).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use super::*;
+use ::keepass::db::{Entry as KeePassEntry, Group, Node};
+use ::keepass::error::{DatabaseKeyError, DatabaseOpenError};
+use ::keepass::DatabaseKey;
+use std::fs::File;
+use std::ops::Not;
+use std::path::Path;
+
+/// Fields of KeePass entries that are converted to the default content, other fields are added as extra content.
+const STANDARD_FIELDS: [&str; 6] = ["Title", "UserName", "Password", "URL", "Notes", "otp"];
+
+/// Opens a KeePass database (KDBX 3 and 4, KDB) with its password.
+/// # Errors
+/// If the file cannot be read, the password is wrong or the file is not a supported KeePass database.
+pub fn read(path: &Path, password: &str) -> Result<::keepass::Database, &'static str> {
+    let mut file = File::open(path).map_err(|_| "Failed to read file")?;
+    ::keepass::Database::open(&mut file, DatabaseKey::new().with_password(password)).map_err(
+        |error| match error {
+            DatabaseOpenError::Io(_) => "Failed to read file",
+            DatabaseOpenError::Key(DatabaseKeyError::IncorrectKey) => "Wrong password",
+            DatabaseOpenError::UnsupportedVersion => "KeePass database version is not supported",
+            _ => "Not a KeePass database",
+        },
+    )
+}
+
+/// Returns the trimmed value of the field, `None` if it is missing or blank.
+fn field<'a>(entry: &'a KeePassEntry, name: &str) -> Option<&'a str> {
+    entry
+        .get(name)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Converts a single entry to a record with content. Category is guessed like for CSV rows: login if the entry has a username, password or URL, note if it has only notes. Values that fail validation are kept as text, see [`validated`]. Path of the group is added as `Group` content, so the folder structure is not lost.
+fn entry_to_record(entry: &KeePassEntry, group: &str) -> Entry {
+    let username = field(entry, "UserName");
+    let password = field(entry, "Password");
+    let url = field(entry, "URL");
+    let notes = field(entry, "Notes");
+    let category = if username.is_some() || password.is_some() || url.is_some() {
+        Category::Login
+    } else if notes.is_some() {
+        Category::Note
+    } else {
+        Category::Other
+    };
+
+    let mut content: Vec<Content> = Vec::new();
+    let mut push = |label: &str, required: bool, value: Value| {
+        let position = content.len() as u32;
+        content.push(Content::new(label.to_string(), position, required, value));
+    };
+    if let Some(url) = url {
+        push(
+            "Website",
+            true,
+            validated(url, false, |url| value::Url::new(url).map(Value::Url)),
+        );
+    }
+    if let Some(username) = username {
+        push(
+            "User",
+            true,
+            Value::Text(value::Text::new(username.to_string())),
+        );
+    }
+    if let Some(password) = password {
+        push(
+            "Password",
+            true,
+            Value::Password(value::Password::new(password.to_string())),
+        );
+    }
+    if let Some(secret) = field(entry, "otp") {
+        push("TOTP", false, validated(secret, true, totp));
+    }
+    if let Some(notes) = notes {
+        push(
+            "Note",
+            category == Category::Note,
+            Value::LongText(value::LongText::new(notes.to_string())),
+        );
+    }
+    let mut custom: Vec<(&String, &::keepass::db::Value)> = entry
+        .fields
+        .iter()
+        .filter(|(name, _)| STANDARD_FIELDS.contains(&name.as_str()).not())
+        .collect();
+    custom.sort_by_key(|(name, _)| name.as_str());
+    for (name, value) in custom {
+        let Some(text) = field(entry, name) else {
+            continue;
+        };
+        let value = match value {
+            ::keepass::db::Value::Protected(_) => {
+                Value::SensitiveText(value::SensitiveText::new(text.to_string()))
+            }
+            _ => Value::Text(value::Text::new(text.to_string())),
+        };
+        push(name, false, value);
+    }
+    if group.is_empty().not() {
+        push(
+            "Group",
+            false,
+            Value::Text(value::Text::new(group.to_string())),
+        );
+    }
+
+    let title = field(entry, "Title").unwrap_or("Untitled").to_string();
+    (
+        Record::new(title, username.unwrap_or_default().to_string(), category),
+        content,
+    )
+}
+
+/// Converts entries of the group and its subgroups. `path` is the path of the group, without the root group.
+fn group_to_records(group: &Group, path: &str, records: &mut Vec<Entry>) {
+    for node in &group.children {
+        match node {
+            Node::Entry(entry) => records.push(entry_to_record(entry, path)),
+            Node::Group(subgroup) => {
+                let path = match path {
+                    "" => subgroup.name.clone(),
+                    _ => format!("{path}/{}", subgroup.name),
+                };
+                group_to_records(subgroup, &path, records);
+            }
+        }
+    }
+}
+
+/// Converts all entries of the KeePass database to records. No entry fails, invalid values are kept as text.
+pub fn to_records(database: &::keepass::Database) -> Converted {
+    let mut records = Vec::new();
+    group_to_records(&database.root, "", &mut records);
+    Converted {
+        records,
+        failed: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ::keepass::config::{DatabaseConfig, KdfConfig};
+    use ::keepass::db::Value as KeePassValue;
+    use tempfile::TempDir;
+
+    fn entry(fields: &[(&str, &str)]) -> KeePassEntry {
+        let mut entry = KeePassEntry::new();
+        for (name, value) in fields {
+            entry.fields.insert(
+                name.to_string(),
+                KeePassValue::Unprotected(value.to_string()),
+            );
+        }
+        entry
+    }
+
+    /// Writes a small KeePass database with a fast key derivation.
+    pub(crate) fn write(path: &Path, password: &str) {
+        let mut database = ::keepass::Database::new(DatabaseConfig {
+            kdf_config: KdfConfig::Aes { rounds: 10 },
+            ..DatabaseConfig::default()
+        });
+        let mut mail = entry(&[
+            ("Title", "Mail"),
+            ("UserName", "john"),
+            ("URL", "https://mail.example.com"),
+            ("Notes", "old account"),
+            (
+                "otp",
+                "otpauth://totp/Mail:john?secret=JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP&issuer=Mail",
+            ),
+            ("Recovery email", "john@example.com"),
+        ]);
+        mail.fields.insert(
+            "Password".to_string(),
+            KeePassValue::Protected(secstr::SecStr::from("secret")),
+        );
+        mail.fields.insert(
+            "PIN".to_string(),
+            KeePassValue::Protected(secstr::SecStr::from("1234")),
+        );
+        let mut email = Group::new("Email");
+        email.add_child(mail);
+        let mut work = Group::new("Work");
+        work.add_child(entry(&[("Title", "Intranet"), ("URL", "not a url")]));
+        email.add_child(work);
+        database.root.add_child(email);
+        database
+            .root
+            .add_child(entry(&[("Title", "Recipe"), ("Notes", "flour")]));
+        database
+            .save(
+                &mut File::create(path).unwrap(),
+                DatabaseKey::new().with_password(password),
+            )
+            .unwrap();
+    }
+
+    fn labels(content: &[Content]) -> Vec<&str> {
+        content.iter().map(|content| content.label()).collect()
+    }
+
+    #[test]
+    fn test_to_records() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database.kdbx");
+        write(&path, "password");
+        let Converted { records, failed } = to_records(&read(&path, "password").unwrap());
+        assert!(failed.is_empty());
+        let titles: Vec<&str> = records.iter().map(|(record, _)| record.title()).collect();
+        assert_eq!(titles, ["Mail", "Intranet", "Recipe"]);
+
+        let (record, content) = &records[0];
+        assert_eq!(record.subtitle(), "john");
+        assert_eq!(record.category(), &Category::Login);
+        assert_eq!(
+            labels(content),
+            [
+                "Website",
+                "User",
+                "Password",
+                "TOTP",
+                "Note",
+                "PIN",
+                "Recovery email",
+                "Group"
+            ]
+        );
+        assert_eq!(
+            content[2].value(),
+            &Value::Password(value::Password::new("secret".to_string()))
+        );
+        assert!(matches!(content[3].value(), Value::TOTPSecret(_)));
+        assert!(matches!(content[5].value(), Value::SensitiveText(_)));
+        assert!(matches!(content[6].value(), Value::Text(_)));
+        assert_eq!(
+            content[7].value(),
+            &Value::Text(value::Text::new("Email".to_string()))
+        );
+
+        let (_, content) = &records[1];
+        assert_eq!(
+            content[0].value(),
+            &Value::Text(value::Text::new("not a url".to_string()))
+        );
+        assert_eq!(
+            content[1].value(),
+            &Value::Text(value::Text::new("Email/Work".to_string()))
+        );
+
+        let (record, content) = &records[2];
+        assert_eq!(record.category(), &Category::Note);
+        assert_eq!(labels(content), ["Note"]);
+    }
+    #[test]
+    fn test_read_invalid() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("database.kdbx");
+        write(&path, "password");
+        assert_eq!(read(&path, "wrong").err(), Some("Wrong password"));
+        let csv = directory.path().join("passwords.csv");
+        std::fs::write(&csv, "title,password\nMail,secret\n").unwrap();
+        assert_eq!(read(&csv, "password").err(), Some("Not a KeePass database"));
+        assert_eq!(
+            read(&directory.path().join("missing.kdbx"), "password").err(),
+            Some("Failed to read file")
+        );
+    }
+}
@@ -0,0 +1,279 @@
+use crate::database::DATABASE_FILE_NAME;
+use crate::file_manager::atomic_write;
+use secrecy::SecretString;
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+
+/// Error returned on systems without a supported keychain.
+pub const NOT_SUPPORTED: &str = "Keychain unlock is not supported on this system";
+
+/// Error returned when the key saved in the keychain no longer opens the database, e.g. after the master password was changed on another device.
+pub const OUTDATED: &str = "Key saved in the keychain is outdated, log in with the master password";
+
+/// Error returned when the keychain has no key for the database.
+const NOT_FOUND: &str = "Key is not saved in the keychain";
+
+/// Service of the keychain items, the account is the path of the database.
+#[cfg(target_os = "macos")]
+const SERVICE: &str = "cz.vsb.PasswordManager.vault-key";
+
+/// Storage of the database key protected by the operating system.
+pub trait KeyStore {
+    /// Saves the key for the account, replacing the previous one.
+    fn store(&self, account: &str, key: &SecretString) -> Result<(), &'static str>;
+    /// Returns the key of the account. The user may be asked to confirm their presence.
+    fn load(&self, account: &str) -> Result<SecretString, &'static str>;
+    /// Deletes the key of the account. Returns [`NOT_FOUND`] if there is none.
+    fn delete(&self, account: &str) -> Result<(), &'static str>;
+}
+
+/// Keychain of the operating system. Only the macOS Keychain is supported, items are readable only after the user confirms their presence with Touch ID or the login password. Other systems return [`NOT_SUPPORTED`].
+pub struct SystemKeychain;
+
+#[cfg(target_os = "macos")]
+impl SystemKeychain {
+    /// Options of the item of the account, in the data protection keychain.
+    fn options(account: &str) -> security_framework::passwords::PasswordOptions {
+        let mut options =
+            security_framework::passwords::PasswordOptions::new_generic_password(SERVICE, account);
+        options.use_protected_keychain();
+        options
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl KeyStore for SystemKeychain {
+    fn store(&self, account: &str, key: &SecretString) -> Result<(), &'static str> {
+        use secrecy::ExposeSecret;
+        use security_framework::passwords_options::AccessControlOptions;
+        // Access control of an existing item cannot be updated
+        self.delete(account).or_else(|error| match error {
+            NOT_FOUND => Ok(()),
+            error => Err(error),
+        })?;
+        let mut options = SystemKeychain::options(account);
+        options.set_access_control_options(AccessControlOptions::USER_PRESENCE);
+        security_framework::passwords::set_generic_password_options(
+            key.expose_secret().as_bytes(),
+            options,
+        )
+        .map_err(|_| "Failed to save key to the keychain")
+    }
+
+    fn load(&self, account: &str) -> Result<SecretString, &'static str> {
+        let key = zeroize::Zeroizing::new(
+            security_framework::passwords::generic_password(SystemKeychain::options(account))
+                .map_err(|error| match error.code() {
+                    ERR_SEC_ITEM_NOT_FOUND => NOT_FOUND,
+                    ERR_SEC_USER_CANCELED => "Canceled by user",
+                    _ => "Failed to read key from the keychain",
+                })?,
+        );
+        let key = std::str::from_utf8(&key).map_err(|_| "Failed to read key from the keychain")?;
+        Ok(SecretString::new(key.to_string()))
+    }
+
+    fn delete(&self, account: &str) -> Result<(), &'static str> {
+        security_framework::passwords::delete_generic_password_options(SystemKeychain::options(
+            account,
+        ))
+        .map_err(|error| match error.code() {
+            ERR_SEC_ITEM_NOT_FOUND => NOT_FOUND,
+            _ => "Failed to delete key from the keychain",
+        })
+    }
+}
+
+/// `errSecItemNotFound` of the Security framework.
+#[cfg(target_os = "macos")]
+const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+
+/// `errSecUserCanceled` of the Security framework.
+#[cfg(target_os = "macos")]
+const ERR_SEC_USER_CANCELED: i32 = -128;
+
+#[cfg(not(target_os = "macos"))]
+impl KeyStore for SystemKeychain {
+    fn store(&self, _account: &str, _key: &SecretString) -> Result<(), &'static str> {
+        Err(NOT_SUPPORTED)
+    }
+
+    fn load(&self, _account: &str) -> Result<SecretString, &'static str> {
+        Err(NOT_SUPPORTED)
+    }
+
+    fn delete(&self, _account: &str) -> Result<(), &'static str> {
+        Err(NOT_SUPPORTED)
+    }
+}
+
+/// Returns true if the keychain of the system is supported, see [`SystemKeychain`].
+pub fn is_supported() -> bool {
+    cfg!(target_os = "macos")
+}
+
+/// Keychain unlock status for the login window.
+#[derive(Debug, PartialEq, Clone, Copy, serde::Serialize)]
+pub struct KeychainStatus {
+    pub supported: bool,
+    pub enabled: bool,
+}
+
+/// Returns path of the file marking that the key of the database is in the keychain. The marker tells the login window to offer keychain unlock without reading the keychain, which would ask the user to confirm their presence.
+pub fn path(database_path: &Path) -> PathBuf {
+    database_path.with_file_name(format!("{DATABASE_FILE_NAME}.keychain"))
+}
+
+/// Returns true if the key of the database was saved to the keychain, see [`enable`].
+pub fn is_enabled(database_path: &Path) -> bool {
+    path(database_path).exists()
+}
+
+/// Returns the keychain account of the database, its path.
+fn account(database_path: &Path) -> Result<&str, &'static str> {
+    database_path.to_str().ok_or("Path is not valid UTF-8")
+}
+
+/// Saves the derived key of the database to the keychain and marks keychain unlock as enabled, see [`crate::database::derived_key`].
+/// # Errors
+/// If the key cannot be saved, e.g. [`NOT_SUPPORTED`]. Keychain unlock is not enabled in that case.
+pub fn enable(
+    store: &impl KeyStore,
+    database_path: &Path,
+    key: &SecretString,
+) -> Result<(), &'static str> {
+    store.store(account(database_path)?, key)?;
+    atomic_write(&path(database_path), b"").map_err(|_| "Failed to enable keychain unlock")
+}
+
+/// Returns the key of the database from the keychain.
+/// # Errors
+/// If keychain unlock is not enabled or the key cannot be read, e.g. the user did not confirm their presence.
+pub fn load(store: &impl KeyStore, database_path: &Path) -> Result<SecretString, &'static str> {
+    if is_enabled(database_path).not() {
+        return Err("Keychain unlock is not enabled");
+    }
+    store.load(account(database_path)?)
+}
+
+/// Deletes the key of the database from the keychain and marks keychain unlock as disabled. A key that is already missing is not an error.
+/// # Errors
+/// If the key or the marker cannot be deleted.
+pub fn disable(store: &impl KeyStore, database_path: &Path) -> Result<(), &'static str> {
+    match store.delete(account(database_path)?) {
+        Ok(()) | Err(NOT_FOUND) => {}
+        // Marker copied from a supported system is still removed
+        Err(NOT_SUPPORTED) if is_enabled(database_path) => {}
+        Err(error) => return Err(error),
+    }
+    let marker = path(database_path);
+    if marker.exists().not() {
+        return Ok(());
+    }
+    std::fs::remove_file(marker).map_err(|_| "Failed to disable keychain unlock")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::ExposeSecret;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    /// Key store keeping the keys in memory.
+    #[derive(Default)]
+    struct MemoryStore(RefCell<HashMap<String, String>>);
+
+    impl KeyStore for MemoryStore {
+        fn store(&self, account: &str, key: &SecretString) -> Result<(), &'static str> {
+            self.0
+                .borrow_mut()
+                .insert(account.to_string(), key.expose_secret().clone());
+            Ok(())
+        }
+
+        fn load(&self, account: &str) -> Result<SecretString, &'static str> {
+            self.0
+                .borrow()
+                .get(account)
+                .map(|key| SecretString::new(key.clone()))
+                .ok_or(NOT_FOUND)
+        }
+
+        fn delete(&self, account: &str) -> Result<(), &'static str> {
+            self.0
+                .borrow_mut()
+                .remove(account)
+                .map(|_| ())
+                .ok_or(NOT_FOUND)
+        }
+    }
+
+    #[test]
+    fn test_enable_load_disable() {
+        let directory = TempDir::new().unwrap();
+        let database_path = directory.path().join(DATABASE_FILE_NAME);
+        let store = MemoryStore::default();
+        assert!(is_enabled(&database_path).not());
+        assert_eq!(
+            load(&store, &database_path).err(),
+            Some("Keychain unlock is not enabled")
+        );
+
+        enable(
+            &store,
+            &database_path,
+            &SecretString::new("x'00'".to_string()),
+        )
+        .unwrap();
+        assert!(is_enabled(&database_path));
+        assert_eq!(
+            load(&store, &database_path).unwrap().expose_secret(),
+            "x'00'"
+        );
+
+        disable(&store, &database_path).unwrap();
+        assert!(is_enabled(&database_path).not());
+        assert!(store.0.borrow().is_empty());
+        assert_eq!(disable(&store, &database_path), Ok(()));
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 0);
+    }
+    #[test]
+    fn test_disable_missing_key() {
+        let directory = TempDir::new().unwrap();
+        let database_path = directory.path().join(DATABASE_FILE_NAME);
+        let store = MemoryStore::default();
+        enable(
+            &store,
+            &database_path,
+            &SecretString::new("x'00'".to_string()),
+        )
+        .unwrap();
+        store.0.borrow_mut().clear();
+        assert_eq!(load(&store, &database_path).err(), Some(NOT_FOUND));
+        assert_eq!(disable(&store, &database_path), Ok(()));
+        assert!(is_enabled(&database_path).not());
+    }
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn test_not_supported() {
+        let directory = TempDir::new().unwrap();
+        let database_path = directory.path().join(DATABASE_FILE_NAME);
+        assert!(is_supported().not());
+        assert_eq!(
+            enable(
+                &SystemKeychain,
+                &database_path,
+                &SecretString::new("x'00'".to_string())
+            ),
+            Err(NOT_SUPPORTED)
+        );
+        assert!(is_enabled(&database_path).not());
+        assert_eq!(
+            load(&SystemKeychain, &database_path).err(),
+            Some("Keychain unlock is not enabled")
+        );
+        assert_eq!(disable(&SystemKeychain, &database_path), Err(NOT_SUPPORTED));
+    }
+}
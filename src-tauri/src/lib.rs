@@ -1,18 +1,78 @@
 #![allow(unused_imports)]
+mod activity;
+mod archive;
+mod autostart;
+mod benchmark;
+mod build_info;
+mod changelog;
+mod clipboard;
 mod cloud;
 mod command;
 mod database;
+mod error;
+mod events;
+mod export;
+mod file_manager;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+mod generator;
+#[cfg(test)]
+mod harness;
+mod hint;
+mod hooks;
+mod http;
+mod import;
+mod keychain;
+mod login;
+mod metrics;
+mod panic;
+mod policy;
+mod presentation;
+mod reauth;
+mod reveal;
+mod search;
+mod session;
+mod throttle;
 mod totp;
+mod typing;
 mod window;
 
+/// Items used by the integration tests in `tests/`, which are built with the fixtures.
+#[cfg(feature = "fixtures")]
+pub mod testing {
+    pub use crate::command::password::group_passwords;
+    pub use crate::database::Database;
+}
+
+use activity::ActivityCache;
+use clipboard::ClipboardOwner;
 use command::authentication::*;
+use command::autostart::*;
+use command::benchmark::*;
+use command::build_info::*;
+use command::changelog::*;
 use command::cloud::*;
 use command::database::*;
+use command::events::*;
+use command::hooks::*;
+use command::import::*;
+use command::metrics::*;
 use command::password::*;
 use command::totp::*;
 use command::validation::*;
 use command::window::*;
 use command::*;
+use database::retention::RetentionReport;
+use database::Database;
+use error::Error;
+use events::{Event, SingleInstancePayload};
+use file_manager::LastExport;
+use generator::GeneratorHistory;
+use metrics::CommandMetrics;
+use policy::PasswordPolicy;
+use reauth::Reauthentication;
+use reveal::RevealTimer;
+use session::{Session, SessionState};
 use tauri::{AppHandle, Manager, Window};
 use tauri_plugin_window_state::{AppHandleExt, StateFlags};
 use totp::TOTPManager;
@@ -29,14 +89,32 @@ fn critical_error(message: &str, app_handle: &AppHandle, window: &Window) {
     .buttons(tauri::api::dialog::MessageDialogButtons::Ok)
     .parent(window)
     .show();
-    app_handle.restart();
+    session::lock(app_handle, SessionState::Locked);
+}
+
+/// Compacts the database when the application exits, if [`database::COMPACT_SETTING`] is enabled. Skipped if a cloud transfer is in progress, so the uploaded file is not rebuilt.
+fn compact_on_exit(app_handle: &AppHandle) {
+    let Some(database) = app_handle.try_state::<Database>() else {
+        return;
+    };
+    let enabled = database
+        .get_setting(database::COMPACT_SETTING)
+        .is_ok_and(|value| value.expose_secret() == "true");
+    if !enabled {
+        return;
+    }
+    if let Ok(_permit) = cloud::pause_transfers() {
+        database
+            .compact(database::COMPACT_THRESHOLD)
+            .unwrap_or_default();
+    }
 }
 
-/// Payload for single instance plugin.
-#[derive(Clone, serde::Serialize)]
-struct Payload {
-    args: Vec<String>,
-    cwd: String,
+/// Checkpoints the database when the application exits, so no committed changes are left only in the write-ahead log ([`Database::checkpoint`]).
+fn checkpoint_on_exit(app_handle: &AppHandle) {
+    if let Some(database) = app_handle.try_state::<Database>() {
+        database.checkpoint().unwrap_or_default();
+    }
 }
 
 /// Runs the tauri application.
@@ -46,48 +124,180 @@ struct Payload {
 /// - https://github.com/tauri-apps/plugins-workspace/tree/v1/plugins/window-state
 ///
 /// Note: The window-state plugin is only used on macOS due to bug on Linux contained in the plugin.
+///
+/// Development builds print warnings, e.g. slow commands ([`metrics::traced`]), to the standard output.
 pub fn run() -> anyhow::Result<()> {
+    #[cfg(debug_assertions)]
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::WARN)
+        .try_init()
+        .unwrap_or_default();
+
     let app_builder = tauri::Builder::default()
         .plugin(tauri_plugin_context_menu::init())
         .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
-            app.emit_all("single-instance", Payload { args: argv, cwd })
-                .unwrap_or_default();
+            window::focus_existing_window(app);
+            events::emit_all(
+                app,
+                Event::SingleInstance(SingleInstancePayload { args: argv, cwd }),
+            )
+            .unwrap_or_default();
         }))
         .manage(TOTPManager::new(50))
-        .invoke_handler(tauri::generate_handler![
+        .manage(GeneratorHistory::new(10))
+        .manage(LastExport::default())
+        .manage(Reauthentication::default())
+        .manage(RevealTimer::default())
+        .manage(ActivityCache::default())
+        .manage(RetentionReport::default())
+        .manage(ClipboardOwner::default())
+        .manage(Session::default())
+        .manage(CommandMetrics::default())
+        .manage(PasswordPolicy::default())
+        .invoke_handler(metrics::traced(tauri::generate_handler![
             initialize_window,
             login,
             register,
             change_password,
+            verify_master_password,
+            set_master_password_hint,
+            clear_master_password_hint,
+            get_master_password_hint,
+            login_with_keychain,
+            get_keychain_status,
+            enable_keychain_unlock,
+            disable_keychain_unlock,
+            get_session_state,
             get_all_records,
+            get_record,
+            get_all_records_with_tags,
             get_compromised_records,
+            get_stale_passwords,
+            acknowledge_finding,
+            get_acknowledgements,
+            revoke_acknowledgement,
+            replace_url_prefix,
             get_all_content_for_record,
             get_content_value,
+            get_content_history,
+            restore_content_version,
+            export_csv,
+            export_inventory,
+            export_encrypted_archive,
+            export_notes_encrypted,
+            cancel_reveal,
             save_record,
+            save_draft,
+            get_draft,
+            discard_draft,
+            set_record_metadata,
+            get_record_metadata,
+            delete_record_metadata,
+            vault_stats,
+            get_retention_stats,
+            get_activity_summary,
+            set_record_locked,
+            set_favorite,
+            add_tag,
+            remove_tag,
+            get_tags_for_record,
+            get_records_by_tag,
+            get_all_tags,
+            mark_recovery_code_used,
             delete_record,
+            delete_records,
+            move_records_to_category,
+            recompute_derived_columns,
+            get_trashed_records,
+            restore_record,
+            purge_record,
             delete_content,
+            send_record_to_vault,
+            search_records,
+            get_records_window,
+            count_records,
+            get_records_page,
+            get_record_count,
+            set_record_rank_between,
+            inspect_csv,
+            import_csv,
+            import_bitwarden,
+            import_keepass,
+            import_csv_mapped,
+            import_browser_csv,
             get_totp_code,
+            check_time_sync,
+            export_totp_bundle,
+            import_totp_bundle,
+            import_encrypted_archive,
+            import_notes_encrypted,
             copy_value_to_clipboard,
+            type_value,
+            copy_record_block,
             check_password,
             check_password_from_database,
             password_strength,
+            check_master_password,
             generate_password,
+            get_generated_history,
+            clear_generated_history,
             validate,
             card_type,
             cloud_data,
             enable_cloud,
             disable_cloud,
             cloud_upload,
-        ]);
+            push_database_sftp,
+            pull_database_sftp,
+            set_autostart,
+            get_autostart_status,
+            get_hooks,
+            set_hook,
+            test_hook,
+            get_changelog,
+            mark_changelog_seen,
+            reveal_in_file_manager,
+            open_presentation_window,
+            set_menu_accelerator,
+            set_panic_shortcut,
+            panic_lock,
+            list_events,
+            benchmark_vault,
+            build_info,
+            get_command_metrics,
+            reset_command_metrics,
+        ]));
 
     #[cfg(target_os = "macos")]
     let app_builder = app_builder.plugin(tauri_plugin_window_state::Builder::default().build());
 
     let app = app_builder.build(tauri::generate_context!())?;
 
+    if let (Some(autostart), Ok(executable)) = (
+        autostart::Autostart::new(&app.app_handle()),
+        std::env::current_exe(),
+    ) {
+        autostart.refresh(&executable).unwrap_or_default();
+    }
+
+    if Database::path(&app.app_handle())
+        .is_some_and(|path| cloud::restore_backup(&path).unwrap_or_default())
+    {
+        tauri::api::dialog::message(
+            None::<&Window>,
+            "Database restored",
+            "Database file was missing after an interrupted cloud download. It was restored from the backup.",
+        );
+    }
+
     initialize_window(app.app_handle())?;
 
-    app.run(|_app_handle, _event| { /* Can react to events */ });
+    app.run(|app_handle, event| {
+        if let tauri::RunEvent::Exit = event {
+            compact_on_exit(app_handle);
+            checkpoint_on_exit(app_handle);
+        }
+    });
 
     Ok(())
 }
@@ -0,0 +1,103 @@
+use crate::error::Error;
+use crate::events::LoginProgress;
+use std::time::Duration;
+
+/// Longest time a cloud stage of the login may take before the login continues without the cloud. Also used as the timeout of blocking SSH operations, so a stalled download fails too.
+pub const STAGE_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Error returned when a stage of the login does not finish within its timeout.
+pub const STAGE_TIMED_OUT: &str = "Cloud did not respond in time";
+
+/// Stage of the login reported to the login window, see [`run_stage`].
+#[derive(Debug, PartialEq, Clone, Copy, serde::Serialize)]
+pub enum LoginStage {
+    ConnectingToCloud,
+    ComparingVersions,
+    Downloading,
+    OpeningVault,
+}
+
+/// Returns the transferred part of the total as a percentage. Empty transfer is complete.
+pub fn percent(transferred: u64, total: u64) -> u8 {
+    match total {
+        0 => 100,
+        total => (transferred.min(total) * 100 / total) as u8,
+    }
+}
+
+/// Reports the stage and runs its blocking `work` on the blocking thread pool, so the login window stays responsive. With a timeout, the login does not wait for the work longer than that. The work cannot be interrupted, it finishes in the background and its result is dropped.
+/// # Errors
+/// Returns the error of the work, or [`STAGE_TIMED_OUT`] if it did not finish within the timeout.
+pub async fn run_stage<T: Send + 'static>(
+    stage: LoginStage,
+    timeout: Option<Duration>,
+    report: &impl Fn(LoginProgress),
+    work: impl FnOnce() -> Result<T, Error> + Send + 'static,
+) -> Result<T, Error> {
+    report(LoginProgress {
+        stage,
+        percent: None,
+    });
+    let handle = tauri::async_runtime::spawn_blocking(work);
+    let result = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, handle)
+            .await
+            .map_err(|_| Error::Network(STAGE_TIMED_OUT))?,
+        None => handle.await,
+    };
+    result.map_err(|_| Error::Other("Login stage failed"))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(0, 200), 0);
+        assert_eq!(percent(50, 200), 25);
+        assert_eq!(percent(200, 200), 100);
+        assert_eq!(percent(300, 200), 100);
+        assert_eq!(percent(0, 0), 100);
+    }
+    #[tokio::test]
+    async fn test_run_stage() {
+        let reported = Mutex::new(Vec::new());
+        let report = |progress: LoginProgress| reported.lock().unwrap().push(progress.stage);
+
+        let result = run_stage(
+            LoginStage::ConnectingToCloud,
+            Some(STAGE_TIMEOUT),
+            &report,
+            || Ok(7),
+        )
+        .await;
+        assert_eq!(result, Ok(7));
+        let result: Result<(), _> = run_stage(LoginStage::ComparingVersions, None, &report, || {
+            Err(Error::Network("Failed to get cloud metadata"))
+        })
+        .await;
+        assert_eq!(result, Err(Error::Network("Failed to get cloud metadata")));
+        assert_eq!(
+            *reported.lock().unwrap(),
+            [LoginStage::ConnectingToCloud, LoginStage::ComparingVersions]
+        );
+    }
+    #[tokio::test]
+    async fn test_run_stage_timeout() {
+        let start = std::time::Instant::now();
+        let result = run_stage(
+            LoginStage::ConnectingToCloud,
+            Some(Duration::from_millis(50)),
+            &|_| {},
+            || {
+                std::thread::sleep(Duration::from_secs(2));
+                Ok(())
+            },
+        )
+        .await;
+        assert_eq!(result, Err(Error::Network(STAGE_TIMED_OUT)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Invoke, Manager, Runtime};
+
+/// Commands running at least this long are logged at warn level.
+pub const SLOW_COMMAND: Duration = Duration::from_millis(100);
+
+/// Upper bounds of the duration histogram buckets in milliseconds. Durations above the last bound fall into an extra last bucket.
+pub const BUCKET_BOUNDS_MS: [u64; 6] = [1, 5, 10, 50, 100, 500];
+
+/// Calls and durations of a single command. Arguments and results are never recorded.
+#[derive(Debug, PartialEq, Clone, Default, serde::Serialize)]
+pub struct CommandMetric {
+    pub calls: u64,
+    pub total_ms: f64,
+    pub max_ms: f64,
+    /// Number of calls in each bucket of [`BUCKET_BOUNDS_MS`], with one more bucket for slower calls.
+    pub histogram: [u64; BUCKET_BOUNDS_MS.len() + 1],
+}
+
+impl CommandMetric {
+    fn record(&mut self, duration: Duration) {
+        let milliseconds = duration.as_secs_f64() * 1000.0;
+        self.calls += 1;
+        self.total_ms += milliseconds;
+        self.max_ms = self.max_ms.max(milliseconds);
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|bound| milliseconds <= *bound as f64)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        self.histogram[bucket] += 1;
+    }
+}
+
+/// Metrics of invoked commands by name for tauri state, recorded by [`traced`].
+#[derive(Default)]
+pub struct CommandMetrics(Mutex<HashMap<String, CommandMetric>>);
+
+impl CommandMetrics {
+    /// Records a call of the command which took `duration`.
+    pub fn record(&self, command: &str, duration: Duration) {
+        if let Ok(mut metrics) = self.0.lock() {
+            metrics
+                .entry(command.to_string())
+                .or_default()
+                .record(duration);
+        }
+    }
+
+    /// Returns metrics of all commands called since the start or the last [`CommandMetrics::reset`], sorted by name.
+    /// # Errors
+    /// If the metrics mutex is poisoned.
+    pub fn snapshot(&self) -> Result<BTreeMap<String, CommandMetric>, &'static str> {
+        self.0
+            .lock()
+            .map(|metrics| metrics.clone().into_iter().collect())
+            .map_err(|_| "Failed to access command metrics")
+    }
+
+    /// Forgets all recorded calls.
+    /// # Errors
+    /// If the metrics mutex is poisoned.
+    pub fn reset(&self) -> Result<(), &'static str> {
+        self.0
+            .lock()
+            .map(|mut metrics| metrics.clear())
+            .map_err(|_| "Failed to access command metrics")
+    }
+}
+
+/// Wraps the invoke handler, so every command call is recorded in [`CommandMetrics`] and calls slower than [`SLOW_COMMAND`] are logged with the command name and duration.
+///
+/// The handler runs on the thread handling IPC messages, which blocks the user interface. Async commands are measured only until they are spawned, the rest runs on the async runtime.
+pub fn traced<R: Runtime>(
+    handler: impl Fn(Invoke<R>) + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) + Send + Sync + 'static {
+    move |invoke: Invoke<R>| {
+        let command = invoke.message.command().to_string();
+        let app_handle = invoke.message.window_ref().app_handle();
+        let start = Instant::now();
+        handler(invoke);
+        let duration = start.elapsed();
+        if let Some(metrics) = app_handle.try_state::<CommandMetrics>() {
+            metrics.record(&command, duration);
+        }
+        if duration >= SLOW_COMMAND {
+            tracing::warn!(
+                command,
+                duration_ms = duration.as_millis() as u64,
+                "Slow command"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let metrics = CommandMetrics::default();
+        for milliseconds in [0, 3, 3, 70, 2000] {
+            metrics.record("save_record", Duration::from_millis(milliseconds));
+        }
+        metrics.record("search_records", Duration::from_millis(7));
+
+        let snapshot = metrics.snapshot().unwrap();
+        assert_eq!(
+            snapshot.keys().collect::<Vec<_>>(),
+            ["save_record", "search_records"]
+        );
+        let save = &snapshot["save_record"];
+        assert_eq!(save.calls, 5);
+        assert_eq!(save.total_ms, 2076.0);
+        assert_eq!(save.max_ms, 2000.0);
+        assert_eq!(save.histogram, [1, 2, 0, 0, 1, 0, 1]);
+        assert_eq!(snapshot["search_records"].histogram, [0, 0, 1, 0, 0, 0, 0]);
+
+        metrics.reset().unwrap();
+        assert!(metrics.snapshot().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::clipboard::ClipboardOwner;
+use crate::database::Database;
+use crate::session::{self, SessionState};
+use crate::window::accelerator;
+use secrecy::ExposeSecret;
+use std::ops::Not;
+use tauri::{AppHandle, GlobalShortcutManager, Manager};
+
+/// Setting with the global shortcut of the panic lock. No shortcut is registered unless it is set.
+pub const SHORTCUT_SETTING: &str = "panic_shortcut";
+
+/// Setting with the time of the last panic lock, the only record it leaves.
+pub const LAST_PANIC_SETTING: &str = "last_panic_lock";
+
+/// Steps of [`panic_lock`], in the order they run.
+pub trait PanicSteps {
+    /// Clears the clipboard if it holds a value copied by the application.
+    fn clear_clipboard(&self) -> Result<(), &'static str>;
+    /// Hides all windows of the application.
+    fn hide_windows(&self) -> Result<(), &'static str>;
+    /// Saves the time of the panic lock, see [`LAST_PANIC_SETTING`].
+    fn record(&self) -> Result<(), &'static str>;
+    /// Locks the vault. Never fails.
+    fn lock(&self);
+}
+
+/// Clears the clipboard, hides all windows, records the panic lock and locks the vault. Every step is best-effort: a failed step does not stop the others and the vault is always locked last.
+/// # Return
+/// Returns errors of the failed steps, if the lock returns at all.
+pub fn panic_lock(steps: &impl PanicSteps) -> Vec<&'static str> {
+    let errors = [
+        steps.clear_clipboard(),
+        steps.hide_windows(),
+        steps.record(),
+    ]
+    .into_iter()
+    .filter_map(Result::err)
+    .collect();
+    steps.lock();
+    errors
+}
+
+/// Steps of the panic lock in the running application. The vault is locked by restarting the application, see [`session::lock`].
+pub struct AppPanic(pub AppHandle);
+
+impl PanicSteps for AppPanic {
+    fn clear_clipboard(&self) -> Result<(), &'static str> {
+        self.0
+            .try_state::<ClipboardOwner>()
+            .map_or(Ok(()), |owner| owner.clear().map(|_| ()))
+    }
+
+    fn hide_windows(&self) -> Result<(), &'static str> {
+        let mut result = Ok(());
+        for window in self.0.windows().values() {
+            if window.hide().is_err() {
+                result = Err("Failed to hide window");
+            }
+        }
+        result
+    }
+
+    fn record(&self) -> Result<(), &'static str> {
+        self.0.try_state::<Database>().map_or(Ok(()), |database| {
+            database
+                .save_setting(LAST_PANIC_SETTING, &chrono::Local::now().to_rfc3339())
+                .map_err(|_| "Failed to save setting")
+        })
+    }
+
+    fn lock(&self) {
+        session::lock(&self.0, SessionState::LockedByPanic);
+    }
+}
+
+/// Runs the panic lock on a new thread, so it is not blocked by a modal dialog on the main thread.
+fn spawn_panic_lock(app_handle: AppHandle) {
+    std::thread::spawn(move || panic_lock(&AppPanic(app_handle)));
+}
+
+/// Registers the panic lock on the global shortcut, replacing the previous shortcut. Empty shortcut only removes the previous one.
+/// # Errors
+/// If the shortcut is not valid or is already used by another application.
+pub fn register(
+    app_handle: &AppHandle,
+    previous: &str,
+    shortcut: &str,
+) -> Result<(), &'static str> {
+    let shortcut = accelerator::parse(shortcut)?;
+    let mut manager = app_handle.global_shortcut_manager();
+    if previous.is_empty().not() {
+        manager.unregister(previous).unwrap_or_default();
+    }
+    if shortcut.is_empty() {
+        return Ok(());
+    }
+    let handle = app_handle.clone();
+    manager
+        .register(&shortcut, move || spawn_panic_lock(handle.clone()))
+        .map_err(|_| "Shortcut is already used")
+}
+
+/// Registers the shortcut from the settings after login, see [`SHORTCUT_SETTING`].
+pub fn register_saved(app_handle: &AppHandle, database: &Database) -> Result<(), &'static str> {
+    match database.get_setting(SHORTCUT_SETTING) {
+        Ok(shortcut) => register(app_handle, "", shortcut.expose_secret()),
+        Err(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Steps that record their order and fail as configured.
+    #[derive(Default)]
+    struct Recorded {
+        steps: RefCell<Vec<&'static str>>,
+        failing: Vec<&'static str>,
+    }
+
+    impl Recorded {
+        fn step(&self, name: &'static str) -> Result<(), &'static str> {
+            self.steps.borrow_mut().push(name);
+            match self.failing.contains(&name) {
+                true => Err(name),
+                false => Ok(()),
+            }
+        }
+    }
+
+    impl PanicSteps for Recorded {
+        fn clear_clipboard(&self) -> Result<(), &'static str> {
+            self.step("clipboard")
+        }
+        fn hide_windows(&self) -> Result<(), &'static str> {
+            self.step("hide")
+        }
+        fn record(&self) -> Result<(), &'static str> {
+            self.step("record")
+        }
+        fn lock(&self) {
+            self.steps.borrow_mut().push("lock");
+        }
+    }
+
+    #[test]
+    fn test_panic_lock_order() {
+        let steps = Recorded::default();
+        assert!(panic_lock(&steps).is_empty());
+        assert_eq!(
+            steps.steps.into_inner(),
+            ["clipboard", "hide", "record", "lock"]
+        );
+    }
+    #[test]
+    fn test_panic_lock_failed_steps() {
+        let steps = Recorded {
+            failing: vec!["clipboard", "record"],
+            ..Recorded::default()
+        };
+        assert_eq!(panic_lock(&steps), ["clipboard", "record"]);
+        assert_eq!(
+            steps.steps.into_inner(),
+            ["clipboard", "hide", "record", "lock"]
+        );
+    }
+}
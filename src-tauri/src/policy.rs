@@ -0,0 +1,117 @@
+/// Rule of the [`PasswordPolicy`] which the master password breaks, with the limit of the rule, so the frontend can show an actionable hint.
+#[derive(Debug, PartialEq, Clone, Copy, serde::Serialize)]
+#[serde(tag = "rule")]
+pub enum PolicyViolation {
+    TooShort { min_length: usize },
+    TooWeak { min_score: f64 },
+    Common,
+}
+
+impl PolicyViolation {
+    /// Returns the message for the violation, used as the error of commands.
+    pub fn message(&self) -> &'static str {
+        match self {
+            PolicyViolation::TooShort { .. } => "Password is too short",
+            PolicyViolation::TooWeak { .. } => {
+                "Password is too weak, use more words or kinds of characters"
+            }
+            PolicyViolation::Common => "Password is too common",
+        }
+    }
+}
+
+/// Requirements on new master passwords for tauri state, checked on registration and password change. The default policy can be replaced by managing a different one.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct PasswordPolicy {
+    /// Minimum number of characters.
+    pub min_length: usize,
+    /// Minimum strength by [`passwords::scorer::score`], from 0 to 100.
+    pub min_score: f64,
+    /// Rejects passwords for which [`passwords::analyzer::is_common_password`] is true.
+    pub reject_common: bool,
+}
+
+impl Default for PasswordPolicy {
+    /// At least 12 characters, a good score (80) and not a common password.
+    fn default() -> Self {
+        PasswordPolicy {
+            min_length: 12,
+            min_score: 80.0,
+            reject_common: true,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Returns all rules the password breaks, empty if it satisfies the policy.
+    pub fn check(&self, password: &str) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        if password.chars().count() < self.min_length {
+            violations.push(PolicyViolation::TooShort {
+                min_length: self.min_length,
+            });
+        }
+        if passwords::scorer::score(&passwords::analyzer::analyze(password)) < self.min_score {
+            violations.push(PolicyViolation::TooWeak {
+                min_score: self.min_score,
+            });
+        }
+        if self.reject_common && passwords::analyzer::is_common_password(password) {
+            violations.push(PolicyViolation::Common);
+        }
+        violations
+    }
+
+    /// Checks the password against the policy, see [`PasswordPolicy::check`].
+    /// # Errors
+    /// Returns the message of the first broken rule, see [`PolicyViolation::message`].
+    pub fn enforce(&self, password: &str) -> Result<(), &'static str> {
+        match self.check(password).first() {
+            Some(violation) => Err(violation.message()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let policy = PasswordPolicy::default();
+        assert_eq!(policy.check("x7#Kq2!vRm9$Lp"), []);
+        assert_eq!(policy.enforce("x7#Kq2!vRm9$Lp"), Ok(()));
+        assert_eq!(
+            policy.check("a")[..2],
+            [
+                PolicyViolation::TooShort { min_length: 12 },
+                PolicyViolation::TooWeak { min_score: 80.0 }
+            ]
+        );
+        assert_eq!(policy.enforce("a"), Err("Password is too short"));
+        assert!(policy
+            .check("aaaaaaaaaaaaaaaa")
+            .contains(&PolicyViolation::TooWeak { min_score: 80.0 }));
+        assert!(policy
+            .check("password1234")
+            .contains(&PolicyViolation::Common));
+    }
+    #[test]
+    fn test_configured() {
+        let policy = PasswordPolicy {
+            min_length: 4,
+            min_score: 0.0,
+            reject_common: false,
+        };
+        assert_eq!(policy.check("password"), []);
+        assert_eq!(
+            policy.check("abc"),
+            [PolicyViolation::TooShort { min_length: 4 }]
+        );
+        assert_eq!(
+            serde_json::to_string(&PolicyViolation::TooShort { min_length: 4 }).unwrap(),
+            r#"{"rule":"TooShort","min_length":4}"#
+        );
+    }
+}
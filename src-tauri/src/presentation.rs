@@ -0,0 +1,239 @@
+use crate::database::model::value::ToSecretString;
+use crate::database::model::{Content, Record};
+use secrecy::ExposeSecret;
+use tauri::{AppHandle, Manager, Runtime, WindowBuilder};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Label of the presentation window, there is at most one.
+pub const WINDOW_LABEL: &str = "presentation";
+
+/// Shown instead of masked values.
+pub const MASK: &str = "••••";
+
+/// Labels of numbers that are masked like sensitive kinds, e.g. the PIN and CVV of bank cards.
+const MASKED_LABELS: [&str; 2] = ["PIN", "CVV"];
+
+/// Content shown in the presentation window. Value of masked content is [`MASK`], the real value never leaves the backend.
+#[derive(Debug, PartialEq, Zeroize, ZeroizeOnDrop, serde::Serialize)]
+pub struct PresentedContent {
+    pub label: String,
+    pub kind: String,
+    pub value: String,
+    pub masked: bool,
+}
+
+/// Record shown in the presentation window, only its title and content, see [`redact`].
+#[derive(Debug, PartialEq, Zeroize, ZeroizeOnDrop, serde::Serialize)]
+pub struct PresentedRecord {
+    pub title: String,
+    pub content: Vec<PresentedContent>,
+}
+
+/// Returns true if the content is masked in the presentation: values masked until revealed ([`crate::database::model::Value::is_masked`]) and numbers labeled as in [`MASKED_LABELS`].
+fn is_masked(content: &Content) -> bool {
+    content.value().is_masked()
+        || (content.kind() == "Number"
+            && MASKED_LABELS
+                .iter()
+                .any(|label| content.label().trim().eq_ignore_ascii_case(label)))
+}
+
+/// Builds the presented record with sensitive values replaced by [`MASK`], so hiding them does not depend on the frontend. Content is ordered by position.
+pub fn redact(record: &Record, content: &[Content]) -> PresentedRecord {
+    let mut content: Vec<&Content> = content.iter().collect();
+    content.sort_by_key(|content| content.position());
+    PresentedRecord {
+        title: record.title().to_string(),
+        content: content
+            .into_iter()
+            .map(|content| {
+                let masked = is_masked(content);
+                PresentedContent {
+                    label: content.label().to_string(),
+                    kind: content.kind().to_string(),
+                    value: match masked {
+                        true => MASK.to_string(),
+                        false => content.value().to_secret_string().expose_secret().clone(),
+                    },
+                    masked,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Opens a small always-on-top window without menu, initialized with the presented record in `window.__PRESENTATION__`. The window is closed on lock, see [`close`].
+/// # Errors
+/// If the presentation window is already open, it is focused instead. Also if the window cannot be created.
+pub fn open<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    presented: &PresentedRecord,
+) -> Result<(), &'static str> {
+    if let Some(window) = app_handle.get_window(WINDOW_LABEL) {
+        window.set_focus().unwrap_or_default();
+        return Err("Presentation window is already open");
+    }
+    let payload = Zeroizing::new(
+        serde_json::to_string(presented).map_err(|_| "Failed to open presentation window")?,
+    );
+    WindowBuilder::new(
+        app_handle,
+        WINDOW_LABEL,
+        tauri::WindowUrl::App("index.html".into()),
+    )
+    .title(&presented.title)
+    .initialization_script(&format!("window.__PRESENTATION__ = {};", payload.as_str()))
+    .disable_file_drop_handler()
+    .always_on_top(true)
+    .resizable(true)
+    .inner_size(360f64, 420f64)
+    .build()
+    .map_err(|_| "Failed to open presentation window")?;
+    Ok(())
+}
+
+/// Closes the presentation window, if it is open.
+pub fn close<R: Runtime>(app_handle: &AppHandle<R>) {
+    if let Some(window) = app_handle.get_window(WINDOW_LABEL) {
+        window.close().unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::model::{value, Category, Value};
+    use tauri::test::{mock_builder, mock_context, noop_assets};
+
+    fn content(label: &str, position: u32, value: Value) -> Content {
+        Content::new(label.to_string(), position, false, value)
+    }
+
+    #[test]
+    fn test_redact() {
+        let record = Record::new("Bank".to_string(), "john".to_string(), Category::Other);
+        let content = [
+            content("User", 0, Value::Text(value::Text::new("john".to_string()))),
+            content(
+                "Password",
+                1,
+                Value::Password(value::Password::new("secret".to_string())),
+            ),
+            content(
+                "TOTP",
+                2,
+                Value::TOTPSecret(
+                    value::TOTPSecret::new("JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP".to_string()).unwrap(),
+                ),
+            ),
+            content(
+                "Card number",
+                3,
+                Value::BankCardNumber(
+                    value::BankCardNumber::new("4111111111111111".to_string()).unwrap(),
+                ),
+            ),
+            content(
+                "PIN",
+                4,
+                Value::Number(value::Number::new("1234".to_string()).unwrap()),
+            ),
+            content(
+                "cvv",
+                5,
+                Value::Number(value::Number::new("123".to_string()).unwrap()),
+            ),
+            content(
+                "Secret",
+                6,
+                Value::SensitiveText(value::SensitiveText::new("hidden".to_string())),
+            ),
+            content(
+                "Security question",
+                7,
+                Value::QuestionAnswer(
+                    value::QuestionAnswer::new("Pet?".to_string(), "Rex".to_string()).unwrap(),
+                ),
+            ),
+            content(
+                "Recovery codes",
+                8,
+                Value::RecoveryCodes(
+                    value::RecoveryCodes::new(vec![("aaaa-bbbb".to_string(), false)]).unwrap(),
+                ),
+            ),
+            content(
+                "Floor",
+                9,
+                Value::Number(value::Number::new("3".to_string()).unwrap()),
+            ),
+            content(
+                "Website",
+                10,
+                Value::Url(value::Url::new("https://bank.example.com".to_string()).unwrap()),
+            ),
+        ];
+        let presented = redact(&record, &content);
+        assert_eq!(presented.title, "Bank");
+        let shown: Vec<(&str, &str)> = presented
+            .content
+            .iter()
+            .map(|content| (content.label.as_str(), content.value.as_str()))
+            .collect();
+        assert_eq!(
+            shown,
+            [
+                ("User", "john"),
+                ("Password", MASK),
+                ("TOTP", MASK),
+                ("Card number", MASK),
+                ("PIN", MASK),
+                ("cvv", MASK),
+                ("Secret", MASK),
+                ("Security question", MASK),
+                ("Recovery codes", MASK),
+                ("Floor", "3"),
+                ("Website", "https://bank.example.com"),
+            ]
+        );
+        let json = serde_json::to_string(&presented).unwrap();
+        for secret in [
+            "secret", "JBSWY3DP", "4111", "1234", "hidden", "Rex", "aaaa",
+        ] {
+            assert!(!json.contains(secret), "{secret} is not masked");
+        }
+    }
+    #[test]
+    fn test_redact_order() {
+        let record = Record::new("Note".to_string(), String::new(), Category::Note);
+        let content = [
+            content("Second", 1, Value::Text(value::Text::new("b".to_string()))),
+            content("First", 0, Value::Text(value::Text::new("a".to_string()))),
+        ];
+        let labels: Vec<String> = redact(&record, &content)
+            .content
+            .iter()
+            .map(|content| content.label.clone())
+            .collect();
+        assert_eq!(labels, ["First", "Second"]);
+    }
+    #[test]
+    fn test_open_single_window() {
+        let app = mock_builder().build(mock_context(noop_assets())).unwrap();
+        let record = Record::new("Mail".to_string(), String::new(), Category::Login);
+        let presented = redact(&record, &[]);
+        assert_eq!(open(&app.handle(), &presented), Ok(()));
+        assert!(app.get_window(WINDOW_LABEL).is_some());
+        assert_eq!(
+            open(&app.handle(), &presented),
+            Err("Presentation window is already open")
+        );
+        assert_eq!(
+            app.windows()
+                .keys()
+                .filter(|label| label.as_str() == WINDOW_LABEL)
+                .count(),
+            1
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use crate::database::Database;
+use crate::error::Error;
+use secrecy::ExposeSecret;
+use std::ops::Not;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Setting with the number of seconds after a successful verification of the master password during which locked records can be viewed.
+pub const WINDOW_SETTING: &str = "reauth_window_seconds";
+
+/// Default window when [`WINDOW_SETTING`] is not set or not valid.
+pub const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Returns the reauthentication window from the settings, see [`WINDOW_SETTING`].
+pub fn window(database: &Database) -> Duration {
+    database
+        .get_setting(WINDOW_SETTING)
+        .ok()
+        .and_then(|seconds| seconds.expose_secret().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_WINDOW)
+}
+
+/// Time of the last successful verification of the master password for tauri state. Lives only for the current session.
+#[derive(Default)]
+pub struct Reauthentication {
+    verified: Mutex<Option<Instant>>,
+}
+
+impl Reauthentication {
+    /// Records a successful verification at the given time.
+    pub fn verified_at(&self, now: Instant) {
+        if let Ok(mut guard) = self.verified.lock() {
+            *guard = Some(now);
+        }
+    }
+
+    /// Returns true if the master password was verified within `window` before `now`.
+    pub fn is_valid_at(&self, now: Instant, window: Duration) -> bool {
+        self.verified
+            .lock()
+            .ok()
+            .and_then(|guard| *guard)
+            .is_some_and(|verified| now.saturating_duration_since(verified) <= window)
+    }
+
+    /// Checks access to a record. Locked records can be accessed only within the window from the settings.
+    /// # Errors
+    /// Returns [`Error::NeedsReauth`] if the record is locked and the master password was not verified recently.
+    pub fn check(&self, database: &Database, locked: bool) -> Result<(), Error> {
+        if locked && self.is_valid_at(Instant::now(), window(database)).not() {
+            return Err(Error::NeedsReauth);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_is_valid_at() {
+        let reauthentication = Reauthentication::default();
+        let now = Instant::now();
+        assert!(!reauthentication.is_valid_at(now, DEFAULT_WINDOW));
+
+        reauthentication.verified_at(now);
+        assert!(reauthentication.is_valid_at(now, DEFAULT_WINDOW));
+        assert!(reauthentication.is_valid_at(now + DEFAULT_WINDOW, DEFAULT_WINDOW));
+        assert!(!reauthentication.is_valid_at(
+            now + DEFAULT_WINDOW + Duration::from_secs(1),
+            DEFAULT_WINDOW
+        ));
+        assert!(
+            !reauthentication.is_valid_at(now + Duration::from_secs(11), Duration::from_secs(10))
+        );
+    }
+    #[test]
+    fn test_check() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        let reauthentication = Reauthentication::default();
+        assert_eq!(reauthentication.check(&database, false), Ok(()));
+        assert_eq!(
+            reauthentication.check(&database, true),
+            Err(Error::NeedsReauth)
+        );
+
+        reauthentication.verified_at(Instant::now());
+        assert_eq!(reauthentication.check(&database, true), Ok(()));
+
+        database.save_setting(WINDOW_SETTING, "0").unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(
+            reauthentication.check(&database, true),
+            Err(Error::NeedsReauth)
+        );
+        assert_eq!(window(&database), Duration::ZERO);
+    }
+}
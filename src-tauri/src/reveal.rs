@@ -0,0 +1,190 @@
+use crate::database::model::SecretValue;
+use crate::database::Database;
+use secrecy::ExposeSecret;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Setting with the number of seconds after which a revealed sensitive value is masked again.
+pub const DURATION_SETTING: &str = "reveal_seconds";
+
+/// Default duration when [`DURATION_SETTING`] is not set or not valid.
+pub const DEFAULT_DURATION: Duration = Duration::from_secs(20);
+
+/// Returns the reveal duration from the settings, see [`DURATION_SETTING`].
+pub fn duration(database: &Database) -> Duration {
+    database
+        .get_setting(DURATION_SETTING)
+        .ok()
+        .and_then(|seconds| seconds.expose_secret().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_DURATION)
+}
+
+/// Value of a content returned to the frontend. Sensitive values come with the token of their reveal and the number of seconds after which the `re-mask` event with the token is emitted.
+#[derive(Debug, serde::Serialize)]
+pub struct RevealedValue {
+    pub value: SecretValue,
+    pub reveal_token: Option<u64>,
+    pub reveal_seconds: Option<u64>,
+}
+
+/// Pending reveals of sensitive values for tauri state. Every reveal has its own token and timer, so concurrent reveals are masked independently.
+#[derive(Default)]
+pub struct RevealTimer {
+    last_token: AtomicU64,
+    pending: Arc<Mutex<HashMap<u64, JoinHandle<()>>>>,
+}
+
+impl RevealTimer {
+    /// Starts a timer of a new reveal. When `duration` elapses, `on_elapsed` is called with the token, unless the reveal was cancelled. Must be called within a tokio runtime.
+    /// # Return
+    /// Returns the token of the reveal.
+    /// # Errors
+    /// Returns an error if the timer mutex is poisoned.
+    pub fn start<F>(&self, duration: Duration, on_elapsed: F) -> Result<u64, &'static str>
+    where
+        F: FnOnce(u64) + Send + 'static,
+    {
+        // Guard is held until the timer is registered, so an elapsed timer always finds itself
+        let mut guard = self
+            .pending
+            .lock()
+            .map_err(|_| "Failed to access reveal timer lock")?;
+        let token = self.last_token.fetch_add(1, Ordering::Relaxed) + 1;
+        let pending = Arc::clone(&self.pending);
+        let handle = tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            let elapsed = pending
+                .lock()
+                .is_ok_and(|mut guard| guard.remove(&token).is_some());
+            if elapsed {
+                on_elapsed(token);
+            }
+        });
+        guard.insert(token, handle);
+        Ok(token)
+    }
+
+    /// Cancels the timer of the reveal.
+    /// # Return
+    /// Returns true if the reveal was pending.
+    pub fn cancel(&self, token: u64) -> bool {
+        self.pending
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.remove(&token))
+            .map(|handle| handle.abort())
+            .is_some()
+    }
+
+    /// Cancels timers of all pending reveals, e.g. when a record is locked.
+    /// # Return
+    /// Returns tokens of the cancelled reveals.
+    pub fn cancel_all(&self) -> Vec<u64> {
+        let Ok(mut guard) = self.pending.lock() else {
+            return Vec::new();
+        };
+        guard
+            .drain()
+            .map(|(token, handle)| {
+                handle.abort();
+                token
+            })
+            .collect()
+    }
+
+    /// Returns true if the reveal has not been masked or cancelled yet.
+    pub fn is_pending(&self, token: u64) -> bool {
+        self.pending
+            .lock()
+            .is_ok_and(|guard| guard.contains_key(&token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Returns a callback recording the masked token.
+    fn record(masked: &Arc<Mutex<Vec<u64>>>) -> impl FnOnce(u64) + Send + 'static {
+        let masked = Arc::clone(masked);
+        move |token| masked.lock().unwrap().push(token)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_start() {
+        let timer = RevealTimer::default();
+        let masked = Arc::new(Mutex::new(Vec::new()));
+        let first = timer
+            .start(Duration::from_secs(20), record(&masked))
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        let second = timer
+            .start(Duration::from_secs(20), record(&masked))
+            .unwrap();
+        assert_ne!(first, second);
+        assert!(timer.is_pending(first));
+        assert!(timer.is_pending(second));
+
+        tokio::time::sleep(Duration::from_secs(16)).await;
+        assert_eq!(*masked.lock().unwrap(), vec![first]);
+        assert!(!timer.is_pending(first));
+        assert!(timer.is_pending(second));
+
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(*masked.lock().unwrap(), vec![first, second]);
+        assert!(!timer.is_pending(second));
+    }
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel() {
+        let timer = RevealTimer::default();
+        let masked = Arc::new(Mutex::new(Vec::new()));
+        let first = timer
+            .start(Duration::from_secs(20), record(&masked))
+            .unwrap();
+        let second = timer
+            .start(Duration::from_secs(20), record(&masked))
+            .unwrap();
+        assert!(timer.cancel(first));
+        assert!(!timer.cancel(first));
+
+        tokio::time::sleep(Duration::from_secs(21)).await;
+        assert_eq!(*masked.lock().unwrap(), vec![second]);
+        assert!(!timer.cancel(second));
+    }
+    #[tokio::test(start_paused = true)]
+    async fn test_cancel_all() {
+        let timer = RevealTimer::default();
+        let masked = Arc::new(Mutex::new(Vec::new()));
+        let mut tokens = vec![
+            timer
+                .start(Duration::from_secs(20), record(&masked))
+                .unwrap(),
+            timer
+                .start(Duration::from_secs(10), record(&masked))
+                .unwrap(),
+        ];
+        let mut cancelled = timer.cancel_all();
+        tokens.sort();
+        cancelled.sort();
+        assert_eq!(cancelled, tokens);
+        assert!(timer.cancel_all().is_empty());
+
+        tokio::time::sleep(Duration::from_secs(21)).await;
+        assert!(masked.lock().unwrap().is_empty());
+    }
+    #[test]
+    fn test_duration() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        assert_eq!(duration(&database), DEFAULT_DURATION);
+        database.save_setting(DURATION_SETTING, "5").unwrap();
+        assert_eq!(duration(&database), Duration::from_secs(5));
+        database.save_setting(DURATION_SETTING, "soon").unwrap();
+        assert_eq!(duration(&database), DEFAULT_DURATION);
+    }
+}
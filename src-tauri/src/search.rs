@@ -0,0 +1,100 @@
+use crate::database::model::Record;
+use crate::database::normalize_search;
+
+/// Score of a record whose title equals the query.
+pub const EXACT_TITLE: u8 = 5;
+/// Score of a record whose title starts with the query.
+pub const TITLE_PREFIX: u8 = 4;
+/// Score of a record whose title contains the query.
+pub const TITLE: u8 = 3;
+/// Score of a record whose subtitle contains the query.
+pub const SUBTITLE: u8 = 2;
+/// Score of a record that was found only by its fields (deep search).
+pub const FIELD: u8 = 1;
+
+/// Record found by the search with its score. Higher score is a better match.
+#[derive(Debug, serde::Serialize)]
+pub struct SearchResult {
+    record: Record,
+    score: u8,
+}
+
+/// Scores the record against the query, case- and accent-insensitive like the search itself ([`normalize_search`]). Records matching neither title nor subtitle are expected to be found by their fields, so they get [`FIELD`].
+pub fn score(query: &str, record: &Record) -> u8 {
+    let query = normalize_search(query.trim());
+    let title = normalize_search(record.title());
+
+    if title == query {
+        EXACT_TITLE
+    } else if title.starts_with(&query) {
+        TITLE_PREFIX
+    } else if title.contains(&query) {
+        TITLE
+    } else if normalize_search(record.subtitle()).contains(&query) {
+        SUBTITLE
+    } else {
+        FIELD
+    }
+}
+
+/// Ranks candidate records by score and then by last modification, the most recent first.
+pub fn rank(query: &str, records: Vec<Record>) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = records
+        .into_iter()
+        .map(|record| SearchResult {
+            score: score(query, &record),
+            record,
+        })
+        .collect();
+    results.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| b.record.last_modified().cmp(&a.record.last_modified()))
+    });
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::model::Category;
+
+    fn record(title: &str, subtitle: &str) -> Record {
+        Record::new(title.to_string(), subtitle.to_string(), Category::Login)
+    }
+
+    #[test]
+    fn test_score() {
+        assert_eq!(score("git", &record("Git", "")), EXACT_TITLE);
+        assert_eq!(score(" GIT ", &record("github", "")), TITLE_PREFIX);
+        assert_eq!(score("git", &record("My GitLab", "")), TITLE);
+        assert_eq!(score("git", &record("Work", "git@example.com")), SUBTITLE);
+        assert_eq!(score("git", &record("Work", "user")), FIELD);
+    }
+    #[test]
+    fn test_rank() {
+        let records = vec![
+            record("Work", "user"),
+            record("Work", "git@example.com"),
+            record("My GitLab", ""),
+            record("GitHub", ""),
+            record("git", ""),
+        ];
+        let results = rank("git", records);
+        let scores: Vec<u8> = results.iter().map(|result| result.score).collect();
+        assert_eq!(
+            scores,
+            vec![EXACT_TITLE, TITLE_PREFIX, TITLE, SUBTITLE, FIELD]
+        );
+        assert_eq!(results[0].record.title(), "git");
+    }
+    #[test]
+    fn test_rank_last_modified() {
+        let mut older = record("GitHub", "older");
+        older.set_last_modified(chrono::Utc::now() - chrono::Duration::days(1));
+        let newer = record("GitLab", "newer");
+        let results = rank("git", vec![older, newer]);
+        assert_eq!(results[0].record.subtitle(), "newer");
+        assert_eq!(results[1].record.subtitle(), "older");
+    }
+}
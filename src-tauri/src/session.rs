@@ -0,0 +1,97 @@
+use crate::database::Database;
+use crate::events::{self, Event, SessionStatePayload};
+use crate::hooks::{self, HookEvent, HookPayload};
+use crate::presentation;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Runtime};
+
+/// Whether the vault is unlocked and why it was locked.
+#[derive(Debug, PartialEq, Clone, Copy, serde::Serialize)]
+pub enum SessionState {
+    Unlocked,
+    Locked,
+    LockedByPanic,
+}
+
+/// Current state of the session for tauri state, changed only by [`transition`]. The session starts locked, until login or registration opens the vault.
+pub struct Session(Mutex<SessionStatePayload>);
+
+impl Default for Session {
+    fn default() -> Self {
+        Session(Mutex::new(SessionStatePayload {
+            state: SessionState::Locked,
+            at: chrono::Utc::now(),
+        }))
+    }
+}
+
+impl Session {
+    /// Returns the current state with the time of the transition to it.
+    /// # Errors
+    /// If the session mutex is poisoned.
+    pub fn current(&self) -> Result<SessionStatePayload, &'static str> {
+        self.0
+            .lock()
+            .map(|current| current.clone())
+            .map_err(|_| "Failed to access session lock")
+    }
+}
+
+/// Moves the session to the state and emits [`Event::SessionState`] to all windows. Every lock and unlock goes through this function, so no window misses a transition. Locking closes the presentation window ([`presentation::close`]), the vault itself is locked by the caller, see [`lock`].
+pub fn transition<R: Runtime>(app_handle: &AppHandle<R>, state: SessionState) {
+    if state != SessionState::Unlocked {
+        presentation::close(app_handle);
+    }
+    let payload = SessionStatePayload {
+        state,
+        at: chrono::Utc::now(),
+    };
+    if let Some(session) = app_handle.try_state::<Session>() {
+        if let Ok(mut current) = session.0.lock() {
+            *current = payload.clone();
+        }
+    }
+    events::emit_all(app_handle, Event::SessionState(payload)).unwrap_or_default();
+}
+
+/// Moves the session to the locked state ([`transition`]) and locks the vault by restarting the application, which drops the opened database from memory. The hook of [`HookEvent::VaultLocked`] is started first, see [`hooks::trigger`].
+pub fn lock(app_handle: &AppHandle, state: SessionState) {
+    if let Some(database) = app_handle.try_state::<Database>() {
+        hooks::trigger(&database, HookPayload::new(HookEvent::VaultLocked));
+    }
+    transition(app_handle, state);
+    app_handle.restart();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tauri::test::{mock_builder, mock_context, noop_assets};
+
+    #[test]
+    fn test_transition() {
+        let app = mock_builder()
+            .manage(Session::default())
+            .build(mock_context(noop_assets()))
+            .unwrap();
+        let session = app.state::<Session>();
+        assert_eq!(session.current().unwrap().state, SessionState::Locked);
+
+        // Login, panic lock after the restart, login and lock after a critical error
+        let pathways = [
+            SessionState::Unlocked,
+            SessionState::LockedByPanic,
+            SessionState::Unlocked,
+            SessionState::Locked,
+        ];
+        // Mock windows drop emitted events, so the recorded state is checked instead
+        let mut previous = session.current().unwrap().at;
+        for state in pathways {
+            transition(&app.handle(), state);
+            let current = session.current().unwrap();
+            assert_eq!(current.state, state);
+            assert!(current.at >= previous);
+            previous = current.at;
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use crate::database::DATABASE_FILE_NAME;
+use crate::error::Error;
+use crate::file_manager::atomic_write;
+use chrono::{DateTime, Utc};
+use std::ops::Not;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Number of failed logins allowed without waiting.
+pub const FREE_ATTEMPTS: u32 = 5;
+
+/// Wait after the last free attempt fails, it doubles with every further failure.
+pub const BASE_DELAY: Duration = Duration::from_secs(5);
+
+/// Longest wait between two login attempts.
+pub const MAX_DELAY: Duration = Duration::from_secs(15 * 60);
+
+/// Failed logins since the last successful one. Stored as plain JSON next to the database, because it is needed before the vault is unlocked.
+#[derive(Debug, PartialEq, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct FailedLogins {
+    pub failures: u32,
+    pub last_failure: Option<DateTime<Utc>>,
+}
+
+/// Returns path of the failed logins file next to the database file.
+pub fn path(database_path: &Path) -> PathBuf {
+    database_path.with_file_name(format!("{DATABASE_FILE_NAME}.attempts"))
+}
+
+impl FailedLogins {
+    /// Loads failed logins of the database. Missing or unreadable file means there are none.
+    pub fn load(database_path: &Path) -> FailedLogins {
+        std::fs::read(path(database_path))
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Returns the wait required after the last failure: none for the first [`FREE_ATTEMPTS`], then [`BASE_DELAY`] doubling with every failure up to [`MAX_DELAY`].
+    pub fn delay(&self) -> Duration {
+        match self.failures.checked_sub(FREE_ATTEMPTS) {
+            None => Duration::ZERO,
+            Some(doublings) => 2u32
+                .checked_pow(doublings)
+                .and_then(|factor| BASE_DELAY.checked_mul(factor))
+                .map_or(MAX_DELAY, |delay| delay.min(MAX_DELAY)),
+        }
+    }
+
+    /// Returns how long the next login has to wait at `now`, zero if it can be attempted.
+    pub fn remaining(&self, now: DateTime<Utc>) -> Duration {
+        let Some(last_failure) = self.last_failure else {
+            return Duration::ZERO;
+        };
+        let elapsed = (now - last_failure).to_std().unwrap_or(Duration::ZERO);
+        self.delay().saturating_sub(elapsed)
+    }
+
+    /// Records a failed login at `now` and returns the updated failed logins.
+    /// # Errors
+    /// Returns [`Error::Io`] if the file cannot be written.
+    pub fn record_failure(database_path: &Path, now: DateTime<Utc>) -> Result<FailedLogins, Error> {
+        let mut failed = FailedLogins::load(database_path);
+        failed.failures = failed.failures.saturating_add(1);
+        failed.last_failure = Some(now);
+        let json = serde_json::to_vec(&failed).map_err(|_| "Failed to save failed logins")?;
+        atomic_write(&path(database_path), &json)
+            .map_err(Error::io("Failed to save failed logins"))?;
+        Ok(failed)
+    }
+
+    /// Forgets failed logins after a successful one.
+    /// # Errors
+    /// Returns [`Error::Io`] if the file exists and cannot be removed.
+    pub fn reset(database_path: &Path) -> Result<(), Error> {
+        let path = path(database_path);
+        if path.exists().not() {
+            return Ok(());
+        }
+        std::fs::remove_file(path).map_err(Error::io("Failed to reset failed logins"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_delay() {
+        let delay = |failures| {
+            FailedLogins {
+                failures,
+                last_failure: None,
+            }
+            .delay()
+        };
+        assert_eq!(delay(0), Duration::ZERO);
+        assert_eq!(delay(FREE_ATTEMPTS - 1), Duration::ZERO);
+        assert_eq!(delay(FREE_ATTEMPTS), BASE_DELAY);
+        assert_eq!(delay(FREE_ATTEMPTS + 1), BASE_DELAY * 2);
+        assert_eq!(delay(FREE_ATTEMPTS + 3), BASE_DELAY * 8);
+        assert_eq!(delay(FREE_ATTEMPTS + 20), MAX_DELAY);
+        assert_eq!(delay(u32::MAX), MAX_DELAY);
+    }
+    #[test]
+    fn test_record_failure_and_reset() {
+        let directory = TempDir::new().unwrap();
+        let database_path = directory.path().join(DATABASE_FILE_NAME);
+        let start = Utc::now();
+        assert_eq!(
+            FailedLogins::load(&database_path).remaining(start),
+            Duration::ZERO
+        );
+
+        let mut delays = Vec::new();
+        for attempt in 0..FREE_ATTEMPTS + 2 {
+            let now = start + chrono::Duration::minutes(attempt as i64);
+            let failed = FailedLogins::record_failure(&database_path, now).unwrap();
+            assert_eq!(failed.failures, attempt + 1);
+            delays.push(failed.remaining(now));
+        }
+        assert!(delays[..FREE_ATTEMPTS as usize - 1]
+            .iter()
+            .all(|delay| delay.is_zero()));
+        assert_eq!(
+            delays[FREE_ATTEMPTS as usize - 1..],
+            [BASE_DELAY, BASE_DELAY * 2, BASE_DELAY * 4]
+        );
+
+        let failed = FailedLogins::load(&database_path);
+        let last_failure = failed.last_failure.unwrap();
+        assert_eq!(
+            failed.remaining(last_failure + chrono::Duration::seconds(15)),
+            BASE_DELAY * 4 - Duration::from_secs(15)
+        );
+        assert_eq!(
+            failed.remaining(last_failure + chrono::Duration::minutes(1)),
+            Duration::ZERO
+        );
+
+        FailedLogins::reset(&database_path).unwrap();
+        assert_eq!(FailedLogins::load(&database_path), FailedLogins::default());
+        assert_eq!(FailedLogins::reset(&database_path), Ok(()));
+
+        let missing = directory.path().join("missing").join(DATABASE_FILE_NAME);
+        let error = FailedLogins::record_failure(&missing, start).unwrap_err();
+        assert_eq!(error.code(), "Io");
+        assert_eq!(error, "Failed to save failed logins");
+    }
+}
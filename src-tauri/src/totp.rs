@@ -1,10 +1,29 @@
+pub mod bundle;
+
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 use totp_rs::{Rfc6238, TOTP};
 
+/// Setting which enables applying the measured clock offset when generating codes.
+pub const COMPENSATE_SETTING: &str = "compensate_clock_skew";
+
+/// Measures offset of the local clock from the server time, in whole seconds. The server time is compared with the middle of the request, so the network delay does not count as offset. Positive offset means the local clock is behind.
+pub fn measure_offset(sent: DateTime<Utc>, received: DateTime<Utc>, server: DateTime<Utc>) -> i64 {
+    let local = sent + (received - sent) / 2;
+    (server - local).num_seconds()
+}
+
 /// TOTP manager for tauri state. Used for managing TOTP secrets and generating codes.
 pub struct TOTPManager {
-    hash_map: Mutex<HashMap<u64, TOTP>>,
+    /// Secrets by content id, with the id of their record.
+    hash_map: Mutex<HashMap<u64, (u64, TOTP)>>,
+    /// Last measured offset of the local clock in seconds, see [`measure_offset`].
+    offset: AtomicI64,
+    /// If the offset is applied when generating codes.
+    compensate: AtomicBool,
 }
 
 impl TOTPManager {
@@ -12,12 +31,19 @@ impl TOTPManager {
     pub fn new(size: usize) -> Self {
         TOTPManager {
             hash_map: Mutex::new(HashMap::with_capacity(size)),
+            offset: AtomicI64::new(0),
+            compensate: AtomicBool::new(false),
         }
     }
-    /// Adds a new TOTP secret to the manager. It takes a constant id and a totp secret
+    /// Sets the measured clock offset and whether it is applied when generating codes.
+    pub fn set_clock_offset(&self, offset: i64, compensate: bool) {
+        self.offset.store(offset, Ordering::Relaxed);
+        self.compensate.store(compensate, Ordering::Relaxed);
+    }
+    /// Adds a new TOTP secret to the manager. It takes a constant id, the id of its record and a totp secret
     /// # Errors
     /// Returns an error if the manager is full or if the secret is invalid.
-    pub fn add_secret(&self, id: u64, secret: String) -> Result<(), &'static str> {
+    pub fn add_secret(&self, id: u64, id_record: u64, secret: String) -> Result<(), &'static str> {
         let mut guard = self
             .hash_map
             .lock()
@@ -37,19 +63,38 @@ impl TOTPManager {
             return Err("Invalid OTP Secret");
         };
 
-        guard.insert(id, totp);
+        guard.insert(id, (id_record, totp));
         Ok(())
     }
 
-    /// Generates a TOTP code for the given secret.
+    /// Returns the id of the record of the secret, or None if the secret does not exist or if the manager mutex is poisoned.
+    pub fn record_id(&self, id: &u64) -> Option<u64> {
+        let guard = self.hash_map.lock().ok()?;
+        guard.get(id).map(|(id_record, _)| *id_record)
+    }
+
+    /// Generates a TOTP code for the given secret at the current system time, see [`TOTPManager::get_code_at`].
+    pub fn get_code(&self, id: &u64) -> Option<(String, u64, bool)> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+        self.get_code_at(id, now)
+    }
+
+    /// Generates a TOTP code for the given secret at the given unix time. The measured clock offset is added to the time if compensation is enabled.
     /// # Return
-    /// Returns the current TOTP code and the time to live in seconds or None if the secret does not exist or if the manager mutex is poisoned.
-    pub fn get_code(&self, id: &u64) -> Option<(String, u64)> {
-        let mut guard = self.hash_map.lock().ok()?;
-        let totp = guard.get_mut(id)?;
-        let current = totp.generate_current().ok()?;
-        let ttl = totp.ttl().ok()?;
-        Some((current, ttl))
+    /// Returns the TOTP code, the time to live in seconds and whether the clock offset is more than half of the period, or None if the secret does not exist or if the manager mutex is poisoned.
+    pub fn get_code_at(&self, id: &u64, now: u64) -> Option<(String, u64, bool)> {
+        let guard = self.hash_map.lock().ok()?;
+        let (_, totp) = guard.get(id)?;
+        let offset = self.offset.load(Ordering::Relaxed);
+        let skewed = offset.unsigned_abs() > totp.step / 2;
+        let time = if self.compensate.load(Ordering::Relaxed) {
+            now.checked_add_signed(offset)?
+        } else {
+            now
+        };
+        let current = totp.generate(time);
+        let ttl = totp.step - time % totp.step;
+        Some((current, ttl, skewed))
     }
 
     /// Removes a TOTP secrets from the manager.
@@ -59,3 +104,59 @@ impl TOTPManager {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    const SECRET: &str = "RFFFMAZ4JSJQ3QURWHZNA2WLJASTMYWV";
+
+    #[test]
+    fn test_measure_offset() {
+        let sent = Utc.timestamp_opt(1_000, 0).unwrap();
+        let received = sent + Duration::seconds(2);
+        assert_eq!(
+            measure_offset(sent, received, sent + Duration::seconds(1)),
+            0
+        );
+        assert_eq!(
+            measure_offset(sent, received, sent + Duration::seconds(41)),
+            40
+        );
+        assert_eq!(
+            measure_offset(sent, received, sent - Duration::seconds(39)),
+            -40
+        );
+    }
+    #[test]
+    fn test_get_code_at() {
+        let manager = TOTPManager::new(1);
+        manager.add_secret(1, 3, SECRET.to_string()).unwrap();
+        assert!(manager.get_code_at(&2, 1_000).is_none());
+        assert_eq!(manager.record_id(&1), Some(3));
+        assert_eq!(manager.record_id(&2), None);
+
+        let (code, ttl, skewed) = manager.get_code_at(&1, 1_000).unwrap();
+        assert_eq!(ttl, 20);
+        assert!(!skewed);
+        let (expected, _, _) = manager.get_code_at(&1, 1_040).unwrap();
+        assert_ne!(code, expected);
+
+        manager.set_clock_offset(40, false);
+        let (uncompensated, ttl, skewed) = manager.get_code_at(&1, 1_000).unwrap();
+        assert_eq!((uncompensated, ttl, skewed), (code.clone(), 20, true));
+
+        manager.set_clock_offset(40, true);
+        assert_eq!(
+            manager.get_code_at(&1, 1_000).unwrap(),
+            (expected, 10, true)
+        );
+
+        manager.set_clock_offset(-15, true);
+        let (_, ttl, skewed) = manager.get_code_at(&1, 1_000).unwrap();
+        assert_eq!((ttl, skewed), (5, false));
+        manager.set_clock_offset(-2_000, true);
+        assert!(manager.get_code_at(&1, 1_000).is_none());
+    }
+}
@@ -0,0 +1,398 @@
+use crate::database::model::value::TOTPSecret;
+use crate::database::model::{Category, Content, Record, Value};
+use crate::database::{self, Database};
+use crate::error::Error;
+use crate::import::{Entry, ImportSummary};
+use rusqlite::{params, Connection};
+use secrecy::ExposeSecret;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Not;
+use std::path::Path;
+
+/// Version of the bundle format, stored as `user_version` of the bundle.
+const VERSION: u32 = 1;
+
+/// Error returned for a wrong passphrase or a modified bundle. Pages of the bundle are authenticated, so the two cannot be told apart.
+pub const INVALID_BUNDLE: &str = "Invalid passphrase or damaged bundle";
+
+/// TOTP secret with the title of its record, the only data in a bundle.
+#[derive(Debug, PartialEq)]
+pub struct BundleEntry {
+    pub title: String,
+    pub label: String,
+    pub secret: TOTPSecret,
+}
+
+/// Collects TOTP secrets of all records. Locked records are left out unless `include_locked` is set.
+/// # Return
+/// Returns the entries and the number of secrets that were left out because their record is locked.
+pub fn collect(
+    records: &[Record],
+    content: &HashMap<u64, Vec<Content>>,
+    include_locked: bool,
+) -> (Vec<BundleEntry>, usize) {
+    let mut entries = Vec::new();
+    let mut locked = 0;
+    for record in records {
+        for content in content.get(&record.id()).into_iter().flatten() {
+            let Value::TOTPSecret(secret) = content.value() else {
+                continue;
+            };
+            if record.locked() && include_locked.not() {
+                locked += 1;
+                continue;
+            }
+            if let Ok(secret) = TOTPSecret::new(secret.value().to_string()) {
+                entries.push(BundleEntry {
+                    title: record.title().to_string(),
+                    label: content.label().to_string(),
+                    secret,
+                });
+            }
+        }
+    }
+    (entries, locked)
+}
+
+/// Writes the entries to a new bundle encrypted with the passphrase. The bundle is a SQLCipher database like the vault, so it is authenticated the same way as an exported database.
+/// # Return
+/// Returns the number of written entries.
+/// # Errors
+/// If the file already exists or the bundle cannot be written. A partially written file is removed.
+pub fn write(path: &Path, passphrase: &str, entries: &[BundleEntry]) -> Result<usize, Error> {
+    if passphrase.trim().is_empty() {
+        return Err(Error::Validation("Passphrase can not be empty".into()));
+    }
+    if path.exists() {
+        return Err(Error::Other("File already exists"));
+    }
+    let result = (|| {
+        let mut connection = Connection::open(path).map_err(|_| "Failed to create bundle")?;
+        database::unlock(
+            &connection,
+            database::normalize_password(passphrase).expose_secret(),
+        )?;
+        let transaction = connection
+            .transaction()
+            .map_err(|_| "Failed to start transaction")?;
+        transaction
+            .execute_batch(&format!(
+                "PRAGMA user_version = {VERSION};
+                CREATE TABLE TOTP (title TEXT NOT NULL, label TEXT NOT NULL, secret TEXT NOT NULL);"
+            ))
+            .map_err(|_| "Failed to create bundle")?;
+        for entry in entries {
+            transaction
+                .execute(
+                    "INSERT INTO TOTP (title, label, secret) VALUES (?1, ?2, ?3);",
+                    params![entry.title, entry.label, entry.secret.value()],
+                )
+                .map_err(|_| "Failed to write bundle")?;
+        }
+        transaction
+            .commit()
+            .map_err(|_| "Failed to commit transaction")?;
+        Ok(entries.len())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+    result
+}
+
+/// Reads entries of a bundle written by [`write`].
+/// # Errors
+/// Returns [`INVALID_BUNDLE`] if the passphrase is wrong or the bundle was modified, or an error if the bundle has an unknown version or contains an invalid secret.
+pub fn read(path: &Path, passphrase: &str) -> Result<Vec<BundleEntry>, &'static str> {
+    if path.exists().not() {
+        return Err("File does not exist");
+    }
+    let connection = Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|_| "Failed to open bundle")?;
+    database::unlock(
+        &connection,
+        database::normalize_password(passphrase).expose_secret(),
+    )
+    .map_err(|_| INVALID_BUNDLE)?;
+    let version: u32 = connection
+        .query_row("PRAGMA user_version;", [], |row| row.get(0))
+        .map_err(|_| INVALID_BUNDLE)?;
+    if version != VERSION {
+        return Err("Unsupported bundle version");
+    }
+    let mut statement = connection
+        .prepare("SELECT title, label, secret FROM TOTP ORDER BY rowid;")
+        .map_err(|_| INVALID_BUNDLE)?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })
+        .map_err(|_| INVALID_BUNDLE)?;
+    let mut entries = Vec::new();
+    for row in rows {
+        let (title, label, secret) = row.map_err(|_| INVALID_BUNDLE)?;
+        entries.push(BundleEntry {
+            title,
+            label,
+            secret: TOTPSecret::new(secret)?,
+        });
+    }
+    Ok(entries)
+}
+
+/// Imports the entries into the database. Entries are matched with records by title, a matching record gets the TOTP secret as new content and other entries create new logins. Secrets the record already has are skipped.
+/// New records are saved in a single transaction, updated records are saved one by one.
+/// # Errors
+/// If the records cannot be loaded or saved.
+pub fn import(database: &Database, entries: Vec<BundleEntry>) -> Result<ImportSummary, Error> {
+    let records = database.get_all_records()?;
+    let mut content = database.get_all_content_grouped()?;
+    let mut new_records: Vec<Entry> = Vec::new();
+    let mut updated = 0;
+    let mut skipped = Vec::new();
+
+    for (index, entry) in entries.into_iter().enumerate() {
+        let existing = records
+            .iter()
+            .find(|record| record.title() == entry.title)
+            .map(|record| (record.clone(), content.entry(record.id()).or_default()));
+        let (record, record_content) = match existing {
+            Some((record, record_content)) => (Some(record), record_content),
+            None => {
+                let index = match new_records
+                    .iter()
+                    .position(|(record, _)| record.title() == entry.title)
+                {
+                    Some(index) => index,
+                    None => {
+                        new_records.push((
+                            Record::new(entry.title.clone(), String::new(), Category::Login),
+                            Vec::new(),
+                        ));
+                        new_records.len() - 1
+                    }
+                };
+                (None, &mut new_records[index].1)
+            }
+        };
+        let exists = record_content.iter().any(|content| {
+            matches!(content.value(), Value::TOTPSecret(secret) if secret.value() == entry.secret.value())
+        });
+        if exists {
+            skipped.push((
+                index + 1,
+                format!("{} already has this TOTP secret", entry.title),
+            ));
+            continue;
+        }
+        let position = record_content
+            .iter()
+            .map(|content| content.position() + 1)
+            .max()
+            .unwrap_or(0);
+        let totp = Content::new(
+            entry.label,
+            position,
+            false,
+            Value::TOTPSecret(entry.secret),
+        );
+        match record {
+            Some(mut record) => {
+                let mut added = [totp];
+                database.save_record_with_content(&mut record, &mut added)?;
+                let [totp] = added;
+                record_content.push(totp);
+                updated += 1;
+            }
+            None => record_content.push(totp),
+        }
+    }
+
+    let imported = database.import_records(&new_records)?;
+    Ok(ImportSummary {
+        imported,
+        updated,
+        skipped,
+        failed: Vec::new(),
+        skipped_file: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::model::value;
+    use tempfile::TempDir;
+
+    const SECRET: &str = "JBSWY3DPEHPK3PXPJBSWY3DPEHPK3PXP";
+    const OTHER_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn entry(title: &str, secret: &str) -> BundleEntry {
+        BundleEntry {
+            title: title.to_string(),
+            label: "TOTP".to_string(),
+            secret: TOTPSecret::new(secret.to_string()).unwrap(),
+        }
+    }
+
+    fn save_login(database: &Database, title: &str, secret: Option<&str>, locked: bool) -> u64 {
+        let mut record = Record::new(title.to_string(), "john".to_string(), Category::Login);
+        let mut content = vec![Content::new(
+            "Password".to_string(),
+            0,
+            true,
+            Value::Password(value::Password::new("password".to_string())),
+        )];
+        if let Some(secret) = secret {
+            content.push(Content::new(
+                "TOTP".to_string(),
+                1,
+                false,
+                Value::TOTPSecret(TOTPSecret::new(secret.to_string()).unwrap()),
+            ));
+        }
+        database
+            .save_record_with_content(&mut record, &mut content)
+            .unwrap();
+        if locked {
+            database.set_record_locked(record.id(), true).unwrap();
+        }
+        record.id()
+    }
+
+    #[test]
+    fn test_collect() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        save_login(&database, "Mail", Some(SECRET), false);
+        save_login(&database, "Bank", Some(OTHER_SECRET), true);
+        save_login(&database, "Shop", None, false);
+        let records = database.get_all_records().unwrap();
+        let content = database.get_all_content_grouped().unwrap();
+
+        let (entries, locked) = collect(&records, &content, false);
+        assert_eq!(entries, vec![entry("Mail", SECRET)]);
+        assert_eq!(locked, 1);
+
+        let (entries, locked) = collect(&records, &content, true);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(locked, 0);
+    }
+    #[test]
+    fn test_write_read() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("bundle");
+        let entries = vec![entry("Mail", SECRET), entry("Přihlášení 🔐", OTHER_SECRET)];
+        assert_eq!(write(&path, "passphrase", &entries), Ok(2));
+        assert_eq!(
+            write(&path, "passphrase", &entries),
+            Err(Error::Other("File already exists"))
+        );
+        assert_eq!(read(&path, "passphrase"), Ok(entries));
+        assert_eq!(read(&path, "wrong"), Err(INVALID_BUNDLE));
+        // Secrets are not stored in plain text
+        let bytes = fs::read(&path).unwrap();
+        assert!(bytes
+            .windows(SECRET.len())
+            .all(|window| window != SECRET.as_bytes()));
+    }
+    #[test]
+    fn test_read_tampered() {
+        let directory = TempDir::new().unwrap();
+        let path = directory.path().join("bundle");
+        write(&path, "passphrase", &[entry("Mail", SECRET)]).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        // First page holds the schema, the last one the entries
+        for offset in [100, bytes.len() - 100] {
+            let mut tampered = bytes.clone();
+            tampered[offset] ^= 0xFF;
+            let tampered_path = directory.path().join(format!("tampered{offset}"));
+            fs::write(&tampered_path, tampered).unwrap();
+            assert_eq!(
+                read(&tampered_path, "passphrase"),
+                Err(INVALID_BUNDLE),
+                "{offset}"
+            );
+        }
+    }
+    #[test]
+    fn test_import() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        let mail = save_login(&database, "Mail", None, false);
+        save_login(&database, "Bank", Some(SECRET), false);
+
+        let summary = import(
+            &database,
+            vec![
+                entry("Mail", SECRET),
+                entry("Bank", SECRET),
+                entry("Shop", SECRET),
+                entry("Shop", OTHER_SECRET),
+            ],
+        )
+        .unwrap();
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.updated, 1);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(summary.skipped[0].0, 2);
+
+        let content = database.get_all_content_for_record(mail).unwrap();
+        assert_eq!(content.len(), 2);
+        assert_eq!(content[1].position(), 1);
+        assert!(
+            matches!(content[1].value(), Value::TOTPSecret(secret) if secret.value() == SECRET)
+        );
+        let shop = database
+            .get_all_records()
+            .unwrap()
+            .into_iter()
+            .find(|record| record.title() == "Shop")
+            .unwrap();
+        assert_eq!(
+            database
+                .get_all_content_for_record(shop.id())
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+    #[test]
+    fn test_round_trip() {
+        let directory = TempDir::new().unwrap();
+        let source = Database::open_path("password", &directory.path().join("source")).unwrap();
+        save_login(&source, "Mail", Some(SECRET), false);
+        save_login(&source, "Bank", Some(OTHER_SECRET), false);
+        let (entries, _) = collect(
+            &source.get_all_records().unwrap(),
+            &source.get_all_content_grouped().unwrap(),
+            false,
+        );
+        let path = directory.path().join("bundle");
+        write(&path, "passphrase", &entries).unwrap();
+
+        let target = Database::open_path("password", &directory.path().join("target")).unwrap();
+        let summary = import(&target, read(&path, "passphrase").unwrap()).unwrap();
+        assert_eq!(summary.imported, 2);
+        let (imported, _) = collect(
+            &target.get_all_records().unwrap(),
+            &target.get_all_content_grouped().unwrap(),
+            false,
+        );
+        assert_eq!(imported.len(), 2);
+        for entry in &entries {
+            assert!(imported.contains(entry));
+        }
+        // Importing the same bundle again changes nothing
+        let summary = import(&target, read(&path, "passphrase").unwrap()).unwrap();
+        assert_eq!(
+            (summary.imported, summary.updated, summary.skipped.len()),
+            (0, 0, 2)
+        );
+    }
+}
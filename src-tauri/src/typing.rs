@@ -0,0 +1,211 @@
+use crate::database::Database;
+use secrecy::ExposeSecret;
+use std::ops::Not;
+use std::time::Duration;
+
+/// Setting with the delay between typed keystrokes in milliseconds.
+pub const DELAY_SETTING: &str = "type_delay_ms";
+
+/// Default delay when [`DELAY_SETTING`] is not set or not valid. Some applications drop keystrokes that come too fast.
+pub const DEFAULT_DELAY: Duration = Duration::from_millis(10);
+
+/// Setting which allows typing new lines as Enter. Disabled by default, Enter usually submits the form.
+pub const ENTER_SETTING: &str = "type_newline_as_enter";
+
+/// Returns the delay between keystrokes from the settings, see [`DELAY_SETTING`].
+pub fn delay(database: &Database) -> Duration {
+    database
+        .get_setting(DELAY_SETTING)
+        .ok()
+        .and_then(|milliseconds| milliseconds.expose_secret().parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_DELAY)
+}
+
+/// Returns whether new lines are typed as Enter, see [`ENTER_SETTING`].
+pub fn allow_enter(database: &Database) -> bool {
+    database
+        .get_setting(ENTER_SETTING)
+        .is_ok_and(|value| value.expose_secret() == "true")
+}
+
+/// Single typed key.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Keystroke {
+    Character(char),
+    Tab,
+    Enter,
+}
+
+/// Converts the value to keystrokes. Tabs are typed as the Tab key, `\r\n` and `\n` as Enter.
+/// # Errors
+/// If the value contains a new line and typing it as Enter is not allowed.
+pub fn keystrokes(value: &str, allow_enter: bool) -> Result<Vec<Keystroke>, &'static str> {
+    let mut keystrokes = Vec::with_capacity(value.len());
+    let mut characters = value.chars().peekable();
+    while let Some(character) = characters.next() {
+        let keystroke = match character {
+            '\t' => Keystroke::Tab,
+            '\r' if characters.peek() == Some(&'\n') => continue,
+            '\r' | '\n' if allow_enter => Keystroke::Enter,
+            '\r' | '\n' => return Err("Value contains a new line, which would be typed as Enter"),
+            character => Keystroke::Character(character),
+        };
+        keystrokes.push(keystroke);
+    }
+    Ok(keystrokes)
+}
+
+/// Keyboard that sends keystrokes to the focused window.
+pub trait Keyboard {
+    /// Types the keystroke.
+    /// # Errors
+    /// If the keystroke cannot be typed.
+    fn press(&mut self, keystroke: Keystroke) -> Result<(), &'static str>;
+}
+
+/// System keyboard simulated by [`enigo`].
+pub struct SystemKeyboard(enigo::Enigo);
+
+impl SystemKeyboard {
+    /// Connects to the system input.
+    /// # Errors
+    /// If input cannot be simulated, e.g. missing accessibility permission on macOS.
+    pub fn new() -> Result<SystemKeyboard, &'static str> {
+        enigo::Enigo::new(&enigo::Settings::default())
+            .map(SystemKeyboard)
+            .map_err(|_| "Failed to simulate keyboard input")
+    }
+}
+
+impl Keyboard for SystemKeyboard {
+    fn press(&mut self, keystroke: Keystroke) -> Result<(), &'static str> {
+        use enigo::{Direction, Key, Keyboard};
+        let result = match keystroke {
+            Keystroke::Character(character) => self.0.text(&character.to_string()),
+            Keystroke::Tab => self.0.key(Key::Tab, Direction::Click),
+            Keystroke::Enter => self.0.key(Key::Return, Direction::Click),
+        };
+        result.map_err(|_| "Failed to type value")
+    }
+}
+
+/// Types the keystrokes one by one with the delay between them. `aborted` is checked before every keystroke.
+/// # Return
+/// Returns the number of typed keystrokes.
+/// # Errors
+/// If typing was aborted or a keystroke cannot be typed. Keystrokes typed before stay typed.
+pub fn type_keystrokes(
+    keyboard: &mut impl Keyboard,
+    keystrokes: &[Keystroke],
+    delay: Duration,
+    aborted: impl Fn() -> bool,
+) -> Result<usize, &'static str> {
+    for (index, keystroke) in keystrokes.iter().enumerate() {
+        if aborted() {
+            return Err("Typing was aborted");
+        }
+        if index > 0 && delay.is_zero().not() {
+            std::thread::sleep(delay);
+        }
+        keyboard.press(*keystroke)?;
+    }
+    Ok(keystrokes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use tempfile::TempDir;
+
+    /// Dry run keyboard recording the keystrokes instead of typing them.
+    #[derive(Default)]
+    struct DryRun(Vec<Keystroke>);
+
+    impl Keyboard for DryRun {
+        fn press(&mut self, keystroke: Keystroke) -> Result<(), &'static str> {
+            self.0.push(keystroke);
+            Ok(())
+        }
+    }
+
+    fn dry_run(value: &str, allow_enter: bool) -> Result<Vec<Keystroke>, &'static str> {
+        let mut keyboard = DryRun::default();
+        type_keystrokes(
+            &mut keyboard,
+            &keystrokes(value, allow_enter)?,
+            Duration::ZERO,
+            || false,
+        )?;
+        Ok(keyboard.0)
+    }
+
+    #[test]
+    fn test_keystrokes_unicode() {
+        assert_eq!(
+            dry_run("Pš🔐e\u{301}", false),
+            Ok(vec![
+                Keystroke::Character('P'),
+                Keystroke::Character('š'),
+                Keystroke::Character('🔐'),
+                Keystroke::Character('e'),
+                Keystroke::Character('\u{301}'),
+            ])
+        );
+        assert_eq!(dry_run("", false), Ok(Vec::new()));
+    }
+    #[test]
+    fn test_keystrokes_tab() {
+        assert_eq!(
+            dry_run("a\tb", false),
+            Ok(vec![
+                Keystroke::Character('a'),
+                Keystroke::Tab,
+                Keystroke::Character('b'),
+            ])
+        );
+    }
+    #[test]
+    fn test_keystrokes_new_line() {
+        for value in ["a\nb", "a\r\nb", "a\rb"] {
+            assert!(dry_run(value, false).is_err(), "{value:?}");
+            assert_eq!(
+                dry_run(value, true),
+                Ok(vec![
+                    Keystroke::Character('a'),
+                    Keystroke::Enter,
+                    Keystroke::Character('b'),
+                ]),
+                "{value:?}"
+            );
+        }
+    }
+    #[test]
+    fn test_type_keystrokes_aborted() {
+        let mut keyboard = DryRun::default();
+        let checks = Cell::new(0);
+        let result = type_keystrokes(
+            &mut keyboard,
+            &keystrokes("secret", false).unwrap(),
+            Duration::ZERO,
+            || {
+                checks.set(checks.get() + 1);
+                checks.get() > 3
+            },
+        );
+        assert_eq!(result, Err("Typing was aborted"));
+        assert_eq!(keyboard.0.len(), 3);
+    }
+    #[test]
+    fn test_settings() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        assert_eq!(delay(&database), DEFAULT_DELAY);
+        assert!(!allow_enter(&database));
+        database.save_setting(DELAY_SETTING, "50").unwrap();
+        database.save_setting(ENTER_SETTING, "true").unwrap();
+        assert_eq!(delay(&database), Duration::from_millis(50));
+        assert!(allow_enter(&database));
+    }
+}
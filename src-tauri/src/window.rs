@@ -1,10 +1,37 @@
-mod menu;
+pub mod menu;
 use super::*;
 use crate::database::Database;
+pub use menu::accelerator;
 use menu::event::*;
 use menu::*;
 use tauri::WindowBuilder;
 
+/// Window labels in the order in which they are brought to the front.
+const WINDOW_PRIORITY: [&str; 3] = ["main", "login", "register"];
+
+/// Selects the window that should be brought to the front from the labels of existing windows. Main window has the highest priority, then login and register.
+fn prioritized_window<'a>(labels: &[&'a str]) -> Option<&'a str> {
+    WINDOW_PRIORITY
+        .iter()
+        .find_map(|priority| labels.iter().find(|label| *label == priority).copied())
+}
+
+/// Brings the existing window to the front ([prioritized_window]). If there is no window (macOS keeps the application running when all windows are closed), the window is created again by [initialize_window].
+pub fn focus_existing_window(app_handle: &AppHandle) {
+    let windows = app_handle.windows();
+    let labels: Vec<&str> = windows.keys().map(String::as_str).collect();
+    match prioritized_window(&labels).and_then(|label| windows.get(label)) {
+        Some(window) => {
+            window.unminimize().unwrap_or_default();
+            window.show().unwrap_or_default();
+            window.set_focus().unwrap_or_default();
+        }
+        None => {
+            initialize_window(app_handle.clone()).ok();
+        }
+    }
+}
+
 /// Creates login window with specific menu ([create_login_menu]).
 /// # Errors
 /// Returns an error if the window cannot be created. It will **not** return an error if the window already exists.
@@ -71,7 +98,7 @@ pub fn create_register_window(app_handle: AppHandle) -> tauri::Result<()> {
     Ok(())
 }
 
-/// Creates main window with specific menu ([create_main_menu]).
+/// Creates main window with specific menu ([create_main_menu]). Accelerators of the menu items are loaded from the settings if the database is open.
 /// # Errors
 /// Returns an error if the window cannot be created. It will **not** return an error if the window already exists.
 pub fn create_main_window(app_handle: AppHandle) -> tauri::Result<()> {
@@ -88,7 +115,15 @@ pub fn create_main_window(app_handle: AppHandle) -> tauri::Result<()> {
     .disable_file_drop_handler()
     .resizable(true)
     .min_inner_size(720f64, 350f64)
-    .menu(create_main_menu(app_handle.package_info().name.as_str()));
+    .menu(create_main_menu(
+        app_handle.package_info().name.as_str(),
+        &accelerator::bindings(
+            &app_handle
+                .try_state::<Database>()
+                .map(|database| accelerator::load_overrides(&database))
+                .unwrap_or_default(),
+        ),
+    ));
 
     #[cfg(target_os = "macos")]
     let window = window.visible(false);
@@ -102,3 +137,19 @@ pub fn create_main_window(app_handle: AppHandle) -> tauri::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_prioritized_window() {
+        assert_eq!(
+            prioritized_window(&["register", "main", "login"]),
+            Some("main")
+        );
+        assert_eq!(prioritized_window(&["register", "login"]), Some("login"));
+        assert_eq!(prioritized_window(&["register"]), Some("register"));
+        assert_eq!(prioritized_window(&["settings"]), None);
+        assert_eq!(prioritized_window(&[]), None);
+    }
+}
@@ -1,6 +1,8 @@
 #![allow(unused_imports, unused_variables)]
+pub mod accelerator;
 pub mod event;
 use super::*;
+use std::collections::HashMap;
 use tauri::{AboutMetadata, CustomMenuItem, Menu, MenuEntry, MenuItem, Submenu};
 
 /// Default macOS menu for non-resizable windows.
@@ -104,8 +106,17 @@ pub fn create_register_menu(package_name: &str) -> Menu {
     menu
 }
 
-/// Creates a menu specific for the resizable main window.
-pub fn create_main_menu(package_name: &str) -> Menu {
+/// Creates a custom menu item with the accelerator from `accelerators`, if there is one for the id.
+fn custom_item(id: &str, title: &str, accelerators: &HashMap<String, String>) -> CustomMenuItem {
+    let item = CustomMenuItem::new(id.to_string(), title);
+    match accelerators.get(id) {
+        Some(accelerator) => item.accelerator(accelerator),
+        None => item,
+    }
+}
+
+/// Creates a menu specific for the resizable main window. Custom items get accelerators from `accelerators`, see [`accelerator::bindings`].
+pub fn create_main_menu(package_name: &str, accelerators: &HashMap<String, String>) -> Menu {
     let mut menu = Menu::default();
 
     #[cfg(target_os = "macos")]
@@ -118,7 +129,7 @@ pub fn create_main_menu(package_name: &str) -> Menu {
                     AboutMetadata::default(),
                 ))
                 .add_native_item(MenuItem::Separator)
-                .add_item(CustomMenuItem::new("Settings".to_string(), "Settings"))
+                .add_item(custom_item("Settings", "Settings", accelerators))
                 .add_native_item(MenuItem::Separator)
                 .add_native_item(MenuItem::Services)
                 .add_native_item(MenuItem::Separator)
@@ -134,29 +145,35 @@ pub fn create_main_menu(package_name: &str) -> Menu {
         .add_submenu(Submenu::new(
             "New".to_string(),
             Menu::new()
-                .add_item(CustomMenuItem::new("New Login".to_string(), "Login"))
-                .add_item(CustomMenuItem::new(
-                    "New Bank Card".to_string(),
-                    "Bank Card",
-                ))
-                .add_item(CustomMenuItem::new("New Note".to_string(), "Note"))
-                .add_item(CustomMenuItem::new("New Other".to_string(), "Other")),
+                .add_item(custom_item("New Login", "Login", accelerators))
+                .add_item(custom_item("New Bank Card", "Bank Card", accelerators))
+                .add_item(custom_item("New Note", "Note", accelerators))
+                .add_item(custom_item("New Other", "Other", accelerators)),
         ))
         .add_native_item(MenuItem::Separator);
 
     #[cfg(target_os = "linux")]
     {
         file_menu = file_menu
-            .add_item(CustomMenuItem::new("Settings".to_string(), "Settings"))
+            .add_item(custom_item("Settings", "Settings", accelerators))
             .add_native_item(MenuItem::Separator);
     }
 
     file_menu = file_menu.add_submenu(Submenu::new(
         "Export".to_string(),
-        Menu::new().add_item(CustomMenuItem::new(
-            "Export Database".to_string(),
-            "Database",
-        )),
+        Menu::new()
+            .add_item(custom_item("Export Database", "Database", accelerators))
+            .add_item(custom_item("Export CSV", "CSV", accelerators))
+            .add_item(custom_item(
+                "Export Inventory CSV",
+                "Inventory CSV",
+                accelerators,
+            ))
+            .add_item(custom_item(
+                "Export Inventory PDF",
+                "Inventory PDF",
+                accelerators,
+            )),
     ));
 
     menu = menu.add_submenu(Submenu::new("File", file_menu));
@@ -191,3 +208,82 @@ pub fn create_main_menu(package_name: &str) -> Menu {
 
     menu
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns ids of all custom menu items, including items in submenus.
+    fn custom_item_ids(menu: &Menu) -> Vec<String> {
+        menu.items
+            .iter()
+            .flat_map(|item| match item {
+                MenuEntry::CustomItem(item) => vec![item.id_str.clone()],
+                MenuEntry::Submenu(submenu) => custom_item_ids(&submenu.inner),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_login_menu() {
+        assert_eq!(
+            custom_item_ids(&create_login_menu("Password Manager")),
+            vec!["Start Over"]
+        );
+    }
+    #[test]
+    fn test_register_menu() {
+        assert_eq!(
+            custom_item_ids(&create_register_menu("Password Manager")),
+            vec!["Choose database"]
+        );
+    }
+    #[test]
+    fn test_main_menu() {
+        let mut ids = custom_item_ids(&create_main_menu("Password Manager", &HashMap::new()));
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                "Export CSV",
+                "Export Database",
+                "Export Inventory CSV",
+                "Export Inventory PDF",
+                "New Bank Card",
+                "New Login",
+                "New Note",
+                "New Other",
+                "Settings"
+            ]
+        );
+    }
+    #[test]
+    fn test_main_menu_accelerators() {
+        fn accelerators(menu: &Menu) -> Vec<(String, Option<String>)> {
+            menu.items
+                .iter()
+                .flat_map(|item| match item {
+                    MenuEntry::CustomItem(item) => {
+                        vec![(item.id_str.clone(), item.keyboard_accelerator.clone())]
+                    }
+                    MenuEntry::Submenu(submenu) => accelerators(&submenu.inner),
+                    _ => Vec::new(),
+                })
+                .collect()
+        }
+        let bindings = accelerator::bindings(&HashMap::from([(
+            "New Note".to_string(),
+            "Ctrl+Alt+N".to_string(),
+        )]));
+        let menu = accelerators(&create_main_menu("Password Manager", &bindings));
+        let get = |id: &str| {
+            menu.iter()
+                .find(|(other, _)| other == id)
+                .and_then(|(_, accelerator)| accelerator.clone())
+        };
+        assert_eq!(get("New Login").as_deref(), Some("CmdOrCtrl+N"));
+        assert_eq!(get("New Note").as_deref(), Some("Ctrl+Alt+N"));
+        assert_eq!(get("New Other"), None);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::database::Database;
+use std::collections::HashMap;
+use std::ops::Not;
+
+/// Setting with user overrides of accelerators, JSON object of menu item ids and accelerators. Empty accelerator removes the default one.
+pub const SETTING: &str = "menu_accelerators";
+
+/// Custom menu items of the main window that can have an accelerator, with their default accelerator.
+const DEFAULTS: [(&str, &str); 9] = [
+    ("New Login", "CmdOrCtrl+N"),
+    ("New Bank Card", "CmdOrCtrl+Shift+B"),
+    ("New Note", "CmdOrCtrl+Shift+N"),
+    ("New Other", ""),
+    ("Settings", "CmdOrCtrl+,"),
+    ("Export Database", "CmdOrCtrl+E"),
+    ("Export CSV", ""),
+    ("Export Inventory CSV", ""),
+    ("Export Inventory PDF", ""),
+];
+
+/// Accelerators of native menu items and common text editing shortcuts, they can not be assigned to custom items.
+const RESERVED: [&str; 10] = [
+    "CmdOrCtrl+Q",
+    "CmdOrCtrl+W",
+    "CmdOrCtrl+H",
+    "CmdOrCtrl+M",
+    "CmdOrCtrl+Z",
+    "CmdOrCtrl+Shift+Z",
+    "CmdOrCtrl+X",
+    "CmdOrCtrl+C",
+    "CmdOrCtrl+V",
+    "CmdOrCtrl+A",
+];
+
+/// Modifiers in the order in which they are written, with their accepted names.
+const MODIFIERS: [(&str, &[&str]); 5] = [
+    ("CmdOrCtrl", &["cmdorctrl", "commandorcontrol"]),
+    ("Cmd", &["cmd", "command", "super"]),
+    ("Ctrl", &["ctrl", "control"]),
+    ("Alt", &["alt", "option"]),
+    ("Shift", &["shift"]),
+];
+
+/// Named keys, other keys are single letters, digits, punctuation or F1 to F24.
+const NAMED_KEYS: [&str; 16] = [
+    "Space",
+    "Tab",
+    "Enter",
+    "Backspace",
+    "Delete",
+    "Escape",
+    "Up",
+    "Down",
+    "Left",
+    "Right",
+    "Home",
+    "End",
+    "PageUp",
+    "PageDown",
+    "Plus",
+    "Minus",
+];
+
+/// Returns the key in its canonical form if it is a valid key.
+fn parse_key(key: &str) -> Option<String> {
+    if let Some(named) = NAMED_KEYS
+        .iter()
+        .find(|named| named.eq_ignore_ascii_case(key))
+    {
+        return Some(named.to_string());
+    }
+    let mut characters = key.chars();
+    match (characters.next(), characters.next()) {
+        (Some(character), None) if character.is_ascii_alphanumeric() => {
+            Some(character.to_ascii_uppercase().to_string())
+        }
+        (Some(character), None) if ",.;'/\\[]`=-".contains(character) => {
+            Some(character.to_string())
+        }
+        (Some('F' | 'f'), Some(_)) => key[1..]
+            .parse::<u8>()
+            .ok()
+            .filter(|number| (1..=24).contains(number))
+            .map(|number| format!("F{number}")),
+        _ => None,
+    }
+}
+
+/// Parses an accelerator, e.g. `CmdOrCtrl+Shift+N`, and returns it in canonical form: modifiers in a fixed order with canonical names and the key last. Empty accelerator stays empty.
+/// # Errors
+/// If a part is not a known modifier or key, if a modifier is repeated, if there is not exactly one key or if there is no modifier and the key is not a function key.
+pub fn parse(accelerator: &str) -> Result<String, &'static str> {
+    let accelerator = accelerator.trim();
+    if accelerator.is_empty() {
+        return Ok(String::new());
+    }
+    // Trailing "+" is the plus key, e.g. "Ctrl++"
+    let (accelerator, plus) = match accelerator.strip_suffix("++") {
+        Some(rest) => (rest, true),
+        None => (accelerator, false),
+    };
+
+    let mut modifiers = [false; MODIFIERS.len()];
+    let mut key: Option<String> = None;
+    for part in accelerator.split('+').map(str::trim) {
+        if let Some(index) = MODIFIERS
+            .iter()
+            .position(|(_, names)| names.contains(&part.to_lowercase().as_str()))
+        {
+            if modifiers[index] {
+                return Err("Modifier is repeated");
+            }
+            modifiers[index] = true;
+        } else if key.is_some() {
+            return Err("Shortcut can have only one key");
+        } else {
+            key = Some(parse_key(part).ok_or("Unknown key")?);
+        }
+    }
+    if plus {
+        if key.is_some() {
+            return Err("Shortcut can have only one key");
+        }
+        key = Some("Plus".to_string());
+    }
+
+    let key = key.ok_or("Shortcut is missing a key")?;
+    let function_key = key.len() > 1 && key.starts_with('F') && key[1..].parse::<u8>().is_ok();
+    if modifiers.iter().any(|modifier| *modifier).not() && function_key.not() {
+        return Err("Shortcut must have a modifier");
+    }
+
+    let mut parts: Vec<String> = MODIFIERS
+        .iter()
+        .zip(modifiers)
+        .filter(|(_, used)| *used)
+        .map(|((name, _), _)| name.to_string())
+        .collect();
+    parts.push(key);
+    Ok(parts.join("+"))
+}
+
+/// Returns the accelerator as it is pressed on this platform, `CmdOrCtrl` is resolved to `Cmd` on macOS and to `Ctrl` elsewhere. Used to detect conflicts.
+fn resolve(accelerator: &str) -> String {
+    let modifier = if cfg!(target_os = "macos") {
+        "Cmd"
+    } else {
+        "Ctrl"
+    };
+    let mut parts: Vec<&str> = accelerator
+        .split('+')
+        .map(|part| if part == "CmdOrCtrl" { modifier } else { part })
+        .collect();
+    let key = parts.pop();
+    parts.sort_unstable();
+    parts.dedup();
+    parts.extend(key);
+    parts.join("+")
+}
+
+/// Returns accelerators of all custom items: defaults with the overrides applied. Items without an accelerator are left out.
+pub fn bindings(overrides: &HashMap<String, String>) -> HashMap<String, String> {
+    DEFAULTS
+        .iter()
+        .map(|(id, default)| {
+            let accelerator = overrides
+                .get(*id)
+                .and_then(|accelerator| parse(accelerator).ok())
+                .unwrap_or(default.to_string());
+            (id.to_string(), accelerator)
+        })
+        .filter(|(_, accelerator)| accelerator.is_empty().not())
+        .collect()
+}
+
+/// Checks that the accelerator can be assigned to the item, it must not be reserved or used by another item.
+/// # Errors
+/// Returns a message naming the item that already uses the accelerator.
+pub fn check_conflict(
+    bindings: &HashMap<String, String>,
+    item_id: &str,
+    accelerator: &str,
+) -> Result<(), String> {
+    if accelerator.is_empty() {
+        return Ok(());
+    }
+    let resolved = resolve(accelerator);
+    if RESERVED
+        .iter()
+        .any(|reserved| resolve(reserved) == resolved)
+    {
+        return Err(format!("{accelerator} is reserved by the system"));
+    }
+    let mut clashes: Vec<&str> = bindings
+        .iter()
+        .filter(|(id, other)| id.as_str() != item_id && resolve(other) == resolved)
+        .map(|(id, _)| id.as_str())
+        .collect();
+    clashes.sort_unstable();
+    if clashes.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "{accelerator} is already used by {}",
+            clashes.join(", ")
+        ))
+    }
+}
+
+/// Loads user overrides from the settings. Missing or invalid setting means no overrides.
+pub fn load_overrides(database: &Database) -> HashMap<String, String> {
+    database
+        .get_setting(SETTING)
+        .ok()
+        .and_then(|overrides| serde_json::from_str(overrides.expose_secret()).ok())
+        .unwrap_or_default()
+}
+
+/// Validates and saves the accelerator of the custom menu item, empty accelerator removes it.
+/// # Errors
+/// If the item does not exist, the accelerator is not valid, conflicts with another item or cannot be saved.
+pub fn save(database: &Database, item_id: &str, accelerator: &str) -> Result<(), String> {
+    if DEFAULTS.iter().all(|(id, _)| *id != item_id) {
+        return Err("Unknown menu item".to_string());
+    }
+    let accelerator = parse(accelerator)?;
+    let mut overrides = load_overrides(database);
+    check_conflict(&bindings(&overrides), item_id, &accelerator)?;
+    overrides.insert(item_id.to_string(), accelerator);
+    database
+        .save_setting(
+            SETTING,
+            &serde_json::to_string(&overrides).map_err(|_| "Failed to save shortcut")?,
+        )
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("CmdOrCtrl+N"), Ok("CmdOrCtrl+N".to_string()));
+        assert_eq!(
+            parse(" shift + commandorcontrol + n "),
+            Ok("CmdOrCtrl+Shift+N".to_string())
+        );
+        assert_eq!(parse("Alt+Option+F2"), Err("Modifier is repeated"));
+        assert_eq!(parse("Ctrl+Alt+f12"), Ok("Ctrl+Alt+F12".to_string()));
+        assert_eq!(parse("F5"), Ok("F5".to_string()));
+        assert_eq!(parse("Ctrl++"), Ok("Ctrl+Plus".to_string()));
+        assert_eq!(parse("Ctrl+,"), Ok("Ctrl+,".to_string()));
+        assert_eq!(parse("Ctrl+pageup"), Ok("Ctrl+PageUp".to_string()));
+        assert_eq!(parse(""), Ok("".to_string()));
+        assert_eq!(parse("N"), Err("Shortcut must have a modifier"));
+        assert_eq!(parse("Ctrl+Shift+Shift"), Err("Modifier is repeated"));
+        assert_eq!(parse("Ctrl+N+M"), Err("Shortcut can have only one key"));
+        assert_eq!(parse("Ctrl+Shift"), Err("Shortcut is missing a key"));
+        assert_eq!(parse("Ctrl+F25"), Err("Unknown key"));
+        assert_eq!(parse("Hyper+N"), Err("Unknown key"));
+    }
+    #[test]
+    fn test_bindings() {
+        let overrides = HashMap::from([
+            ("New Login".to_string(), "Ctrl+Alt+L".to_string()),
+            ("Export Database".to_string(), "".to_string()),
+            ("New Note".to_string(), "invalid+".to_string()),
+        ]);
+        let bindings = bindings(&overrides);
+        assert_eq!(bindings.get("New Login").unwrap(), "Ctrl+Alt+L");
+        assert_eq!(bindings.get("New Note").unwrap(), "CmdOrCtrl+Shift+N");
+        assert!(!bindings.contains_key("Export Database"));
+        assert!(!bindings.contains_key("New Other"));
+    }
+    #[test]
+    fn test_check_conflict() {
+        let bindings = bindings(&HashMap::new());
+        assert_eq!(
+            check_conflict(&bindings, "New Other", "CmdOrCtrl+Shift+N"),
+            Err("CmdOrCtrl+Shift+N is already used by New Note".to_string())
+        );
+        let native = if cfg!(target_os = "macos") {
+            "Cmd+Q"
+        } else {
+            "Ctrl+Q"
+        };
+        assert_eq!(
+            check_conflict(&bindings, "New Other", native),
+            Err(format!("{native} is reserved by the system"))
+        );
+        let same = if cfg!(target_os = "macos") {
+            "Shift+Cmd+N"
+        } else {
+            "Shift+Ctrl+N"
+        };
+        assert!(check_conflict(&bindings, "New Other", &parse(same).unwrap()).is_err());
+        assert!(check_conflict(&bindings, "New Note", "CmdOrCtrl+Shift+N").is_ok());
+        assert!(check_conflict(&bindings, "New Other", "CmdOrCtrl+Alt+O").is_ok());
+        assert!(check_conflict(&bindings, "New Other", "").is_ok());
+    }
+    #[test]
+    fn test_save() {
+        let directory = TempDir::new().unwrap();
+        let database = Database::open_path("password", &directory.path().join("database")).unwrap();
+        assert_eq!(
+            save(&database, "Quit", "CmdOrCtrl+K"),
+            Err("Unknown menu item".to_string())
+        );
+        assert_eq!(
+            save(&database, "New Other", "CmdOrCtrl+E"),
+            Err("CmdOrCtrl+E is already used by Export Database".to_string())
+        );
+        save(&database, "Export Database", "").unwrap();
+        save(&database, "New Other", "cmdorctrl+e").unwrap();
+        let bindings = bindings(&load_overrides(&database));
+        assert_eq!(bindings.get("New Other").unwrap(), "CmdOrCtrl+E");
+        assert!(!bindings.contains_key("Export Database"));
+    }
+}
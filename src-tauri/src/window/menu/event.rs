@@ -1,41 +1,35 @@
 use super::*;
+use crate::command::database::InventoryFormat;
 use crate::database::model::*;
 use crate::database::DATABASE_FILE_NAME;
+use crate::events::{self, Event};
+use crate::hooks::{self, HookEvent, HookPayload};
+use crate::session::{self, SessionState};
 use std::fs;
-use std::path::Path;
-use tauri::{MenuEvent, Window};
+use std::path::{Path, PathBuf};
+use tauri::{MenuEvent, Runtime, Window};
 
 /// Handles all menu events.
 pub fn menu_event(event: MenuEvent, app_handle: AppHandle, window: Window) {
     match event.menu_item_id() {
         "Start Over" => start_over(app_handle, window),
         "Choose database" => choose_database(app_handle, window),
-        "Settings" => window.emit("settings", ()).unwrap_or_default(),
-        "New Login" => window
-            .emit_all(
-                "new_record",
-                Record::new("".to_string(), "".to_string(), Category::Login),
-            )
-            .unwrap_or_default(),
-        "New Bank Card" => window
-            .emit(
-                "new_record",
-                Record::new("".to_string(), "".to_string(), Category::BankCard),
-            )
-            .unwrap_or_default(),
-        "New Note" => window
-            .emit(
-                "new_record",
-                Record::new("".to_string(), "".to_string(), Category::Note),
-            )
-            .unwrap_or_default(),
-        "New Other" => window
-            .emit(
-                "new_record",
-                Record::new("".to_string(), "".to_string(), Category::Other),
-            )
+        "Settings" => events::emit_to_main(&app_handle, Event::Settings).unwrap_or_default(),
+        "New Login" => {
+            events::emit_to_main(&app_handle, Event::NewRecord(Category::Login)).unwrap_or_default()
+        }
+        "New Bank Card" => events::emit_to_main(&app_handle, Event::NewRecord(Category::BankCard))
             .unwrap_or_default(),
+        "New Note" => {
+            events::emit_to_main(&app_handle, Event::NewRecord(Category::Note)).unwrap_or_default()
+        }
+        "New Other" => {
+            events::emit_to_main(&app_handle, Event::NewRecord(Category::Other)).unwrap_or_default()
+        }
         "Export Database" => export_database(app_handle, window),
+        "Export CSV" => export_csv(app_handle, window),
+        "Export Inventory CSV" => export_inventory(app_handle, window, InventoryFormat::Csv),
+        "Export Inventory PDF" => export_inventory(app_handle, window, InventoryFormat::Pdf),
         _ => tauri::api::dialog::message(
             Some(&window),
             "Error",
@@ -61,7 +55,7 @@ pub fn start_over(app_handle: AppHandle, window: Window) {
                     );
                 }
 
-                app_handle.restart()
+                session::lock(&app_handle, SessionState::Locked)
             }
         } else {
             tauri::api::dialog::message(Some(&window), "Error", "Failed to get database path");
@@ -83,6 +77,9 @@ pub fn choose_database(app_handle: AppHandle, window: Window) {
                     .set_parent(&window)
                     .set_title("Set database").add_filter("Password Manager", &["password_manager"])
                     .pick_file() {
+                    if let Some(database) = app_handle.try_state::<Database>() {
+                        database.checkpoint().unwrap_or_default();
+                    }
                     if let Err(error) = fs::copy(new_database, old_database) {
                         tauri::api::dialog::blocking::message(
                             Some(&window),
@@ -90,14 +87,14 @@ pub fn choose_database(app_handle: AppHandle, window: Window) {
                             format!("Failed to copy database file: {}", error),
                         );
                     }
-                    app_handle.restart();
+                    session::lock(&app_handle, SessionState::Locked);
                 }
             }
         }
     });
 }
 
-/// Exports the database file. Has dialog.
+/// Exports the database file, see [`database_exported`]. Has dialog.
 pub fn export_database(app_handle: AppHandle, window: Window) {
     tauri::async_runtime::spawn_blocking(move || {
         if let Some(source) = Database::path(&app_handle) {
@@ -107,14 +104,85 @@ pub fn export_database(app_handle: AppHandle, window: Window) {
                 .set_file_name(DATABASE_FILE_NAME)
                 .save_file()
             {
-                if let Err(error) = fs::copy(source, destination) {
+                let checkpoint = app_handle
+                    .try_state::<Database>()
+                    .map_or(Ok(()), |database| database.checkpoint());
+                if let Err(error) = checkpoint {
                     tauri::api::dialog::blocking::message(
                         Some(&window),
                         "Error",
-                        format!("Failed to copy database file: {}", error),
+                        error.to_string(),
                     );
+                    return;
+                }
+                match fs::copy(source, &destination) {
+                    Ok(_) => database_exported(&app_handle, destination),
+                    Err(error) => tauri::api::dialog::blocking::message(
+                        Some(&window),
+                        "Error",
+                        format!("Failed to copy database file: {}", error),
+                    ),
                 }
             }
         }
     });
 }
+
+/// Records the copied database file as [`LastExport`] and runs the hook of [`HookEvent::ExportFinished`].
+pub fn database_exported<R: Runtime>(app_handle: &AppHandle<R>, destination: PathBuf) {
+    app_handle.state::<LastExport>().set(destination);
+    if let Some(database) = app_handle.try_state::<Database>() {
+        hooks::trigger(
+            &database,
+            HookPayload {
+                format: Some("database"),
+                ..HookPayload::new(HookEvent::ExportFinished)
+            },
+        );
+    }
+}
+
+/// Exports all records except locked ones to a CSV file, see [`crate::command::database::export_csv`]. Has dialogs.
+pub fn export_csv(app_handle: AppHandle, window: Window) {
+    tauri::async_runtime::spawn(async move {
+        let result = crate::command::database::export_csv(
+            false,
+            app_handle.state(),
+            app_handle.state(),
+            app_handle.state(),
+            window.clone(),
+        )
+        .await;
+        match result {
+            Ok(summary) if summary.locked > 0 => tauri::api::dialog::message(
+                Some(&window),
+                "Export CSV",
+                format!(
+                    "{} locked records were left out. Unlock them to include them in the export.",
+                    summary.locked
+                ),
+            ),
+            Ok(_) => {}
+            Err(error) if error == "Canceled by user" => {}
+            Err(error) => tauri::api::dialog::message(Some(&window), "Error", error.to_string()),
+        }
+    });
+}
+
+/// Exports the list of records without secrets, see [`crate::command::database::export_inventory`]. Has dialogs.
+pub fn export_inventory(app_handle: AppHandle, window: Window, format: InventoryFormat) {
+    tauri::async_runtime::spawn(async move {
+        let result = crate::command::database::export_inventory(
+            format,
+            app_handle.state(),
+            app_handle.state(),
+            window.clone(),
+        )
+        .await;
+        match result {
+            Ok(_) => {}
+            Err(error) if error == "Canceled by user" => {}
+            Err(error) => tauri::api::dialog::message(Some(&window), "Error", error.to_string()),
+        }
+    });
+}
@@ -0,0 +1,83 @@
+//! Memory used by the search of compromised passwords. The counting allocator replaces the global allocator of the whole binary, so it lives in its own test.
+use password_manager::fixtures::{create_fixture_vault, FixtureSpec};
+use password_manager::testing::{group_passwords, Database};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ops::Not;
+use tempfile::TempDir;
+
+/// Allocator counting bytes allocated by each thread, so tests running in parallel do not affect each other.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+/// Adds `bytes` to the allocated bytes of the current thread and updates the peak.
+fn count(bytes: isize) {
+    let _ = ALLOCATED.try_with(|allocated| {
+        allocated.set(allocated.get() + bytes);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(allocated.get())));
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let pointer = System.alloc(layout);
+        if pointer.is_null().not() {
+            count(layout.size() as isize);
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        System.dealloc(pointer, layout);
+        count(-(layout.size() as isize));
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the peak of bytes allocated by the current thread while `run` runs.
+fn peak_allocated(run: impl FnOnce()) -> isize {
+    ALLOCATED.with(|allocated| allocated.set(0));
+    PEAK.with(|peak| peak.set(0));
+    run();
+    PEAK.with(Cell::get)
+}
+
+#[test]
+fn test_group_passwords_memory() {
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("database");
+    let spec = FixtureSpec {
+        records: 2000,
+        seed: 1,
+    };
+    create_fixture_vault(&path, "password", spec).unwrap();
+    let database = Database::open_path("password", &path).unwrap();
+
+    // Loading of all records and content used before
+    let loaded = peak_allocated(|| {
+        let records = database.get_all_records().unwrap();
+        let content = database.get_all_content_grouped().unwrap();
+        assert_eq!(records.len(), spec.records);
+        assert!(content.is_empty().not());
+    });
+    // The fixture has about 400 distinct passwords, so they are read in several batches. The last record of a batch may add a few passwords over the limit
+    let limit = 100;
+    let mut batches = 0;
+    let streamed = peak_allocated(|| {
+        let mut after = Some(0);
+        while let Some(start) = after {
+            let groups = group_passwords(&database, false, start, limit).unwrap();
+            assert!(groups.hashes.len() < limit + 10);
+            after = groups.next;
+            batches += 1;
+        }
+    });
+    assert!(batches > 1, "{batches}");
+    assert!(streamed * 4 < loaded, "{streamed} of {loaded} bytes");
+}
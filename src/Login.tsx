@@ -1,7 +1,8 @@
-import {createSignal, JSX} from "solid-js";
+import {createResource, createSignal, JSX, onCleanup, Show} from "solid-js";
 import {invoke} from "@tauri-apps/api/tauri";
 import {appWindow} from '@tauri-apps/api/window'
 import GetSVG from "./GetSVG.tsx";
+import {errorMessage} from "./Model.tsx";
 // @ts-ignore
 import logo from "./assets/logo.png";
 
@@ -15,22 +16,39 @@ export default function Login(): JSX.Element {
     const [visibility, setVisibility] = createSignal(false);
     const [loading, setLoading] = createSignal(false);
     const [error, setError] = createSignal("");
+    const [stage, setStage] = createSignal("");
+    const stages: Record<string, string> = {
+        ConnectingToCloud: "Connecting to cloud",
+        ComparingVersions: "Comparing versions",
+        Downloading: "Downloading",
+        OpeningVault: "Opening vault"
+    };
+    const unlisten = appWindow.listen<{ stage: string, percent: number | null }>("login_progress", (event) => {
+        const percent = event.payload.percent;
+        setStage(stages[event.payload.stage] + (percent === null ? "…" : ` ${percent}%`));
+    });
+    onCleanup(() => unlisten.then((unlisten) => unlisten()));
+    const [keychain] = createResource(() => invoke<{ supported: boolean, enabled: boolean }>("get_keychain_status"));
+    const login = async (command: string, args?: Record<string, string>) => {
+        setLoading(true);
+        try {
+            await invoke<void>(command, args)
+            await appWindow.close();
+        } catch (e) {
+            setError(errorMessage(e));
+
+        } finally {
+            setLoading(false);
+            setStage("");
+        }
+    };
 
     return (
         <div class="h-full flex flex-col items-center justify-center gap-0">
             <img src={logo} alt="Password Manager" class="w-60 mb-4" draggable="false"></img>
             <form class="flex flex-row gap-1 w-72" onSubmit={async (event) => {
                 event.preventDefault();
-                setLoading(true);
-                try {
-                    await invoke<void>("login", {password: password()})
-                    await appWindow.close();
-                } catch (e) {
-                    setError(e as string);
-
-                } finally {
-                    setLoading(false);
-                }
+                await login("login", {password: password()});
             }}>
                 <div class="relative w-full">
                     <input placeholder="Enter your master password" type={visibility() ? 'text' : 'password'}
@@ -47,6 +65,15 @@ export default function Login(): JSX.Element {
                     <GetSVG name={loading() ? "spinner" : "right-to-bracket"} class={loading() ? "animate-spin" : ""}/>
                 </button>
             </form>
+            <Show when={keychain()?.supported && keychain()?.enabled}>
+                <button class="mt-2 text-[14px]" disabled={loading()}
+                        onClick={() => login("login_with_keychain")}>
+                    Unlock with Touch ID
+                </button>
+            </Show>
+            <Show when={loading() && stage() !== ""}>
+                <p class="text-[14px]">{stage()}</p>
+            </Show>
             <p class="text-[14px] text-[#EB5545]">{error()}</p>
         </div>
     )
@@ -16,7 +16,7 @@ import {
 import {invoke} from "@tauri-apps/api/tauri";
 import Loading from "./Loading.tsx";
 import GetSVG from "./GetSVG.tsx";
-import {Record, KindSVG, editSignal} from "./Model.tsx";
+import {Record, KindSVG, editSignal, errorMessage} from "./Model.tsx";
 import RecordDetail from "./RecordDetail.tsx";
 import {confirm, message} from '@tauri-apps/api/dialog';
 import {listen, UnlistenFn} from "@tauri-apps/api/event";
@@ -38,6 +38,7 @@ export default function Main(): JSX.Element {
     const filteredRecords = createMemo(() => allRecords.latest.filter(record => compromisedOnly() ? compromisedRecords.latest?.includes(record.id as number) : true).filter(record => (record.title.toLowerCase().includes(search().toLowerCase()) || record.subtitle.toLowerCase().includes(search().toLowerCase()) || record.category.toLowerCase().includes(search().toLowerCase()))));
     const [selected, setSelected] = createSignal<Record | "Settings" | undefined>(undefined);
     const [cloud, {refetch: upload}] = createResource(load, async () => invoke<string>("cloud_upload"));
+    createResource(load, async () => invoke<number>("check_time_sync").catch(_ => 0));
     const [edit, setEdit] = editSignal;
 
     createEffect(() => {
@@ -88,7 +89,7 @@ export default function Main(): JSX.Element {
                 try {
                     await invoke("delete_record", {record: record});
                 } catch (e) {
-                    await message(e as string, {title: 'Error', type: 'error'});
+                    await message(errorMessage(e), {title: 'Error', type: 'error'});
                 }
                 refetchAllRecords();
                 await select(undefined);
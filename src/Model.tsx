@@ -31,9 +31,11 @@ export class Content {
     required: boolean;
     kind: string;
     value?: string;
+    truncated?: boolean;
 
-    constructor(label: string, position: number, required: boolean, kind: string, value?: string, id?: number) {
+    constructor(label: string, position: number, required: boolean, kind: string, value?: string, id?: number, truncated?: boolean) {
         this.id = id;
+        this.truncated = truncated;
         this.label = label;
         this.position = position;
         this.required = required;
@@ -66,4 +68,14 @@ export function KindSVG(kind: string): string {
 /**
  * Signal for editing a record.
  */
-export const editSignal = createSignal(false);
\ No newline at end of file
+export const editSignal = createSignal(false);
+
+/**
+ * Returns the user-facing message of an error returned by a command. Errors have a machine-readable `code` and a `message`.
+ */
+export function errorMessage(error: unknown): string {
+    if (typeof error === "object" && error !== null && "message" in error) {
+        return String(error.message);
+    }
+    return String(error);
+}
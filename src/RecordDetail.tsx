@@ -1,4 +1,4 @@
-import {Content, KindSVG, Record, editSignal} from "./Model.tsx";
+import {Content, KindSVG, Record, editSignal, errorMessage} from "./Model.tsx";
 import {createEffect, createResource, createSignal, For, JSX, Match, onCleanup, onMount, Show, Switch} from "solid-js";
 import {invoke} from "@tauri-apps/api/tauri";
 import GetSVG from "./GetSVG.tsx";
@@ -32,8 +32,23 @@ async function copyTextToClipboard(text: string) {
  */
 
 export default function RecordDetail({record, refresh}: { record: () => Record, refresh: () => void }): JSX.Element {
-    const [allContent, {mutate: newContent}] = createResource(record, async () => (await invoke<Content[]>("get_all_content_for_record", {record: record()})).map((item: Content) => new Content(item.label, item.position, item.required, item.kind, item.value, item.id)).sort((a, b) => a.position - b.position));
     const [edit, setEdit] = editSignal;
+    const [allContent, {mutate: newContent}] = createResource(record, async () => {
+        let content = await invoke<Content[]>("get_all_content_for_record", {record: record()});
+        const draft = await invoke<string | null>("get_draft", {id: record().id ?? 0});
+        if (draft !== null) {
+            if (await confirm("This record has unsaved changes. Do you want to restore them?", {title: "Restore changes", type: "info"})) {
+                const snapshot: { record: Record, content: Content[] } = JSON.parse(draft);
+                record().title = snapshot.record.title;
+                record().subtitle = snapshot.record.subtitle;
+                content = snapshot.content;
+                setEdit(true);
+            } else {
+                await invoke("discard_draft", {id: record().id ?? 0});
+            }
+        }
+        return content.map((item: Content) => new Content(item.label, item.position, item.required, item.kind, item.value, item.id, item.truncated)).sort((a, b) => a.position - b.position);
+    });
     const [error, setError] = createSignal("");
 
     createEffect(() => {
@@ -50,7 +65,7 @@ export default function RecordDetail({record, refresh}: { record: () => Record,
             try {
                 await invoke("copy_value_to_clipboard", {id: Number.parseInt(event?.payload)})
             } catch (e) {
-                await message(e as string, {title: 'Error', type: 'error'})
+                await message(errorMessage(e), {title: 'Error', type: 'error'})
             }
         });
 
@@ -80,16 +95,23 @@ export default function RecordDetail({record, refresh}: { record: () => Record,
         <div id="record-detail" class="m-10 flex justify-center">
             <form
                 class="grid grid-cols-1 min-h-full w-full max-w-6xl rounded-md border border-[#E7E7E7] dark:border-[#3A3A3A] bg-[#F2F2F2] dark:bg-[#2B2B2B]"
+                onChange={async () => {
+                    if (edit()) {
+                        await invoke("save_draft", {
+                            id: record().id ?? 0,
+                            snapshot: JSON.stringify({record: record(), content: allContent()})
+                        }).catch(() => undefined);
+                    }
+                }}
                 onSubmit={async (event) => {
                     event.preventDefault();
                     if (edit()) {
                         try {
                             record().id = await invoke<number>("save_record", {
-                                record: record(),
-                                content: allContent()
+                                payload: JSON.stringify({record: record(), content: allContent()})
                             });
                         } catch (e) {
-                            await message(e as string, {title: 'Error', type: 'error'});
+                            await message(errorMessage(e), {title: 'Error', type: 'error'});
                         }
 
                         refresh();
@@ -188,9 +210,6 @@ export default function RecordDetail({record, refresh}: { record: () => Record,
                                                         if (!confirmed) {
                                                             return;
                                                         }
-                                                        if (content.id !== undefined && content.id !== 0) {
-                                                            await invoke("delete_content", {content: content});
-                                                        }
 
                                                         let temp: Content[] = Object.assign([], allContent() as Content[]);
                                                         temp.splice(index(), 1);
@@ -216,7 +235,7 @@ export default function RecordDetail({record, refresh}: { record: () => Record,
                                         </Show>
                                     </div>
                                 </Show>
-                                <ContentValue content={content}/>
+                                <ContentValue content={content} recordId={record().id}/>
                             </div>
                             <Show
                                 when={(index() < (allContent()?.length as number - 1)) || (edit() && allContent()?.length! < 50)}>
@@ -298,12 +317,17 @@ export default function RecordDetail({record, refresh}: { record: () => Record,
     )
 }
 
+/**
+ * Value returned by get_content_value. Masked values have a reveal token, the backend emits re-mask with it when the reveal expires.
+ */
+type RevealedValue = { value: string, reveal_token: number | null, reveal_seconds: number | null };
+
 /**
  * Content value component for the record detail
  * @param content - The content to be displayed.
  * @return {JSX.Element} - Div containing the content value.
  */
-function ContentValue({content}: { content: Content }): JSX.Element {
+function ContentValue({content, recordId}: { content: Content, recordId?: number }): JSX.Element {
     const [edit, _] = editSignal;
     const [error, setError] = createSignal("");
     const [visibility, setVisibility] = createSignal(false);
@@ -317,14 +341,33 @@ function ContentValue({content}: { content: Content }): JSX.Element {
     const [totp, setTotp] = createSignal(["", 0]);
 
     let unlistenVisibility: UnlistenFn | undefined = undefined;
+    let unlistenReMask: UnlistenFn | undefined = undefined;
+    let revealToken: number | null = null;
 
     onMount(async () => {
-        unlistenVisibility = await listen("visibility" + content.id?.toString(), () => setVisibility(!visibility()));
+        unlistenVisibility = await listen("visibility" + content.id?.toString(), async () => {
+            if (visibility() && revealToken !== null) {
+                await invoke("cancel_reveal", {token: revealToken});
+                revealToken = null;
+            }
+            setVisibility(!visibility());
+        });
+        unlistenReMask = await listen<{ token: number }>("re-mask", (event) => {
+            if (event.payload.token === revealToken) {
+                revealToken = null;
+                if (!edit())
+                    setVisibility(false);
+            }
+        });
     });
 
     onCleanup(() => {
         if (unlistenVisibility)
             unlistenVisibility();
+        if (unlistenReMask)
+            unlistenReMask();
+        if (revealToken !== null)
+            invoke("cancel_reveal", {token: revealToken});
     });
 
     const [value, {mutate: setValue}] = createResource(
@@ -335,16 +378,25 @@ function ContentValue({content}: { content: Content }): JSX.Element {
                     content.value = "";
                 } else if (edit || visible) {
                     try {
-                        content.value = await invoke<string>("get_content_value", {id: content.id as number});
+                        const revealed = await invoke<RevealedValue>("get_content_value", {id: content.id as number, expected_record_id: recordId});
+                        content.value = revealed.value;
+                        revealToken = revealed.reveal_token;
                         if (edit && content.kind === "Password") {
                             setPasswordStrength(await invoke<number>("password_strength", {password: content.value}));
                         }
                     } catch (e) {
-                        await message(e as string, {title: 'Error', type: 'error'});
+                        await message(errorMessage(e), {title: 'Error', type: 'error'});
                     }
                 } else {
                     content.value = "*******************" as string;
                 }
+            } else if (content.kind === "LongText" && content.truncated && edit) {
+                try {
+                    content.value = (await invoke<RevealedValue>("get_content_value", {id: content.id as number, expected_record_id: recordId})).value;
+                    content.truncated = false;
+                } catch (e) {
+                    await message(errorMessage(e), {title: 'Error', type: 'error'});
+                }
             } else if ((content.kind === "Date") && (content.id === undefined || content.id === 0)) {
                 content.value = (new Date()).toISOString().slice(0, 10);
             }
@@ -388,8 +440,12 @@ function ContentValue({content}: { content: Content }): JSX.Element {
         case "TOTPSecret": {
             placeholder = "Totp secret";
             if (content.id !== undefined && content.id !== 0) {
-                invoke("get_totp_code", {id: content.id as number}).then((value) => setTotp(value as [string, number]));
-                let intervalId = setInterval(async () => setTotp(await invoke("get_totp_code", {id: content.id as number})), 1000);
+                const updateTotp = (value: [string, number, boolean]) => {
+                    setTotp([value[0], value[1]]);
+                    setError(value[2] ? "System clock is off, codes may be rejected" : "");
+                };
+                invoke<[string, number, boolean]>("get_totp_code", {id: content.id as number, expected_record_id: recordId}).then(updateTotp);
+                let intervalId = setInterval(async () => updateTotp(await invoke<[string, number, boolean]>("get_totp_code", {id: content.id as number, expected_record_id: recordId})), 1000);
 
                 onCleanup(() => {
                     if (intervalId) {
@@ -534,7 +590,7 @@ function ContentValue({content}: { content: Content }): JSX.Element {
                                                 setError("");
                                                 event.target.parentNode?.parentNode?.parentNode?.parentNode?.parentNode?.querySelector("input")?.setCustomValidity("")
                                             } catch (e) {
-                                                setError(e as string);
+                                                setError(errorMessage(e));
                                             }
                                         }}>
                                             <GetSVG name="arrows-rotate"
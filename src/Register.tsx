@@ -2,6 +2,7 @@ import {createSignal, JSX, Show} from "solid-js";
 import {invoke} from "@tauri-apps/api/tauri";
 import {appWindow} from '@tauri-apps/api/window'
 import GetSVG from "./GetSVG.tsx";
+import {errorMessage} from "./Model.tsx";
 import PasswordStrengthIndicator from "./PasswordStrengthIndicator.tsx";
 
 /**
@@ -28,7 +29,7 @@ export default function Register(): JSX.Element {
                     await invoke<void>("register", {password: password(), confirm_password: confirmPassword()})
                     await appWindow.close();
                 } catch (e) {
-                    setError(e as string);
+                    setError(errorMessage(e));
 
                 } finally {
                     setLoading(false);
@@ -1,6 +1,7 @@
 import {createResource, createSignal, JSX, Show} from "solid-js";
 import {invoke} from "@tauri-apps/api/tauri";
 import GetSVG from "./GetSVG.tsx";
+import {errorMessage} from "./Model.tsx";
 import PasswordStrengthIndicator from "./PasswordStrengthIndicator.tsx";
 import {message} from "@tauri-apps/api/dialog";
 import {appWindow} from '@tauri-apps/api/window'
@@ -33,8 +34,10 @@ function SettingsForm({children}: { children: JSX.Element }) {
 }
 
 function PasswordChangeForm() {
+    const [currentPassword, setCurrentPassword] = createSignal<string>("");
     const [password, setPassword] = createSignal<string>("");
     const [confirmPassword, setConfirmPassword] = createSignal<string>("");
+    const [currentVisibility, setCurrentVisibility] = createSignal(false);
     const [visibility, setVisibility] = createSignal(false);
     const [confirmVisibility, setConfirmVisibility] = createSignal(false);
     const [strength, setStrength] = createSignal(0);
@@ -46,20 +49,40 @@ function PasswordChangeForm() {
             event.preventDefault();
             setLoading(true);
             try {
-                await invoke<void>("change_password", {password: password(), confirm_password: confirmPassword()})
+                await invoke<void>("change_password", {
+                    current_password: currentPassword(),
+                    password: password(),
+                    confirm_password: confirmPassword()
+                })
                 await appWindow.emit("upload");
                 setError("");
+                setCurrentPassword("");
                 setPassword("");
                 setConfirmPassword("");
                 await message("Password changed successfully!", {title: "Success", type: "info"})
             } catch (e) {
-                setError(e as string);
+                setError(errorMessage(e));
 
             } finally {
                 setLoading(false);
             }
         }}>
             <p class="text-[18px]">Change master password</p>
+            <div class="relative w-full mt-3">
+                <input placeholder="Enter current master password" type={currentVisibility() ? 'text' : 'password'}
+                       class={`pl-4 w-full h-7 rounded-xl pr-10 ${error() === "Current password is incorrect" ? 'outline outline-[#EB5545]' : ''}`}
+                       value={currentPassword()}
+                       onInput={(event) => {
+                           setCurrentPassword(event.target.value);
+                       }}>
+                </input>
+                <div class="absolute inset-y-0 right-3 flex items-center cursor-pointer"
+                     onClick={() => setCurrentVisibility(!currentVisibility())}
+                     title={(currentVisibility() ? "Hide" : "View") + " password"}>
+                    <GetSVG name={currentVisibility() ? "eye-slash" : "eye"}
+                            class={`h-full p-1 ${currentVisibility() ? 'pr-0.5' : ''}`}/>
+                </div>
+            </div>
             <div class="relative w-full my-3">
                 <input placeholder="Enter new master password" type={visibility() ? 'text' : 'password'}
                        class="pl-4 w-full h-7 rounded-xl pr-10" value={password()}
@@ -136,7 +159,7 @@ function CloudForm() {
                     await invoke<void>("enable_cloud", {
                         address: address(),
                         username: username(),
-                        password: password()
+                        auth: {method: "password", password: password()}
                     });
                 }
 
@@ -144,7 +167,7 @@ function CloudForm() {
                 setPassword("");
                 refetchStatus();
             } catch (e) {
-                setError(e as string);
+                setError(errorMessage(e));
 
             } finally {
                 setLoading(false);
@@ -1,6 +1,7 @@
 import {createResource, Suspense, Switch, Match, JSX} from "solid-js";
 import {render} from "solid-js/web";
 import { invoke } from "@tauri-apps/api/tauri";
+import { message } from "@tauri-apps/api/dialog";
 import Login from './Login.tsx';
 import Register from './Register.tsx';
 import Main from './Main.tsx';
@@ -17,11 +18,26 @@ window.addEventListener('contextmenu', function(event) {
 });
 
 /**
- * Main App component. Based on backend response, it renders the login, register or main page.
+ * Backend response of the window initialization.
+ */
+interface WindowInitialization {
+    window: string;
+    read_only_reason: string | null;
+    show_whats_new: boolean;
+}
+
+/**
+ * Main App component. Based on backend response, it renders the login, register or main page. If the database is opened in read-only mode, the reason is shown in a dialog.
  * @return {JSX.Element} - Div containing the main app.
  */
 function App(): JSX.Element {
-    const [window] = createResource(async () => invoke<string>("initialize_window"));
+    const [window] = createResource(async () => {
+        const initialization = await invoke<WindowInitialization>("initialize_window");
+        if (initialization.read_only_reason) {
+            await message(initialization.read_only_reason, {title: 'Read-only', type: 'warning'});
+        }
+        return initialization.window;
+    });
 
     return (
         <Suspense fallback={<Loading/>}>